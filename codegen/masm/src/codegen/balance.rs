@@ -0,0 +1,160 @@
+use cranelift_entity::SecondaryMap;
+use miden_diagnostics::{DiagnosticsHandler, Severity, Spanned};
+use miden_hir::{self as hir, diagnostic};
+
+use super::OperandStack;
+
+/// Describes a loop whose body does not leave the operand stack in the same state it was in
+/// when the loop was entered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnbalancedLoop {
+    /// The header block of the loop
+    pub header: hir::Block,
+    /// The depth of the operand stack, in field elements, on entry to the loop
+    pub expected: usize,
+    /// The depth of the operand stack, in field elements, along a loopback edge to the header
+    pub actual: usize,
+}
+
+/// This analysis tracks the depth of the operand stack on entry to each loop header during
+/// code generation, and verifies that every loopback edge to that header leaves the operand
+/// stack at the same depth it had when the loop was first entered.
+///
+/// A loop which grows (or shrinks) the operand stack on each iteration will eventually overflow
+/// (or underflow) it at runtime. The emitter cannot always prevent this from happening, as it
+/// is generally a symptom of a bug elsewhere in code generation, but it can detect it, so that
+/// we fail to compile rather than silently produce a broken program.
+#[derive(Default)]
+pub struct LoopStackBalance {
+    depths: SecondaryMap<hir::Block, Option<usize>>,
+    unbalanced: Vec<UnbalancedLoop>,
+}
+impl LoopStackBalance {
+    /// Record the state of `stack` on entry to the loop header `header`.
+    ///
+    /// The first visit to a header establishes the expected depth of the operand stack for
+    /// that loop. Every subsequent visit, i.e. along a loopback edge, is compared against it.
+    pub fn visit(&mut self, header: hir::Block, stack: &OperandStack) {
+        let actual = stack.raw_len();
+        match self.depths[header] {
+            None => {
+                self.depths[header] = Some(actual);
+            }
+            Some(expected) if expected != actual => {
+                self.unbalanced.push(UnbalancedLoop {
+                    header,
+                    expected,
+                    actual,
+                });
+            }
+            Some(_) => (),
+        }
+    }
+
+    /// Returns true if all loops visited so far are balanced
+    #[inline]
+    pub fn is_balanced(&self) -> bool {
+        self.unbalanced.is_empty()
+    }
+
+    /// Returns the set of unbalanced loops found so far
+    #[inline]
+    pub fn unbalanced(&self) -> &[UnbalancedLoop] {
+        self.unbalanced.as_slice()
+    }
+
+    /// Emit a diagnostic for each unbalanced loop found in `function`
+    ///
+    /// Returns an error if there were any unbalanced loops.
+    pub fn report(
+        &self,
+        function: &hir::Function,
+        diagnostics: &DiagnosticsHandler,
+    ) -> anyhow::Result<()> {
+        if self.is_balanced() {
+            return Ok(());
+        }
+
+        for unbalanced in self.unbalanced() {
+            let UnbalancedLoop {
+                header,
+                expected,
+                actual,
+            } = *unbalanced;
+            let span = function
+                .dfg
+                .last_inst(header)
+                .map(|inst| function.dfg.inst_span(inst))
+                .unwrap_or_else(|| function.id.span());
+            diagnostic!(
+                diagnostics,
+                Severity::Error,
+                "unbalanced operand stack in loop",
+                span,
+                format!(
+                    "the loop headed by {header} is entered with {expected} elements on the \
+                     operand stack, but {actual} elements are on the stack when it loops back"
+                ),
+                "Every iteration of a loop must leave the operand stack the way it was found, \
+                 otherwise the stack will eventually overflow. This is a bug in code generation."
+            );
+        }
+
+        let unbalanced = &self.unbalanced[0];
+        Err(anyhow::anyhow!(
+            "invalid code generated for '{}': the loop headed by {} does not preserve the depth \
+             of the operand stack (expected {}, got {})",
+            function.id,
+            unbalanced.header,
+            unbalanced.expected,
+            unbalanced.actual
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{testing::TestContext, Signature, Type};
+
+    use super::*;
+
+    #[test]
+    fn loop_stack_balance_test() {
+        let context = TestContext::default();
+
+        let id = "test::unbalanced".parse().unwrap();
+        let mut function = hir::Function::new(id, Signature::new([], []));
+        let header = function.dfg.create_block();
+
+        let mut stack = OperandStack::default();
+        stack.push(Type::U32);
+        stack.push(Type::U64);
+
+        let mut analysis = LoopStackBalance::default();
+        analysis.visit(header, &stack);
+        assert!(analysis.is_balanced());
+
+        // Revisiting with the same stack depth is fine
+        analysis.visit(header, &stack);
+        assert!(analysis.is_balanced());
+        analysis.report(&function, &context.session.diagnostics).unwrap();
+
+        // Growing the stack along the loopback edge is not
+        stack.push(Type::U32);
+        analysis.visit(header, &stack);
+        assert!(!analysis.is_balanced());
+        assert_eq!(
+            analysis.unbalanced(),
+            &[UnbalancedLoop {
+                header,
+                expected: 3,
+                actual: 4,
+            }]
+        );
+
+        let err = analysis
+            .report(&function, &context.session.diagnostics)
+            .expect_err("expected unbalanced loop to be reported");
+        assert!(err.to_string().contains(&format!("the loop headed by {header}")));
+    }
+}
@@ -21,7 +21,7 @@ impl<'a> OpEmitter<'a> {
                     // [hi_equal, b_lo, a_lo]
                     Op::Eq,
                     // [b_lo, a_lo, hi_equal]
-                    Op::Movdn(3),
+                    Op::Movdn(2),
                     // [lo_equal, hi_equal]
                     Op::Eq,
                     // [is_equal]
@@ -62,7 +62,7 @@ impl<'a> OpEmitter<'a> {
                     // [hi_equal, b_lo, a_lo]
                     Op::Eq,
                     // [b_lo, a_lo, hi_equal]
-                    Op::Movdn(3),
+                    Op::Movdn(2),
                     // [lo_equal, hi_equal]
                     Op::Eq,
                     // [is_equal]
@@ -96,7 +96,7 @@ impl<'a> OpEmitter<'a> {
                     // [hi_not_equal, b_lo, a_lo]
                     Op::Neq,
                     // [b_lo, a_lo, hi_not_equal]
-                    Op::Movdn(3),
+                    Op::Movdn(2),
                     // [lo_not_equal, hi_not_equal]
                     Op::Neq,
                     // [is_not_equal]
@@ -136,7 +136,7 @@ impl<'a> OpEmitter<'a> {
                     // [hi_not_equal, b_lo, a_lo]
                     Op::Neq,
                     // [b_lo, a_lo, hi_not_equal]
-                    Op::Movdn(3),
+                    Op::Movdn(2),
                     // [lo_not_equal, hi_not_equal]
                     Op::Neq,
                     // [is_not_equal]
@@ -166,6 +166,7 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit(Op::U32Gt);
             }
+            Type::U64 => self.gt_u64(),
             Type::I32 => self.emit(Op::Exec("intrinsics::i32::is_gt".parse().unwrap())),
             ty => unimplemented!("gt is not yet implemented for {ty}"),
         }
@@ -183,6 +184,10 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit_all(&[Op::PushU32(imm.as_u32().unwrap()), Op::U32Gt]);
             }
+            Type::U64 => {
+                self.push_immediate(imm);
+                self.gt_u64();
+            }
             Type::I32 => {
                 self.emit_all(&[
                     Op::PushU32(imm.as_i32().unwrap() as u32),
@@ -206,6 +211,7 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit(Op::U32Gte);
             }
+            Type::U64 => self.gte_u64(),
             Type::I32 => self.emit(Op::Exec("intrinsics::i32::is_gte".parse().unwrap())),
             ty => unimplemented!("gte is not yet implemented for {ty}"),
         }
@@ -223,6 +229,10 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit_all(&[Op::PushU32(imm.as_u32().unwrap()), Op::U32Gte]);
             }
+            Type::U64 => {
+                self.push_immediate(imm);
+                self.gte_u64();
+            }
             Type::I32 => {
                 self.emit_all(&[
                     Op::PushU32(imm.as_i32().unwrap() as u32),
@@ -246,6 +256,7 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit(Op::U32Lt);
             }
+            Type::U64 => self.lt_u64(),
             Type::I32 => self.emit(Op::Exec("intrinsics::i32::is_lt".parse().unwrap())),
             ty => unimplemented!("lt is not yet implemented for {ty}"),
         }
@@ -263,6 +274,10 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit_all(&[Op::PushU32(imm.as_u32().unwrap()), Op::U32Lt]);
             }
+            Type::U64 => {
+                self.push_immediate(imm);
+                self.lt_u64();
+            }
             Type::I32 => {
                 self.emit_all(&[
                     Op::PushU32(imm.as_i32().unwrap() as u32),
@@ -286,6 +301,7 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit(Op::U32Lte);
             }
+            Type::U64 => self.lte_u64(),
             Type::I32 => self.emit(Op::Exec("intrinsics::i32::is_lte".parse().unwrap())),
             ty => unimplemented!("lte is not yet implemented for {ty}"),
         }
@@ -303,6 +319,10 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit_all(&[Op::PushU32(imm.as_u32().unwrap()), Op::U32Lte]);
             }
+            Type::U64 => {
+                self.push_immediate(imm);
+                self.lte_u64();
+            }
             Type::I32 => {
                 self.emit_all(&[
                     Op::PushU32(imm.as_i32().unwrap() as u32),
@@ -326,6 +346,9 @@ impl<'a> OpEmitter<'a> {
             Type::U64 => {
                 self.add_u64(overflow);
             }
+            Type::I64 => {
+                self.add_i64(overflow);
+            }
             Type::U32 => {
                 self.add_u32(overflow);
             }
@@ -356,6 +379,10 @@ impl<'a> OpEmitter<'a> {
                 self.push_immediate(imm);
                 self.add_u64(overflow);
             }
+            Type::I64 => {
+                self.push_immediate(imm);
+                self.add_i64(overflow);
+            }
             Type::U32 => {
                 self.add_imm_u32(imm.as_u32().unwrap(), overflow);
             }
@@ -385,6 +412,9 @@ impl<'a> OpEmitter<'a> {
             Type::U64 => {
                 self.sub_u64(overflow);
             }
+            Type::I64 => {
+                self.sub_i64(overflow);
+            }
             Type::U32 => {
                 self.sub_u32(overflow);
             }
@@ -414,6 +444,10 @@ impl<'a> OpEmitter<'a> {
                 self.push_immediate(imm);
                 self.sub_u64(overflow);
             }
+            Type::I64 => {
+                self.push_immediate(imm);
+                self.sub_i64(overflow);
+            }
             Type::U32 => {
                 self.sub_imm_u32(imm.as_u32().unwrap(), overflow);
             }
@@ -454,6 +488,7 @@ impl<'a> OpEmitter<'a> {
                 todo!()
             }
             Type::U64 => self.mul_u64(overflow),
+            Type::I64 => self.mul_i64(overflow),
             Type::Felt => {
                 assert_matches!(
                     overflow,
@@ -487,6 +522,10 @@ impl<'a> OpEmitter<'a> {
                 self.push_immediate(imm);
                 self.mul_u64(overflow);
             }
+            Type::I64 => {
+                self.push_immediate(imm);
+                self.mul_i64(overflow);
+            }
             Type::Felt => {
                 assert_matches!(
                     overflow,
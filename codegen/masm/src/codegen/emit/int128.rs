@@ -1,4 +1,6 @@
-use super::OpEmitter;
+use miden_hir::Felt;
+
+use super::{int64::to_raw_parts, OpEmitter};
 use crate::masm::Op;
 
 #[allow(unused)]
@@ -27,8 +29,16 @@ impl<'a> OpEmitter<'a> {
         let lo = u64::from_le_bytes([
             bytes[8], bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14], bytes[15],
         ]);
-        self.push_u64(lo);
-        self.push_u64(hi);
+        // This is equivalent to pushing `lo` and then `hi` as u64 values, but pushes the zero
+        // word with a single instruction
+        let (lo_hi, lo_lo) = to_raw_parts(lo);
+        let (hi_hi, hi_lo) = to_raw_parts(hi);
+        self.push_word([
+            Felt::new(hi_hi as u64),
+            Felt::new(hi_lo as u64),
+            Felt::new(lo_hi as u64),
+            Felt::new(lo_lo as u64),
+        ]);
     }
 
     /// Convert an i128 value to a field element value.
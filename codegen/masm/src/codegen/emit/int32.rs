@@ -295,7 +295,7 @@ impl<'a> OpEmitter<'a> {
         // Mask out any bits between N and 32.
         let unused_bits = 32 - n;
         if unused_bits > 0 {
-            self.const_mask_u32(1 << ((32 - unused_bits) - 1));
+            self.const_mask_u32((1 << n) - 1);
         }
     }
 
@@ -354,16 +354,17 @@ impl<'a> OpEmitter<'a> {
         self.emit_n(needed as usize, Op::Dup(0));
     }
 
-    /// Push a u32 value on the stack
+    /// Push a u32 value on the stack, using the narrowest push instruction which can encode it
     #[inline(always)]
     pub fn push_u32(&mut self, i: u32) {
-        self.emit(Op::PushU32(i));
+        self.emit(push_u32_op(i));
     }
 
-    /// Push a i32 value on the stack
+    /// Push a i32 value on the stack, using the narrowest push instruction which can encode the
+    /// bit pattern of its two's complement representation
     #[inline(always)]
     pub fn push_i32(&mut self, i: i32) {
-        self.emit(Op::PushU32(i as u32));
+        self.push_u32(i as u32);
     }
 
     /// Pops two u32 values off the stack, `b` and `a`, and performs `a + b`.
@@ -918,3 +919,12 @@ impl<'a> OpEmitter<'a> {
         ]);
     }
 }
+
+/// Returns the narrowest push instruction which can encode `value`
+pub fn push_u32_op(value: u32) -> Op {
+    match value {
+        0..=0xff => Op::PushU8(value as u8),
+        0x100..=0xffff => Op::PushU16(value as u16),
+        _ => Op::PushU32(value),
+    }
+}
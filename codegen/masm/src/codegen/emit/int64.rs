@@ -245,10 +245,30 @@ impl<'a> OpEmitter<'a> {
 
     /// Pops two u64 values off the stack, `b` and `a`, and pushes `a < b` on the stack.
     ///
+    /// This is computed limb-wise, as `a < b` if and only if `a - b` borrows out of the high limb.
+    ///
     /// This operation is checked, so if the values are not valid u64, execution will trap.
-    #[inline]
     pub fn lt_u64(&mut self) {
-        self.emit(Op::Exec("std::math::u64::checked_lt".parse().unwrap()));
+        self.emit_all(&[
+            Op::U32Assertw,
+            // [b_hi, b_lo, a_hi, a_lo] => [b_lo, a_lo, b_hi, a_hi]
+            Op::Movup(3),
+            Op::Movup(2),
+            // [borrow_lo, lo, b_hi, a_hi] => [b_hi, a_hi, borrow_lo]
+            Op::U32OverflowingSub,
+            Op::Swap(1),
+            Op::Drop,
+            Op::Movdn(2),
+            // [borrow_hi, hi, borrow_lo] => [borrow_lo, hi, borrow_hi]
+            Op::U32OverflowingSub,
+            Op::Swap(2),
+            // [borrow, hi', borrow_hi] => [borrow, borrow_hi]
+            Op::U32OverflowingSub,
+            Op::Swap(1),
+            Op::Drop,
+            // [a < b]
+            Op::Or,
+        ]);
     }
 
     /// Pops two u64 values off the stack, `b` and `a`, and pushes `a <= b` on the stack.
@@ -256,7 +276,8 @@ impl<'a> OpEmitter<'a> {
     /// This operation is checked, so if the values are not valid u64, execution will trap.
     #[inline]
     pub fn lte_u64(&mut self) {
-        self.emit(Op::Exec("std::math::u64::checked_lte".parse().unwrap()));
+        self.gt_u64();
+        self.emit(Op::Not);
     }
 
     /// Pops two u64 values off the stack, `b` and `a`, and pushes `a > b` on the stack.
@@ -264,7 +285,9 @@ impl<'a> OpEmitter<'a> {
     /// This operation is checked, so if the values are not valid u64, execution will trap.
     #[inline]
     pub fn gt_u64(&mut self) {
-        self.emit(Op::Exec("std::math::u64::checked_gt".parse().unwrap()));
+        // [b_hi, b_lo, a_hi, a_lo] => [a_hi, a_lo, b_hi, b_lo], i.e. `b < a`
+        self.emit_all(&[Op::Movup(3), Op::Movup(3)]);
+        self.lt_u64();
     }
 
     /// Pops two u64 values off the stack, `b` and `a`, and pushes `a >= b` on the stack.
@@ -272,7 +295,8 @@ impl<'a> OpEmitter<'a> {
     /// This operation is checked, so if the values are not valid u64, execution will trap.
     #[inline]
     pub fn gte_u64(&mut self) {
-        self.emit(Op::Exec("std::math::u64::checked_gte".parse().unwrap()));
+        self.lt_u64();
+        self.emit(Op::Not);
     }
 
     /// Pops two u64 values off the stack, `b` and `a`, and pushes `a == b` on the stack.
@@ -319,7 +343,8 @@ impl<'a> OpEmitter<'a> {
     ///
     /// The semantics of this operation depend on the `overflow` setting:
     ///
-    /// * There is no unchecked variant for u64, so wrapping is used instead
+    /// * There is no unchecked variant for u64, so wrapping is used instead, which is lowered to
+    /// a sequence of operations on the two u32 limbs
     /// * When checked, both the operands and the result are validated to ensure
     /// they are valid u64 values.
     /// * Overflowing and wrapping variants follow the usual semantics, with the
@@ -334,9 +359,7 @@ impl<'a> OpEmitter<'a> {
             Overflow::Checked => {
                 self.emit(Op::Exec("std::math::u64::checked_add".parse().unwrap()));
             }
-            Overflow::Unchecked | Overflow::Wrapping => {
-                self.emit(Op::Exec("std::math::u64::wrapping_add".parse().unwrap()));
-            }
+            Overflow::Unchecked | Overflow::Wrapping => self.wrapping_add_int64(),
             Overflow::Overflowing => {
                 self.emit(Op::Exec("std::math::u64::overflowing_add".parse().unwrap()));
             }
@@ -347,7 +370,8 @@ impl<'a> OpEmitter<'a> {
     ///
     /// The semantics of this operation depend on the `overflow` setting:
     ///
-    /// * There is no unchecked variant for u64, so wrapping is used instead
+    /// * There is no unchecked variant for u64, so wrapping is used instead, which is lowered to
+    /// a sequence of operations on the two u32 limbs
     /// * When checked, both the operands and the result are validated to ensure
     /// they are valid u64 values.
     /// * Overflowing and wrapping variants follow the usual semantics, with the
@@ -362,9 +386,7 @@ impl<'a> OpEmitter<'a> {
             Overflow::Checked => {
                 self.emit(Op::Exec("std::math::u64::checked_sub".parse().unwrap()));
             }
-            Overflow::Unchecked | Overflow::Wrapping => {
-                self.emit(Op::Exec("std::math::u64::wrapping_sub".parse().unwrap()));
-            }
+            Overflow::Unchecked | Overflow::Wrapping => self.wrapping_sub_int64(),
             Overflow::Overflowing => {
                 self.emit(Op::Exec("std::math::u64::overflowing_sub".parse().unwrap()));
             }
@@ -375,7 +397,8 @@ impl<'a> OpEmitter<'a> {
     ///
     /// The semantics of this operation depend on the `overflow` setting:
     ///
-    /// * There is no unchecked variant for u64, so wrapping is used instead
+    /// * There is no unchecked variant for u64, so wrapping is used instead, which is lowered to
+    /// a sequence of operations on the two u32 limbs
     /// * When checked, both the operands and the result are validated to ensure
     /// they are valid u64 values.
     /// * Overflowing and wrapping variants follow the usual semantics, with the
@@ -390,15 +413,117 @@ impl<'a> OpEmitter<'a> {
             Overflow::Checked => {
                 self.emit(Op::Exec("std::math::u64::checked_mul".parse().unwrap()));
             }
-            Overflow::Unchecked | Overflow::Wrapping => {
-                self.emit(Op::Exec("std::math::u64::wrapping_mul".parse().unwrap()));
-            }
+            Overflow::Unchecked | Overflow::Wrapping => self.wrapping_mul_int64(),
             Overflow::Overflowing => {
                 self.emit(Op::Exec("std::math::u64::overflowing_mul".parse().unwrap()));
             }
         }
     }
 
+    /// Pops two i64 values off the stack, `b` and `a`, and performs `a + b`.
+    ///
+    /// Only unchecked and wrapping semantics are currently supported for i64, for which the
+    /// result is the same as for u64, as both are computed modulo 2^64.
+    #[inline]
+    pub fn add_i64(&mut self, overflow: Overflow) {
+        match overflow {
+            Overflow::Unchecked | Overflow::Wrapping => self.wrapping_add_int64(),
+            overflow => {
+                unimplemented!("add with {overflow} semantics is not yet implemented for i64")
+            }
+        }
+    }
+
+    /// Pops two i64 values off the stack, `b` and `a`, and performs `a - b`.
+    ///
+    /// Only unchecked and wrapping semantics are currently supported for i64, for which the
+    /// result is the same as for u64, as both are computed modulo 2^64.
+    #[inline]
+    pub fn sub_i64(&mut self, overflow: Overflow) {
+        match overflow {
+            Overflow::Unchecked | Overflow::Wrapping => self.wrapping_sub_int64(),
+            overflow => {
+                unimplemented!("sub with {overflow} semantics is not yet implemented for i64")
+            }
+        }
+    }
+
+    /// Pops two i64 values off the stack, `b` and `a`, and performs `a * b`.
+    ///
+    /// Only unchecked and wrapping semantics are currently supported for i64, for which the
+    /// result is the same as for u64, as both are computed modulo 2^64.
+    #[inline]
+    pub fn mul_i64(&mut self, overflow: Overflow) {
+        match overflow {
+            Overflow::Unchecked | Overflow::Wrapping => self.wrapping_mul_int64(),
+            overflow => {
+                unimplemented!("mul with {overflow} semantics is not yet implemented for i64")
+            }
+        }
+    }
+
+    /// Pops two 64-bit integers off the stack, `b` and `a`, and pushes `a + b` modulo 2^64.
+    ///
+    /// The carry out of the low limb is propagated into the high limb, and any carry out of the
+    /// high limb is discarded, so both limbs of the result are always valid u32 values.
+    pub fn wrapping_add_int64(&mut self) {
+        self.emit_all(&[
+            // [b_hi, b_lo, a_hi, a_lo] => [a_lo, b_lo, a_hi, b_hi]
+            Op::Movdn(3),
+            Op::Movup(2),
+            // [carry, lo, a_hi, b_hi]
+            Op::U32OverflowingAdd,
+            // [b_hi, a_hi, carry, lo] => [hi, lo]
+            Op::Movup(2),
+            Op::Movup(3),
+            Op::U32WrappingAdd3,
+        ]);
+    }
+
+    /// Pops two 64-bit integers off the stack, `b` and `a`, and pushes `a - b` modulo 2^64.
+    ///
+    /// The borrow out of the low limb is propagated into the high limb, and any borrow out of the
+    /// high limb is discarded, so both limbs of the result are always valid u32 values.
+    pub fn wrapping_sub_int64(&mut self) {
+        self.emit_all(&[
+            // [b_hi, b_lo, a_hi, a_lo] => [b_lo, a_lo, b_hi, a_hi]
+            Op::Movup(3),
+            Op::Movup(2),
+            // [borrow, lo, b_hi, a_hi]
+            Op::U32OverflowingSub,
+            // [b_hi, a_hi, borrow, lo] => [hi', borrow, lo]
+            Op::Movup(3),
+            Op::Movup(3),
+            Op::U32WrappingSub,
+            // [hi, lo]
+            Op::Swap(1),
+            Op::U32WrappingSub,
+        ]);
+    }
+
+    /// Pops two 64-bit integers off the stack, `b` and `a`, and pushes `a * b` modulo 2^64.
+    ///
+    /// The high half of the product of the low limbs is carried into the high limb, and any bits
+    /// of the product above 2^64 are discarded, so both limbs of the result are always valid u32
+    /// values.
+    pub fn wrapping_mul_int64(&mut self) {
+        self.emit_all(&[
+            // [b_hi, b_lo, a_hi, a_lo] => [b_lo, a_lo, b_hi, b_lo, a_hi, a_lo]
+            Op::Dup(3),
+            Op::Dup(2),
+            // [carry, lo, b_hi, b_lo, a_hi, a_lo]
+            Op::U32OverflowingMul,
+            // [a_lo, b_hi, carry, lo, b_lo, a_hi] => [carry', lo, b_lo, a_hi]
+            Op::Movup(2),
+            Op::Movup(5),
+            Op::U32WrappingMadd,
+            // [a_hi, b_lo, carry', lo] => [hi, lo]
+            Op::Movup(2),
+            Op::Movup(3),
+            Op::U32WrappingMadd,
+        ]);
+    }
+
     /// Pops two u64 values off the stack, `b` and `a`, and pushes the result of `a / b` on the
     /// stack.
     ///
@@ -1,7 +1,10 @@
-use miden_hir::{StructType, Type};
+use miden_hir::{self as hir, Felt, StructType, Type};
 
-use super::OpEmitter;
-use crate::masm::{NativePtr, Op};
+use super::{stack_index, OpEmitter};
+use crate::{
+    codegen::TypedValue,
+    masm::{NativePtr, Op},
+};
 
 /// Allocation
 impl<'a> OpEmitter<'a> {
@@ -22,6 +25,86 @@ impl<'a> OpEmitter<'a> {
     }
 }
 
+/// Linear memory
+impl<'a> OpEmitter<'a> {
+    /// Pop the number of pages to grow linear memory by from the operand stack, and grow it,
+    /// placing the previous size of linear memory in pages, or -1 on failure, on the stack.
+    ///
+    /// See `intrinsics::mem::memory_grow` for details.
+    pub fn mem_grow(&mut self) {
+        let num_pages = self.pop().expect("operand stack is empty");
+        assert_eq!(num_pages.ty(), Type::U32, "expected memory.grow operand to be a u32");
+        self.emit(Op::Exec("intrinsics::mem::memory_grow".parse().unwrap()));
+        self.push(Type::I32);
+    }
+}
+
+/// Spills
+impl<'a> OpEmitter<'a> {
+    /// Spill the value at index `index` on the operand stack to a new procedure local,
+    /// removing it from the operand stack.
+    ///
+    /// The value can be placed back on the operand stack using [Self::reload].
+    ///
+    /// NOTE: Only SSA values which fit in a single word can be spilled.
+    pub fn spill(&mut self, index: usize) {
+        let operand = &self.stack[index];
+        let value = TypedValue {
+            value: operand.as_value().expect("invalid spill: expected an ssa value"),
+            ty: operand.ty(),
+        };
+        let size = operand.size();
+        assert!(size <= 4, "invalid spill: {} does not fit in a word", &value);
+
+        if index > 0 {
+            self.movup(stack_index(index));
+        }
+        self.stack.pop();
+        let local = self.function.alloc_local(value.ty.clone());
+        match size {
+            1 => self.emit(Op::LocStore(local)),
+            n => {
+                // Values smaller than a word are padded with zeroes, which are discarded on reload
+                for _ in n..4 {
+                    self.emit(Op::PushU8(0));
+                }
+                self.emit_all(&[Op::LocStorew(local), Op::Dropw]);
+            }
+        }
+        self.stack.spill(value, local);
+    }
+
+    /// Reload the spilled `value` from its procedure local, placing it on top of the stack.
+    ///
+    /// If `last_use` is true, `value` is no longer considered spilled afterwards, otherwise it
+    /// can be reloaded again later.
+    pub fn reload(&mut self, value: hir::Value, last_use: bool) {
+        let (spilled, local) = self
+            .stack
+            .spilled(&value)
+            .map(|(spilled, local)| (spilled.clone(), local))
+            .unwrap_or_else(|| {
+                panic!(
+                    "invalid reload: {value} was not spilled, operand stack:\n{}",
+                    self.stack.describe()
+                )
+            });
+        match spilled.ty.size_in_felts() {
+            1 => self.emit(Op::LocLoad(local)),
+            n => {
+                self.emit_all(&[Op::Padw, Op::LocLoadw(local)]);
+                for _ in n..4 {
+                    self.emit(Op::Drop);
+                }
+            }
+        }
+        if last_use {
+            self.stack.discard_spill(&value);
+        }
+        self.stack.push(spilled);
+    }
+}
+
 /// Loads
 impl<'a> OpEmitter<'a> {
     /// Load a value of corresponding to the pointee type of a pointer operand on the stack.
@@ -29,10 +112,23 @@ impl<'a> OpEmitter<'a> {
     /// The type of the pointer determines what address space the pointer value represents;
     /// either the Miden-native address space (word-addressable), or the IR's byte-addressable
     /// address space.
+    ///
+    /// The address is not assumed to be aligned, regardless of any alignment recorded on the load
+    /// in the IR, as that is only a hint, e.g. Wasm permits a misaligned access whatever alignment
+    /// is given. So a 32-bit integer, which may span two elements, is assembled from the bytes of
+    /// both at runtime, as is a 16-bit integer.
+    ///
+    /// Integers smaller than 32 bits are zero-extended to 32 bits when loaded, it is up to the
+    /// caller to sign-extend them if needed.
     pub fn load(&mut self, ty: Type) {
         let ptr = self.stack.pop().expect("operand stack is empty");
         match ptr.ty() {
             Type::Ptr(_) => {
+                if matches!(ty, Type::I16 | Type::U16) {
+                    self.load_unaligned_u16();
+                    self.stack.push(ty);
+                    return;
+                }
                 // Converet the pointer to a native pointer representation
                 self.emit_native_ptr();
                 match &ty {
@@ -40,9 +136,8 @@ impl<'a> OpEmitter<'a> {
                     Type::I64 | Type::U64 => self.load_double_word(None),
                     Type::Felt => self.load_felt(None),
                     Type::I32 | Type::U32 => self.load_word(None),
-                    ty @ (Type::I16 | Type::U16 | Type::U8 | Type::I8 | Type::I1) => {
-                        self.load_word(None);
-                        self.trunc_int32(ty.size_in_bits() as u32);
+                    ty @ (Type::U8 | Type::I8 | Type::I1) => {
+                        self.load_small(ty.size_in_bits() as u32);
                     }
                     ty => todo!("support for loading {ty} is not yet implemented"),
                 }
@@ -66,14 +161,47 @@ impl<'a> OpEmitter<'a> {
             Type::Felt => self.load_felt(Some(ptr)),
             Type::I32 | Type::U32 => self.load_word(Some(ptr)),
             Type::I16 | Type::U16 | Type::U8 | Type::I8 | Type::I1 => {
-                self.load_word(Some(ptr));
-                self.trunc_int32(ty.size_in_bits() as u32);
+                self.load_small_imm(ptr, ty.size_in_bits() as u32);
+            }
+            ty @ (Type::Struct(_) | Type::Array(..)) => {
+                self.load_elements_imm(ptr, ty.size_in_felts());
             }
             ty => todo!("support for loading {ty} is not yet implemented"),
         }
         self.stack.push(ty);
     }
 
+    /// Load `n` consecutive elements starting at `ptr`, such that the first element is on top of
+    /// the operand stack, i.e. the lowest-addressed bytes of the value are closest to the top.
+    ///
+    /// This is used to load aggregates which span more than a single element, but which fit
+    /// within a word when placed on the operand stack.
+    fn load_elements_imm(&mut self, ptr: NativePtr, n: usize) {
+        assert!(
+            ptr.is_element_aligned(),
+            "aggregate values must be naturally aligned, got {ptr:#?}"
+        );
+        assert!(n > 0 && n <= 4, "expected a value between 1 and 4 elements in size, got {n}");
+
+        // If the value is a full word, and the address is word-aligned, a single load suffices
+        if n == 4 && ptr.index == 0 {
+            return self.emit_all(&[Op::Padw, Op::MemLoadwImm(ptr.waddr)]);
+        }
+
+        // Otherwise, load each element individually, starting with the last, so that the
+        // first element is on top of the stack when we're done
+        for i in (0..n).rev() {
+            let index = ptr.index as usize + i;
+            let element = NativePtr {
+                waddr: ptr.waddr + (index / 4) as u32,
+                index: (index % 4) as u8,
+                offset: 0,
+                addrspace: ptr.addrspace,
+            };
+            self.load_felt_imm(element);
+        }
+    }
+
     /// Emit a sequence of instructions to translate a raw pointer value to
     /// a native pointer value, as a triple of `(waddr, index, offset)`, in
     /// that order on the stack.
@@ -131,9 +259,8 @@ impl<'a> OpEmitter<'a> {
                 self.emit_all(&[
                     Op::Padw,
                     Op::MemLoadwImm(ptr.waddr),
-                    Op::Movup(4),
-                    Op::Movup(4),
                     Op::Drop,
+                    Op::Movdn(2),
                     Op::Drop,
                     Op::Drop,
                 ]);
@@ -301,6 +428,72 @@ impl<'a> OpEmitter<'a> {
         }
     }
 
+    /// Loads an N-bit integer, where N < 32, from the byte offset of a native pointer triplet on
+    /// the stack, zero-extended to 32 bits.
+    ///
+    /// The bytes of an element are stored in little-endian order, so the value is found by shifting
+    /// the element containing it right by the byte offset, and masking out the bits above N. The
+    /// value must not span two elements.
+    fn load_small(&mut self, n: u32) {
+        self.emit_all(&[
+            // Load the element containing the value, ignoring the byte offset for now
+            //
+            // [waddr, index, 0, offset]
+            Op::PushU32(0),
+            Op::Movdn(2),
+            // [element, offset]
+            Op::Exec("intrinsics::mem::load_felt".parse().unwrap()),
+            // Shift the value into the low bits of the element
+            //
+            // [offset * 8, element]
+            Op::Swap(1),
+            Op::U32WrappingMulImm(8),
+            // [element >> (offset * 8)]
+            Op::U32Shr,
+        ]);
+        self.trunc_int32(n);
+    }
+
+    /// Like [Self::load_small], but the value is loaded from an immediate address.
+    fn load_small_imm(&mut self, ptr: NativePtr, n: u32) {
+        // A 16-bit integer in the last byte of an element spans two elements, so is assembled
+        // from its two bytes, the first of which holds the low bits of the value
+        if ptr.offset as u32 * 8 + n > 32 {
+            self.load_small_imm(NativePtr::from_ptr(ptr.as_ptr() + 1), 8);
+            self.emit(Op::U32ShlImm(8));
+            self.load_small_imm(ptr, 8);
+            self.emit(Op::U32Or);
+            return;
+        }
+
+        let element = NativePtr { offset: 0, ..ptr };
+        self.load_felt_imm(element);
+        if ptr.offset > 0 {
+            self.emit(Op::U32ShrImm(ptr.offset as u32 * 8));
+        }
+        self.trunc_int32(n);
+    }
+
+    /// Loads a 16-bit integer from a pointer on the stack, which may span two elements if the
+    /// pointer is not 2-byte aligned, zero-extended to 32 bits.
+    ///
+    /// The value is assembled from its two bytes, the first of which holds the low bits of the
+    /// value.
+    fn load_unaligned_u16(&mut self) {
+        // [addr, addr]
+        self.emit(Op::Dup(0));
+        self.emit_native_ptr();
+        // [lo, addr]
+        self.load_small(8);
+        // [addr + 1, lo]
+        self.emit_all(&[Op::Swap(1), Op::U32WrappingAddImm(1)]);
+        self.emit_native_ptr();
+        // [hi, lo]
+        self.load_small(8);
+        // [(hi << 8) | lo]
+        self.emit_all(&[Op::U32ShlImm(8), Op::U32Or]);
+    }
+
     /// Load a pair of machine words (32-bit elements) to the operand stack
     fn load_double_word(&mut self, ptr: Option<NativePtr>) {
         if let Some(imm) = ptr {
@@ -830,6 +1023,11 @@ impl<'a> OpEmitter<'a> {
         assert!(!value_ty.is_zst(), "cannot store a zero-sized type in memory");
         match ptr_ty {
             Type::Ptr(_) => {
+                // The alignment of the address is not known, so a 16-bit integer may span two
+                // elements, and is stored one byte at a time
+                if matches!(value_ty, Type::I16 | Type::U16) {
+                    return self.store_unaligned_u16();
+                }
                 // Converet the pointer to a native pointer representation
                 self.emit_native_ptr();
                 match value_ty {
@@ -911,16 +1109,246 @@ impl<'a> OpEmitter<'a> {
         todo!()
     }
 
-    fn store_word(&mut self, _ptr: Option<NativePtr>) {
-        todo!()
+    /// Stores a single 32-bit machine word, i.e. a single field element, not the Miden notion of a
+    /// word
+    ///
+    /// Expects a native pointer triplet on the stack, followed by the value, if an immediate
+    /// address is not given.
+    ///
+    /// A value at an address which is not element-aligned spans two elements: its low bytes
+    /// replace the high bytes of the first element, and its high bytes replace the low bytes of
+    /// the next element. As the alignment is generally not known until runtime, both elements are
+    /// always updated, the second one is simply left unchanged when the address is aligned.
+    fn store_word(&mut self, ptr: Option<NativePtr>) {
+        if let Some(imm) = ptr {
+            // An element-aligned value replaces a single element entirely
+            if imm.is_element_aligned() {
+                self.emit_all(&[
+                    Op::PushU32(imm.index as u32),
+                    Op::PushU32(imm.waddr),
+                    Op::Movup(2),
+                ]);
+                return self.store_element();
+            }
+            self.emit_all(&[
+                Op::PushU32(imm.offset as u32),
+                Op::PushU32(imm.index as u32),
+                Op::PushU32(imm.waddr),
+            ]);
+        }
+
+        self.emit_all(&[
+            // Keep a copy of the address of the first element, to store it back later
+            //
+            // [waddr, index, waddr, index, offset, value]
+            Op::Dup(1),
+            Op::Dup(1),
+            // Load the first element
+            //
+            // [element, waddr, index, waddr, index, offset, value]
+            Op::Dup(1),
+            Op::Dup(1),
+            Op::PushU32(0),
+            Op::Movdn(2),
+            Op::Exec("intrinsics::mem::load_felt".parse().unwrap()),
+            // Clear the bits of the element at and above the byte offset
+            //
+            // [shift, element, ..]
+            Op::Dup(5),
+            Op::U32WrappingMulImm(8),
+            // [!(u32::MAX << shift), shift, element, ..]
+            Op::PushU32(u32::MAX),
+            Op::Dup(1),
+            Op::U32Shl,
+            Op::U32Not,
+            // [element & !(u32::MAX << shift), shift, waddr, index, waddr, index, offset, value]
+            Op::Movup(2),
+            Op::U32And,
+            // Combine it with the low bytes of the value, shifted into position
+            //
+            // [value << shift, element, waddr, index, waddr, index, offset, value]
+            Op::Dup(7),
+            Op::Movup(2),
+            Op::U32Shl,
+            // [element, waddr, index, waddr, index, offset, value]
+            Op::U32Or,
+        ]);
+        // [waddr, index, offset, value]
+        self.store_element();
+
+        self.emit_all(&[
+            // Compute the address of the next element, which may be in the next word
+            //
+            // [index + 1, waddr, offset, value]
+            Op::Swap(1),
+            Op::U32WrappingAddImm(1),
+            // [waddr + (index + 1) / 4, index + 1, offset, value]
+            Op::Dup(0),
+            Op::U32DivImm(4),
+            Op::Movup(2),
+            Op::U32WrappingAdd,
+            // [waddr, index, offset, value]
+            Op::Swap(1),
+            Op::U32ModImm(4),
+            Op::Swap(1),
+            // Load the next element
+            //
+            // [element, waddr, index, offset, value]
+            Op::Dup(1),
+            Op::Dup(1),
+            Op::PushU32(0),
+            Op::Movdn(2),
+            Op::Exec("intrinsics::mem::load_felt".parse().unwrap()),
+            // Clear the bits of the element below the byte offset, of which there are none when
+            // the address is aligned
+            //
+            // [shift, element, waddr, index, value]
+            Op::Movup(3),
+            Op::U32WrappingMulImm(8),
+            // [u32::MAX << shift, shift, element, waddr, index, value]
+            Op::PushU32(u32::MAX),
+            Op::Dup(1),
+            Op::U32Shl,
+            // [element & (u32::MAX << shift), shift, waddr, index, value]
+            Op::Movup(2),
+            Op::U32And,
+            // Combine it with the high bytes of the value, i.e. `value >> (32 - shift)`, computed
+            // in two steps, as a shift by 32 is not permitted, and must produce zero here
+            //
+            // [value >> 1, element, shift, waddr, index]
+            Op::Movup(4),
+            Op::U32ShrImm(1),
+            // [31 - shift, value >> 1, element, waddr, index]
+            Op::Movup(2),
+            Op::PushU32(31),
+            Op::Swap(1),
+            Op::U32WrappingSub,
+            // [(value >> 1) >> (31 - shift), element, waddr, index]
+            Op::U32Shr,
+            // [element, waddr, index]
+            Op::U32Or,
+        ]);
+        self.store_element();
+    }
+
+    /// Stores an element on top of the stack at the word address and element index which follow
+    /// it on the stack.
+    ///
+    /// There is no instruction to store a single element at a dynamic index of a word, so the word
+    /// is loaded, the element at the given index is replaced, and the word is stored again.
+    fn store_element(&mut self) {
+        self.emit_all(&[
+            // [w0, w1, w2, w3, element, waddr, index]
+            Op::Padw,
+            Op::Dup(5),
+            Op::MemLoadw,
+        ]);
+        // Visit each element of the word in turn, selecting the new element in place of the
+        // current one if its index is the one being stored to, and moving the result to the
+        // bottom of the word, so that the word is in its original order when we're done
+        for i in 0..4u64 {
+            self.emit_all(&[
+                // [element, wi, .., element, waddr, index]
+                Op::Dup(4),
+                // [index == i, element, wi, .., element, waddr, index]
+                Op::Dup(7),
+                Op::EqImm(Felt::new(i)),
+                // [wi', .., element, waddr, index]
+                Op::Cdrop,
+                Op::Movdn(3),
+            ]);
+        }
+        self.emit_all(&[
+            // [w0, w1, w2, w3, waddr, index]
+            Op::Movup(4),
+            Op::Drop,
+            // [waddr, w0, w1, w2, w3, index]
+            Op::Movup(4),
+            Op::MemStorew,
+            Op::Dropw,
+            Op::Drop,
+        ]);
     }
 
     fn store_felt(&mut self, _ptr: Option<NativePtr>) {
         todo!()
     }
 
-    fn store_small(&mut self, _ty: &Type, _ptr: Option<NativePtr>) {
-        todo!()
+    /// Stores an integer smaller than 32 bits, by replacing its bytes in the element containing
+    /// them, leaving the other bytes of the element unchanged.
+    ///
+    /// Expects a native pointer triplet on the stack, followed by the value, if an immediate
+    /// address is not given. The value must not span two elements.
+    fn store_small(&mut self, ty: &Type, ptr: Option<NativePtr>) {
+        let n = ty.size_in_bits() as u32;
+        if let Some(imm) = ptr {
+            // A 16-bit integer in the last byte of an element spans two elements
+            if imm.offset as u32 * 8 + n > 32 {
+                self.emit(Op::PushU32(imm.as_ptr()));
+                return self.store_unaligned_u16();
+            }
+            self.emit_all(&[
+                Op::PushU32(imm.offset as u32),
+                Op::PushU32(imm.index as u32),
+                Op::PushU32(imm.waddr),
+            ]);
+        }
+
+        let mask = u32::MAX >> (32 - n);
+        self.emit_all(&[
+            // Load the element containing the value, keeping the native pointer for later
+            //
+            // [waddr, index, 0, waddr, index, offset, value]
+            Op::Dup(1),
+            Op::Dup(1),
+            Op::PushU32(0),
+            Op::Movdn(2),
+            // [element, waddr, index, offset, value]
+            Op::Exec("intrinsics::mem::load_felt".parse().unwrap()),
+            // Compute the number of bits the value is shifted by within the element
+            //
+            // [shift, element, waddr, index, value]
+            Op::Movup(3),
+            Op::U32WrappingMulImm(8),
+            // Clear the bits of the element which hold the value
+            //
+            // [!(mask << shift), shift, element, waddr, index, value]
+            Op::PushU32(mask),
+            Op::Dup(1),
+            Op::U32Shl,
+            Op::U32Not,
+            // [element & !(mask << shift), shift, waddr, index, value]
+            Op::Movup(2),
+            Op::U32And,
+            // Shift the value into position, and combine it with the element
+            //
+            // [value & mask, element, shift, waddr, index]
+            Op::Movup(4),
+            Op::PushU32(mask),
+            Op::U32And,
+            // [(value & mask) << shift, element, waddr, index]
+            Op::Movup(2),
+            Op::U32Shl,
+            // [element, waddr, index]
+            Op::U32Or,
+        ]);
+        self.store_element();
+    }
+
+    /// Stores a 16-bit integer to a pointer on the stack, followed by the value, which may not be
+    /// 2-byte aligned, and so may span two elements.
+    ///
+    /// The value is stored one byte at a time, starting with the low bits of the value.
+    fn store_unaligned_u16(&mut self) {
+        // [addr, value, addr, value]
+        self.emit_all(&[Op::Dup(1), Op::Dup(1)]);
+        self.emit_native_ptr();
+        // [addr, value]
+        self.store_small(&Type::U8, None);
+        // [addr + 1, value >> 8]
+        self.emit_all(&[Op::U32WrappingAddImm(1), Op::Swap(1), Op::U32ShrImm(8), Op::Swap(1)]);
+        self.emit_native_ptr();
+        self.store_small(&Type::U8, None);
     }
 
     fn store_array(&mut self, _element_ty: &Type, _ptr: Option<NativePtr>) {
@@ -75,6 +75,18 @@ macro_rules! assert_valid_stack_index {
     };
 }
 
+/// Converts `index`, the position of an operand on the stack, to the index operand of a stack
+/// manipulation instruction such as `dup` or `movup`.
+///
+/// Unlike a cast, this asserts that the operand is directly accessible, rather than truncating
+/// positions which do not fit in a `u8` to a small, but wrong, index.
+#[inline]
+#[track_caller]
+pub(super) fn stack_index(index: usize) -> u8 {
+    assert_valid_stack_index!(index);
+    index as u8
+}
+
 pub mod binary;
 pub mod felt;
 pub mod int128;
@@ -87,11 +99,28 @@ pub mod unary;
 
 use core::ops::{Deref, DerefMut};
 
-use miden_hir::{self as hir, Immediate, Type};
+use miden_hir::{self as hir, Felt, FieldElement, Immediate, StarkField, Type};
+use smallvec::SmallVec;
 
 use super::{Operand, OperandStack};
 use crate::masm::{self as masm, Op};
 
+/// This error is produced when moving, copying or dropping an operand would require access to an
+/// element of the operand stack which is not directly addressable by Miden Assembly, i.e. one
+/// that is deeper than the first 16 elements on the stack.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "invalid stack access: {} would be accessed at position {position} on the operand stack, but \
+     only the first 16 elements on the stack are directly accessible",
+    .value.map(|v| v.to_string()).unwrap_or_else(|| "an operand".to_string())
+)]
+pub struct StackDepthError {
+    /// The IR value being accessed, if the operand corresponds to one
+    pub value: Option<hir::Value>,
+    /// The position on the operand stack, in field elements, of the deepest element accessed
+    pub position: usize,
+}
+
 /// This structure is used to emit the Miden Assembly ops corresponding to an IR instruction.
 ///
 /// When dropped, it ensures that the operand stack is updated to reflect the results of the
@@ -122,6 +151,16 @@ impl<'a> InstOpEmitter<'a> {
         self.emitter.exec(import);
     }
 
+    pub fn exec_with_memory_args(&mut self, callee: hir::FunctionIdent) {
+        let import = self.dfg.get_import(&callee).unwrap();
+        self.emitter.exec_with_memory_args(import);
+    }
+
+    pub fn call(&mut self, callee: hir::FunctionIdent) {
+        let import = self.dfg.get_import(&callee).unwrap();
+        self.emitter.call(import);
+    }
+
     pub fn syscall(&mut self, callee: hir::FunctionIdent) {
         let import = self.dfg.get_import(&callee).unwrap();
         self.emitter.syscall(import);
@@ -237,22 +276,46 @@ impl<'a> OpEmitter<'a> {
 
     /// Push an immediate value on the operand stack
     ///
+    /// Values which fit in a u32 are pushed using the narrowest push instruction which can
+    /// encode them, regardless of their type, e.g. a `u32` of 5 is pushed with `PushU8`.
+    ///
     /// This has no effect on the state of the emulated operand stack
     #[inline]
     pub fn push_immediate(&mut self, imm: Immediate) {
         match imm {
-            Immediate::I1(i) => self.emit(Op::PushU8(i as u8)),
-            Immediate::I8(i) => self.emit(Op::PushU8(i as u8)),
-            Immediate::U8(i) => self.emit(Op::PushU8(i)),
-            Immediate::U16(i) => self.emit(Op::PushU32(i as u32)),
-            Immediate::I16(i) => self.emit(Op::PushU32(i as u16 as u32)),
-            Immediate::U32(i) => self.emit(Op::PushU32(i)),
-            Immediate::I32(i) => self.emit(Op::PushU32(i as u32)),
+            Immediate::I1(i) => self.push_u32(i as u32),
+            Immediate::I8(i) => self.push_u32(i as u8 as u32),
+            Immediate::U8(i) => self.push_u32(i as u32),
+            Immediate::U16(i) => self.push_u32(i as u32),
+            Immediate::I16(i) => self.push_u32(i as u16 as u32),
+            Immediate::U32(i) => self.push_u32(i),
+            Immediate::I32(i) => self.push_i32(i),
             Immediate::U64(i) => self.push_u64(i),
             Immediate::I64(i) => self.push_i64(i),
             Immediate::I128(i) => self.push_i128(i),
-            Immediate::Felt(i) => self.emit(Op::Push(i)),
-            Immediate::F64(_) => unimplemented!("floating-point immediates are not supported"),
+            Immediate::Felt(i) => match u32::try_from(i.as_int()) {
+                Ok(i) => self.push_u32(i),
+                Err(_) => self.emit(Op::Push(i)),
+            },
+            // Miden has no native floating-point support, so we push the IEEE-754 bit pattern
+            // of the value, in the same representation as a u64, for use with soft-float routines
+            Immediate::F64(f) => self.push_u64(f.to_bits()),
+        }
+    }
+
+    /// Push a word-sized constant on the operand stack, i.e. `padw` for the zero word, and two
+    /// pairs of elements otherwise.
+    ///
+    /// The first element of `word` will be on top of the stack. The elements are pushed in pairs,
+    /// rather than as a word literal, as the order in which each pair is pushed is unambiguous.
+    ///
+    /// NOTE: This only emits the instructions, it does not update the emulated stack.
+    pub fn push_word(&mut self, word: [Felt; 4]) {
+        if word.iter().all(|elem| elem == &Felt::ZERO) {
+            self.emit(Op::Padw);
+        } else {
+            let [a, b, c, d] = word;
+            self.emit_all(&[Op::Push2([d, c]), Op::Push2([b, a])]);
         }
     }
 
@@ -418,50 +481,148 @@ impl<'a> OpEmitter<'a> {
     }
 
     /// Remove all but the top `n` values on the operand stack
-    pub fn truncate_stack(&mut self, n: usize) {
+    ///
+    /// Returns an error if any of the values to be removed are not directly accessible.
+    pub fn truncate_stack(&mut self, n: usize) -> Result<(), StackDepthError> {
         let stack_size = self.stack.len();
         let num_to_drop = stack_size - n;
 
         if num_to_drop == 0 {
-            return;
+            return Ok(());
         }
 
         if stack_size == num_to_drop {
             let raw_size = self.stack.raw_len();
             self.stack.dropn(num_to_drop);
             self.emit_n(raw_size / 4, Op::Dropw);
-            self.emit_n(raw_size % 4, Op::Dropw);
-            return;
+            self.emit_n(raw_size % 4, Op::Drop);
+            return Ok(());
         }
 
-        // This is the common case, and can be handled simply
-        // by moving the value to the bottom of the stack and
-        // dropping everything in-between
-        if n == 1 {
-            match stack_size {
-                2 => {
-                    self.swap(1);
-                    self.drop();
-                }
-                n => {
-                    self.movdn(n as u8 - 1);
-                    self.dropn(n - 1);
+        // A single value can simply be swapped with the value below it
+        if n == 1 && stack_size == 2 {
+            self.swap(1);
+            self.drop();
+            return Ok(());
+        }
+
+        // Move the values to keep below as many of the values to drop as are within reach, and
+        // drop those in one go, repeating until only the values to keep remain. Unless the values
+        // to keep are large, this is much cheaper than dropping each value individually.
+        let individual_cost = self
+            .stack
+            .iter()
+            .rev()
+            .skip(n)
+            .map(|o| o.size() + if o.size() == 4 { 1 } else { o.size() })
+            .sum::<usize>();
+        if let Some((chunks, cost)) = self.truncate_stack_chunks(n) {
+            if cost <= individual_cost {
+                for chunk in chunks {
+                    for _ in 0..n {
+                        self.movdn((n - 1 + chunk) as u8);
+                    }
+                    self.dropn(chunk);
                 }
+                return Ok(());
             }
-            return;
         }
 
-        // TODO: This is a very neive algorithm for clearing
-        // the stack of all but the top `n` values, we should
-        // come up with a smarter/more efficient method
         for offset in 0..num_to_drop {
             let index = stack_size - 1 - offset;
-            self.drop_operand_at_position(index);
+            self.drop_operand_at_position(index)?;
+        }
+
+        Ok(())
+    }
+
+    /// Plan the removal of all but the top `n` values on the operand stack, by moving them below
+    /// chunks of the values to be removed, and dropping each chunk, see [Self::truncate_stack].
+    ///
+    /// Returns the number of values in each chunk, and the number of instructions required, or
+    /// `None` if the values to keep cannot be moved below the values to be removed.
+    fn truncate_stack_chunks(&self, n: usize) -> Option<(SmallVec<[usize; 4]>, usize)> {
+        let keep_size = self.stack.iter().rev().take(n).map(|o| o.size()).sum::<usize>();
+        // Every element of a chunk must be within reach when moving the values to keep below it
+        let capacity = 16usize.checked_sub(keep_size).filter(|capacity| *capacity > 0)?;
+
+        let mut remaining = self.stack.iter().rev().skip(n).map(|o| o.size()).sum::<usize>();
+        let mut sizes = self.stack.iter().rev().skip(n).map(|o| o.size()).peekable();
+        let mut chunks = SmallVec::new();
+        let mut cost = 0;
+        while remaining > 0 {
+            // Unless this is the last chunk, prefer a multiple of four elements, which can be
+            // dropped a word at a time
+            let aligned = capacity - capacity % 4;
+            let limit = if remaining > capacity && aligned > 0 {
+                aligned
+            } else {
+                capacity
+            };
+            let mut chunk = 0;
+            let mut chunk_size = 0;
+            while let Some(size) = sizes.next_if(|size| chunk_size + size <= limit) {
+                chunk += 1;
+                chunk_size += size;
+            }
+            if chunk == 0 {
+                return None;
+            }
+            chunks.push(chunk);
+            cost += keep_size + chunk_size / 4 + chunk_size % 4;
+            remaining -= chunk_size;
         }
+
+        Some((chunks, cost))
+    }
+
+    /// Returns an error if the operand at index `n` would have to be accessed at `position` on
+    /// the operand stack, and that position is not directly accessible.
+    fn validate_stack_position(&self, n: usize, position: usize) -> Result<(), StackDepthError> {
+        if position < 16 {
+            return Ok(());
+        }
+        Err(StackDepthError {
+            value: self.stack[n].as_value(),
+            position,
+        })
+    }
+
+    /// Returns an error if any element of the operand at index `n` is not directly accessible
+    fn validate_operand_access(&self, n: usize) -> Result<(), StackDepthError> {
+        self.validate_stack_position(n, self.stack.effective_index_inclusive(n))
+    }
+
+    /// Returns an error if the operand at index `n`, once on top of the stack, cannot be moved
+    /// down to index `m`, i.e. if the last element it would occupy is not directly accessible.
+    ///
+    /// If `is_copy` is true, the operand is assumed to be a copy of the `n`th operand, pushed on
+    /// top of the stack as it is currently, rather than the `n`th operand itself.
+    fn validate_operand_move_down(
+        &self,
+        n: usize,
+        m: usize,
+        is_copy: bool,
+    ) -> Result<(), StackDepthError> {
+        let size = self.stack[n].size();
+        let position = if is_copy || m < n {
+            // The operand is moved down past the first `m` operands on the stack as it is now
+            size + self.stack.iter().rev().take(m).map(|o| o.size()).sum::<usize>() - 1
+        } else {
+            // The operand itself is among those it is moved down past
+            self.stack.effective_index_inclusive(m)
+        };
+        self.validate_stack_position(n, position)
     }
 
     /// Remove the `n`th value from the top of the operand stack
-    pub fn drop_operand_at_position(&mut self, n: usize) {
+    ///
+    /// Returns an error if the operand is not directly accessible, in which case the operand
+    /// stack is left unmodified.
+    pub fn drop_operand_at_position(&mut self, n: usize) -> Result<(), StackDepthError> {
+        if n > 0 {
+            self.validate_operand_access(n)?;
+        }
         match n {
             0 => {
                 self.drop();
@@ -471,10 +632,12 @@ impl<'a> OpEmitter<'a> {
                 self.drop();
             }
             n => {
-                self.movup(n as u8);
+                self.movup(stack_index(n));
                 self.drop();
             }
         }
+
+        Ok(())
     }
 
     /// Copy the `n`th operand on the stack, and make it the `m`th operand on the stack.
@@ -484,35 +647,44 @@ impl<'a> OpEmitter<'a> {
     /// stack, this function may leave it on top of the stack instead, since the order of the
     /// operands is not strict. This can result in fewer stack manipulation instructions in some
     /// scenarios.
+    ///
+    /// Returns an error if the operand, or its destination, is not directly accessible, in which
+    /// case the operand stack is left unmodified.
     pub fn copy_operand_to_position(
         &mut self,
         n: usize,
         m: usize,
         is_commutative_binary_operand: bool,
-    ) {
+    ) -> Result<(), StackDepthError> {
+        self.validate_operand_access(n)?;
+        if m > 1 {
+            self.validate_operand_move_down(n, m, true)?;
+        }
         match (n, m) {
             (0, 0) => {
                 self.dup(0);
             }
             (actual, 0) => {
-                self.dup(actual as u8);
+                self.dup(stack_index(actual));
             }
             (actual, 1) => {
                 // If the dependent is binary+commutative, we can
                 // leave operands in either the 0th or 1st position,
                 // as long as both operands are on top of the stack
                 if !is_commutative_binary_operand {
-                    self.dup(actual as u8);
+                    self.dup(stack_index(actual));
                     self.swap(1);
                 } else {
-                    self.dup(actual as u8);
+                    self.dup(stack_index(actual));
                 }
             }
             (actual, expected) => {
-                self.dup(actual as u8);
-                self.movdn(expected as u8);
+                self.dup(stack_index(actual));
+                self.movdn(stack_index(expected));
             }
         }
+
+        Ok(())
     }
 
     /// Make the `n`th operand on the stack, the `m`th operand on the stack.
@@ -522,12 +694,22 @@ impl<'a> OpEmitter<'a> {
     /// on the stack, this function may leave the operand in it's current position if it is
     /// already one of the first two items on the stack, since the order of the operands is not
     /// strict. This can result in fewer stack manipulation instructions in some scenarios.
+    ///
+    /// Returns an error if the operand, or its destination, is not directly accessible, in which
+    /// case the operand stack is left unmodified.
     pub fn move_operand_to_position(
         &mut self,
         n: usize,
         m: usize,
         is_commutative_binary_operand: bool,
-    ) {
+    ) -> Result<(), StackDepthError> {
+        if n == m {
+            return Ok(());
+        }
+        self.validate_operand_access(n)?;
+        if m > 1 {
+            self.validate_operand_move_down(n, m, false)?;
+        }
         match (n, m) {
             (n, m) if n == m => (),
             (1, 0) | (0, 1) => {
@@ -538,18 +720,23 @@ impl<'a> OpEmitter<'a> {
                     self.swap(1);
                 }
             }
+            (0, expected) => {
+                self.movdn(stack_index(expected));
+            }
             (actual, 0) => {
-                self.movup(actual as u8);
+                self.movup(stack_index(actual));
             }
             (actual, 1) => {
-                self.movup(actual as u8);
+                self.movup(stack_index(actual));
                 self.swap(1);
             }
             (actual, expected) => {
-                self.movup(actual as u8);
-                self.movdn(expected as u8);
+                self.movup(stack_index(actual));
+                self.movdn(stack_index(expected));
             }
         }
+
+        Ok(())
     }
 
     /// Get mutable access to the current block we're emitting to
@@ -576,6 +763,7 @@ impl<'a> OpEmitter<'a> {
 #[cfg(test)]
 mod tests {
     use miden_hir::{AbiParam, Felt, FieldElement, Overflow, Signature};
+    use proptest::prelude::*;
 
     use super::*;
     use crate::{codegen::TypedValue, masm::Function};
@@ -603,8 +791,8 @@ mod tests {
             let block = emitter.current_block();
             let ops = block.ops.as_slice();
             assert_eq!(ops.len(), 5);
-            assert_eq!(ops[0], Op::PushU32(1));
-            assert_eq!(ops[1], Op::PushU32(2));
+            assert_eq!(ops[0], Op::PushU8(1));
+            assert_eq!(ops[1], Op::PushU8(2));
             assert_eq!(ops[2], Op::PushU8(3));
             assert_eq!(ops[3], Op::Push2([Felt::new(1), Felt::ZERO]));
             assert_eq!(ops[4], Op::Push2([Felt::new(3), Felt::new(u32::MAX as u64)]));
@@ -745,7 +933,7 @@ mod tests {
             assert_eq!(ops[18], Op::Drop); // [three, five_c, five_d, five_a, five_b]
         }
 
-        emitter.copy_operand_to_position(5, 3, false);
+        emitter.copy_operand_to_position(5, 3, false).unwrap();
         assert_eq!(emitter.stack()[0], three);
         assert_eq!(emitter.stack()[1], five);
         assert_eq!(emitter.stack()[2], five);
@@ -753,14 +941,14 @@ mod tests {
         assert_eq!(emitter.stack()[4], three);
         assert_eq!(emitter.stack()[5], two);
 
-        emitter.drop_operand_at_position(4);
+        emitter.drop_operand_at_position(4).unwrap();
         assert_eq!(emitter.stack()[0], three);
         assert_eq!(emitter.stack()[1], five);
         assert_eq!(emitter.stack()[2], five);
         assert_eq!(emitter.stack()[3], one);
         assert_eq!(emitter.stack()[4], two);
 
-        emitter.move_operand_to_position(4, 2, false);
+        emitter.move_operand_to_position(4, 2, false).unwrap();
         assert_eq!(emitter.stack()[0], three);
         assert_eq!(emitter.stack()[1], five);
         assert_eq!(emitter.stack()[2], two);
@@ -768,6 +956,112 @@ mod tests {
         assert_eq!(emitter.stack()[4], one);
     }
 
+    #[test]
+    fn op_emitter_push_narrowest_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        let small = Immediate::U32(5);
+        let medium = Immediate::U32(300);
+        let large = Immediate::U32(70000);
+        let negative = Immediate::I32(-1);
+        let small_felt = Immediate::Felt(Felt::new(5));
+        let large_felt = Immediate::Felt(Felt::new(2u64.pow(32)));
+
+        emitter.literal(small);
+        emitter.literal(medium);
+        emitter.literal(large);
+        emitter.literal(negative);
+        emitter.literal(small_felt);
+        emitter.literal(large_felt);
+
+        {
+            let block = emitter.current_block();
+            let ops = block.ops.as_slice();
+            assert_eq!(ops.len(), 6);
+            assert_eq!(ops[0], Op::PushU8(5));
+            assert_eq!(ops[1], Op::PushU16(300));
+            assert_eq!(ops[2], Op::PushU32(70000));
+            assert_eq!(ops[3], Op::PushU32(u32::MAX));
+            assert_eq!(ops[4], Op::PushU8(5));
+            assert_eq!(ops[5], Op::Push(Felt::new(2u64.pow(32))));
+        }
+
+        // The operands retain their original types, regardless of the instruction used
+        assert_eq!(emitter.stack()[0], large_felt);
+        assert_eq!(emitter.stack()[1], small_felt);
+        assert_eq!(emitter.stack()[2], negative);
+        assert_eq!(emitter.stack()[3], large);
+        assert_eq!(emitter.stack()[4], medium);
+        assert_eq!(emitter.stack()[5], small);
+        assert_eq!(emitter.stack()[5].ty(), Type::U32);
+    }
+
+    #[test]
+    fn op_emitter_truncate_stack_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        // 1 + 2 + 4 = 7 elements, i.e. one word and three elements
+        emitter.literal(Immediate::U32(1));
+        emitter.literal(Immediate::U64(2));
+        emitter.literal(Immediate::I128(3));
+        assert_eq!(emitter.stack_len(), 3);
+
+        // Truncating to zero results empties the stack entirely, as when returning from a
+        // function with no results
+        emitter.truncate_stack(0).unwrap();
+        assert_eq!(emitter.stack_len(), 0);
+
+        {
+            let block = emitter.current_block();
+            let ops = block.ops.as_slice();
+            assert_eq!(ops.len(), 7);
+            assert_eq!(&ops[3..], &[Op::Dropw, Op::Drop, Op::Drop, Op::Drop]);
+        }
+    }
+
+    #[test]
+    fn op_emitter_truncate_stack_to_one_test() {
+        // The number of instructions needed to remove all but the top value from a stack of
+        // single-element values of the given size
+        let truncate = |stack_size: u32| {
+            let mut function = setup();
+            let entry = function.body.id();
+            let mut stack = OperandStack::default();
+            let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+            for i in 0..stack_size {
+                emitter.literal(Immediate::U32(i));
+            }
+            let top = emitter.stack()[0].clone();
+
+            emitter.truncate_stack(1).unwrap();
+            assert_eq!(emitter.stack_len(), 1);
+            assert_eq!(emitter.stack()[0], top);
+
+            let block = emitter.current_block();
+            block.ops[(stack_size as usize)..].to_vec()
+        };
+
+        // movdn.2 drop drop
+        assert_eq!(truncate(3), &[Op::Movdn(2), Op::Drop, Op::Drop]);
+        // movdn.6 dropw drop drop
+        assert_eq!(truncate(7).len(), 4);
+        // movdn.11 dropw dropw drop drop drop
+        assert_eq!(truncate(12).len(), 6);
+        assert_eq!(truncate(12)[0], Op::Movdn(11));
+        // Values beyond the reach of a single movdn are dropped in chunks, i.e. movdn.12 dropw
+        // dropw dropw, followed by movdn.7 dropw drop drop drop
+        let ops = truncate(20);
+        assert_eq!(ops.len(), 9);
+        assert_eq!(ops[0], Op::Movdn(12));
+        assert_eq!(ops[4], Op::Movdn(7));
+    }
+
     #[test]
     fn op_emitter_copy_operand_to_position_test() {
         let mut function = setup();
@@ -816,7 +1110,7 @@ mod tests {
 
         assert_eq!(emitter.stack()[4], v10);
         assert_eq!(emitter.stack()[2], v15);
-        emitter.copy_operand_to_position(4, 2, false);
+        emitter.copy_operand_to_position(4, 2, false).unwrap();
         assert_eq!(emitter.stack()[5], v10);
         assert_eq!(emitter.stack()[2], v10);
 
@@ -829,6 +1123,97 @@ mod tests {
         }
     }
 
+    #[test]
+    fn op_emitter_stack_depth_overflow_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        // 9 operands, but 18 elements, so the deepest operand is out of reach, even though
+        // its index is not
+        let values = (0..9).map(hir::Value::from_u32).collect::<Vec<_>>();
+        for value in values.iter().copied() {
+            emitter.push(TypedValue {
+                value,
+                ty: Type::U64,
+            });
+        }
+        assert_eq!(emitter.stack().raw_len(), 18);
+        let v0 = values[0];
+        let v8 = values[8];
+
+        let expected = StackDepthError {
+            value: Some(v0),
+            position: 17,
+        };
+        assert_eq!(emitter.move_operand_to_position(8, 0, false), Err(expected.clone()));
+        assert_eq!(emitter.copy_operand_to_position(8, 0, false), Err(expected.clone()));
+        assert_eq!(emitter.drop_operand_at_position(8), Err(expected));
+
+        // Moving the top operand to the bottom of the stack would place it out of reach
+        assert_eq!(
+            emitter.move_operand_to_position(0, 8, false),
+            Err(StackDepthError {
+                value: Some(v8),
+                position: 17,
+            })
+        );
+        assert_eq!(
+            emitter.copy_operand_to_position(0, 8, false),
+            Err(StackDepthError {
+                value: Some(v8),
+                position: 17,
+            })
+        );
+
+        // Nothing was emitted, and the stack is unchanged
+        assert!(emitter.current_block().ops.is_empty());
+        assert_eq!(emitter.stack_len(), 9);
+        assert_eq!(emitter.stack()[8], v0);
+        assert_eq!(emitter.stack()[0], v8);
+
+        // The operands which are in reach can still be manipulated
+        emitter.move_operand_to_position(7, 0, false).unwrap();
+        assert_eq!(emitter.stack()[0], values[1]);
+        {
+            let block = emitter.current_block();
+            assert_eq!(block.ops.as_slice(), &[Op::Movup(15), Op::Movup(15)]);
+        }
+    }
+
+    #[test]
+    fn op_emitter_stack_index_overflow_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        // A position of 300 would be truncated to 44 by a cast to `u8`
+        let values = (0..301).map(hir::Value::from_u32).collect::<Vec<_>>();
+        for value in values.iter().copied() {
+            emitter.push(TypedValue {
+                value,
+                ty: Type::U32,
+            });
+        }
+
+        let expected = StackDepthError {
+            value: Some(values[0]),
+            position: 300,
+        };
+        assert_eq!(emitter.move_operand_to_position(300, 0, false), Err(expected.clone()));
+        assert_eq!(emitter.copy_operand_to_position(300, 1, false), Err(expected.clone()));
+        assert_eq!(emitter.drop_operand_at_position(300), Err(expected));
+        assert!(emitter.current_block().ops.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "directly accessible, got 300")]
+    fn stack_index_overflow_test() {
+        stack_index(300);
+    }
+
     #[test]
     fn op_emitter_u32_add_test() {
         let mut function = setup();
@@ -1846,6 +2231,55 @@ mod tests {
         assert_eq!(emitter.stack()[0], return_ty);
     }
 
+    #[test]
+    fn op_emitter_push_word_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        let zero = Immediate::I128(0);
+        let word = Immediate::I128(i128::MAX);
+
+        emitter.literal(zero);
+        emitter.literal(word);
+        assert_eq!(emitter.stack_len(), 2);
+        assert_eq!(emitter.stack()[0], word);
+        assert_eq!(emitter.stack()[1], zero);
+
+        {
+            let block = emitter.current_block();
+            let ops = block.ops.as_slice();
+            assert_eq!(ops.len(), 3);
+            assert_eq!(ops[0], Op::Padw);
+            assert_eq!(ops[1], Op::Push2([Felt::new(u32::MAX as u64), Felt::new(i32::MAX as u64)]));
+            assert_eq!(ops[2], Op::Push2([Felt::new(u32::MAX as u64), Felt::new(u32::MAX as u64)]));
+        }
+    }
+
+    #[test]
+    fn op_emitter_f64_literal_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        let one = Immediate::F64(1.0);
+
+        emitter.literal(one);
+        assert_eq!(emitter.stack_len(), 1);
+        assert_eq!(emitter.stack()[0], one);
+        assert_eq!(emitter.stack()[0], Type::F64);
+
+        {
+            let block = emitter.current_block();
+            let ops = block.ops.as_slice();
+            assert_eq!(ops.len(), 1);
+            // 1.0 is 0x3ff0000000000000
+            assert_eq!(ops[0], Op::Push2([Felt::new(0x3ff00000), Felt::ZERO]));
+        }
+    }
+
     #[test]
     fn op_emitter_u32_load_test() {
         let mut function = setup();
@@ -1868,6 +2302,278 @@ mod tests {
         assert_eq!(emitter.stack()[1], Type::U32);
     }
 
+    #[test]
+    fn op_emitter_struct_load_imm_test() {
+        use miden_hir::StructType;
+
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        // A 4-element struct at a word-aligned address is loaded with a single word load
+        let quad = Type::Struct(StructType::new([Type::U32, Type::U32, Type::U32, Type::U32]));
+        assert_eq!(quad.size_in_felts(), 4);
+        emitter.load_imm(128, quad.clone());
+        assert_eq!(emitter.stack_len(), 1);
+        assert_eq!(emitter.stack().effective_index_inclusive(0), 3);
+        assert_eq!(emitter.stack()[0], quad);
+
+        {
+            let block = emitter.current_block();
+            let ops = block.ops.as_slice();
+            assert_eq!(ops.len(), 2);
+            assert_eq!(ops[0], Op::Padw);
+            assert_eq!(ops[1], Op::MemLoadwImm(8));
+        }
+
+        // A 2-element struct at an element-aligned address is loaded element-by-element,
+        // with the last element loaded first
+        let pair = Type::Struct(StructType::new([Type::U32, Type::U32]));
+        emitter.load_imm(128 + 12, pair.clone());
+        assert_eq!(emitter.stack_len(), 2);
+        assert_eq!(emitter.stack()[0], pair);
+        assert_eq!(emitter.stack()[1], quad);
+
+        {
+            let block = emitter.current_block();
+            let ops = block.ops.as_slice();
+            assert_eq!(ops.len(), 8);
+            assert_eq!(ops[2], Op::MemLoadImm(9));
+            assert_eq!(ops[3], Op::Padw);
+            assert_eq!(ops[4], Op::MemLoadwImm(8));
+            assert_eq!(ops[5], Op::Drop);
+            assert_eq!(ops[6], Op::Drop);
+            assert_eq!(ops[7], Op::Drop);
+        }
+    }
+
+    #[test]
+    fn op_emitter_felt_load_imm_index_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        // The second element of a word is loaded by loading the word, and dropping the other
+        // elements, without disturbing anything beneath the word on the operand stack
+        emitter.load_imm(128 + 4, Type::Felt);
+        assert_eq!(emitter.stack_len(), 1);
+        assert_eq!(emitter.stack()[0], Type::Felt);
+
+        let block = emitter.current_block();
+        assert_eq!(
+            block.ops.as_slice(),
+            &[Op::Padw, Op::MemLoadwImm(8), Op::Drop, Op::Movdn(2), Op::Drop, Op::Drop]
+        );
+    }
+
+    #[test]
+    fn op_emitter_mem_grow_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        emitter.literal(Immediate::U32(1));
+        emitter.mem_grow();
+        assert_eq!(emitter.stack_len(), 1);
+        assert_eq!(emitter.stack()[0], Type::I32);
+
+        {
+            let block = emitter.current_block();
+            let ops = block.ops.as_slice();
+            assert_eq!(ops.len(), 2);
+            assert_eq!(ops[1], Op::Exec("intrinsics::mem::memory_grow".parse().unwrap()));
+        }
+    }
+
+    /// A concrete operand stack, with the top of the stack first, in which each element is
+    /// labeled with the value it belongs to, and its index among the elements of that value
+    type ConcreteStack = Vec<(hir::Value, usize)>;
+
+    /// Returns the concrete elements of `stack`, as [OperandStack] claims they are laid out
+    fn concrete_stack(stack: &OperandStack) -> ConcreteStack {
+        (0..stack.len())
+            .flat_map(|index| {
+                let operand = &stack[index];
+                let value = operand.as_value().expect("expected only values on the stack");
+                (0..operand.size()).map(move |element| (value, element))
+            })
+            .collect()
+    }
+
+    /// Execute the stack manipulation instructions in `ops` against `stack`
+    fn interpret(ops: &[Op], stack: &mut ConcreteStack) {
+        for op in ops {
+            match *op {
+                Op::Drop => {
+                    stack.remove(0);
+                }
+                Op::Dup(n) => {
+                    let element = stack[n as usize];
+                    stack.insert(0, element);
+                }
+                Op::Swap(n) => stack.swap(0, n as usize),
+                Op::Movup(n) => {
+                    let element = stack.remove(n as usize);
+                    stack.insert(0, element);
+                }
+                Op::Movdn(n) => {
+                    let element = stack.remove(0);
+                    stack.insert(n as usize, element);
+                }
+                ref op => panic!("unexpected instruction: {op:?}"),
+            }
+        }
+    }
+
+    /// Construct an operand stack of distinct values, with the first value on top, where each
+    /// value is a `u64` if the corresponding flag in `wide` is set, and a `u32` otherwise
+    fn operand_stack(wide: &[bool]) -> OperandStack {
+        let mut stack = OperandStack::default();
+        for (id, wide) in wide.iter().copied().enumerate().rev() {
+            stack.push(TypedValue {
+                value: hir::Value::from_u32(id as u32),
+                ty: if wide { Type::U64 } else { Type::U32 },
+            });
+        }
+        stack
+    }
+
+    /// Apply `shuffles` to a stack of values of the given widths using [OpEmitter], checking that
+    /// executing the instructions emitted for each of them has the effect on a concrete stack which
+    /// the emitter claims it has.
+    ///
+    /// Each shuffle is a kind of movement, and the indices of the operands involved, which are
+    /// wrapped to fit the stack at the time the shuffle is applied.
+    fn run_shuffles(
+        wide: Vec<bool>,
+        shuffles: Vec<(u8, usize, usize)>,
+    ) -> Result<(), TestCaseError> {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = operand_stack(&wide);
+        let mut concrete = concrete_stack(&stack);
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+        for (kind, n, m) in shuffles {
+            let len = emitter.stack_len();
+            if len == 0 {
+                break;
+            }
+            let (n, m) = (n % len, m % len);
+            let num_ops = emitter.current_block().ops.len();
+            let result = match kind {
+                0 => emitter.move_operand_to_position(n, m, false),
+                1 => emitter.copy_operand_to_position(n, m, false),
+                _ => emitter.drop_operand_at_position(n),
+            };
+            let ops = emitter.current_block().ops[num_ops..].to_vec();
+            if result.is_err() {
+                // Nothing is emitted for operands out of reach
+                prop_assert!(ops.is_empty());
+                continue;
+            }
+            interpret(&ops, &mut concrete);
+            prop_assert_eq!(
+                &concrete,
+                &concrete_stack(emitter.stack()),
+                "instructions {:?} do not match the operand stack",
+                ops
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Move the first `arity` values of a stack of values of the given widths into place on top of
+    /// the stack, in order, having shuffled the stack according to `permutation`, and check that
+    /// executing the instructions emitted by the operand movement constraint solver has the effect
+    /// on a concrete stack which the emitter claims it has.
+    fn run_solver(
+        wide: Vec<bool>,
+        permutation: Vec<usize>,
+        arity: usize,
+        copies: Vec<bool>,
+    ) -> Result<(), TestCaseError> {
+        use crate::codegen::{
+            opt::{OperandMovementConstraintSolver, SolverError},
+            Constraint,
+        };
+
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let ordered = operand_stack(&wide);
+        for index in permutation.into_iter().rev() {
+            stack.push(ordered[index].clone());
+        }
+        let expected = (0..arity).map(|id| hir::Value::from_u32(id as u32)).collect::<Vec<_>>();
+        let constraints = copies[..arity]
+            .iter()
+            .map(|copy| {
+                if *copy {
+                    Constraint::Copy
+                } else {
+                    Constraint::Move
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut concrete = concrete_stack(&stack);
+        let solver = match OperandMovementConstraintSolver::new(&expected, &constraints, &stack) {
+            Ok(solver) => solver,
+            Err(SolverError::AlreadySolved) => return Ok(()),
+            Err(err) => panic!("invalid solver context: {err:?}"),
+        };
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+        let result = solver.solve_and_apply(&mut emitter);
+        prop_assert!(result.is_ok(), "solver returned error {:?}", result);
+
+        let ops = emitter.current_block().ops.to_vec();
+        interpret(&ops, &mut concrete);
+        prop_assert_eq!(
+            &concrete,
+            &concrete_stack(emitter.stack()),
+            "instructions {:?} do not match the operand stack",
+            ops
+        );
+        for (index, value) in expected.iter().enumerate() {
+            prop_assert_eq!(&emitter.stack()[index], value);
+        }
+
+        Ok(())
+    }
+
+    prop_compose! {
+        fn solver_problem()
+            ((wide, arity) in (1..8usize)
+                .prop_flat_map(|len| (prop::collection::vec(any::<bool>(), len), 1..=len)))
+            (permutation in Just((0..wide.len()).collect::<Vec<_>>()).prop_shuffle(),
+             copies in prop::collection::vec(any::<bool>(), wide.len()),
+             wide in Just(wide),
+             arity in Just(arity)) -> (Vec<bool>, Vec<usize>, usize, Vec<bool>) {
+            (wide, permutation, arity, copies)
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(1000))]
+
+        #[test]
+        fn op_emitter_shuffle_equivalence(
+            wide in prop::collection::vec(any::<bool>(), 1..=8),
+            shuffles in prop::collection::vec((0..3u8, 0..16usize, 0..16usize), 1..32),
+        ) {
+            run_shuffles(wide, shuffles)?;
+        }
+
+        #[test]
+        fn op_emitter_solver_equivalence((wide, permutation, arity, copies) in solver_problem()) {
+            run_solver(wide, permutation, arity, copies)?;
+        }
+    }
+
     #[inline]
     fn setup() -> Function {
         Function::new(
@@ -1,7 +1,7 @@
 use miden_hir::{self as hir, ArgumentExtension, ArgumentPurpose, Felt, Immediate, Type};
 
 use super::{int64, OpEmitter};
-use crate::masm::Op;
+use crate::masm::{self, Op};
 
 impl<'a> OpEmitter<'a> {
     /// Assert that an integer value on the stack has the value 1
@@ -195,120 +195,174 @@ impl<'a> OpEmitter<'a> {
     ///
     /// A function called using this operation is invoked in the same memory context as the caller.
     pub fn exec(&mut self, callee: &hir::ExternalFunction) {
-        let import = callee;
+        for i in 0..callee.signature.arity() {
+            self.coerce_argument(callee, i);
+            self.stack.drop();
+        }
+
+        for result in callee.signature.results.iter() {
+            self.stack.push(result.ty.clone());
+        }
+
+        self.emit(Op::Exec(callee.id));
+    }
+
+    /// Call the given procedure.
+    ///
+    /// A function called using this operation is invoked in a new memory context, so its arguments
+    /// and results are always passed on the operand stack.
+    pub fn call(&mut self, callee: &hir::ExternalFunction) {
+        for i in 0..callee.signature.arity() {
+            self.coerce_argument(callee, i);
+            self.stack.drop();
+        }
+
+        for result in callee.signature.results.iter() {
+            self.stack.push(result.ty.clone());
+        }
+
+        self.emit(Op::Call(callee.id));
+    }
+
+    /// Execute the given procedure, passing its arguments in memory rather than on the operand
+    /// stack, see [masm::CALL_ARGS_ADDR].
+    ///
+    /// The arguments are consumed from the operand stack, and stored in the argument region,
+    /// one field element per word, before the procedure is executed.
+    ///
+    /// NOTE: The arguments must fit in the argument region, see [masm::fits_call_args_region]
+    pub fn exec_with_memory_args(&mut self, callee: &hir::ExternalFunction) {
+        debug_assert!(
+            masm::fits_call_args_region(&callee.signature),
+            "invalid call to {}: arguments do not fit in the argument region of {} words",
+            callee.id,
+            masm::CALL_ARGS_WORDS
+        );
+        let mut addr = masm::CALL_ARGS_ADDR;
+        for i in 0..callee.signature.arity() {
+            self.coerce_argument(callee, i);
+            let size = self.stack.peek().expect("operand stack is empty").size();
+            for _ in 0..size {
+                self.emit(Op::MemStoreImm(addr));
+                addr += 1;
+            }
+            self.stack.drop();
+        }
+
+        for result in callee.signature.results.iter() {
+            self.stack.push(result.ty.clone());
+        }
+
+        self.emit(Op::Exec(callee.id));
+    }
+
+    /// Validate the argument on top of the operand stack against the parameter at index `i` of the
+    /// signature of `import`, extending it to the parameter type if required by the ABI.
+    ///
+    /// The (possibly extended) argument is left on top of the operand stack.
+    fn coerce_argument(&mut self, import: &hir::ExternalFunction, i: usize) {
         let callee = import.id;
         let signature = &import.signature;
-        for i in 0..signature.arity() {
-            let param = &signature.params[i];
-            let arg = self.stack.pop().expect("operand stack is empty");
-            let ty = arg.ty();
-            // Validate the purpose matches
-            match param.purpose {
-                ArgumentPurpose::StructReturn => {
-                    assert_eq!(
-                        i, 0,
-                        "invalid function signature: sret parameters must be the first parameter, \
-                         and only one sret parameter is allowed"
-                    );
-                    assert_eq!(
-                        signature.results.len(),
-                        0,
-                        "invalid function signature: a function with sret parameters cannot also \
-                         have results"
-                    );
-                    assert!(
-                        ty.is_pointer(),
-                        "invalid exec to {callee}: invalid argument for sret parameter, expected \
-                         {}, got {ty}",
-                        &param.ty
-                    );
-                }
-                ArgumentPurpose::Default => (),
+        let param = &signature.params[i];
+        let arg = self.stack.pop().expect("operand stack is empty");
+        let ty = arg.ty();
+        // Validate the purpose matches
+        match param.purpose {
+            ArgumentPurpose::StructReturn => {
+                assert_eq!(
+                    i, 0,
+                    "invalid function signature: sret parameters must be the first parameter, and \
+                     only one sret parameter is allowed"
+                );
+                assert_eq!(
+                    signature.results.len(),
+                    0,
+                    "invalid function signature: a function with sret parameters cannot also have \
+                     results"
+                );
+                assert!(
+                    ty.is_pointer(),
+                    "invalid exec to {callee}: invalid argument for sret parameter, expected {}, \
+                     got {ty}",
+                    &param.ty
+                );
             }
-            // Validate that the argument type is valid for the parameter ABI
-            match param.extension {
-                // Types must match exactly
-                ArgumentExtension::None => {
-                    assert_eq!(
-                        ty, param.ty,
-                        "invalid call to {callee}: invalid argument type for parameter at index \
-                         {i}"
-                    );
-                }
-                // Caller can provide a smaller type which will be zero-extended to the expected
-                // type
-                //
-                // However, the argument must be an unsigned integer, and of smaller or equal size
-                // in order for the types to differ
-                ArgumentExtension::Zext if ty != param.ty => {
-                    assert!(
-                        param.ty.is_unsigned_integer(),
-                        "invalid function signature: zero-extension is only valid for unsigned \
-                         integer types"
-                    );
+            ArgumentPurpose::Default => (),
+        }
+        // Validate that the argument type is valid for the parameter ABI
+        match param.extension {
+            // Types must match exactly
+            ArgumentExtension::None => {
+                assert_eq!(
+                    ty, param.ty,
+                    "invalid call to {callee}: invalid argument type for parameter at index {i}"
+                );
+                self.stack.push(arg);
+            }
+            // Caller can provide a smaller type which will be zero-extended to the expected
+            // type
+            //
+            // However, the argument must be an unsigned integer, and of smaller or equal size
+            // in order for the types to differ
+            ArgumentExtension::Zext if ty != param.ty => {
+                assert!(
+                    param.ty.is_unsigned_integer(),
+                    "invalid function signature: zero-extension is only valid for unsigned \
+                     integer types"
+                );
+                assert!(
+                    ty.is_unsigned_integer(),
+                    "invalid call to {callee}: invalid argument type for parameter at index {i}, \
+                     expected unsigned integer type, got {ty}"
+                );
+                let expected_size = param.ty.size_in_bits();
+                let provided_size = param.ty.size_in_bits();
+                assert!(
+                    provided_size <= expected_size,
+                    "invalid call to {callee}: invalid argument type for parameter at index {i}, \
+                     expected integer width to be <= {expected_size} bits"
+                );
+                // Zero-extend this argument
+                self.stack.push(arg);
+                self.zext(&param.ty);
+            }
+            // Caller can provide a smaller type which will be sign-extended to the expected
+            // type
+            //
+            // However, the argument must be an integer which can fit in the range of the
+            // expected type
+            ArgumentExtension::Sext if ty != param.ty => {
+                assert!(
+                    param.ty.is_signed_integer(),
+                    "invalid function signature: sign-extension is only valid for signed integer \
+                     types"
+                );
+                assert!(
+                    ty.is_integer(),
+                    "invalid call to {callee}: invalid argument type for parameter at index {i}, \
+                     expected integer type, got {ty}"
+                );
+                let expected_size = param.ty.size_in_bits();
+                let provided_size = param.ty.size_in_bits();
+                if ty.is_unsigned_integer() {
                     assert!(
-                        ty.is_unsigned_integer(),
+                        provided_size < expected_size,
                         "invalid call to {callee}: invalid argument type for parameter at index \
-                         {i}, expected unsigned integer type, got {ty}"
+                         {i}, expected unsigned integer width to be < {expected_size} bits"
                     );
-                    let expected_size = param.ty.size_in_bits();
-                    let provided_size = param.ty.size_in_bits();
+                } else {
                     assert!(
                         provided_size <= expected_size,
                         "invalid call to {callee}: invalid argument type for parameter at index \
                          {i}, expected integer width to be <= {expected_size} bits"
                     );
-                    // Zero-extend this argument
-                    self.stack.push(arg);
-                    self.zext(&param.ty);
-                    self.stack.drop();
-                }
-                // Caller can provide a smaller type which will be sign-extended to the expected
-                // type
-                //
-                // However, the argument must be an integer which can fit in the range of the
-                // expected type
-                ArgumentExtension::Sext if ty != param.ty => {
-                    assert!(
-                        param.ty.is_signed_integer(),
-                        "invalid function signature: sign-extension is only valid for signed \
-                         integer types"
-                    );
-                    assert!(
-                        ty.is_integer(),
-                        "invalid call to {callee}: invalid argument type for parameter at index \
-                         {i}, expected integer type, got {ty}"
-                    );
-                    let expected_size = param.ty.size_in_bits();
-                    let provided_size = param.ty.size_in_bits();
-                    if ty.is_unsigned_integer() {
-                        assert!(
-                            provided_size < expected_size,
-                            "invalid call to {callee}: invalid argument type for parameter at \
-                             index {i}, expected unsigned integer width to be < {expected_size} \
-                             bits"
-                        );
-                    } else {
-                        assert!(
-                            provided_size <= expected_size,
-                            "invalid call to {callee}: invalid argument type for parameter at \
-                             index {i}, expected integer width to be <= {expected_size} bits"
-                        );
-                    }
-                    // Push the operand back on the stack for `sext`
-                    self.stack.push(arg);
-                    self.sext(&param.ty);
-                    self.stack.drop();
                 }
-                ArgumentExtension::Zext | ArgumentExtension::Sext => (),
+                // Push the operand back on the stack for `sext`
+                self.stack.push(arg);
+                self.sext(&param.ty);
             }
+            ArgumentExtension::Zext | ArgumentExtension::Sext => self.stack.push(arg),
         }
-
-        for result in signature.results.iter() {
-            self.stack.push(result.ty.clone());
-        }
-
-        self.emit(Op::Exec(callee));
     }
 
     /// Execute the given procedure as a syscall.
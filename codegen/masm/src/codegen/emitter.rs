@@ -8,13 +8,33 @@ use miden_hir_analysis::{
 use smallvec::SmallVec;
 
 use super::{
-    emit::{InstOpEmitter, OpEmitter},
-    opt::{OperandMovementConstraintSolver, SolverError},
+    balance::LoopStackBalance,
+    emit::{stack_index, InstOpEmitter, OpEmitter, StackDepthError},
+    opt::{outline::LoopHeaderCopies, OperandMovementConstraintSolver, SolverError},
     scheduler::{BlockInfo, InstInfo, Schedule, ScheduleOp},
+    verify::{DivergentJoinError, JoinPointVerifier},
     Constraint, OperandStack,
 };
 use crate::masm::{self, Op};
 
+/// This error type is produced by [FunctionEmitter] when it is unable to emit valid code
+#[derive(Debug, thiserror::Error)]
+pub enum EmitError {
+    /// An operand had to be accessed deeper on the operand stack than is possible
+    #[error(transparent)]
+    StackDepth(#[from] StackDepthError),
+    /// Two paths to the same join point disagree on the state of the operand stack
+    #[error(transparent)]
+    DivergentJoin(#[from] DivergentJoinError),
+    /// A block other than a loop header was reached more than once, which means the control flow
+    /// graph was not treeified prior to code generation
+    #[error(
+        "unexpected cycle at {block}: only loop headers may be reached more than once, is the \
+         control flow graph treeified?"
+    )]
+    UnexpectedCycle { block: hir::Block },
+}
+
 pub struct FunctionEmitter<'a> {
     f: &'a hir::Function,
     f_prime: &'a mut masm::Function,
@@ -23,6 +43,36 @@ pub struct FunctionEmitter<'a> {
     liveness: &'a LivenessAnalysis,
     globals: &'a GlobalVariableLayout,
     visited: SecondaryMap<hir::Block, bool>,
+    loop_balance: LoopStackBalance,
+    join_points: Option<JoinPointVerifier>,
+    spill_threshold: Option<usize>,
+    annotate_blocks: bool,
+    memory_call_args: Option<&'a dyn Fn(hir::FunctionIdent) -> bool>,
+    strict_returns: bool,
+    observer: Option<&'a mut dyn SchedulerObserver>,
+    loop_headers: Option<&'a mut LoopHeaderCopies>,
+}
+
+/// A [SchedulerObserver] receives statistics about the code emitted by a [FunctionEmitter], see
+/// [FunctionEmitter::with_observer].
+///
+/// This is intended for profiling the quality of instruction schedules, e.g. to quantify how a
+/// change to the scheduler affects the amount of stack manipulation needed to execute them. All
+/// callbacks do nothing by default.
+pub trait SchedulerObserver {
+    /// Called before emitting code for `block`, with the number of nodes in its tree graph
+    ///
+    /// Loop headers are emitted again for each loopback edge, so this may be called more than once
+    /// for the same block.
+    fn enter_block(&mut self, _block: hir::Block, _treegraph_nodes: usize) {}
+    /// Called after emitting code for `block`, with the number of instructions emitted
+    fn exit_block(&mut self, _block: hir::Block, _ops: usize) {}
+    /// Called for each operand of an instruction, or argument of a successor block, moved into
+    /// place on the operand stack, according to `constraint`
+    fn operand(&mut self, _value: hir::Value, _constraint: Constraint) {}
+    /// Called with the depth of the operand stack, in field elements, after each operation in the
+    /// schedule of a block
+    fn stack_depth(&mut self, _depth: usize) {}
 }
 
 struct BlockEmitter<'b, 'f: 'b> {
@@ -103,10 +153,93 @@ impl<'a> FunctionEmitter<'a> {
             liveness,
             globals,
             visited: SecondaryMap::new(),
+            loop_balance: LoopStackBalance::default(),
+            join_points: cfg!(debug_assertions).then(JoinPointVerifier::default),
+            spill_threshold: None,
+            annotate_blocks: false,
+            memory_call_args: None,
+            strict_returns: false,
+            observer: None,
+            loop_headers: None,
+        }
+    }
+
+    /// Spill values to procedure locals whenever the operand stack grows deeper than
+    /// `threshold` field elements, rather than keeping them out of reach of the stack
+    /// manipulation instructions.
+    ///
+    /// NOTE: This function will panic if `threshold` is greater than 16
+    pub fn with_spill_threshold(mut self, threshold: Option<usize>) -> Self {
+        if let Some(threshold) = threshold {
+            assert!(
+                threshold <= 16,
+                "invalid spill threshold: only the first 16 elements on the stack are directly \
+                 accessible, got {threshold}"
+            );
         }
+        self.spill_threshold = threshold;
+        self
     }
 
-    pub fn emit(mut self, schedule: Schedule, stack: OperandStack) {
+    /// Record the block of the source function each block of code is emitted from, see
+    /// [masm::Block::origin]
+    pub fn with_block_annotations(mut self, annotate_blocks: bool) -> Self {
+        self.annotate_blocks = annotate_blocks;
+        self
+    }
+
+    /// Pass the arguments of calls in memory, rather than on the operand stack, see
+    /// [masm::CALL_ARGS_ADDR], to callees for which `is_defined` returns true, i.e. those defined
+    /// in the program being compiled, as opposed to those provided at runtime.
+    pub fn with_memory_call_args(
+        mut self,
+        is_defined: &'a dyn Fn(hir::FunctionIdent) -> bool,
+    ) -> Self {
+        self.memory_call_args = Some(is_defined);
+        self
+    }
+
+    /// Panic if a dead value other than the results is left on the operand stack on return,
+    /// rather than dropping it, as it should have been dropped after its last use
+    pub fn with_strict_returns(mut self, strict_returns: bool) -> Self {
+        self.strict_returns = strict_returns;
+        self
+    }
+
+    /// Report statistics about the emitted code to `observer`
+    ///
+    /// Without an observer, no statistics are gathered at all.
+    pub fn with_observer(mut self, observer: &'a mut dyn SchedulerObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Record the code emitted for each copy of a loop header in `copies`, so that it can be
+    /// factored out of the function afterwards, see [LoopHeaderCopies]
+    pub fn with_loop_header_copies(mut self, copies: &'a mut LoopHeaderCopies) -> Self {
+        self.loop_headers = Some(copies);
+        self
+    }
+
+    /// Emit the function body according to `schedule`, starting with `stack` as the state of
+    /// the operand stack on entry.
+    ///
+    /// Returns the [LoopStackBalance] analysis computed during emission, which can be used to
+    /// report any loops which do not preserve the depth of the operand stack.
+    ///
+    /// Returns an error if an operand had to be accessed deeper on the operand stack than is
+    /// possible with the stack manipulation instructions, in which case emission is abandoned.
+    ///
+    /// When debug assertions are enabled, this also verifies that every path reaching a join
+    /// point agrees on the state of the operand stack, returning an error if any do not.
+    ///
+    /// The control flow graph of the function is expected to have been treeified, if a block other
+    /// than a loop header is reached more than once, an error is returned.
+    pub fn emit(
+        mut self,
+        schedule: Schedule,
+        stack: OperandStack,
+    ) -> Result<LoopStackBalance, EmitError> {
         let mut tasks = Tasks::from_iter([Task::Block {
             block: self.f.dfg.entry_block(),
             controlling_loop: None,
@@ -115,52 +248,90 @@ impl<'a> FunctionEmitter<'a> {
         while let Some(task) = tasks.pop() {
             match task {
                 Task::Block {
-                    block: block_id,
+                    block,
                     controlling_loop,
                     stack,
                 } => {
-                    let block_info = schedule.block_info(block_id);
-                    let target = block_info.target;
-                    let block_schedule = schedule.get(block_id);
-                    let visited = core::mem::replace(&mut self.visited[block_id], true);
-                    let emitter = BlockEmitter {
-                        function: &mut self,
-                        block_infos: &schedule.block_infos,
-                        block_info,
-                        controlling_loop,
-                        target,
-                        stack,
-                        visited,
-                    };
-                    emitter.emit(block_schedule, &mut tasks);
+                    let target = schedule.block_info(block).target;
+                    if self.annotate_blocks {
+                        self.f_prime.body.block_mut(target).origin = Some(block);
+                    }
+                    self.emit_block(&schedule, block, target, controlling_loop, stack, &mut tasks)?;
                 }
                 Task::Inline {
                     target,
-                    block: block_id,
+                    block,
                     controlling_loop,
                     stack,
                 } => {
-                    let block_info = schedule.block_info(block_id);
-                    let block_schedule = schedule.get(block_id);
-                    let visited = core::mem::replace(&mut self.visited[block_id], true);
-                    let emitter = BlockEmitter {
-                        function: &mut self,
-                        block_infos: &schedule.block_infos,
-                        block_info,
-                        controlling_loop,
-                        target,
-                        stack,
-                        visited,
-                    };
-                    emitter.emit(block_schedule, &mut tasks);
+                    self.emit_block(&schedule, block, target, controlling_loop, stack, &mut tasks)?;
                 }
             }
         }
+
+        if let Some(join_points) = self.join_points.as_ref() {
+            join_points.verify()?;
+        }
+
+        Ok(self.loop_balance)
+    }
+
+    /// Emit the code for `block` into `target`, according to `schedule`, starting with `stack` as
+    /// the state of the operand stack on entry.
+    ///
+    /// Any successors of `block` which remain to be emitted are pushed on to `tasks`.
+    fn emit_block(
+        &mut self,
+        schedule: &Schedule,
+        block: hir::Block,
+        target: masm::BlockId,
+        controlling_loop: Option<Loop>,
+        stack: OperandStack,
+        tasks: &mut Tasks,
+    ) -> Result<(), EmitError> {
+        let block_info = schedule.block_info(block);
+        let visited = core::mem::replace(&mut self.visited[block], true);
+        let num_ops = self.observe_enter_block(&block_info, target);
+        let emitter = BlockEmitter {
+            function: self,
+            block_infos: &schedule.block_infos,
+            block_info,
+            controlling_loop,
+            target,
+            stack,
+            visited,
+        };
+        emitter.emit(schedule.get(block), tasks)?;
+        self.observe_exit_block(block, target, num_ops);
+        Ok(())
+    }
+
+    /// Notify the observer, if any, that `block` is about to be emitted into `target`
+    ///
+    /// Returns the number of instructions in `target` prior to emitting the block.
+    fn observe_enter_block(&mut self, block_info: &BlockInfo, target: masm::BlockId) -> usize {
+        let Some(observer) = self.observer.as_deref_mut() else {
+            return 0;
+        };
+        observer.enter_block(block_info.source, block_info.treegraph.iter().count());
+        self.f_prime.block(target).ops.len()
+    }
+
+    /// Notify the observer, if any, that `block` has been emitted into `target`, which contained
+    /// `num_ops` instructions prior to emitting the block
+    fn observe_exit_block(&mut self, block: hir::Block, target: masm::BlockId, num_ops: usize) {
+        if let Some(observer) = self.observer.as_deref_mut() {
+            observer.exit_block(block, self.f_prime.block(target).ops.len() - num_ops);
+        }
     }
 }
 
 impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
-    pub fn emit(mut self, block_schedule: &[ScheduleOp], tasks: &mut Tasks) {
+    pub fn emit(
+        mut self,
+        block_schedule: &[ScheduleOp],
+        tasks: &mut Tasks,
+    ) -> Result<(), EmitError> {
         // Before we emit any scheduling operations, compare the current stack
         // against the set of live-in values expected by this block. If there are
         // any values on the stack which are not live-in, then they should be dropped
@@ -175,41 +346,92 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         // up. This causes issues with operand stack coherence in loops. We can't avoid
         // making the copy in the original block, instead responsibility for cleaning
         // up these unused values is pushed into the successor on entry.
-        self.drop_unused_operands();
+        self.drop_unused_operands()?;
+
+        // The code of a loop header is emitted again for each loopback edge, so keep track of where
+        // each copy starts, if requested
+        let record_header =
+            self.block_info.is_loop_header() && self.function.loop_headers.is_some();
+        let header_start =
+            record_header.then(|| self.function.f_prime.block(self.target).ops.len());
+
+        // Keep track of the operand stack depth on entry to loop headers, so that we can verify
+        // that all loopback edges leave the stack balanced
+        if self.block_info.is_loop_header() {
+            self.function.loop_balance.visit(self.block_info.source, &self.stack);
+            if let Some(join_points) = self.function.join_points.as_mut() {
+                join_points.visit(self.block_info.source, &self.stack);
+            }
+        }
 
         // Continue normally, by emitting the contents of the block based on the given schedule
         for op in block_schedule.iter() {
             match op {
                 ScheduleOp::Init(_) | ScheduleOp::Enter(_) | ScheduleOp::Exit => continue,
-                ScheduleOp::Inst(inst_info) => self.emit_inst(inst_info, tasks),
+                ScheduleOp::Inst(inst_info) => {
+                    let is_terminator =
+                        self.function.f.dfg.inst(inst_info.inst).opcode().is_terminator();
+                    if let (true, Some(start)) = (is_terminator, header_start) {
+                        self.record_loop_header_copy(start);
+                    }
+                    self.emit_inst(inst_info, tasks)?;
+                    if !is_terminator {
+                        self.spill_excess_operands();
+                    }
+                }
                 ScheduleOp::Drop(value) => {
+                    // A spilled value has nothing on the operand stack to drop
+                    if self.stack.find(value).is_none() && self.stack.discard_spill(value) {
+                        continue;
+                    }
                     let mut emitter = self.emitter();
-                    let pos = emitter
-                        .stack()
-                        .find(value)
-                        .expect("could not find value on the operand stack");
-                    emitter.drop_operand_at_position(pos);
+                    let pos = emitter.stack().find(value).unwrap_or_else(|| {
+                        panic!(
+                            "could not find {value} on the operand stack:\n{}",
+                            emitter.stack().describe()
+                        )
+                    });
+                    emitter.drop_operand_at_position(pos)?;
                 }
             }
+            if let Some(observer) = self.function.observer.as_deref_mut() {
+                observer.stack_depth(self.stack.raw_len());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record the code emitted for the current loop header since `start`, up to its terminator
+    fn record_loop_header_copy(&mut self, start: usize) {
+        let end = self.function.f_prime.block(self.target).ops.len();
+        if let Some(copies) = self.function.loop_headers.as_deref_mut() {
+            copies.insert(self.block_info.source, self.target, start..end);
         }
     }
 
-    fn emit_inst(&mut self, inst_info: &InstInfo, tasks: &mut Tasks) {
+    fn emit_inst(&mut self, inst_info: &InstInfo, tasks: &mut Tasks) -> Result<(), EmitError> {
         use miden_hir::Instruction;
 
         // Move instruction operands into place, minimizing unnecessary stack manipulation ops
         //
         // NOTE: This does not include block arguments for control flow instructions, those are
         // handled separately within the specific handlers for those instructions
-        let args = self.function.f.dfg.inst_args(inst_info.inst);
-        self.schedule_operands(args, inst_info.plain_arguments()).unwrap_or_else(|err| {
-            panic!("failed to schedule operands for {}: {err:?}", inst_info.inst)
-        });
+        let mut args =
+            SmallVec::<[hir::Value; 4]>::from_slice(self.function.f.dfg.inst_args(inst_info.inst));
+        let mut constraints = SmallVec::<[Constraint; 4]>::from_slice(inst_info.plain_arguments());
+        self.canonicalize_commutative_operands(inst_info, &mut args, &mut constraints);
+        self.schedule_operands(&args, &constraints).map_err(|err| match err {
+            SolverError::StackDepth(err) => err,
+            err => panic!("failed to schedule operands for {}: {err:?}", inst_info.inst),
+        })?;
 
         match self.function.f.dfg.inst(inst_info.inst) {
-            ix @ (Instruction::RetImm(_) | Instruction::Ret(_)) => self.emit_ret(inst_info, ix),
-            Instruction::Br(ref op) => self.emit_br(inst_info, op, tasks),
-            Instruction::CondBr(ref op) => self.emit_cond_br(inst_info, op, tasks),
+            ix @ (Instruction::RetImm(_) | Instruction::Ret(_)) => {
+                return self.emit_ret(inst_info, ix);
+            }
+            Instruction::Br(ref op) => return self.emit_br(inst_info, op, tasks),
+            Instruction::CondBr(ref op) => return self.emit_cond_br(inst_info, op, tasks),
             Instruction::GlobalValue(op) => self.emit_global_value(inst_info, op),
             Instruction::UnaryOpImm(op) => self.emit_unary_imm_op(inst_info, op),
             Instruction::UnaryOp(op) => self.emit_unary_op(inst_info, op),
@@ -221,27 +443,46 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
             Instruction::PrimOpImm(op) => self.emit_primop_imm(inst_info, op),
             Instruction::Call(op) => self.emit_call_op(inst_info, op),
             Instruction::InlineAsm(op) => self.emit_inline_asm(inst_info, op),
-            Instruction::Switch(_) => {
-                panic!("expected switch instructions to have been rewritten before stackification")
-            }
+            Instruction::Switch(ref op) => return self.emit_switch(inst_info, op, tasks),
         }
+
+        Ok(())
     }
 
-    fn emit_ret(&mut self, inst_info: &InstInfo, ix: &hir::Instruction) {
+    fn emit_ret(&mut self, inst_info: &InstInfo, ix: &hir::Instruction) -> Result<(), EmitError> {
         use miden_hir::Instruction;
-        assert!(
-            !self.visited,
-            "invalid control flow graph: unexpected return instruction in loop in {}",
-            self.function.f.dfg.inst_block(inst_info.inst).unwrap(),
-        );
+        // A block ending in a return can never be a loop header, so it should only be visited once
+        if self.visited {
+            return Err(EmitError::UnexpectedCycle {
+                block: self.block_info.source,
+            });
+        }
 
         let num_args = self.function.f.dfg.inst_args(inst_info.inst).len();
         let level = self.controlling_loop_level().unwrap_or(0);
 
+        // The results were moved into position when scheduling the operands of this instruction,
+        // so they must be on top of the operand stack, in order, with the first result on top
+        let results = self.function.f.dfg.inst_args(inst_info.inst);
+        let mut operands = self.stack.iter().rev();
+        assert!(
+            results
+                .iter()
+                .all(|result| operands.next().is_some_and(|operand| operand == result)),
+            "expected the results of {} on top of the operand stack, in order:\n{}",
+            inst_info.inst,
+            self.stack.describe()
+        );
+
+        if self.function.strict_returns {
+            self.check_dead_operands_on_return(inst_info.inst, num_args);
+        }
+
         let mut emitter = self.emitter();
         // Upon return, the operand stack should only contain the function result(s),
-        // so empty the stack before proceeding.
-        emitter.truncate_stack(num_args);
+        // so empty the stack before proceeding. The results are kept as operands, so a
+        // result wider than one element, e.g. a word, keeps all of its elements, in order.
+        emitter.truncate_stack(num_args)?;
         // If this instruction is the immediate variant, we need to push the return
         // value on the stack at this point.
         if let Instruction::RetImm(hir::RetImm { arg, .. }) = ix {
@@ -252,6 +493,29 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         for _ in 0..level {
             emitter.literal(false);
         }
+
+        Ok(())
+    }
+
+    /// Every value should be dropped after its last use, so the only values left on the operand
+    /// stack on return should be the results. Those below the results are dropped on return
+    /// anyway, but if any of them is dead at `ret`, it means that it was not dropped when it
+    /// should have been, so we panic rather than masking the bug.
+    fn check_dead_operands_on_return(&self, ret: hir::Inst, num_results: usize) {
+        let pp = hir::ProgramPoint::Inst(ret);
+        for operand in self.stack.iter().rev().skip(num_results) {
+            let Some(value) = operand.as_value() else {
+                continue;
+            };
+            if self.function.liveness.next_use(&value, pp) == u32::MAX {
+                panic!(
+                    "{value} was left on the operand stack on return from '{}::{}', but it is \
+                     dead: it should have been dropped after its last use",
+                    self.function.f.id.module.as_str(),
+                    self.function.f.id.function.as_str()
+                );
+            }
+        }
     }
 
     /// Lower an unconditional branch instruction.
@@ -268,7 +532,12 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
     /// edge, and the successor must be a loop header. We must emit the loop header inline in the
     /// current block, up to the terminator, and then emit instructions to either continue the
     /// loop, or exit the current loop to the target loop.
-    fn emit_br(&mut self, inst_info: &InstInfo, op: &hir::Br, tasks: &mut Tasks) {
+    fn emit_br(
+        &mut self,
+        inst_info: &InstInfo,
+        op: &hir::Br,
+        tasks: &mut Tasks,
+    ) -> Result<(), EmitError> {
         let destination = op.destination;
 
         let is_first_visit = !self.visited;
@@ -277,9 +546,10 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         // Move block arguments into position
         let args = op.args.as_slice(&self.function.f.dfg.value_lists);
         self.schedule_operands(args, inst_info.block_arguments(destination))
-            .unwrap_or_else(|err| {
-                panic!("failed to schedule operands for {}: {err:?}", inst_info.inst)
-            });
+            .map_err(|err| match err {
+                SolverError::StackDepth(err) => err,
+                err => panic!("failed to schedule operands for {}: {err:?}", inst_info.inst),
+            })?;
         // Rename operands on stack to destination block parameters
         let params = self.function.f.dfg.block_params(destination);
         for (idx, param) in params.iter().enumerate() {
@@ -309,7 +579,11 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         } else {
             // We should only be emitting code for a block more than once if that block
             // is a loop header. All other blocks should only be visited a single time.
-            assert!(in_loop_header, "unexpected cycle at {}", self.block_info.source);
+            if !in_loop_header {
+                return Err(EmitError::UnexpectedCycle {
+                    block: self.block_info.source,
+                });
+            }
 
             // Calculate
             let current_level = self.controlling_loop_level().unwrap_or_else(|| {
@@ -322,9 +596,16 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
                 emitter.literal(false);
             }
         }
+
+        Ok(())
     }
 
-    fn emit_cond_br(&mut self, inst_info: &InstInfo, op: &hir::CondBr, tasks: &mut Tasks) {
+    fn emit_cond_br(
+        &mut self,
+        inst_info: &InstInfo,
+        op: &hir::CondBr,
+        tasks: &mut Tasks,
+    ) -> Result<(), EmitError> {
         let cond = op.cond;
         let then_dest = op.then_dest.0;
         let else_dest = op.else_dest.0;
@@ -337,7 +618,12 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
             cond
         );
 
+        let do_while = self.do_while_loop(op);
         if !self.visited {
+            if let Some(invert) = do_while {
+                return self.emit_do_while(inst_info, op, invert, tasks);
+            }
+
             let then_blk = self.masm_block_id(then_dest);
             let else_blk = self.masm_block_id(else_dest);
 
@@ -370,12 +656,13 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
                     masm_block,
                     &mut stack,
                 )
-                .unwrap_or_else(|err| {
-                    panic!(
+                .map_err(|err| match err {
+                    SolverError::StackDepth(err) => err,
+                    err => panic!(
                         "failed to schedule operands for successor {block} of {}: {err:?}",
                         inst_info.inst
-                    )
-                });
+                    ),
+                })?;
 
                 // Now that the block arguments are in place, we need to rename
                 // the stack operands to use the value names the successor expects
@@ -395,11 +682,19 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         } else {
             // We should only be emitting code for a block more than once if that block
             // is a loop header. All other blocks should only be visited a single time.
-            assert!(
-                self.block_info.is_loop_header(),
-                "unexpected cycle caused by branch to {}",
-                self.block_info.source,
-            );
+            if !self.block_info.is_loop_header() {
+                return Err(EmitError::UnexpectedCycle {
+                    block: self.block_info.source,
+                });
+            }
+
+            // The condition itself controls whether a tail-controlled loop continues
+            if let Some(invert) = do_while {
+                if invert {
+                    self.emit_op(Op::Not);
+                }
+                return Ok(());
+            }
 
             let current_level = self.controlling_loop_level().unwrap_or_else(|| {
                 panic!("expected controlling loop to be set in {}", self.block_info.source)
@@ -414,6 +709,315 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
                 emitter.literal(false);
             }
         }
+
+        Ok(())
+    }
+
+    /// Determine if the current block is the header of a tail-controlled loop, i.e. `do { body }
+    /// while (cond)`, terminated by `op`.
+    ///
+    /// This is the case when one successor of `op` is the loop body, a block in the same loop
+    /// which unconditionally branches back to the header, and the other successor is outside the
+    /// loop, and is not itself a loop header. As a result, the only exit from the loop is via `op`.
+    ///
+    /// Returns `Some(invert)` if the current block heads such a loop, where `invert` is true if
+    /// the loop continues when `op.cond` is false, rather than when it is true.
+    fn do_while_loop(&self, op: &hir::CondBr) -> Option<bool> {
+        let loops = self.function.loops;
+        let header = self.block_info.source;
+        let lp = loops.is_loop_header(header)?;
+        let then_dest = op.then_dest.0;
+        let else_dest = op.else_dest.0;
+        let (body, exit, invert) =
+            match (loops.is_in_loop(then_dest, lp), loops.is_in_loop(else_dest, lp)) {
+                (true, false) => (then_dest, else_dest, false),
+                (false, true) => (else_dest, then_dest, true),
+                _ => return None,
+            };
+        if body == header
+            || loops.innermost_loop(body) != Some(lp)
+            || loops.is_loop_header(exit).is_some()
+        {
+            return None;
+        }
+
+        let dfg = &self.function.f.dfg;
+        match dfg.analyze_branch(dfg.last_inst(body)?) {
+            hir::BranchInfo::SingleDest(destination, _) if destination == header => Some(invert),
+            _ => None,
+        }
+    }
+
+    /// Lower the conditional branch `op` which terminates the header of a tail-controlled loop.
+    ///
+    /// Rather than the general form of loop, which is entered unconditionally, and dispatches on
+    /// the condition computed by the header in each iteration, the condition is used to control
+    /// the `while.true` directly, and the exit successor is emitted after the loop:
+    ///
+    /// ```text,ignore
+    /// <header>
+    /// while.true
+    ///   <body>
+    ///   <header>
+    /// end
+    /// <exit>
+    /// ```
+    ///
+    /// See [Self::do_while_loop] for the conditions under which this is valid.
+    fn emit_do_while(
+        &mut self,
+        inst_info: &InstInfo,
+        op: &hir::CondBr,
+        invert: bool,
+        tasks: &mut Tasks,
+    ) -> Result<(), EmitError> {
+        let (body, exit) = if invert {
+            (&op.else_dest, &op.then_dest)
+        } else {
+            (&op.then_dest, &op.else_dest)
+        };
+
+        if invert {
+            self.emit_op(Op::Not);
+        }
+        let body_blk = self.masm_block_id(body.0);
+        self.emit_op(Op::While(body_blk));
+
+        // The body is emitted in the loop, while the exit is emitted after it, in the current
+        // block, where we are no longer in the loop headed by the current block
+        let header = self.block_info.source;
+        let lp = self.function.loops.is_loop_header(header).unwrap();
+        let successors = [
+            (body.0, body_blk, body.1, self.target_controlling_loop(body.0)),
+            (exit.0, self.target, exit.1, self.function.loops.loop_parent(lp)),
+        ];
+        for (block, masm_block, args, controlling_loop) in successors.into_iter() {
+            let mut stack = self.stack.clone();
+            let args = args.as_slice(&self.function.f.dfg.value_lists);
+            self.schedule_operands_in_block(
+                args,
+                inst_info.block_arguments(block),
+                masm_block,
+                &mut stack,
+            )
+            .map_err(|err| match err {
+                SolverError::StackDepth(err) => err,
+                err => panic!(
+                    "failed to schedule operands for successor {block} of {}: {err:?}",
+                    inst_info.inst
+                ),
+            })?;
+
+            let params = self.function.f.dfg.block_params(block);
+            for (idx, param) in params.iter().enumerate() {
+                stack.rename(idx, *param);
+            }
+
+            if block == body.0 {
+                tasks.push(Task::Block {
+                    block,
+                    controlling_loop,
+                    stack,
+                });
+            } else {
+                tasks.push(Task::Inline {
+                    target: self.target,
+                    block,
+                    controlling_loop,
+                    stack,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lower a multi-way branch to a balanced binary decision tree over its arms.
+    ///
+    /// The selector is kept on top of the operand stack while dispatching, and is dropped on entry
+    /// to whichever successor is selected. First, we check whether the selector matches any arm
+    /// at all, falling through to the default successor if not. Then, knowing that some arm
+    /// matches, we repeatedly split the remaining arms in half, until only one is left:
+    ///
+    /// ```text,ignore
+    /// dup.0 lt.3 if.true      # arms 0..3, default otherwise
+    ///   dup.0 eq.0 if.true    # arm 0, else arms 1..3
+    ///     drop <arm 0>
+    ///   else
+    ///     dup.0 eq.1 if.true  # arm 1, else arm 2
+    ///       drop <arm 1>
+    ///     else
+    ///       drop <arm 2>
+    ///     end
+    ///   end
+    /// else
+    ///   drop <default>
+    /// end
+    /// ```
+    ///
+    /// Each successor of the switch must be distinct, which is guaranteed by splitting critical
+    /// edges prior to treeification.
+    fn emit_switch(
+        &mut self,
+        inst_info: &InstInfo,
+        op: &hir::Switch,
+        tasks: &mut Tasks,
+    ) -> Result<(), EmitError> {
+        let selector = op.arg;
+
+        // Ensure `selector` is on top of the stack, and remove it at the same time, it remains on
+        // the stack while dispatching, but is dropped by the selected successor
+        assert_eq!(
+            self.stack.pop().unwrap().as_value(),
+            Some(selector),
+            "expected {} on top of the stack",
+            selector
+        );
+
+        if self.visited {
+            // As with conditional branches, we leave the selector on the operand stack to be
+            // dispatched on by the body of the target loop
+            if !self.block_info.is_loop_header() {
+                return Err(EmitError::UnexpectedCycle {
+                    block: self.block_info.source,
+                });
+            }
+
+            let current_level = self.controlling_loop_level().unwrap_or_else(|| {
+                panic!("expected controlling loop to be set in {}", self.block_info.source)
+            });
+            let target_level = self.loop_level(self.block_info.source);
+            let mut emitter = self.emitter();
+            emitter.literal(true);
+            for _ in 0..(current_level - target_level) {
+                emitter.literal(false);
+            }
+            return Ok(());
+        }
+
+        let mut arms = op.arms.clone();
+        arms.sort_unstable_by_key(|(discriminant, _)| *discriminant);
+
+        // If the current block is a loop header, we dispatch in the body of the loop
+        let dispatch_blk = if self.block_info.is_loop_header() {
+            let body_blk = self.function.f_prime.create_block();
+            self.emit_ops([Op::PushU8(1), Op::While(body_blk)]);
+            body_blk
+        } else {
+            self.target
+        };
+
+        // Check if the selector matches any arm, by testing for membership in each run of
+        // consecutive discriminants
+        let mut start = 0;
+        while start < arms.len() {
+            let mut end = start + 1;
+            while end < arms.len() && arms[end - 1].0.checked_add(1) == Some(arms[end].0) {
+                end += 1;
+            }
+            let first = hir::Felt::new(arms[start].0 as u64);
+            let len = hir::Felt::new((end - start) as u64);
+            // After the first run, the result of the membership test so far is above the selector
+            self.emit_op_to(dispatch_blk, Op::Dup((start > 0) as u8));
+            if end - start == 1 {
+                self.emit_op_to(dispatch_blk, Op::EqImm(first));
+            } else {
+                if arms[start].0 > 0 {
+                    self.emit_op_to(dispatch_blk, Op::SubImm(first));
+                }
+                self.emit_op_to(dispatch_blk, Op::LtImm(len));
+            }
+            if start > 0 {
+                self.emit_op_to(dispatch_blk, Op::Or);
+            }
+            start = end;
+        }
+        // A switch without arms always selects the default successor
+        if arms.is_empty() {
+            self.emit_op_to(dispatch_blk, Op::PushU8(0));
+        }
+        // When there is a single arm, the membership test has already selected it
+        let arms_blk = match arms.as_slice() {
+            [(_, block)] => self.masm_block_id(*block),
+            _ => {
+                let arms_blk = self.function.f_prime.create_block();
+                if !arms.is_empty() {
+                    self.emit_switch_arms(arms_blk, &arms);
+                }
+                arms_blk
+            }
+        };
+        let default_blk = self.masm_block_id(op.default);
+        self.emit_op_to(dispatch_blk, Op::If(arms_blk, default_blk));
+
+        // Enqueue tasks to emit code for each successor
+        let f = self.function.f;
+        let jts = match f.dfg.analyze_branch(inst_info.inst) {
+            hir::BranchInfo::MultiDest(jts) => jts,
+            _ => unreachable!(),
+        };
+        for jt in jts.iter() {
+            let block = jt.destination;
+            let masm_block = self.masm_block_id(block);
+            self.emit_op_to(masm_block, Op::Drop);
+
+            // Move block arguments for this successor into place, along the control flow edge to
+            // that successor, and rename them to the value names the successor expects
+            let mut stack = self.stack.clone();
+            self.schedule_operands_in_block(
+                jt.args,
+                inst_info.block_arguments(block),
+                masm_block,
+                &mut stack,
+            )
+            .map_err(|err| match err {
+                SolverError::StackDepth(err) => err,
+                err => panic!(
+                    "failed to schedule operands for successor {block} of {}: {err:?}",
+                    inst_info.inst
+                ),
+            })?;
+            let params = f.dfg.block_params(block);
+            for (idx, param) in params.iter().enumerate() {
+                stack.rename(idx, *param);
+            }
+
+            let controlling_loop = self.target_controlling_loop(block);
+            tasks.push(Task::Block {
+                block,
+                controlling_loop,
+                stack,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Emit the decision tree which selects between `arms` into `masm_block`, given that the
+    /// selector on top of the stack is known to match one of them.
+    ///
+    /// There must be at least two arms, a single arm is selected directly by the caller.
+    fn emit_switch_arms(&mut self, masm_block: masm::BlockId, arms: &[(u32, hir::Block)]) {
+        let mid = arms.len() / 2;
+        let (left, right) = arms.split_at(mid);
+        self.emit_op_to(masm_block, Op::Dup(0));
+        if left.len() == 1 {
+            self.emit_op_to(masm_block, Op::EqImm(hir::Felt::new(left[0].0 as u64)));
+        } else {
+            self.emit_op_to(masm_block, Op::LtImm(hir::Felt::new(right[0].0 as u64)));
+        }
+        let mut branch = |arms: &[(u32, hir::Block)]| {
+            if let [(_, block)] = arms {
+                self.masm_block_id(*block)
+            } else {
+                let blk = self.function.f_prime.create_block();
+                self.emit_switch_arms(blk, arms);
+                blk
+            }
+        };
+        let then_blk = branch(left);
+        let else_blk = branch(right);
+        self.emit_op_to(masm_block, Op::If(then_blk, else_blk));
     }
 
     fn emit_global_value(&mut self, inst_info: &InstInfo, op: &hir::GlobalValueOp) {
@@ -677,6 +1281,11 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
                 assert_eq!(args.len(), 3);
                 emitter.memcpy();
             }
+            // Grow linear memory by the given number of pages
+            hir::Opcode::MemGrow => {
+                assert_eq!(args.len(), 1);
+                emitter.mem_grow();
+            }
             // Conditionally select between two values
             hir::Opcode::Select => {
                 assert_eq!(args.len(), 3);
@@ -695,9 +1304,26 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
     fn emit_call_op(&mut self, inst_info: &InstInfo, op: &hir::Call) {
         assert_ne!(op.callee, self.function.f.id, "unexpected recursive call");
 
+        let callee = self.function.f.dfg.get_import(&op.callee);
+        // Callees which are invoked in a new memory context must be called, rather than executed
+        let is_cross_context =
+            callee.is_some_and(|callee| callee.signature.cc == hir::CallConv::Account);
+        // Only callees compiled under the memory calling convention expect their arguments in
+        // memory, i.e. not those provided at runtime, such as the standard library or the
+        // transaction kernel, nor those whose arguments do not fit in the argument region, nor
+        // those which do not use the `SystemV` calling convention, see
+        // `ConvertHirToMasm<&hir::Function>`
+        let memory_call_args =
+            self.function.memory_call_args.is_some_and(|is_defined| is_defined(op.callee))
+                && callee.is_some_and(|callee| {
+                    callee.signature.cc == hir::CallConv::SystemV
+                        && masm::fits_call_args_region(&callee.signature)
+                });
         let mut emitter = self.inst_emitter(inst_info.inst);
         match op.op {
             hir::Opcode::Syscall => emitter.syscall(op.callee),
+            hir::Opcode::Call if is_cross_context => emitter.call(op.callee),
+            hir::Opcode::Call if memory_call_args => emitter.exec_with_memory_args(op.callee),
             hir::Opcode::Call => emitter.exec(op.callee),
             opcode => unimplemented!("unrecognized procedure call opcode: '{opcode}'"),
         }
@@ -756,7 +1382,11 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
                         *body_blk = mapped_blocks[prev_body_blk];
                         rewrites.push((prev_body_blk, *body_blk));
                     }
-                    Op::LocAddr(_) | Op::LocStore(_) | Op::LocStorew(_) => {
+                    Op::LocAddr(_)
+                    | Op::LocStore(_)
+                    | Op::LocStorew(_)
+                    | Op::LocLoad(_)
+                    | Op::LocLoadw(_) => {
                         unimplemented!(
                             "locals are not currently supported in inline assembly blocks"
                         )
@@ -772,23 +1402,44 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
     /// the current block.
     ///
     /// This is intended to be called before scheduling any instructions in the block.
-    fn drop_unused_operands(&mut self) {
+    fn drop_unused_operands(&mut self) -> Result<(), StackDepthError> {
+        let pp = hir::ProgramPoint::Block(self.block_info.source);
+
+        // Spilled values which are no longer live can simply be forgotten
+        let dead_spills = self
+            .stack
+            .spills()
+            .map(|spilled| spilled.value)
+            .filter(|value| !self.function.liveness.is_live_at(value, pp))
+            .collect::<SmallVec<[hir::Value; 2]>>();
+        for value in dead_spills.into_iter() {
+            self.stack.discard_spill(&value);
+        }
+
         // We start by computing the set of unused operands on the stack at this point
         // in the program. We will use the resulting vectors to schedule instructions
         // that will move those operands to the top of the stack to be discarded
-        let pp = hir::ProgramPoint::Block(self.block_info.source);
+        //
+        // A value may be on the stack more than once, if it was copied, in which case every copy
+        // is dropped, and the copies are kept together in the order they are found on the stack,
+        // from the top, so that the order in which they are dropped does not depend on where the
+        // other unused operands are.
         let mut unused = SmallVec::<[hir::Value; 4]>::default();
         let mut constraints = SmallVec::<[Constraint; 4]>::default();
         for operand in self.stack.iter().rev() {
-            let value = operand.as_value().expect("unexpected non-ssa value on stack");
+            let value = operand.as_value().unwrap_or_else(|| {
+                panic!("unexpected non-ssa value on the operand stack:\n{}", self.stack.describe())
+            });
             // If the given value is not live on entry to this block, it should be dropped
-            if !self.function.liveness.is_live_at(&value, pp) {
+            if !unused.contains(&value) && !self.function.liveness.is_live_at(&value, pp) {
                 println!(
                     "should drop {value} at {} (visited={})",
                     self.block_info.source, self.visited
                 );
-                unused.push(value);
-                constraints.push(Constraint::Move);
+                for _ in self.stack.find_all(&value) {
+                    unused.push(value);
+                    constraints.push(Constraint::Move);
+                }
             }
         }
 
@@ -880,7 +1531,7 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
                                 .count();
                             let mut emitter = self.emitter();
                             if unused_chunk_size > 1 {
-                                emitter.movdn(unused_chunk_size as u8);
+                                emitter.movdn(stack_index(unused_chunk_size));
                                 emitter.dropn(unused_chunk_size);
                             } else {
                                 emitter.swap(1);
@@ -891,7 +1542,7 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
                         // to move the unused value to the top and drop it
                         _ => {
                             let mut emitter = self.emitter();
-                            emitter.movup(current_index as u8);
+                            emitter.movup(stack_index(current_index));
                             emitter.drop();
                         }
                     }
@@ -899,16 +1550,80 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
                     current_index = 0;
                 }
             } else {
-                self.schedule_operands(&unused, &constraints).unwrap_or_else(|err| {
-                    panic!(
-                        "failed to schedule unused operands for {}: {err:?}",
-                        self.block_info.source
-                    )
-                });
+                let source = self.block_info.source;
+                self.schedule_operands(&unused, &constraints).map_err(|err| match err {
+                    SolverError::StackDepth(err) => err,
+                    err => panic!("failed to schedule unused operands for {source}: {err:?}"),
+                })?;
                 let mut emitter = self.emitter();
                 emitter.dropn(unused.len());
             }
         }
+
+        Ok(())
+    }
+
+    /// Spill operands to procedure locals until the operand stack is no deeper than the
+    /// configured spill threshold, if one was set.
+    ///
+    /// The deepest operands are spilled first, as they are the least likely to be needed soon.
+    fn spill_excess_operands(&mut self) {
+        let Some(threshold) = self.function.spill_threshold else {
+            return;
+        };
+
+        while self.stack.raw_len() > threshold {
+            // Find the deepest operand which is still in reach of the stack manipulation
+            // instructions, and which fits in a procedure local
+            let Some(index) = (0..self.stack.len()).rev().find(|index| {
+                let operand = &self.stack[*index];
+                operand.size() <= 4
+                    && operand.as_value().is_some()
+                    && self.stack.effective_index_inclusive(*index) < 16
+            }) else {
+                break;
+            };
+            self.emitter().spill(index);
+        }
+    }
+
+    /// The operands of a commutative binary operator may be placed on the operand stack in either
+    /// order, so rather than following the order of the arguments in the IR, which is arbitrary,
+    /// order them the way the schedule has left them on the operand stack, i.e. the operand nearest
+    /// the top of the stack becomes the second operand, which is expected on top.
+    ///
+    /// This avoids a `swap` whenever the operands are already in place, but in the opposite order.
+    fn canonicalize_commutative_operands(
+        &self,
+        inst_info: &InstInfo,
+        args: &mut [hir::Value],
+        constraints: &mut [Constraint],
+    ) {
+        let is_commutative_binary_op = matches!(
+            self.function.f.dfg.inst(inst_info.inst),
+            hir::Instruction::BinaryOp(op) if op.op.is_commutative()
+        );
+        if !is_commutative_binary_op {
+            return;
+        }
+
+        // Operands which are spilled are reloaded on top of the stack in argument order anyway
+        let (Some(rhs), Some(lhs)) = (self.stack.find(&args[0]), self.stack.find(&args[1])) else {
+            return;
+        };
+        if lhs < rhs {
+            args.swap(0, 1);
+            constraints.swap(0, 1);
+        }
+    }
+
+    /// Notify the observer, if any, of the operands about to be moved into place
+    fn observe_operands(&mut self, expected: &[hir::Value], constraints: &[Constraint]) {
+        if let Some(observer) = self.function.observer.as_deref_mut() {
+            for (value, constraint) in expected.iter().zip(constraints) {
+                observer.operand(*value, *constraint);
+            }
+        }
     }
 
     fn schedule_operands(
@@ -916,7 +1631,9 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         expected: &[hir::Value],
         constraints: &[Constraint],
     ) -> Result<(), SolverError> {
-        match OperandMovementConstraintSolver::new(expected, constraints, &self.stack) {
+        self.observe_operands(expected, constraints);
+        let constraints = reload_spilled_operands(&mut self.emitter(), expected, constraints);
+        match OperandMovementConstraintSolver::new(expected, &constraints, &self.stack) {
             Ok(solver) => {
                 let mut emitter = self.emitter();
                 solver.solve_and_apply(&mut emitter)
@@ -935,7 +1652,13 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         block: masm::BlockId,
         stack: &mut OperandStack,
     ) -> Result<(), SolverError> {
-        match OperandMovementConstraintSolver::new(expected, constraints, stack) {
+        self.observe_operands(expected, constraints);
+        let constraints = reload_spilled_operands(
+            &mut OpEmitter::new(self.function.f_prime, block, stack),
+            expected,
+            constraints,
+        );
+        match OperandMovementConstraintSolver::new(expected, &constraints, stack) {
             Ok(solver) => {
                 let mut emitter = OpEmitter::new(self.function.f_prime, block, stack);
                 solver.solve_and_apply(&mut emitter)
@@ -1079,3 +1802,27 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         OpEmitter::new(self.function.f_prime, self.target, &mut self.stack)
     }
 }
+
+/// Reload any of the `expected` operands which were spilled to procedure locals, so that they
+/// are on the operand stack when scheduling them according to `constraints`.
+///
+/// Returns the constraints to use when scheduling. As reloading an operand places a fresh copy
+/// of it on the operand stack, reloaded operands are always moved.
+fn reload_spilled_operands(
+    emitter: &mut OpEmitter<'_>,
+    expected: &[hir::Value],
+    constraints: &[Constraint],
+) -> SmallVec<[Constraint; 4]> {
+    expected
+        .iter()
+        .zip(constraints.iter())
+        .map(|(value, constraint)| {
+            let stack = emitter.stack();
+            if stack.find(value).is_some() || stack.spilled(value).is_none() {
+                return *constraint;
+            }
+            emitter.reload(*value, matches!(constraint, Constraint::Move));
+            Constraint::Move
+        })
+        .collect()
+}
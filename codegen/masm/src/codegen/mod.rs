@@ -1,11 +1,16 @@
+mod balance;
 mod emit;
 mod emitter;
 mod opt;
 mod scheduler;
 mod stack;
+mod verify;
 
 pub use self::{
-    emitter::FunctionEmitter,
-    scheduler::Scheduler,
-    stack::{Constraint, Operand, OperandStack, TypedValue},
+    emit::StackDepthError,
+    emitter::{EmitError, FunctionEmitter, SchedulerObserver},
+    opt::{dedup, drops, nops, outline, peephole},
+    scheduler::{compute_schedule, BlockInfo, InstInfo, Schedule, ScheduleOp, Scheduler},
+    stack::{Constraint, Operand, OperandStack, OperandStackSnapshot, SnapshotError, TypedValue},
+    verify::DivergentJoinError,
 };
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+
+use miden_hir::FunctionIdent;
+
+use crate::masm::{self, Op};
+
+/// Merge functions of `module` whose bodies are structurally identical, i.e. they consist of the
+/// same instructions, with the same control structure, and use the same locals.
+///
+/// For each set of identical functions, the first one in the module is kept as the canonical
+/// definition, and the others are merged into it:
+///
+/// * Internal functions are removed, and calls to them, as well as references to them via
+/// `procref`, are redirected to the canonical definition.
+/// * Exported functions must remain available under their own name, so their body is replaced
+/// with a call to the canonical definition instead.
+///
+/// The entrypoint of a program is never merged.
+///
+/// Returns the number of functions which were merged.
+pub fn merge_duplicate_functions(module: &mut masm::Module) -> usize {
+    let mut functions = vec![];
+    while let Some(function) = module.pop_front() {
+        functions.push(function);
+    }
+
+    let mut redirects = BTreeMap::<FunctionIdent, FunctionIdent>::new();
+    let mut canonical = Vec::<usize>::new();
+    let mut merged = 0;
+    for index in 0..functions.len() {
+        let function = &functions[index];
+        let original = if function.is_entrypoint() {
+            None
+        } else {
+            canonical.iter().copied().find(|&c| is_identical(&functions[c], function))
+        };
+        let Some(original) = original else {
+            canonical.push(index);
+            continue;
+        };
+
+        merged += 1;
+        let original = functions[original].name;
+        if !function.signature.is_public() {
+            redirects.insert(function.name, original);
+            continue;
+        }
+
+        let mut alias = masm::Function::new(function.name, function.signature.clone());
+        alias.span = function.span;
+        alias.attrs = function.attrs.clone();
+        let entry = alias.body.id();
+        alias.block_mut(entry).push(Op::Exec(original));
+        alias.update_may_trap();
+        functions[index] = Box::new(alias);
+    }
+
+    for mut function in functions {
+        if redirects.contains_key(&function.name) {
+            continue;
+        }
+        for block in function.body.blocks.values_mut() {
+            for op in block.ops.iter_mut() {
+                match op {
+                    Op::Exec(callee)
+                    | Op::Call(callee)
+                    | Op::Syscall(callee)
+                    | Op::ProcRef(callee) => {
+                        if let Some(original) = redirects.get(callee) {
+                            *callee = *original;
+                        }
+                    }
+                    _ => continue,
+                }
+            }
+        }
+        module.push_back(function);
+    }
+
+    merged
+}
+
+/// Returns true if `a` and `b` can be used interchangeably
+fn is_identical(a: &masm::Function, b: &masm::Function) -> bool {
+    a.signature.params == b.signature.params
+        && a.signature.results == b.signature.results
+        && a.signature.cc == b.signature.cc
+        && a.locals() == b.locals()
+        && is_identical_block(a, a.body.id(), b, b.body.id())
+}
+
+/// Returns true if block `a_blk` of `a` is identical to block `b_blk` of `b`, comparing the blocks
+/// referenced by control flow instructions, rather than their ids.
+fn is_identical_block(
+    a: &masm::Function,
+    a_blk: masm::BlockId,
+    b: &masm::Function,
+    b_blk: masm::BlockId,
+) -> bool {
+    let a_ops = &a.block(a_blk).ops;
+    let b_ops = &b.block(b_blk).ops;
+    a_ops.len() == b_ops.len()
+        && a_ops.iter().zip(b_ops.iter()).all(|ops| match ops {
+            (Op::If(a_then, a_else), Op::If(b_then, b_else)) => {
+                is_identical_block(a, *a_then, b, *b_then)
+                    && is_identical_block(a, *a_else, b, *b_else)
+            }
+            (Op::While(a_body), Op::While(b_body)) => is_identical_block(a, *a_body, b, *b_body),
+            (Op::Repeat(a_n, a_body), Op::Repeat(b_n, b_body)) => {
+                a_n == b_n && is_identical_block(a, *a_body, b, *b_body)
+            }
+            (a_op, b_op) => a_op == b_op,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{Linkage, Signature};
+
+    use super::*;
+
+    /// Construct a function named `name` which returns the larger of its arguments
+    fn max(name: &str, linkage: Linkage) -> Box<masm::Function> {
+        let mut signature = Signature::new([], []);
+        signature.linkage = linkage;
+        let mut function = masm::Function::new(name.parse().unwrap(), signature);
+        let entry = function.body.id();
+        let then_blk = function.create_block();
+        let else_blk = function.create_block();
+        function.block_mut(entry).extend_from_slice(&[
+            Op::Dup(1),
+            Op::Dup(1),
+            Op::Gt,
+            Op::If(then_blk, else_blk),
+        ]);
+        function.block_mut(then_blk).push(Op::Drop);
+        function.block_mut(else_blk).extend_from_slice(&[Op::Swap(1), Op::Drop]);
+        Box::new(function)
+    }
+
+    #[test]
+    fn merge_duplicate_functions_test() {
+        let mut module = masm::Module::new("test".into());
+        let mut caller =
+            masm::Function::new("test::caller".parse().unwrap(), Signature::new([], []));
+        let entry = caller.body.id();
+        caller.block_mut(entry).extend_from_slice(&[
+            Op::Exec("test::a".parse().unwrap()),
+            Op::Exec("test::b".parse().unwrap()),
+            Op::ProcRef("test::b".parse().unwrap()),
+        ]);
+        module.push_back(Box::new(caller));
+        module.push_back(max("test::a", Linkage::Internal));
+        module.push_back(max("test::b", Linkage::Internal));
+
+        assert_eq!(merge_duplicate_functions(&mut module), 1);
+        let a = "test::a".parse().unwrap();
+        let names = module.functions().map(|f| f.name).collect::<Vec<_>>();
+        assert_eq!(names, ["test::caller".parse().unwrap(), a]);
+        let caller = module.functions().next().unwrap();
+        assert_eq!(
+            caller.block(caller.body.id()).ops.as_slice(),
+            &[Op::Exec(a), Op::Exec(a), Op::ProcRef(a)]
+        );
+
+        // Nothing left to merge
+        assert_eq!(merge_duplicate_functions(&mut module), 0);
+    }
+
+    #[test]
+    fn merge_exported_duplicate_functions_test() {
+        let mut module = masm::Module::new("test".into());
+        module.push_back(max("test::a", Linkage::Internal));
+        module.push_back(max("test::b", Linkage::External));
+
+        // The exported function is preserved, but only as an alias of the canonical definition
+        assert_eq!(merge_duplicate_functions(&mut module), 1);
+        let a = "test::a".parse().unwrap();
+        let b = "test::b".parse().unwrap();
+        let names = module.functions().map(|f| f.name).collect::<Vec<_>>();
+        assert_eq!(names, [a, b]);
+        let alias = module.functions().nth(1).unwrap();
+        assert!(alias.signature.is_public());
+        assert_eq!(alias.block(alias.body.id()).ops.as_slice(), &[Op::Exec(a)]);
+    }
+}
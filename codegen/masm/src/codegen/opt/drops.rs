@@ -0,0 +1,125 @@
+use smallvec::SmallVec;
+
+use crate::masm::{self, Op};
+
+/// Hoist drops shared by both branches of a conditional out of the branches, and into the block
+/// containing the conditional, immediately following it.
+///
+/// Code generation drops values which are no longer live on entry to each block, so when a value
+/// dies along every path through a conditional, each branch ends with its own copy of the same
+/// drop sequence. As both branches rejoin at the end of the conditional, the longest sequence of
+/// `drop` and `dropw` instructions which ends both branches can be executed once, after the
+/// conditional, instead.
+///
+/// Hoisting drops out of a conditional which itself ends a branch of another conditional may
+/// allow them to be hoisted again, so this is repeated until no more drops can be hoisted.
+///
+/// Returns true if any instructions were hoisted.
+pub fn optimize(region: &mut masm::Region) -> bool {
+    let mut changed = false;
+    loop {
+        let mut hoisted = false;
+        let blocks = region.blocks.keys().collect::<SmallVec<[masm::BlockId; 8]>>();
+        for id in blocks {
+            let mut index = 0;
+            while index < region.blocks[id].ops.len() {
+                if let Op::If(then_blk, else_blk) = region.blocks[id].ops[index] {
+                    let drops = common_trailing_drops(
+                        &region.blocks[then_blk].ops,
+                        &region.blocks[else_blk].ops,
+                    );
+                    if drops > 0 {
+                        let then_ops = &mut region.blocks[then_blk].ops;
+                        let start = then_ops.len() - drops;
+                        let hoisted_ops = then_ops.drain(start..).collect::<SmallVec<[Op; 4]>>();
+                        let else_ops = &mut region.blocks[else_blk].ops;
+                        else_ops.truncate(else_ops.len() - drops);
+                        region.blocks[id].ops.insert_many(index + 1, hoisted_ops);
+                        hoisted = true;
+                        index += drops;
+                    }
+                }
+                index += 1;
+            }
+        }
+
+        if !hoisted {
+            break;
+        }
+        changed = true;
+    }
+
+    changed
+}
+
+/// Returns the length of the longest sequence of drops which ends both `a` and `b`
+fn common_trailing_drops(a: &[Op], b: &[Op]) -> usize {
+    a.iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(a, b)| a == b && matches!(a, Op::Drop | Op::Dropw))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::Signature;
+
+    use super::*;
+
+    #[test]
+    fn hoist_common_drops_test() {
+        let mut function =
+            masm::Function::new("test::drops".parse().unwrap(), Signature::new([], []));
+        let entry = function.body.id();
+        let then_blk = function.create_block();
+        let else_blk = function.create_block();
+
+        function.block_mut(entry).push(Op::If(then_blk, else_blk));
+        function.block_mut(entry).push(Op::Add);
+        // Both branches drop the same two values before rejoining
+        function
+            .block_mut(then_blk)
+            .extend_from_slice(&[Op::Dropw, Op::Mul, Op::Drop, Op::Drop]);
+        function.block_mut(else_blk).extend_from_slice(&[Op::Dropw, Op::Drop, Op::Drop]);
+
+        assert!(optimize(&mut function.body));
+        assert_eq!(
+            function.block(entry).ops.as_slice(),
+            &[Op::If(then_blk, else_blk), Op::Drop, Op::Drop, Op::Add]
+        );
+        assert_eq!(function.block(then_blk).ops.as_slice(), &[Op::Dropw, Op::Mul]);
+        assert_eq!(function.block(else_blk).ops.as_slice(), &[Op::Dropw]);
+
+        // Nothing left in common
+        assert!(!optimize(&mut function.body));
+    }
+
+    #[test]
+    fn hoist_common_drops_nested_test() {
+        let mut function =
+            masm::Function::new("test::drops".parse().unwrap(), Signature::new([], []));
+        let entry = function.body.id();
+        let then_blk = function.create_block();
+        let else_blk = function.create_block();
+        let inner_then_blk = function.create_block();
+        let inner_else_blk = function.create_block();
+
+        function.block_mut(entry).push(Op::If(then_blk, else_blk));
+        function.block_mut(then_blk).push(Op::If(inner_then_blk, inner_else_blk));
+        function.block_mut(inner_then_blk).extend_from_slice(&[Op::Add, Op::Drop]);
+        function.block_mut(inner_else_blk).extend_from_slice(&[Op::Mul, Op::Drop]);
+        function.block_mut(else_blk).extend_from_slice(&[Op::Sub, Op::Drop]);
+
+        // The drop is hoisted out of the inner conditional, and then out of the outer one
+        assert!(optimize(&mut function.body));
+        assert_eq!(function.block(entry).ops.as_slice(), &[Op::If(then_blk, else_blk), Op::Drop]);
+        assert_eq!(
+            function.block(then_blk).ops.as_slice(),
+            &[Op::If(inner_then_blk, inner_else_blk)]
+        );
+        assert_eq!(function.block(inner_then_blk).ops.as_slice(), &[Op::Add]);
+        assert_eq!(function.block(inner_else_blk).ops.as_slice(), &[Op::Mul]);
+        assert_eq!(function.block(else_blk).ops.as_slice(), &[Op::Sub]);
+    }
+}
@@ -1,3 +1,8 @@
+pub mod dedup;
+pub mod drops;
+pub mod nops;
 pub mod operands;
+pub mod outline;
+pub mod peephole;
 
 pub use self::operands::{OperandMovementConstraintSolver, SolverError};
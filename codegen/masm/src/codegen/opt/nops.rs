@@ -0,0 +1,325 @@
+use std::collections::BTreeSet;
+
+use miden_hir::StarkField;
+use smallvec::{smallvec, SmallVec};
+
+use crate::masm::{self, Op};
+
+/// Remove stack-neutral sequences of instructions from `region`, i.e. sequences which have no
+/// effect other than pushing a value which is immediately consumed again.
+///
+/// The lowering of loops and branches pushes constants which control `while.true` and `if.true`,
+/// which after other simplifications may end up immediately preceding the instruction consuming
+/// them. The following sequences are rewritten:
+///
+/// * `push.0 while.true <body> end` is removed, as the loop is never entered
+/// * `push.1 while.true <body> end` is replaced with `<body>`, if every path through `<body>` which
+/// exits the loop does so by pushing a literal `0`, i.e. the loop is entered once, and is never
+/// re-entered at the end of the first iteration. The pushes of those zeroes are removed as well,
+/// including those in nested loops which exit both the nested loop and this one, e.g. on return
+/// * `push.1 if.true <then> else <else> end` is replaced with `<then>`
+/// * `push.0 if.true <then> else <else> end` is replaced with `<else>`
+/// * `push.N drop` is removed
+///
+/// The code of blocks which become unreachable is removed as well, but the blocks themselves are
+/// left in place, as they are no longer referenced.
+///
+/// Removing a sequence may expose another one, e.g. when the body of a loop which is inlined ends
+/// with a `push.N`, so this is repeated until no more sequences can be removed.
+///
+/// Returns true if any instructions were removed.
+pub fn optimize(region: &mut masm::Region) -> bool {
+    let mut changed = false;
+    loop {
+        let mut removed = false;
+        let blocks = region.blocks.keys().collect::<SmallVec<[masm::BlockId; 8]>>();
+        for id in blocks {
+            let mut index = 0;
+            while index + 1 < region.blocks[id].ops.len() {
+                let ops = &region.blocks[id].ops;
+                let Some(value) = literal(&ops[index]) else {
+                    index += 1;
+                    continue;
+                };
+
+                let replacement = match (value, ops[index + 1]) {
+                    (_, Op::Drop) => SmallVec::new(),
+                    (0, Op::While(body_blk)) => {
+                        region.blocks[body_blk].ops.clear();
+                        SmallVec::new()
+                    }
+                    (1, Op::While(body_blk)) => {
+                        let Some(flags) = exit_flags(region, body_blk) else {
+                            index += 1;
+                            continue;
+                        };
+                        // Remove in reverse, so that the positions of the remaining flags are
+                        // unaffected by the removal
+                        for (blk, flag) in flags.into_iter().rev() {
+                            region.blocks[blk].ops.remove(flag);
+                        }
+                        core::mem::take(&mut region.blocks[body_blk].ops)
+                    }
+                    (0 | 1, Op::If(then_blk, else_blk)) => {
+                        let (taken, not_taken) = if value == 1 {
+                            (then_blk, else_blk)
+                        } else {
+                            (else_blk, then_blk)
+                        };
+                        region.blocks[not_taken].ops.clear();
+                        core::mem::take(&mut region.blocks[taken].ops)
+                    }
+                    _ => {
+                        index += 1;
+                        continue;
+                    }
+                };
+
+                let ops = &mut region.blocks[id].ops;
+                ops.drain(index..(index + 2));
+                ops.insert_many(index, replacement);
+                removed = true;
+            }
+        }
+
+        if !removed {
+            break;
+        }
+        changed = true;
+    }
+
+    changed
+}
+
+/// The location of an instruction in a [masm::Region], i.e. its block, and its index in that block
+type Position = (masm::BlockId, usize);
+
+/// The literals left on top of the operand stack along a path exiting a block, starting from the
+/// top of the stack, along with the instructions which pushed them
+type ExitPath = SmallVec<[(Position, u64); 4]>;
+
+/// If the flag controlling the loop whose body is `body_blk` is a literal zero on every path
+/// exiting the body, i.e. the loop is never re-entered, returns the instructions pushing the flag.
+fn exit_flags(region: &masm::Region, body_blk: masm::BlockId) -> Option<BTreeSet<Position>> {
+    let mut flags = BTreeSet::new();
+    for path in exit_paths(region, body_blk)? {
+        match path.first() {
+            Some(&(position, 0)) => {
+                flags.insert(position);
+            }
+            _ => return None,
+        }
+    }
+    Some(flags)
+}
+
+/// Returns the literals left on top of the operand stack by each path exiting `blk`.
+///
+/// A path is cut short at the first instruction which is not a literal, or control flow we can see
+/// through, i.e. `if.true`, or a loop which is always entered. For such loops, the paths continuing
+/// the loop are ignored, while the flag is stripped from the paths exiting the loop.
+///
+/// Returns `None` if the flag of a nested loop is unknown on some path, as we then cannot tell what
+/// that path leaves on the operand stack when the loop exits.
+fn exit_paths(region: &masm::Region, blk: masm::BlockId) -> Option<SmallVec<[ExitPath; 2]>> {
+    let ops = &region.blocks[blk].ops;
+    let mut index = ops.len();
+    let mut tail = ExitPath::new();
+    while let Some(value) = index.checked_sub(1).and_then(|i| literal(&ops[i])) {
+        index -= 1;
+        tail.push(((blk, index), value));
+    }
+
+    let rest = match index.checked_sub(1).map(|i| &ops[i]) {
+        Some(Op::If(then_blk, else_blk)) => {
+            let mut paths = exit_paths(region, *then_blk)?;
+            paths.extend(exit_paths(region, *else_blk)?);
+            paths
+        }
+        Some(Op::While(body_blk)) if index > 1 && literal(&ops[index - 2]) == Some(1) => {
+            let mut paths = SmallVec::new();
+            for path in exit_paths(region, *body_blk)? {
+                match path.first()? {
+                    (_, 0) => paths.push(ExitPath::from_slice(&path[1..])),
+                    _ => continue,
+                }
+            }
+            paths
+        }
+        _ => smallvec![ExitPath::new()],
+    };
+
+    Some(
+        rest.into_iter()
+            .map(|path| {
+                let mut full = tail.clone();
+                full.extend(path);
+                full
+            })
+            .collect(),
+    )
+}
+
+/// Returns the value pushed by `op`, if it pushes a single constant on the operand stack
+fn literal(op: &Op) -> Option<u64> {
+    match op {
+        Op::Push(value) => Some(value.as_int()),
+        Op::PushU8(value) => Some(*value as u64),
+        Op::PushU16(value) => Some(*value as u64),
+        Op::PushU32(value) => Some(*value as u64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::Signature;
+
+    use super::*;
+
+    #[test]
+    fn remove_degenerate_loop_test() {
+        let mut function =
+            masm::Function::new("test::nops".parse().unwrap(), Signature::new([], []));
+        let entry = function.body.id();
+        let body_blk = function.create_block();
+        let never_blk = function.create_block();
+
+        // A loop which is unconditionally entered, and unconditionally exits after one iteration
+        function
+            .block_mut(entry)
+            .extend_from_slice(&[Op::PushU8(1), Op::While(body_blk), Op::Add]);
+        function
+            .block_mut(body_blk)
+            .extend_from_slice(&[Op::Dup(0), Op::Mul, Op::PushU8(0)]);
+        // A loop which is never entered
+        function
+            .block_mut(entry)
+            .extend_from_slice(&[Op::PushU8(0), Op::While(never_blk)]);
+        function.block_mut(never_blk).extend_from_slice(&[Op::Sub, Op::PushU8(1)]);
+
+        assert!(optimize(&mut function.body));
+        assert_eq!(function.block(entry).ops.as_slice(), &[Op::Dup(0), Op::Mul, Op::Add]);
+        assert!(function.block(body_blk).ops.is_empty());
+        assert!(function.block(never_blk).ops.is_empty());
+
+        // Nothing left to remove
+        assert!(!optimize(&mut function.body));
+    }
+
+    #[test]
+    fn remove_nested_control_pushes_test() {
+        let mut function =
+            masm::Function::new("test::nops".parse().unwrap(), Signature::new([], []));
+        let entry = function.body.id();
+        let body_blk = function.create_block();
+        let then_blk = function.create_block();
+        let else_blk = function.create_block();
+        let loop_blk = function.create_block();
+
+        function
+            .block_mut(entry)
+            .extend_from_slice(&[Op::PushU8(1), Op::While(body_blk)]);
+        // Once the conditional is inlined, the loop body ends with `push.0`
+        function
+            .block_mut(body_blk)
+            .extend_from_slice(&[Op::PushU8(1), Op::If(then_blk, else_blk)]);
+        function.block_mut(then_blk).extend_from_slice(&[
+            Op::PushU32(5),
+            Op::Drop,
+            Op::Add,
+            Op::PushU8(0),
+        ]);
+        function.block_mut(else_blk).extend_from_slice(&[Op::Mul, Op::PushU8(1)]);
+        // A loop which does not exit after the first iteration is preserved
+        function
+            .block_mut(entry)
+            .extend_from_slice(&[Op::PushU8(1), Op::While(loop_blk)]);
+        function.block_mut(loop_blk).extend_from_slice(&[Op::Dup(0), Op::Eq]);
+
+        assert!(optimize(&mut function.body));
+        assert_eq!(
+            function.block(entry).ops.as_slice(),
+            &[Op::Add, Op::PushU8(1), Op::While(loop_blk)]
+        );
+        assert_eq!(function.block(loop_blk).ops.as_slice(), &[Op::Dup(0), Op::Eq]);
+    }
+
+    #[test]
+    fn remove_loop_never_reentered_test() {
+        let mut function =
+            masm::Function::new("test::nops".parse().unwrap(), Signature::new([], []));
+        let entry = function.body.id();
+        let outer_blk = function.create_block();
+        let inner_blk = function.create_block();
+        let ret_blk = function.create_block();
+        let continue_blk = function.create_block();
+
+        // The outer loop only ever continues the inner loop, or returns from both loops
+        function
+            .block_mut(entry)
+            .extend_from_slice(&[Op::PushU8(1), Op::While(outer_blk)]);
+        function
+            .block_mut(outer_blk)
+            .extend_from_slice(&[Op::PushU8(1), Op::While(inner_blk)]);
+        function
+            .block_mut(inner_blk)
+            .extend_from_slice(&[Op::Dup(0), Op::If(ret_blk, continue_blk)]);
+        function
+            .block_mut(ret_blk)
+            .extend_from_slice(&[Op::Drop, Op::PushU8(0), Op::PushU8(0)]);
+        function.block_mut(continue_blk).extend_from_slice(&[Op::Incr, Op::PushU8(1)]);
+
+        // Once the outer loop is removed, only the zero exiting the inner loop is needed on return
+        assert!(optimize(&mut function.body));
+        assert_eq!(function.block(entry).ops.as_slice(), &[Op::PushU8(1), Op::While(inner_blk)]);
+        assert!(function.block(outer_blk).ops.is_empty());
+        assert_eq!(function.block(ret_blk).ops.as_slice(), &[Op::Drop, Op::PushU8(0)]);
+        assert_eq!(function.block(continue_blk).ops.as_slice(), &[Op::Incr, Op::PushU8(1)]);
+
+        // The inner loop is re-entered, so it must be preserved
+        assert!(!optimize(&mut function.body));
+    }
+
+    #[test]
+    fn preserve_reentered_loop_test() {
+        let mut function =
+            masm::Function::new("test::nops".parse().unwrap(), Signature::new([], []));
+        let entry = function.body.id();
+        let outer_blk = function.create_block();
+        let inner_blk = function.create_block();
+        let ret_blk = function.create_block();
+        let continue_blk = function.create_block();
+        let continue_inner_blk = function.create_block();
+        let continue_outer_blk = function.create_block();
+
+        // Both loops are re-entered, the outer loop by exiting the inner loop
+        function
+            .block_mut(entry)
+            .extend_from_slice(&[Op::PushU8(1), Op::While(outer_blk)]);
+        function
+            .block_mut(outer_blk)
+            .extend_from_slice(&[Op::PushU8(1), Op::While(inner_blk)]);
+        function
+            .block_mut(inner_blk)
+            .extend_from_slice(&[Op::Dup(0), Op::If(ret_blk, continue_blk)]);
+        function
+            .block_mut(ret_blk)
+            .extend_from_slice(&[Op::Drop, Op::PushU8(0), Op::PushU8(0)]);
+        function.block_mut(continue_blk).extend_from_slice(&[
+            Op::Incr,
+            Op::Dup(0),
+            Op::If(continue_inner_blk, continue_outer_blk),
+        ]);
+        function.block_mut(continue_inner_blk).push(Op::PushU8(1));
+        function
+            .block_mut(continue_outer_blk)
+            .extend_from_slice(&[Op::PushU8(1), Op::PushU8(0)]);
+
+        let original = function.body.clone();
+        assert!(!optimize(&mut function.body));
+        for (blk, block) in original.blocks.iter() {
+            assert_eq!(function.block(blk).ops, block.ops);
+        }
+    }
+}
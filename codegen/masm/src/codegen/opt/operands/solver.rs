@@ -2,7 +2,7 @@ use miden_hir as hir;
 use smallvec::SmallVec;
 
 use super::{tactics::Tactic, *};
-use crate::codegen::Constraint;
+use crate::codegen::{Constraint, StackDepthError};
 
 /// This error type is produced by the [OperandMovementConstraintSolver]
 #[derive(Debug)]
@@ -11,6 +11,13 @@ pub enum SolverError {
     AlreadySolved,
     /// All of the tactics we tried failed
     NoSolution,
+    /// Applying the solution would require accessing an operand too deep on the operand stack
+    StackDepth(StackDepthError),
+}
+impl From<StackDepthError> for SolverError {
+    fn from(err: StackDepthError) -> Self {
+        Self::StackDepth(err)
+    }
 }
 
 /// The [OperandMovementConstraintSolver] is used to produce a solution to the following problem:
@@ -102,6 +109,19 @@ impl OperandMovementConstraintSolver {
     ) -> Result<Self, SolverError> {
         assert_eq!(expected.len(), constraints.len());
 
+        // The most common case is that the expected operands are already on top of the stack, in
+        // order, and none of them need to be copied. Check for that before doing the comparatively
+        // expensive work of computing the solver context, which would reach the same conclusion.
+        let is_in_place = expected.len() <= stack.len()
+            && constraints.iter().all(|constraint| matches!(constraint, Constraint::Move))
+            && expected
+                .iter()
+                .enumerate()
+                .all(|(pos, value)| stack[pos].as_value() == Some(*value));
+        if is_in_place {
+            return Err(SolverError::AlreadySolved);
+        }
+
         let context = SolverContext::new(expected, constraints, stack)?;
 
         Ok(Self {
@@ -241,24 +261,26 @@ impl OperandMovementConstraintSolver {
                 let expected = self.context.expected()[0];
                 if let Some(current_position) = self.context.stack().position(&expected.value) {
                     if current_position > 0 {
-                        emitter.move_operand_to_position(current_position, 0, false);
+                        emitter.move_operand_to_position(current_position, 0, false)?;
                     }
                 } else {
                     assert!(
                         self.context.copies().has_copies(&expected.value),
-                        "{:?} was not found on the operand stack",
-                        expected.value
+                        "{:?} was not found on the operand stack:\n{}",
+                        expected.value,
+                        emitter.stack().describe()
                     );
                     let current_position =
                         self.context.stack().position(&expected.value.unaliased()).unwrap_or_else(
                             || {
                                 panic!(
-                                    "{:?} was not found on the operand stack",
-                                    expected.value.unaliased()
+                                    "{:?} was not found on the operand stack:\n{}",
+                                    expected.value.unaliased(),
+                                    emitter.stack().describe()
                                 )
                             },
                         );
-                    emitter.copy_operand_to_position(current_position, 0, false);
+                    emitter.copy_operand_to_position(current_position, 0, false)?;
                 }
 
                 Ok(())
@@ -269,7 +291,7 @@ impl OperandMovementConstraintSolver {
                 for action in actions.into_iter() {
                     match action {
                         Action::Copy(index) => {
-                            emitter.copy_operand_to_position(index as usize, 0, false);
+                            emitter.copy_operand_to_position(index as usize, 0, false)?;
                         }
                         Action::Swap(index) => {
                             emitter.swap(index);
@@ -306,6 +328,43 @@ mod tests {
             .try_init();
     }
 
+    #[test]
+    fn operand_movement_constraint_solver_in_place_test() {
+        let v1 = hir::Value::from_u32(1);
+        let v2 = hir::Value::from_u32(2);
+        let v3 = hir::Value::from_u32(3);
+        let v4 = hir::Value::from_u32(4);
+
+        let mut stack = crate::codegen::OperandStack::default();
+        for value in [v4, v3, v2, v1] {
+            stack.push(crate::codegen::TypedValue {
+                ty: Type::I32,
+                value,
+            });
+        }
+
+        // The operands are already on top of the stack in order, so no stack ops are needed
+        let expected = [v1, v2, v3];
+        let constraints = [Constraint::Move; 3];
+        assert!(matches!(
+            OperandMovementConstraintSolver::new(&expected, &constraints, &stack),
+            Err(SolverError::AlreadySolved)
+        ));
+
+        // The same operands in the same order still require a solution if one must be copied
+        let constraints = [Constraint::Move, Constraint::Copy, Constraint::Move];
+        let solver = OperandMovementConstraintSolver::new(&expected, &constraints, &stack)
+            .expect("expected a copy to require a solution");
+        assert!(!solver.solve().expect("no solution found").is_empty());
+
+        // As do operands which are out of order
+        let expected = [v2, v1, v3];
+        let constraints = [Constraint::Move; 3];
+        let solver = OperandMovementConstraintSolver::new(&expected, &constraints, &stack)
+            .expect("expected out of order operands to require a solution");
+        assert!(!solver.solve().expect("no solution found").is_empty());
+    }
+
     #[test]
     fn operand_movement_constraint_solver_example() {
         let v1 = hir::Value::from_u32(1);
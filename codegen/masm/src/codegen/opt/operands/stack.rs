@@ -121,6 +121,21 @@ impl Stack {
         let len = self.stack.len();
         self.stack.get(len - index - 1)
     }
+
+    /// Render the operands on the stack, from the top, one per line, for use in diagnostics
+    pub fn describe(&self) -> String {
+        if self.stack.is_empty() {
+            return "<empty>".to_string();
+        }
+
+        self.stack
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(index, operand)| format!("[{index}] {:?}", operand.value))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 impl core::ops::Index<usize> for Stack {
     type Output = Operand;
@@ -200,7 +200,9 @@ impl<'a> SolutionBuilder<'a> {
     pub fn unwrap_current_position(&self, value: &ValueOrAlias) -> u8 {
         match self.get_current_position(value) {
             Some(pos) => pos,
-            None => panic!("value {value:?} not found on operand stack"),
+            None => {
+                panic!("value {value:?} not found on operand stack:\n{}", self.pending.describe())
+            }
         }
     }
 
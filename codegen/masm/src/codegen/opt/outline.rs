@@ -0,0 +1,238 @@
+use std::{cmp::Reverse, collections::BTreeMap, ops::Range};
+
+use miden_hir::{self as hir, FunctionIdent, Ident, Linkage, Signature, Symbol};
+
+use crate::masm::{self, Op};
+
+/// The code emitted for each copy of a loop header, see [outline_loop_headers]
+#[derive(Debug, Default)]
+pub struct LoopHeaderCopies {
+    copies: BTreeMap<hir::Block, Vec<(masm::BlockId, Range<usize>)>>,
+}
+impl LoopHeaderCopies {
+    /// Record that a copy of the code of `header`, excluding its terminator, was emitted to the
+    /// instructions in `range` of `block`
+    pub fn insert(&mut self, header: hir::Block, block: masm::BlockId, range: Range<usize>) {
+        self.copies.entry(header).or_default().push((block, range));
+    }
+}
+
+/// Factor the code of loop headers out of `function`, where it has been duplicated for the
+/// entry to the loop, and each of its loopback edges, replacing each copy with an `exec` of a new
+/// procedure containing that code.
+///
+/// A loop header is only factored out if:
+///
+/// * Every copy of it is identical, i.e. the operand stack was in the same state on each edge
+/// * It does not contain control flow, or refer to procedure locals or the caller, as the code
+/// would then no longer be valid, or would behave differently, in a procedure of its own
+/// * Doing so reduces the number of instructions in the program, i.e. the header has more than one
+/// instruction, and there is more than one copy of it
+///
+/// The copies in `headers` must not overlap, and must refer to the code of `function` as it was
+/// emitted, i.e. prior to any other optimizations.
+///
+/// Each new procedure is named after `function` and the loop header, e.g. `foo_loop_header1`,
+/// with a numeric suffix if that name is already taken, i.e. if `is_defined` returns true for it.
+///
+/// Returns the new procedures, which must be defined before `function`.
+pub fn outline_loop_headers(
+    function: &mut masm::Function,
+    headers: &LoopHeaderCopies,
+    is_defined: &dyn Fn(Ident) -> bool,
+) -> Vec<masm::Function> {
+    let mut outlined = vec![];
+    let mut replacements = vec![];
+    for (header, copies) in headers.copies.iter() {
+        let Some((blk, range)) = copies.first() else {
+            continue;
+        };
+        let ops = &function.block(*blk).ops[range.clone()];
+        let num_copies = copies.len();
+        if num_copies * ops.len() <= num_copies + ops.len() || !ops.iter().all(is_outlinable) {
+            continue;
+        }
+        let is_identical = copies
+            .iter()
+            .all(|(blk, range)| &function.block(*blk).ops[range.clone()] == ops);
+        if !is_identical {
+            continue;
+        }
+
+        let base = format!("{}_loop_header{}", function.name.function.as_str(), header.as_u32());
+        let is_taken = |name: Ident| {
+            name == function.name.function
+                || is_defined(name)
+                || outlined.iter().any(|p: &masm::Function| p.name.function == name)
+        };
+        let mut name = Ident::with_empty_span(Symbol::intern(&base));
+        let mut suffix = 0;
+        while is_taken(name) {
+            suffix += 1;
+            name = Ident::with_empty_span(Symbol::intern(format!("{base}_{suffix}")));
+        }
+        let name = FunctionIdent {
+            module: function.name.module,
+            function: name,
+        };
+        let mut signature = Signature::new([], []);
+        signature.linkage = Linkage::Internal;
+        let mut procedure = masm::Function::new(name, signature);
+        procedure.span = function.span;
+        let entry = procedure.body.id();
+        procedure.block_mut(entry).extend_from_slice(ops);
+        procedure.update_may_trap();
+        outlined.push(procedure);
+
+        replacements.extend(copies.iter().map(|(blk, range)| (*blk, range.clone(), name)));
+    }
+
+    // Replace the copies in reverse order within each block, so that the ranges of the remaining
+    // copies are unaffected by the replacement
+    replacements.sort_by_key(|(blk, range, _)| (*blk, Reverse(range.start)));
+    for (blk, range, name) in replacements {
+        let ops = &mut function.block_mut(blk).ops;
+        let start = range.start;
+        ops.drain(range);
+        ops.insert(start, Op::Exec(name));
+    }
+    if !outlined.is_empty() {
+        function.update_may_trap();
+    }
+
+    outlined
+}
+
+/// Returns true if `op` behaves the same when moved into a procedure of its own
+fn is_outlinable(op: &Op) -> bool {
+    !matches!(
+        op,
+        Op::If(..)
+            | Op::While(_)
+            | Op::Repeat(..)
+            | Op::LocAddr(_)
+            | Op::LocStore(_)
+            | Op::LocStorew(_)
+            | Op::LocLoad(_)
+            | Op::LocLoadw(_)
+            | Op::Caller
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outline_loop_headers_test() {
+        let mut function =
+            masm::Function::new("test::outline".parse().unwrap(), Signature::new([], []));
+        let entry = function.body.id();
+        let body_blk = function.create_block();
+        let header = hir::Block::from_u32(1);
+        let other = hir::Block::from_u32(2);
+
+        // The header of the outer loop is emitted on entry, and on its loopback edge, while the
+        // header of the other loop is too small to be worth factoring out
+        function.block_mut(entry).extend_from_slice(&[
+            Op::Dup(0),
+            Op::Dup(0),
+            Op::Mul,
+            Op::PushU8(1),
+            Op::While(body_blk),
+        ]);
+        function.block_mut(body_blk).extend_from_slice(&[
+            Op::Incr,
+            Op::Dup(0),
+            Op::Dup(0),
+            Op::Mul,
+            Op::Drop,
+            Op::Dup(0),
+            Op::Dup(0),
+            Op::Mul,
+            Op::PushU8(1),
+        ]);
+        let mut headers = LoopHeaderCopies::default();
+        headers.insert(header, entry, 0..3);
+        headers.insert(header, body_blk, 5..8);
+        headers.insert(header, body_blk, 1..4);
+        headers.insert(other, body_blk, 4..5);
+
+        let outlined = outline_loop_headers(&mut function, &headers, &|_| false);
+        assert_eq!(outlined.len(), 1);
+        let procedure = &outlined[0];
+        let name = "test::outline_loop_header1".parse().unwrap();
+        assert_eq!(procedure.name, name);
+        assert!(!procedure.signature.is_public());
+        assert_eq!(
+            procedure.block(procedure.body.id()).ops.as_slice(),
+            &[Op::Dup(0), Op::Dup(0), Op::Mul]
+        );
+        assert_eq!(
+            function.block(entry).ops.as_slice(),
+            &[Op::Exec(name), Op::PushU8(1), Op::While(body_blk)]
+        );
+        assert_eq!(
+            function.block(body_blk).ops.as_slice(),
+            &[Op::Incr, Op::Exec(name), Op::Drop, Op::Exec(name), Op::PushU8(1)]
+        );
+    }
+
+    #[test]
+    fn preserve_divergent_loop_headers_test() {
+        let mut function =
+            masm::Function::new("test::outline".parse().unwrap(), Signature::new([], []));
+        let entry = function.body.id();
+        let body_blk = function.create_block();
+        let header = hir::Block::from_u32(1);
+
+        // The operand stack differs between the copies
+        function.block_mut(entry).extend_from_slice(&[
+            Op::Dup(0),
+            Op::Dup(0),
+            Op::Mul,
+            Op::While(body_blk),
+        ]);
+        function.block_mut(body_blk).extend_from_slice(&[
+            Op::Dup(1),
+            Op::Dup(1),
+            Op::Mul,
+            Op::PushU8(1),
+        ]);
+        let mut headers = LoopHeaderCopies::default();
+        headers.insert(header, entry, 0..3);
+        headers.insert(header, body_blk, 0..3);
+
+        let original = function.body.clone();
+        assert!(outline_loop_headers(&mut function, &headers, &|_| false).is_empty());
+        for (blk, block) in original.blocks.iter() {
+            assert_eq!(function.block(blk).ops, block.ops);
+        }
+    }
+    #[test]
+    fn outline_loop_headers_unique_names_test() {
+        let mut function =
+            masm::Function::new("test::outline".parse().unwrap(), Signature::new([], []));
+        let entry = function.body.id();
+        let header = hir::Block::from_u32(1);
+        function.block_mut(entry).extend_from_slice(&[
+            Op::Dup(0),
+            Op::Dup(0),
+            Op::Mul,
+            Op::Dup(0),
+            Op::Dup(0),
+            Op::Mul,
+        ]);
+        let mut headers = LoopHeaderCopies::default();
+        headers.insert(header, entry, 0..3);
+        headers.insert(header, entry, 3..6);
+
+        // The module already defines a function with the name the procedure would otherwise get
+        let taken = Ident::from("outline_loop_header1");
+        let outlined = outline_loop_headers(&mut function, &headers, &|name| name == taken);
+        assert_eq!(outlined.len(), 1);
+        let name = "test::outline_loop_header1_1".parse().unwrap();
+        assert_eq!(outlined[0].name, name);
+        assert_eq!(function.block(entry).ops.as_slice(), &[Op::Exec(name), Op::Exec(name)]);
+    }
+}
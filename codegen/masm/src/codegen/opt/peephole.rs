@@ -0,0 +1,150 @@
+use smallvec::SmallVec;
+
+use crate::masm::{self, Op};
+
+/// Apply peephole optimizations to the instructions of `block`, removing adjacent sequences of
+/// stack manipulation instructions which cancel each other out.
+///
+/// The following sequences are removed:
+///
+/// * `swap.N swap.N` and `swapw.N swapw.N`
+/// * `movup.N movdn.N` and `movdn.N movup.N`, as well as their word-sized equivalents
+/// * `dup.N drop` and `dupw.N dropw`
+///
+/// As `movup.1` and `movdn.1` are both equivalent to `swap.1`, they are treated as such.
+///
+/// This operates purely on the instruction stream, without emulating the operand stack. Removing
+/// a sequence may expose another one, e.g. `swap.1 movup.2 movdn.2 swap.1`, which is removed as
+/// well, so applying this more than once has no further effect.
+///
+/// Returns true if any instructions were removed.
+pub fn optimize(block: &mut masm::Block) -> bool {
+    let mut ops = SmallVec::<[Op; 4]>::with_capacity(block.ops.len());
+    for op in block.ops.iter().copied() {
+        match ops.last() {
+            Some(prev) if cancels(prev, &op) => {
+                ops.pop();
+            }
+            _ => ops.push(op),
+        }
+    }
+
+    let changed = ops.len() != block.ops.len();
+    block.ops = ops;
+    changed
+}
+
+/// Returns true if executing `a` followed by `b` leaves the operand stack unchanged
+fn cancels(a: &Op, b: &Op) -> bool {
+    match (canonicalize(a), canonicalize(b)) {
+        (Op::Swap(a), Op::Swap(b))
+        | (Op::Swapw(a), Op::Swapw(b))
+        | (Op::Movup(a), Op::Movdn(b))
+        | (Op::Movdn(a), Op::Movup(b))
+        | (Op::Movupw(a), Op::Movdnw(b))
+        | (Op::Movdnw(a), Op::Movupw(b)) => a == b,
+        (Op::Dup(_), Op::Drop) | (Op::Dupw(_), Op::Dropw) => true,
+        _ => false,
+    }
+}
+
+/// Rewrite instructions which have more than one form to a single canonical form
+fn canonicalize(op: &Op) -> Op {
+    match op {
+        Op::Movup(1) | Op::Movdn(1) => Op::Swap(1),
+        op => *op,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift_entity::EntityRef;
+    use smallvec::smallvec;
+
+    use super::*;
+
+    fn block(ops: SmallVec<[Op; 4]>) -> masm::Block {
+        masm::Block {
+            id: masm::BlockId::new(0),
+            ops,
+            origin: None,
+        }
+    }
+
+    #[test]
+    fn peephole_removes_cancelling_pairs_test() {
+        let mut blk = block(smallvec![
+            Op::Swap(1),
+            Op::Swap(1),
+            Op::Movup(2),
+            Op::Movdn(2),
+            Op::Movdn(3),
+            Op::Movup(3),
+            Op::Dup(0),
+            Op::Drop,
+            Op::Dupw(1),
+            Op::Dropw,
+            Op::Movup(1),
+            Op::Swap(1),
+            Op::Add,
+        ]);
+
+        assert!(optimize(&mut blk));
+        assert_eq!(blk.ops.as_slice(), &[Op::Add]);
+    }
+
+    #[test]
+    fn peephole_removes_nested_pairs_test() {
+        let mut blk = block(smallvec![
+            Op::Dup(2),
+            Op::Swap(1),
+            Op::Movup(2),
+            Op::Movdn(2),
+            Op::Swap(1),
+            Op::Drop,
+            Op::Add,
+        ]);
+
+        assert!(optimize(&mut blk));
+        assert_eq!(blk.ops.as_slice(), &[Op::Add]);
+    }
+
+    #[test]
+    fn peephole_preserves_non_cancelling_sequences_test() {
+        let ops: SmallVec<[Op; 4]> = smallvec![
+            Op::Swap(1),
+            Op::Swap(2),
+            Op::Movup(2),
+            Op::Movdn(3),
+            Op::Movup(3),
+            Op::Movup(3),
+            Op::Drop,
+            Op::Dup(0),
+            Op::Add,
+        ];
+        let mut blk = block(ops.clone());
+
+        assert!(!optimize(&mut blk));
+        assert_eq!(blk.ops, ops);
+    }
+
+    #[test]
+    fn peephole_is_idempotent_test() {
+        let mut blk = block(smallvec![
+            Op::Swap(1),
+            Op::Dup(0),
+            Op::Swap(2),
+            Op::Swap(2),
+            Op::Drop,
+            Op::Mul,
+            Op::Movdn(2),
+        ]);
+
+        assert!(optimize(&mut blk));
+        let once = blk.ops.clone();
+        assert_eq!(once.as_slice(), &[Op::Swap(1), Op::Mul, Op::Movdn(2)]);
+
+        assert!(!optimize(&mut blk));
+        assert_eq!(blk.ops, once);
+    }
+}
@@ -4,12 +4,15 @@ use cranelift_entity::SecondaryMap;
 use miden_hir::{
     self as hir,
     adt::{SmallMap, SmallSet, SparseMap, SparseMapValue},
-    assert_matches, BranchInfo, ProgramPoint,
+    assert_matches,
+    pass::{AnalysisManager, AnalysisResult},
+    BranchInfo, ProgramPoint,
 };
 use miden_hir_analysis::{
     dependency_graph::{ArgumentNode, DependencyGraph, Node, NodeId},
     DominatorTree, LivenessAnalysis, Loop, LoopAnalysis, OrderedTreeGraph,
 };
+use midenc_session::Session;
 use smallvec::SmallVec;
 
 use crate::{codegen::Constraint, masm};
@@ -35,10 +38,13 @@ pub struct ValueInfo {
     /// This vector is sorted such that the earlier a user appears in it,
     /// the later they are scheduled in the block.
     users: SmallVec<[NodeId; 1]>,
+    /// True if at least one of `users` uses this value as an argument, rather than only being
+    /// ordered after the instruction which produces it via a control dependency
+    is_used_as_argument: bool,
 }
 impl ValueInfo {
     pub fn is_used(&self) -> bool {
-        self.is_externally_used || !self.users.is_empty()
+        self.is_externally_used || self.is_used_as_argument
     }
 
     /// Return the [NodeId] of the first user of this value to be emitted
@@ -339,6 +345,29 @@ impl<'a> Scheduler<'a> {
     }
 }
 
+/// Compute the instruction [Schedule] of `function`, independently of code generation.
+///
+/// The dominator tree, loop and liveness analyses of `function` are taken from `analyses` if
+/// present, and computed otherwise. The [BlockInfo] of each block in the resulting schedule
+/// provides access to the dependency graph and tree graph the block was scheduled from.
+///
+/// This is intended for inspecting the decisions made by the [Scheduler], e.g. in tests; code
+/// generation constructs a [Scheduler] directly, as the schedule refers to the blocks of the
+/// function being emitted.
+pub fn compute_schedule(
+    function: &hir::Function,
+    analyses: &mut AnalysisManager,
+    session: &Session,
+) -> AnalysisResult<Schedule> {
+    let domtree = analyses.get_or_compute::<DominatorTree>(function, session)?;
+    let loops = analyses.get_or_compute::<LoopAnalysis>(function, session)?;
+    let liveness = analyses.get_or_compute::<LivenessAnalysis>(function, session)?;
+
+    // The blocks of this function are only used as targets in the schedule
+    let mut f_prime = masm::Function::new(function.id, function.signature.clone());
+    Ok(Scheduler::new(function, &mut f_prime, &domtree, &loops, &liveness).build())
+}
+
 struct BlockScheduler<'a> {
     f: &'a hir::Function,
     liveness: &'a LivenessAnalysis,
@@ -452,7 +481,9 @@ impl<'a> BlockScheduler<'a> {
                 // when it has no results (and thus no dependents/predecessors in the graph),
                 // because in all other cases it will always have a predecessor of Result type.
                 //
-                // In practice, we only observe these nodes when handling block terminators.
+                // In practice, we only observe these nodes when handling block terminators, or
+                // instructions with side effects which are the control dependency of multiple
+                // instructions, e.g. a store followed by multiple loads.
                 Node::Inst { id: inst, .. } => {
                     let inst_info = self.get_or_analyze_inst_info(inst, node_id);
                     self.plan_inst(inst_info);
@@ -531,9 +562,9 @@ impl<'a> BlockScheduler<'a> {
                 Node::Stack(_) => continue,
                 // This is a control dependency, so it must be materialized
                 //
-                // Currently, control dependencies are only attached to a single instruction,
-                // so we do not need to check if another dependent will materialize it, it is
-                // definitely on us.
+                // If the control dependency was shared by multiple instructions, it would be a
+                // treegraph root, so we do not need to check if another dependent will
+                // materialize it, it is definitely on us.
                 Node::Inst { id: inst, .. } => {
                     let inst_info = self.get_or_analyze_inst_info(inst, dependency_id);
                     self.materialize_inst_results(inst_info);
@@ -862,13 +893,10 @@ impl<'a> BlockScheduler<'a> {
             .successors(inst_node_id)
             .filter(|succ| !succ.dependency.is_argument())
         {
-            let succ_node_id = if succ.dependency.is_instruction() {
-                succ.dependency
-            } else {
-                assert!(succ.dependency.is_result());
-                self.block_info.depgraph.unwrap_child(succ.dependency)
-            };
-            inst_info.pre.insert(succ_node_id);
+            // A result node is scheduled like an argument would be, so that the instruction
+            // producing it is only materialized once, even if it has other dependents
+            assert!(succ.dependency.is_instruction() || succ.dependency.is_result());
+            inst_info.pre.insert(succ.dependency);
         }
 
         Rc::from(inst_info)
@@ -896,11 +924,13 @@ impl<'a> BlockScheduler<'a> {
                 node: result_node_id,
                 is_externally_used,
                 users: Default::default(),
+                is_used_as_argument: false,
             };
 
             // Record all of the instructions in the current block which use this result
             for pred in self.block_info.depgraph.predecessors(result_node_id) {
                 if pred.dependent.is_argument() {
+                    info.is_used_as_argument = true;
                     info.users.push(self.block_info.depgraph.unwrap_parent(pred.dependent));
                 } else {
                     assert!(pred.dependent.is_instruction());
@@ -935,6 +965,8 @@ impl<'a> BlockScheduler<'a> {
     /// An operand must be copied unless all of the following are true:
     ///
     /// * This is the last use (in the current block) of the value
+    /// * This is the last of the arguments of its instruction which use the value, when the same
+    ///   value is used more than once by that instruction
     /// * The value is not live in any successor, with the exception of successors which define the
     ///   value (as seen along loopback edges)
     ///
@@ -985,13 +1017,59 @@ impl<'a> BlockScheduler<'a> {
             }
             _ => unreachable!(),
         };
-        let is_last_use = !is_live_after && is_last_dependent;
+        let is_last_argument =
+            self.is_last_argument_use(arg_node, arg_sourced_from, this_dependent);
+        let is_last_use = !is_live_after && is_last_dependent && is_last_argument;
         if is_last_use {
             Constraint::Move
         } else {
             Constraint::Copy
         }
     }
+
+    /// Returns true if `arg_node` is the argument of `dependent` which consumes the value
+    /// produced by `arg_sourced_from`, when that value is used by more than one of its arguments.
+    ///
+    /// Only one such argument may move the value, the rest must copy it, or the value would be
+    /// consumed before all of the arguments are materialized. The argument which is allowed to
+    /// move the value is chosen as follows:
+    ///
+    /// * Direct arguments are materialized before any successor arguments, so a direct argument can
+    ///   only move the value if no successor argument uses it. Among direct arguments, the one with
+    ///   the highest index is the last to be materialized.
+    /// * Each successor receives its own copy of the operand stack, so successor arguments only
+    ///   conflict with other arguments to the same successor, where the one with the highest index
+    ///   is the last to be materialized.
+    fn is_last_argument_use(
+        &self,
+        arg_node: NodeId,
+        arg_sourced_from: NodeId,
+        dependent: NodeId,
+    ) -> bool {
+        let depgraph = &self.block_info.depgraph;
+        let Node::Argument(arg) = arg_node.into() else {
+            unreachable!()
+        };
+        depgraph
+            .predecessors(arg_sourced_from)
+            .map(|pred| pred.dependent)
+            .filter(|&node| {
+                node != arg_node && node.is_argument() && depgraph.unwrap_parent(node) == dependent
+            })
+            .all(|node| {
+                let Node::Argument(other) = node.into() else {
+                    unreachable!()
+                };
+                match (arg.successor(), other.successor()) {
+                    (None, None) => arg.index() > other.index(),
+                    (None, Some(_)) => false,
+                    (Some(_), None) => true,
+                    (Some(successor), Some(other_successor)) => {
+                        successor != other_successor || arg.index() > other.index()
+                    }
+                }
+            })
+    }
 }
 
 fn transitive_instruction_dependents(
@@ -1001,10 +1079,9 @@ fn transitive_instruction_dependents(
     current_block_info
         .depgraph
         .predecessors(stack_or_result_node)
-        .map(|p| {
-            assert!(p.dependent.is_argument());
-            current_block_info.depgraph.unwrap_parent(p.dependent)
-        })
+        // Control dependents are ordered after the value is produced, but never consume it
+        .filter(|p| p.dependent.is_argument())
+        .map(|p| current_block_info.depgraph.unwrap_parent(p.dependent))
         .collect()
 }
 
@@ -1019,6 +1096,9 @@ fn build_dependency_graph(
     let mut materialized_args = SmallSet::<hir::Value, 4>::default();
     // This map represents values used as block arguments, and the successors which use them
     let mut block_arg_uses = SmallMap::<hir::Value, SmallSet<hir::Block, 2>>::default();
+    // These are the instructions which access memory seen so far, along with the memory they
+    // access, and whether they may write to it, see below
+    let mut accesses = SmallVec::<[(NodeId, MemoryAccess, bool); 4]>::default();
 
     // For each instruction, record it and it's arguments/results in the graph
     for (inst_index, inst) in function.dfg.block_insts(block_id).enumerate() {
@@ -1109,6 +1189,33 @@ fn build_dependency_graph(
             }
             BranchInfo::NotABranch => (),
         }
+
+        // Instructions which access memory must not be reordered with a preceding instruction
+        // which may access the same memory, unless neither of them writes to it, e.g. a load from
+        // an address must observe a preceding store to that address, and a store must not clobber
+        // the value read by a preceding load. As such instructions do not necessarily use the
+        // results of one another, an explicit control dependency is added instead.
+        //
+        // Reads whose results are never used need not be ordered before later writes, as they
+        // are removed by `dce` below.
+        //
+        // The block terminator is handled by `assign_control_dependencies` below.
+        if let Some(access) = MemoryAccess::of(inst, function) {
+            let writes = function.dfg.inst(inst).has_side_effects();
+            for (prior, ..) in accesses.iter().filter(|(_, prior_access, prior_writes)| {
+                (writes || *prior_writes) && prior_access.may_alias(&access)
+            }) {
+                graph.add_control_dependency(node_id, *prior);
+            }
+            let is_used = function
+                .dfg
+                .inst_results(inst)
+                .iter()
+                .any(|result| liveness.is_live_after(result, pp));
+            if writes || is_used {
+                accesses.push((node_id, access, writes));
+            }
+        }
     }
 
     // HACK: If there are any instruction nodes with no predecessors, with the exception of the
@@ -1133,6 +1240,89 @@ fn build_dependency_graph(
     graph
 }
 
+/// The memory accessed by an instruction, as far as can be determined statically
+#[derive(Debug, Copy, Clone)]
+enum MemoryAccess {
+    /// `size` bytes at `offset` from the address of the global variable `symbol`
+    Global {
+        symbol: hir::Symbol,
+        offset: i64,
+        size: usize,
+    },
+    /// `size` bytes at the address given by `addr`
+    Value { addr: hir::Value, size: usize },
+    /// Any memory may be accessed, e.g. by a call
+    Unknown,
+}
+impl MemoryAccess {
+    /// Returns the memory accessed by `inst`, or `None` if `inst` neither reads memory, nor has
+    /// any side effects.
+    ///
+    /// Instructions with side effects other than loads and stores are assumed to access any
+    /// memory. Block terminators are not considered.
+    fn of(inst: hir::Inst, function: &hir::Function) -> Option<Self> {
+        match function.dfg.inst(inst) {
+            hir::Instruction::Load(hir::LoadOp { addr, ty, .. }) => {
+                Some(Self::at(*addr, ty.size_in_bytes(), function))
+            }
+            data if data.opcode() == hir::Opcode::Store => {
+                let args = function.dfg.inst_args(inst);
+                let size = function.dfg.value_type(args[1]).size_in_bytes();
+                Some(Self::at(args[0], size, function))
+            }
+            data if data.opcode().is_terminator() => None,
+            data if data.has_side_effects() => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+
+    /// Returns the access of `size` bytes at the address given by `addr`
+    fn at(addr: hir::Value, size: usize, function: &hir::Function) -> Self {
+        if let hir::ValueData::Inst { inst, .. } = function.dfg.value_data(addr) {
+            if let hir::Instruction::GlobalValue(hir::GlobalValueOp { global, .. }) =
+                function.dfg.inst(*inst)
+            {
+                if let hir::GlobalValueData::Symbol { name, offset } =
+                    function.dfg.global_value(*global)
+                {
+                    return Self::Global {
+                        symbol: name.as_symbol(),
+                        offset: *offset as i64,
+                        size,
+                    };
+                }
+            }
+        }
+        Self::Value { addr, size }
+    }
+
+    /// Returns true unless `self` and `other` are known to access disjoint memory.
+    ///
+    /// This is conservative: the only accesses known to be disjoint are those of distinct global
+    /// variables, or of non-overlapping ranges of the same global variable.
+    fn may_alias(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Global {
+                    symbol: a,
+                    offset: a_offset,
+                    size: a_size,
+                },
+                Self::Global {
+                    symbol: b,
+                    offset: b_offset,
+                    size: b_size,
+                },
+            ) => {
+                a == b
+                    && *a_offset < *b_offset + *b_size as i64
+                    && *b_offset < *a_offset + *a_size as i64
+            }
+            _ => true,
+        }
+    }
+}
+
 /// Discover any instructions in the given block that have no predecessors, but that must be
 /// scheduled anyway, i.e. due to side effects - and make the block terminator dependent on them to
 /// ensure that they are scheduled.
@@ -1234,6 +1424,25 @@ fn dce(
     // are used, or are live beyond it's containing block; and the instruction has no
     // side-effects, then remove all of the nodes related to that instruction, continuing
     // until there are no more nodes to process.
+    //
+    // Instructions which a dead instruction was ordered after via a control dependency are made
+    // dependencies of the block terminator again if nothing else depends on them, as they would
+    // otherwise be left unscheduled.
+    //
+    // NOTE: `liveness` is not updated to reflect the instructions removed here. Uses within this
+    // block are tracked by the dependency graph, so this only matters for values used by a dead
+    // instruction in another block, which are still considered live in the blocks preceding it,
+    // and would be copied rather than moved there. Such instructions are expected to have been
+    // removed before code generation by the `EliminateDeadCode` rewrite, which recomputes liveness
+    // after each round of removals.
+    let terminator_id: NodeId = {
+        let block = function.dfg.block(block_id);
+        Node::Inst {
+            id: block.last().unwrap(),
+            pos: (block.len() - 1) as u16,
+        }
+        .into()
+    };
     let mut worklist = VecDeque::<(hir::Inst, NodeId)>::from_iter(
         function.dfg.block_insts(block_id).enumerate().map(|(i, inst)| {
             (
@@ -1247,6 +1456,7 @@ fn dce(
         }),
     );
     let mut remove_nodes = Vec::<NodeId>::default();
+    let mut control_dependencies = SmallVec::<[NodeId; 2]>::default();
     while let Some((inst, inst_node)) = worklist.pop_front() {
         // If the instruction is not dead at this point, leave it alone
         if !is_dead_instruction(inst, block_id, function, liveness, graph) {
@@ -1339,12 +1549,16 @@ fn dce(
                         hir::ValueData::Param { .. } => {}
                     }
                 }
-                // This is a control dependency added intentionally, skip it
-                Node::Inst { .. } => continue,
-                // No other node types are possible
-                Node::Result { .. } | Node::Stack(_) => {
-                    unreachable!("invalid successor for instruction node")
+                // This is a control dependency added intentionally, keep it
+                Node::Inst { .. } => {
+                    control_dependencies.push(dependency_node_id);
+                    continue;
                 }
+                // This is a control dependency on an instruction with results, which is
+                // scheduled via those results whether they have other dependents or not
+                Node::Result { .. } => continue,
+                // No other node types are possible
+                Node::Stack(_) => unreachable!("invalid successor for instruction node"),
             }
             remove_nodes.push(dependency_node_id);
         }
@@ -1358,9 +1572,15 @@ fn dce(
         remove_nodes.push(inst_node);
 
         // All of the nodes to be removed are queued, so remove them now before we proceed
-        for remove_id in remove_nodes.iter().copied() {
+        for remove_id in remove_nodes.drain(..) {
             graph.remove_node(remove_id);
         }
+
+        for dependency_id in control_dependencies.drain(..) {
+            if graph.num_predecessors(dependency_id) == 0 {
+                graph.add_dependency(terminator_id, dependency_id);
+            }
+        }
     }
 }
 
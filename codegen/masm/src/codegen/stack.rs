@@ -1,10 +1,14 @@
 use core::{
     fmt,
     hash::{Hash, Hasher},
+    num::NonZeroU16,
     ops::{Index, IndexMut},
 };
 
-use miden_hir::{Felt, FieldElement, Immediate, Type, Value};
+use miden_hir::{
+    AddressSpace, Alignable, Felt, FieldElement, Immediate, LocalId, StarkField, StructType, Type,
+    TypeRepr, Value,
+};
 use smallvec::{smallvec, SmallVec};
 
 /// This represents a constraint an operand's usage at
@@ -274,6 +278,12 @@ impl Default for Operand {
         }
     }
 }
+impl Eq for Operand {}
+impl PartialEq for Operand {
+    fn eq(&self, other: &Self) -> bool {
+        self.word == other.word && self.operand == other.operand
+    }
+}
 impl PartialEq<Value> for Operand {
     #[inline(always)]
     fn eq(&self, other: &Value) -> bool {
@@ -518,18 +528,56 @@ impl Operand {
 /// In addition to the state tracked, this structure also has an API that mimics the
 /// stack manipulation instructions we can emit in the code generator, so that as we
 /// emit instructions and modify this structure at the same time, 1:1.
+///
+/// Values which have been spilled to procedure locals, in order to keep the depth of the
+/// operand stack within the range of the stack manipulation instructions, are tracked
+/// here as well, until they are either reloaded for their last use, or discarded.
 #[derive(Clone)]
 pub struct OperandStack {
     stack: Vec<Operand>,
+    spills: SmallVec<[(TypedValue, LocalId); 2]>,
 }
 impl Default for OperandStack {
     fn default() -> Self {
         Self {
             stack: Vec::with_capacity(16),
+            spills: Default::default(),
         }
     }
 }
 impl OperandStack {
+    /// Record that `value` has been removed from the operand stack, and stored in `local`
+    pub fn spill(&mut self, value: TypedValue, local: LocalId) {
+        assert!(
+            self.spilled(&value.value).is_none(),
+            "{} has already been spilled",
+            &value.value
+        );
+        self.spills.push((value, local));
+    }
+
+    /// If `value` was spilled from the operand stack, returns the value, along with the
+    /// procedure local it was stored in.
+    pub fn spilled(&self, value: &Value) -> Option<(&TypedValue, LocalId)> {
+        self.spills
+            .iter()
+            .find_map(|(spilled, local)| (&spilled.value == value).then_some((spilled, *local)))
+    }
+
+    /// Forget that `value` was spilled, as it is no longer needed
+    ///
+    /// Returns true if `value` was spilled
+    pub fn discard_spill(&mut self, value: &Value) -> bool {
+        let len = self.spills.len();
+        self.spills.retain(|(spilled, _)| &spilled.value != value);
+        len != self.spills.len()
+    }
+
+    /// Returns an iterator over the values which are currently spilled
+    pub fn spills(&self) -> impl Iterator<Item = &TypedValue> {
+        self.spills.iter().map(|(value, _)| value)
+    }
+
     /// Renames the `n`th operand from the top of the stack to `value`
     ///
     /// The type is assumed to remain unchanged
@@ -555,6 +603,51 @@ impl OperandStack {
         self.stack.iter().rev().position(|v| v == value)
     }
 
+    /// Returns the positions of every operand on the stack corresponding to `value`, starting
+    /// from the top of the stack.
+    ///
+    /// A value can be on the stack more than once, i.e. when it has been copied by `dup`.
+    pub fn find_all(&self, value: &Value) -> SmallVec<[usize; 4]> {
+        self.stack
+            .iter()
+            .rev()
+            .enumerate()
+            .filter_map(|(index, v)| if v == value { Some(index) } else { None })
+            .collect()
+    }
+
+    /// Render the operands on the stack, from the top, one per line, followed by the values which
+    /// are currently spilled, for use in diagnostics.
+    ///
+    /// Each operand is rendered with its index, and either the value and type of an SSA value, the
+    /// value and type of a constant, or just the type of an intermediate value. An SSA value which
+    /// is on the stack more than once is annotated with the index of its topmost copy.
+    pub fn describe(&self) -> String {
+        if self.stack.is_empty() && self.spills.is_empty() {
+            return "<empty>".to_string();
+        }
+
+        let mut lines = Vec::with_capacity(self.stack.len() + self.spills.len());
+        for (index, operand) in self.stack.iter().rev().enumerate() {
+            let operand = match operand.operand {
+                OperandType::Value(ref value) => match self.find(&value.value) {
+                    Some(topmost) if topmost < index => format!("{value} (copy of [{topmost}])"),
+                    _ => value.to_string(),
+                },
+                OperandType::Const(ConstantValue::Imm(imm)) => {
+                    format!("const {imm}: {}", imm.ty())
+                }
+                OperandType::Const(ref bytes) => format!("const {bytes:?}: {}", bytes.ty()),
+                OperandType::Type(ref ty) => format!("_: {ty}"),
+            };
+            lines.push(format!("[{index}] {operand}"));
+        }
+        for (value, local) in self.spills.iter() {
+            lines.push(format!("[spilled] {value} in {local}"));
+        }
+        lines.join("\n")
+    }
+
     /// Returns true if the operand stack is empty
     #[allow(unused)]
     #[inline(always)]
@@ -903,6 +996,465 @@ impl fmt::Debug for OperandStack {
     }
 }
 
+impl OperandStack {
+    /// Capture the current state of this operand stack, i.e. every operand on it, including
+    /// which values alias each other, as well as the values which are currently spilled.
+    ///
+    /// The state can be restored later with [OperandStack::restore], e.g. to resume code
+    /// generation at a loop header, without having to emit the code which precedes it.
+    pub fn snapshot(&self) -> OperandStackSnapshot {
+        OperandStackSnapshot {
+            stack: self.stack.clone(),
+            spills: self.spills.clone(),
+        }
+    }
+
+    /// Construct an operand stack in the state captured by `snapshot`
+    pub fn restore(snapshot: OperandStackSnapshot) -> Self {
+        Self {
+            stack: snapshot.stack,
+            spills: snapshot.spills,
+        }
+    }
+}
+
+/// The state of an [OperandStack] at some program point, as captured by [OperandStack::snapshot].
+///
+/// A snapshot can be serialized with [OperandStackSnapshot::to_bytes], so that it can be cached
+/// across compilations. The encoding is versioned, and is only ever extended, so that bytes
+/// produced by one version of the compiler are either decoded identically, or rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperandStackSnapshot {
+    stack: Vec<Operand>,
+    spills: SmallVec<[(TypedValue, LocalId); 2]>,
+}
+
+/// The error produced when decoding an invalid [OperandStackSnapshot]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("invalid operand stack snapshot: missing or corrupted header")]
+    InvalidHeader,
+    #[error("invalid operand stack snapshot: unsupported version {0}")]
+    UnsupportedVersion(u8),
+    #[error("invalid operand stack snapshot: unexpected end of input")]
+    UnexpectedEof,
+    #[error("invalid operand stack snapshot: unexpected trailing bytes")]
+    TrailingBytes,
+    #[error("invalid operand stack snapshot: invalid {0} tag {1}")]
+    InvalidTag(&'static str, u8),
+    #[error("invalid operand stack snapshot: {0}")]
+    InvalidOperand(&'static str),
+    #[error("cannot encode operand stack snapshot: {0}")]
+    Unencodable(&'static str),
+}
+
+impl OperandStackSnapshot {
+    const MAGIC: &'static [u8; 4] = b"MSTK";
+    const VERSION: u8 = 1;
+
+    /// Encode this snapshot as bytes
+    ///
+    /// The encoding consists of a header, the operands on the stack from the bottom up, and then
+    /// the spilled values, with all integers in little-endian byte order.
+    ///
+    /// Returns an error if the snapshot contains something which is too large to be encoded, e.g.
+    /// a struct type with more than 255 fields.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut bytes = Vec::with_capacity(16 + self.stack.len() * 8);
+        bytes.extend_from_slice(Self::MAGIC);
+        bytes.push(Self::VERSION);
+        encode_len(self.stack.len(), &mut bytes)?;
+        for operand in self.stack.iter() {
+            let size = u8::try_from(operand.word.len())
+                .map_err(|_| SnapshotError::Unencodable("operand is too large"))?;
+            bytes.push(size);
+            for ty in operand.word.iter() {
+                encode_type(ty, &mut bytes)?;
+            }
+            match &operand.operand {
+                OperandType::Const(ConstantValue::Imm(imm)) => {
+                    bytes.push(0);
+                    encode_immediate(imm, &mut bytes);
+                }
+                OperandType::Const(ConstantValue::Bytes(data)) => {
+                    bytes.push(1);
+                    encode_len(data.len(), &mut bytes)?;
+                    bytes.extend_from_slice(data);
+                }
+                OperandType::Value(value) => {
+                    bytes.push(2);
+                    encode_typed_value(value, &mut bytes)?;
+                }
+                OperandType::Type(ty) => {
+                    bytes.push(3);
+                    encode_type(ty, &mut bytes)?;
+                }
+            }
+        }
+        encode_len(self.spills.len(), &mut bytes)?;
+        for (value, local) in self.spills.iter() {
+            encode_typed_value(value, &mut bytes)?;
+            let local = u8::try_from(local.as_usize())
+                .map_err(|_| SnapshotError::Unencodable("invalid local id"))?;
+            bytes.push(local);
+        }
+        Ok(bytes)
+    }
+
+    /// Decode a snapshot previously encoded with [OperandStackSnapshot::to_bytes]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = SnapshotReader { bytes };
+        if reader.take::<4>().ok().as_ref() != Some(Self::MAGIC) {
+            return Err(SnapshotError::InvalidHeader);
+        }
+        match reader.u8()? {
+            Self::VERSION => (),
+            version => return Err(SnapshotError::UnsupportedVersion(version)),
+        }
+
+        let len = reader.u32()? as usize;
+        let mut stack = Vec::with_capacity(len.min(bytes.len()));
+        for _ in 0..len {
+            let size = reader.u8()? as usize;
+            if size == 0 || size > 4 {
+                return Err(SnapshotError::InvalidOperand(
+                    "operand must be 1 to 4 elements in size",
+                ));
+            }
+            let mut word = SmallVec::<[Type; 4]>::new();
+            for _ in 0..size {
+                word.push(reader.ty()?);
+            }
+            let operand = match reader.u8()? {
+                0 => OperandType::Const(ConstantValue::Imm(reader.immediate()?)),
+                1 => {
+                    let len = reader.u32()? as usize;
+                    OperandType::Const(ConstantValue::Bytes(SmallVec::from(reader.slice(len)?)))
+                }
+                2 => OperandType::Value(reader.typed_value()?),
+                3 => OperandType::Type(reader.ty()?),
+                tag => return Err(SnapshotError::InvalidTag("operand", tag)),
+            };
+            stack.push(Operand { word, operand });
+        }
+
+        let len = reader.u32()? as usize;
+        let mut spills = SmallVec::with_capacity(len.min(bytes.len()));
+        for _ in 0..len {
+            let value = reader.typed_value()?;
+            let local = match reader.u8()? {
+                u8::MAX => return Err(SnapshotError::InvalidOperand("invalid local id")),
+                local => LocalId::from_u8(local),
+            };
+            spills.push((value, local));
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(SnapshotError::TrailingBytes);
+        }
+
+        Ok(Self { stack, spills })
+    }
+}
+
+/// Encode the number of items in a sequence
+fn encode_len(len: usize, bytes: &mut Vec<u8>) -> Result<(), SnapshotError> {
+    let len = u32::try_from(len).map_err(|_| SnapshotError::Unencodable("too many items"))?;
+    bytes.extend_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+fn encode_typed_value(value: &TypedValue, bytes: &mut Vec<u8>) -> Result<(), SnapshotError> {
+    bytes.extend_from_slice(&value.value.as_u32().to_le_bytes());
+    encode_type(&value.ty, bytes)
+}
+
+/// NOTE: The tags used here are part of the snapshot encoding, and must not be changed
+fn encode_immediate(imm: &Immediate, bytes: &mut Vec<u8>) {
+    match imm {
+        Immediate::I1(value) => bytes.extend_from_slice(&[0, *value as u8]),
+        Immediate::U8(value) => bytes.extend_from_slice(&[1, *value]),
+        Immediate::I8(value) => bytes.extend_from_slice(&[2, *value as u8]),
+        Immediate::U16(value) => {
+            bytes.push(3);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Immediate::I16(value) => {
+            bytes.push(4);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Immediate::U32(value) => {
+            bytes.push(5);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Immediate::I32(value) => {
+            bytes.push(6);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Immediate::U64(value) => {
+            bytes.push(7);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Immediate::I64(value) => {
+            bytes.push(8);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Immediate::I128(value) => {
+            bytes.push(9);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Immediate::F64(value) => {
+            bytes.push(10);
+            bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        Immediate::Felt(value) => {
+            bytes.push(11);
+            bytes.extend_from_slice(&value.as_int().to_le_bytes());
+        }
+    }
+}
+
+/// NOTE: The tags used here are part of the snapshot encoding, and must not be changed
+fn encode_type(ty: &Type, bytes: &mut Vec<u8>) -> Result<(), SnapshotError> {
+    match ty {
+        Type::Unknown => bytes.push(0),
+        Type::Unit => bytes.push(1),
+        Type::Never => bytes.push(2),
+        Type::I1 => bytes.push(3),
+        Type::I8 => bytes.push(4),
+        Type::U8 => bytes.push(5),
+        Type::I16 => bytes.push(6),
+        Type::U16 => bytes.push(7),
+        Type::I32 => bytes.push(8),
+        Type::U32 => bytes.push(9),
+        Type::I64 => bytes.push(10),
+        Type::U64 => bytes.push(11),
+        Type::I128 => bytes.push(12),
+        Type::U128 => bytes.push(13),
+        Type::U256 => bytes.push(14),
+        Type::F64 => bytes.push(15),
+        Type::Felt => bytes.push(16),
+        Type::Ptr(pointee) => {
+            bytes.push(17);
+            encode_type(pointee, bytes)?;
+        }
+        Type::NativePtr(pointee, addrspace) => {
+            bytes.push(18);
+            match addrspace {
+                AddressSpace::Unknown => bytes.extend_from_slice(&[0, 0, 0]),
+                AddressSpace::Root => bytes.extend_from_slice(&[1, 0, 0]),
+                AddressSpace::Id(id) => {
+                    bytes.push(2);
+                    bytes.extend_from_slice(&id.get().to_le_bytes());
+                }
+            }
+            encode_type(pointee, bytes)?;
+        }
+        Type::Struct(struct_ty) => {
+            bytes.push(19);
+            match struct_ty.repr() {
+                TypeRepr::Default => bytes.extend_from_slice(&[0, 0, 0]),
+                TypeRepr::Align(align) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&align.get().to_le_bytes());
+                }
+                TypeRepr::Packed(align) => {
+                    bytes.push(2);
+                    bytes.extend_from_slice(&align.get().to_le_bytes());
+                }
+                TypeRepr::Transparent => bytes.extend_from_slice(&[3, 0, 0]),
+            }
+            let len = u8::try_from(struct_ty.len())
+                .map_err(|_| SnapshotError::Unencodable("struct type has too many fields"))?;
+            bytes.push(len);
+            for field in struct_ty.fields() {
+                encode_type(&field.ty, bytes)?;
+            }
+        }
+        Type::Array(element_ty, arity) => {
+            bytes.push(20);
+            bytes.extend_from_slice(&(*arity as u64).to_le_bytes());
+            encode_type(element_ty, bytes)?;
+        }
+        Type::List(element_ty) => {
+            bytes.push(21);
+            encode_type(element_ty, bytes)?;
+        }
+    }
+    Ok(())
+}
+
+/// The maximum depth to which types may be nested in an encoded [OperandStackSnapshot], e.g. a
+/// pointer to a pointer is nested two levels deep
+const MAX_TYPE_DEPTH: usize = 64;
+
+/// A cursor over the bytes of an encoded [OperandStackSnapshot]
+struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+}
+impl<'a> SnapshotReader<'a> {
+    fn slice(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.bytes.len() < len {
+            return Err(SnapshotError::UnexpectedEof);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        self.slice(N).map(|bytes| bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        self.take::<1>().map(|[byte]| byte)
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    fn typed_value(&mut self) -> Result<TypedValue, SnapshotError> {
+        let value = Value::from_u32(self.u32()?);
+        let ty = self.ty()?;
+        Ok(TypedValue { value, ty })
+    }
+
+    fn immediate(&mut self) -> Result<Immediate, SnapshotError> {
+        Ok(match self.u8()? {
+            0 => Immediate::I1(self.u8()? != 0),
+            1 => Immediate::U8(self.u8()?),
+            2 => Immediate::I8(self.u8()? as i8),
+            3 => Immediate::U16(self.u16()?),
+            4 => Immediate::I16(self.take().map(i16::from_le_bytes)?),
+            5 => Immediate::U32(self.u32()?),
+            6 => Immediate::I32(self.take().map(i32::from_le_bytes)?),
+            7 => Immediate::U64(self.u64()?),
+            8 => Immediate::I64(self.take().map(i64::from_le_bytes)?),
+            9 => Immediate::I128(self.take().map(i128::from_le_bytes)?),
+            10 => Immediate::F64(f64::from_bits(self.u64()?)),
+            11 => match self.u64()? {
+                n if n < Felt::MODULUS => Immediate::Felt(Felt::new(n)),
+                _ => return Err(SnapshotError::InvalidOperand("field element out of range")),
+            },
+            tag => return Err(SnapshotError::InvalidTag("immediate", tag)),
+        })
+    }
+
+    fn ty(&mut self) -> Result<Type, SnapshotError> {
+        self.nested_ty(0)
+    }
+
+    /// Decode a type which is nested `depth` levels deep in another type
+    ///
+    /// The decoded type is validated, so that computing its layout cannot panic or overflow, e.g.
+    /// due to an invalid alignment, or an array too large to fit in memory.
+    fn nested_ty(&mut self, depth: usize) -> Result<Type, SnapshotError> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(SnapshotError::InvalidOperand("type is nested too deeply"));
+        }
+        Ok(match self.u8()? {
+            0 => Type::Unknown,
+            1 => Type::Unit,
+            2 => Type::Never,
+            3 => Type::I1,
+            4 => Type::I8,
+            5 => Type::U8,
+            6 => Type::I16,
+            7 => Type::U16,
+            8 => Type::I32,
+            9 => Type::U32,
+            10 => Type::I64,
+            11 => Type::U64,
+            12 => Type::I128,
+            13 => Type::U128,
+            14 => Type::U256,
+            15 => Type::F64,
+            16 => Type::Felt,
+            17 => Type::Ptr(Box::new(self.nested_ty(depth + 1)?)),
+            18 => {
+                let tag = self.u8()?;
+                let id = self.u16()?;
+                let addrspace = match (tag, NonZeroU16::new(id)) {
+                    (0, _) => AddressSpace::Unknown,
+                    (1, _) => AddressSpace::Root,
+                    (2, Some(id)) => AddressSpace::Id(id),
+                    (2, None) => {
+                        return Err(SnapshotError::InvalidOperand("invalid address space id"))
+                    }
+                    (tag, _) => return Err(SnapshotError::InvalidTag("address space", tag)),
+                };
+                Type::NativePtr(Box::new(self.nested_ty(depth + 1)?), addrspace)
+            }
+            19 => {
+                let tag = self.u8()?;
+                let align = self.u16()?;
+                let repr = match (tag, NonZeroU16::new(align)) {
+                    (0, _) => TypeRepr::Default,
+                    (1, Some(align)) if align.is_power_of_two() => TypeRepr::Align(align),
+                    (2, Some(align)) if align.is_power_of_two() => TypeRepr::Packed(align),
+                    (1 | 2, _) => {
+                        return Err(SnapshotError::InvalidOperand("invalid struct alignment"))
+                    }
+                    (3, _) => TypeRepr::Transparent,
+                    (tag, _) => {
+                        return Err(SnapshotError::InvalidTag("struct representation", tag))
+                    }
+                };
+                let len = self.u8()? as usize;
+                let mut fields = Vec::with_capacity(len);
+                for _ in 0..len {
+                    fields.push(self.nested_ty(depth + 1)?);
+                }
+                if fields.iter().any(|field| matches!(field, Type::List(_))) {
+                    return Err(SnapshotError::InvalidOperand("struct field has no size"));
+                }
+                // Bound the size of the struct, including the padding of each field, and of the
+                // struct itself, so that its layout can be computed without overflow
+                let size = fields.iter().try_fold(u16::MAX as u64, |size, field| {
+                    size.checked_add(field.size_in_bytes() as u64 + field.min_alignment() as u64)
+                });
+                if size.map_or(true, |size| size >= u32::MAX as u64) {
+                    return Err(SnapshotError::InvalidOperand("struct type is too large"));
+                }
+                if matches!(repr, TypeRepr::Transparent)
+                    && fields.iter().filter(|field| field.size_in_bytes() > 0).count() > 1
+                {
+                    return Err(SnapshotError::InvalidOperand(
+                        "transparent struct type has more than one non-zero sized field",
+                    ));
+                }
+                Type::Struct(StructType::new_with_repr(repr, fields))
+            }
+            20 => {
+                let arity = self.u64()?;
+                let element_ty = self.nested_ty(depth + 1)?;
+                if matches!(element_ty, Type::List(_)) {
+                    return Err(SnapshotError::InvalidOperand("array element has no size"));
+                }
+                let element_size = element_ty.size_in_bits() as u64;
+                let padded_element_size =
+                    element_size.align_up(element_ty.min_alignment() as u64 * 8);
+                let size = padded_element_size.checked_mul(arity);
+                if size.map_or(true, |size| size > u32::MAX as u64 * 8) {
+                    return Err(SnapshotError::InvalidOperand("array type is too large"));
+                }
+                Type::Array(Box::new(element_ty), arity as usize)
+            }
+            21 => Type::List(Box::new(self.nested_ty(depth + 1)?)),
+            tag => return Err(SnapshotError::InvalidTag("type", tag)),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use miden_hir::StructType;
@@ -1297,4 +1849,201 @@ mod tests {
         assert_eq!(stack[1], two);
         assert_eq!(stack[2], zero);
     }
+
+    #[test]
+    fn operand_stack_find_all_test() {
+        let mut stack = OperandStack::default();
+
+        let v0 = Value::from_u32(0);
+        let v1 = Value::from_u32(1);
+
+        stack.push(TypedValue {
+            value: v0,
+            ty: Type::U32,
+        });
+        stack.push(TypedValue {
+            value: v1,
+            ty: Type::U64,
+        });
+        stack.dup(1);
+        stack.dup(0);
+        assert_eq!(stack.find_all(&v0).as_slice(), &[0, 1, 3]);
+        assert_eq!(stack.find_all(&v1).as_slice(), &[2]);
+        assert_eq!(stack.find(&v0), Some(0));
+
+        stack.drop();
+        assert_eq!(stack.find_all(&v0).as_slice(), &[0, 2]);
+        assert!(stack.find_all(&Value::from_u32(2)).is_empty());
+    }
+
+    #[test]
+    fn operand_stack_describe_test() {
+        let mut stack = OperandStack::default();
+        assert_eq!(stack.describe(), "<empty>");
+
+        let v0 = Value::from_u32(0);
+        let v1 = Value::from_u32(1);
+
+        stack.push(TypedValue {
+            value: v0,
+            ty: Type::U32,
+        });
+        stack.push(Type::U64);
+        stack.push(Immediate::U32(7));
+        stack.dup(2);
+        stack.spill(
+            TypedValue {
+                value: v1,
+                ty: Type::I64,
+            },
+            LocalId::from_u8(0),
+        );
+
+        let expected = "\
+[0] v0: u32
+[1] const 7: u32
+[2] _: u64
+[3] v0: u32 (copy of [0])
+[spilled] v1: i64 in local0";
+        assert_eq!(stack.describe(), expected);
+    }
+
+    #[test]
+    fn operand_stack_snapshot_round_trip_test() {
+        let mut stack = OperandStack::default();
+
+        let v0 = Value::from_u32(0);
+        let v1 = Value::from_u32(1);
+        let v2 = Value::from_u32(2);
+        let v3 = Value::from_u32(3);
+        let packed =
+            Type::Struct(StructType::new_with_repr(TypeRepr::packed(1), [Type::U8, Type::U32]));
+
+        stack.push(Immediate::U64(u64::MAX));
+        stack.push(Immediate::Felt(Felt::new(7)));
+        stack.push(packed.clone());
+        stack.push(TypedValue {
+            value: v0,
+            ty: Type::Ptr(Box::new(Type::U8)),
+        });
+        stack.push(TypedValue {
+            value: v1,
+            ty: Type::U32,
+        });
+        // Copy `v1`, and rename the copy, as is done for the results of instructions which are
+        // computed in place, then copy `v1` again, so that it is on the stack twice
+        stack.dup(0);
+        stack.rename(0, v2);
+        stack.dup(1);
+        stack.spill(
+            TypedValue {
+                value: v3,
+                ty: Type::I64,
+            },
+            LocalId::from_u8(0),
+        );
+
+        let snapshot = stack.snapshot();
+        let bytes = snapshot.to_bytes().expect("unencodable snapshot");
+        let decoded = OperandStackSnapshot::from_bytes(&bytes).expect("invalid snapshot");
+        assert_eq!(decoded, snapshot);
+
+        let restored = OperandStack::restore(decoded);
+        assert_eq!(format!("{restored:?}"), format!("{stack:?}"));
+        assert_eq!(restored.snapshot().to_bytes(), Ok(bytes.clone()));
+        assert_eq!(restored.len(), 7);
+        assert_eq!(restored.raw_len(), 10);
+        assert_eq!(restored[0], v1);
+        assert_eq!(restored[1], v2);
+        assert_eq!(restored[2], v1);
+        assert_eq!(restored[3], v0);
+        assert_eq!(restored[4], packed);
+        assert_eq!(restored[5], Immediate::Felt(Felt::new(7)));
+        assert_eq!(restored[6], Immediate::U64(u64::MAX));
+        assert_eq!(restored.find(&v1), Some(0));
+        assert_eq!(restored.find(&v2), Some(1));
+        assert_eq!(restored.spilled(&v3).map(|(_, local)| local), Some(LocalId::from_u8(0)));
+
+        // Truncated and unknown encodings are rejected
+        assert_eq!(
+            OperandStackSnapshot::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(SnapshotError::UnexpectedEof)
+        );
+        let mut unsupported = bytes.clone();
+        unsupported[4] = 2;
+        assert_eq!(
+            OperandStackSnapshot::from_bytes(&unsupported),
+            Err(SnapshotError::UnsupportedVersion(2))
+        );
+
+        // Types which are too large to be encoded are rejected, rather than truncated
+        let mut stack = OperandStack::default();
+        stack.push(TypedValue {
+            value: v0,
+            ty: Type::Ptr(Box::new(Type::Struct(StructType::new(vec![Type::U8; 256])))),
+        });
+        assert_eq!(
+            stack.snapshot().to_bytes(),
+            Err(SnapshotError::Unencodable("struct type has too many fields"))
+        );
+    }
+
+    /// Corrupted types are rejected, rather than panicking or recursing without bound
+    #[test]
+    fn operand_stack_snapshot_corrupted_types_test() {
+        // Encode a snapshot with a single operand of the given encoded type
+        fn snapshot_of_type(ty: &[u8]) -> Vec<u8> {
+            let mut bytes = b"MSTK".to_vec();
+            bytes.push(1);
+            bytes.extend_from_slice(&1u32.to_le_bytes());
+            bytes.extend_from_slice(&[1, 9, 3]);
+            bytes.extend_from_slice(ty);
+            bytes.extend_from_slice(&0u32.to_le_bytes());
+            bytes
+        }
+        fn array_of_u8(arity: u64) -> Vec<u8> {
+            let mut bytes = vec![20];
+            bytes.extend_from_slice(&arity.to_le_bytes());
+            bytes.push(5);
+            bytes
+        }
+
+        let decoded = OperandStackSnapshot::from_bytes(&snapshot_of_type(&[17, 9]))
+            .expect("invalid snapshot");
+        assert_eq!(OperandStack::restore(decoded)[0], Type::Ptr(Box::new(Type::U32)));
+
+        let invalid = |ty: &[u8]| OperandStackSnapshot::from_bytes(&snapshot_of_type(ty));
+        // A struct aligned to 3 bytes
+        assert_eq!(
+            invalid(&[19, 1, 3, 0, 1, 9]),
+            Err(SnapshotError::InvalidOperand("invalid struct alignment"))
+        );
+        // A transparent struct with two non-zero sized fields
+        assert_eq!(
+            invalid(&[19, 3, 0, 0, 2, 9, 9]),
+            Err(SnapshotError::InvalidOperand(
+                "transparent struct type has more than one non-zero sized field"
+            ))
+        );
+        // An array whose size in bits overflows
+        assert_eq!(
+            invalid(&array_of_u8(u64::MAX)),
+            Err(SnapshotError::InvalidOperand("array type is too large"))
+        );
+        // A struct of two arrays which are each small enough to fit in memory, but not together
+        let mut large_struct = vec![19, 0, 0, 0, 2];
+        large_struct.extend(array_of_u8(1 << 31));
+        large_struct.extend(array_of_u8(1 << 31));
+        assert_eq!(
+            invalid(&large_struct),
+            Err(SnapshotError::InvalidOperand("struct type is too large"))
+        );
+        // A pointer to a pointer to a pointer, and so on
+        let mut nested = vec![17; 10_000];
+        nested.push(9);
+        assert_eq!(
+            invalid(&nested),
+            Err(SnapshotError::InvalidOperand("type is nested too deeply"))
+        );
+    }
 }
@@ -0,0 +1,137 @@
+use cranelift_entity::SecondaryMap;
+use miden_hir as hir;
+
+use super::OperandStack;
+
+/// This error is produced when two control flow paths reaching the same join point disagree on
+/// the state of the operand stack at that point.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "inconsistent operand stack at join point {block}: when {block} was first reached, the stack \
+     was:\n{expected}\nbut along another path, it was:\n{actual}"
+)]
+pub struct DivergentJoinError {
+    /// The block at which control flow joins
+    pub block: hir::Block,
+    /// A description of the operand stack on the first path to reach `block`
+    pub expected: String,
+    /// A description of the operand stack on a subsequent path to reach `block`
+    pub actual: String,
+}
+
+/// This verifier records the state of the operand stack on entry to each join point in the
+/// control flow graph during code generation, and checks that every path reaching a join point
+/// agrees on that state, i.e. that every operand has the same type in the same position.
+///
+/// All predecessors of a join point must agree on the abstract state of the operand stack, as
+/// the code emitted for the join point assumes that state. Since the IR is treeified prior to
+/// code generation, the only join points are loop headers, which are reached on entry to the
+/// loop, i.e. the `while.true` entry, and then along each loopback edge.
+///
+/// Paths which disagree on the depth of the operand stack are reported by
+/// [super::balance::LoopStackBalance] instead, so they are ignored here.
+#[derive(Default)]
+pub struct JoinPointVerifier {
+    snapshots: SecondaryMap<hir::Block, Option<OperandStack>>,
+    divergent: Vec<DivergentJoinError>,
+}
+impl JoinPointVerifier {
+    /// Record the state of `stack` on entry to the join point `block`.
+    ///
+    /// The first visit to a join point records the expected state of the operand stack, which
+    /// every subsequent visit is compared against.
+    pub fn visit(&mut self, block: hir::Block, stack: &OperandStack) {
+        let Some(expected) = self.snapshots[block].as_ref() else {
+            self.snapshots[block] = Some(stack.clone());
+            return;
+        };
+
+        if expected.raw_len() != stack.raw_len() {
+            return;
+        }
+
+        let diverges = expected.len() != stack.len()
+            || expected.iter().zip(stack.iter()).any(|(a, b)| a.ty() != b.ty());
+        if diverges {
+            self.divergent.push(DivergentJoinError {
+                block,
+                expected: expected.describe(),
+                actual: stack.describe(),
+            });
+        }
+    }
+
+    /// Returns an error describing the first join point at which the operand stack diverged, if
+    /// any were found.
+    pub fn verify(&self) -> Result<(), DivergentJoinError> {
+        match self.divergent.first() {
+            None => Ok(()),
+            Some(err) => Err(err.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{Signature, Type};
+
+    use super::*;
+    use crate::codegen::TypedValue;
+
+    #[test]
+    fn join_point_verifier_test() {
+        let id = "test::join".parse().unwrap();
+        let mut function = hir::Function::new(id, Signature::new([], []));
+        let header = function.dfg.create_block();
+
+        let v0 = hir::Value::from_u32(0);
+        let v1 = hir::Value::from_u32(1);
+
+        let mut stack = OperandStack::default();
+        stack.push(TypedValue {
+            value: v0,
+            ty: Type::U64,
+        });
+        stack.push(TypedValue {
+            value: v1,
+            ty: Type::U32,
+        });
+        stack.push(TypedValue {
+            value: v1,
+            ty: Type::U32,
+        });
+
+        let mut verifier = JoinPointVerifier::default();
+        verifier.visit(header, &stack);
+        verifier.visit(header, &stack);
+        verifier.verify().unwrap();
+
+        // Both predecessors leave 4 elements on the stack, but disagree on their layout
+        let mut divergent = OperandStack::default();
+        divergent.push(TypedValue {
+            value: v1,
+            ty: Type::U32,
+        });
+        divergent.push(TypedValue {
+            value: v1,
+            ty: Type::U32,
+        });
+        divergent.push(TypedValue {
+            value: v0,
+            ty: Type::U64,
+        });
+        verifier.visit(header, &divergent);
+
+        let err = verifier.verify().expect_err("expected divergent join point to be reported");
+        assert_eq!(err.block, header);
+        assert_eq!(
+            err.expected,
+            format!("[0] {v1}: u32\n[1] {v1}: u32 (copy of [0])\n[2] {v0}: u64")
+        );
+        assert_eq!(
+            err.actual,
+            format!("[0] {v0}: u64\n[1] {v1}: u32\n[2] {v1}: u32 (copy of [1])")
+        );
+        assert!(err.to_string().contains(&format!("join point {header}")));
+    }
+}
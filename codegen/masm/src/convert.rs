@@ -1,13 +1,21 @@
+use std::collections::BTreeSet;
+
+use miden_diagnostics::Severity;
 use miden_hir::{
-    self as hir,
-    pass::{AnalysisManager, ConversionPass, ConversionResult},
-    ConversionPassRegistration, PassInfo,
+    self as hir, diagnostic,
+    pass::{AnalysisError, AnalysisManager, ConversionError, ConversionPass, ConversionResult},
+    symbols, ConversionPassRegistration, PassInfo,
 };
 use miden_hir_analysis as analysis;
-use midenc_session::Session;
+use midenc_session::{OptLevel, Session};
 
 use crate::{
-    codegen::{FunctionEmitter, OperandStack, Scheduler, TypedValue},
+    codegen::{
+        dedup, drops, nops,
+        outline::{outline_loop_headers, LoopHeaderCopies},
+        peephole, EmitError, FunctionEmitter, OperandStack, Scheduler, SchedulerObserver,
+        TypedValue,
+    },
     masm,
 };
 
@@ -51,22 +59,48 @@ impl ConversionPass for ConvertHirToMasm<hir::Program> {
 
     fn convert(
         &mut self,
-        mut program: Self::From,
+        program: Self::From,
         analyses: &mut AnalysisManager,
         session: &Session,
     ) -> ConversionResult<Self::To> {
+        self.convert_program(program, analyses, session, None)
+    }
+}
+
+impl ConvertHirToMasm<hir::Program> {
+    /// Convert `program` to Miden Assembly, reporting statistics about the code emitted for each
+    /// function to `observer`, if given, see [SchedulerObserver]
+    pub(crate) fn convert_program(
+        &mut self,
+        mut program: Box<hir::Program>,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+        mut observer: Option<&mut (dyn SchedulerObserver + '_)>,
+    ) -> ConversionResult<Box<masm::Program>> {
         let mut masm_program = Box::new(masm::Program::from(program.as_ref()));
 
         // Remove the set of modules to compile from the program
         let modules = program.modules_mut().take();
 
+        // Calls may pass arguments in memory to any function defined in the program
+        let defined = modules
+            .iter()
+            .flat_map(|module| module.functions().map(|f| f.id))
+            .collect::<BTreeSet<_>>();
+
         // Ensure global variable analysis is computed
         analyses.get_or_compute::<ProgramGlobalVariableAnalysis>(&program, session)?;
 
         for module in modules.into_iter() {
             // Convert the module
             let mut convert_to_masm = ConvertHirToMasm::<hir::Module>::default();
-            let masm_module = convert_to_masm.convert(module, analyses, session)?;
+            let masm_module = convert_to_masm.convert_module_in_program(
+                module,
+                &defined,
+                analyses,
+                session,
+                observer.as_deref_mut(),
+            )?;
 
             // If this module makes use of any intrinsics modules, and those modules are not
             // already present, add them to the program.
@@ -100,10 +134,38 @@ impl ConversionPass for ConvertHirToMasm<hir::Module> {
 
     fn convert(
         &mut self,
-        mut module: Self::From,
+        module: Self::From,
         analyses: &mut AnalysisManager,
         session: &Session,
     ) -> ConversionResult<Self::To> {
+        self.convert_module(module, analyses, session, None)
+    }
+}
+
+impl ConvertHirToMasm<hir::Module> {
+    /// Convert `module` to Miden Assembly, reporting statistics about the code emitted for each
+    /// function to `observer`, if given, see [SchedulerObserver]
+    pub(crate) fn convert_module(
+        &mut self,
+        module: Box<hir::Module>,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+        observer: Option<&mut (dyn SchedulerObserver + '_)>,
+    ) -> ConversionResult<Box<masm::Module>> {
+        let defined = module.functions().map(|f| f.id).collect::<BTreeSet<_>>();
+        self.convert_module_in_program(module, &defined, analyses, session, observer)
+    }
+
+    /// Convert `module` to Miden Assembly, as part of a program which defines the functions in
+    /// `defined`, see [Self::convert_module]
+    fn convert_module_in_program(
+        &mut self,
+        mut module: Box<hir::Module>,
+        defined: &BTreeSet<hir::FunctionIdent>,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+        mut observer: Option<&mut (dyn SchedulerObserver + '_)>,
+    ) -> ConversionResult<Box<masm::Module>> {
         use miden_hir::ProgramAnalysisKey;
 
         let mut masm_module = Box::new(masm::Module::new(module.name));
@@ -121,44 +183,473 @@ impl ConversionPass for ConvertHirToMasm<hir::Module> {
         // the next function in the module. Once the end of the module
         // is reached, the cursor will point to the null object, and
         // `remove` will return `None`.
+        //
+        // When optimizing, the code of loop headers is factored out of each function into
+        // procedures of its own, which are defined just before the function using them.
+        let optimize = !matches!(session.options.optimize, OptLevel::None);
         while let Some(function) = module.pop_front() {
             let mut convert_to_masm = ConvertHirToMasm::<&hir::Function>::default();
-            let masm_function = convert_to_masm.convert(&function, analyses, session)?;
+            let mut outlined = vec![];
+            let is_defined = |id: hir::FunctionIdent| {
+                defined.contains(&id)
+                    || (id.module == module.name && masm_module.contains(id.function))
+            };
+            let masm_function = convert_to_masm.convert_function(
+                &function,
+                analyses,
+                session,
+                optimize.then_some(&mut outlined),
+                &is_defined,
+                observer.as_deref_mut(),
+            )?;
+            for procedure in outlined {
+                masm_module.push_back(Box::new(procedure));
+            }
             masm_module.push_back(Box::new(masm_function));
         }
 
+        // Functions with identical bodies are merged into a single definition
+        if optimize {
+            dedup::merge_duplicate_functions(&mut masm_module);
+        }
+
         Ok(masm_module)
     }
 }
 
-impl<'a> ConversionPass for ConvertHirToMasm<&'a hir::Function> {
-    type From = &'a hir::Function;
-    type To = masm::Function;
+/// This error type is produced when converting a function to Miden Assembly fails
+///
+/// When converted via [ConversionPass], these errors are returned as [ConversionError::Failed],
+/// except for [CodegenError::Analysis], and can be recovered using `anyhow::Error::downcast_ref`.
+#[derive(Debug, thiserror::Error)]
+pub enum CodegenError {
+    /// The function contains constructs which code cannot yet be generated for, see
+    /// [ConvertHirToMasm::validate]
+    #[error(
+        "unable to generate code for '{function}': found {} unsupported constructs",
+        .errors.len()
+    )]
+    Unsupported {
+        function: hir::FunctionIdent,
+        errors: Vec<UnsupportedError>,
+    },
+    /// Neither a program-wide, nor a module-wide, global variable analysis is available
+    #[error(
+        "unable to generate code for '{function}': expected global variable analysis to be \
+         available"
+    )]
+    MissingGlobalVariableAnalysis { function: hir::FunctionIdent },
+    /// An analysis required by code generation failed
+    #[error(transparent)]
+    Analysis(#[from] AnalysisError),
+    /// Code could not be emitted for the function, e.g. an operand was out of reach
+    #[error("invalid code generated for '{function}': {error}")]
+    Emit {
+        function: hir::FunctionIdent,
+        error: EmitError,
+    },
+    /// A loop in the generated code does not preserve the depth of the operand stack
+    #[error(
+        "invalid code generated for '{function}': the loop headed by {header} does not preserve \
+         the depth of the operand stack (expected {expected}, got {actual})"
+    )]
+    UnbalancedLoop {
+        function: hir::FunctionIdent,
+        header: hir::Block,
+        expected: usize,
+        actual: usize,
+    },
+    /// The control flow of the generated code is nested more deeply than allowed, see
+    /// `max_nesting_depth` in [midenc_session::Options]
+    #[error(
+        "invalid code generated for '{function}': control flow is nested {depth} levels deep, but \
+         the maximum is {max_depth}"
+    )]
+    NestingDepth {
+        function: hir::FunctionIdent,
+        depth: usize,
+        max_depth: usize,
+    },
+}
+impl From<CodegenError> for ConversionError {
+    fn from(err: CodegenError) -> Self {
+        match err {
+            CodegenError::Analysis(err) => Self::Analysis(err),
+            err => Self::Failed(err.into()),
+        }
+    }
+}
 
-    fn convert(
+/// A construct in the IR of a function which code cannot yet be generated for
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UnsupportedError {
+    /// A function calls itself, directly
+    #[error("{inst}: recursive calls are not supported, but '{callee}' calls itself")]
+    RecursiveCall {
+        inst: hir::Inst,
+        callee: hir::FunctionIdent,
+    },
+    /// A block of inline assembly refers to procedure locals
+    #[error("{inst}: procedure locals are not supported in inline assembly")]
+    LocalsInInlineAsm { inst: hir::Inst },
+    /// An arithmetic operator is used with overflow semantics not implemented for its type
+    #[error("{inst}: '{opcode}' with {overflow} semantics is not supported for {ty}")]
+    Overflow {
+        inst: hir::Inst,
+        opcode: hir::Opcode,
+        overflow: hir::Overflow,
+        ty: hir::Type,
+    },
+    /// An operator is used with operands of a type it is not implemented for
+    #[error("{inst}: '{opcode}' is not supported for operands of type {ty}")]
+    OperandType {
+        inst: hir::Inst,
+        opcode: hir::Opcode,
+        ty: hir::Type,
+    },
+    /// An operator which code cannot be generated for at all
+    #[error("{inst}: '{opcode}' is not supported")]
+    Opcode {
+        inst: hir::Inst,
+        opcode: hir::Opcode,
+    },
+}
+
+impl<'a> ConvertHirToMasm<&'a hir::Function> {
+    /// Check that code can be generated for `f`, without generating any.
+    ///
+    /// Conversion panics on the first construct it does not support, whereas this reports all of
+    /// them, e.g. for tools which need to know whether a function can be compiled, or why not.
+    ///
+    /// Only the IR of `f` is checked, i.e. recursive calls, procedure locals in inline assembly,
+    /// and the operand types and overflow semantics of binary and test operators. Limits on the
+    /// generated code, such as its nesting depth, are checked by [Self::dry_run].
+    pub fn validate(&self, f: &hir::Function) -> Result<(), Vec<UnsupportedError>> {
+        let errors = unsupported_constructs(f, true);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Returns the constructs in `f` which code cannot be generated for, in the order they appear.
+///
+/// The operand types and overflow semantics of operators are only checked if `check_operators`
+/// is true, as these are checked against a copy of what the emitters implement, which is not
+/// authoritative. Conversion must not reject a function based on it, only tools which ask.
+fn unsupported_constructs(f: &hir::Function, check_operators: bool) -> Vec<UnsupportedError> {
+    let mut errors = vec![];
+    for (block, _) in f.dfg.blocks() {
+        for inst in f.dfg.block_insts(block) {
+            match f.dfg.inst(inst) {
+                hir::Instruction::Call(hir::Call { callee, .. }) if *callee == f.id => {
+                    errors.push(UnsupportedError::RecursiveCall {
+                        inst,
+                        callee: *callee,
+                    });
+                }
+                hir::Instruction::InlineAsm(asm) => {
+                    let uses_locals =
+                        asm.blocks.values().flat_map(|block| block.ops.iter()).any(|op| {
+                            matches!(
+                                op,
+                                masm::Op::LocAddr(_)
+                                    | masm::Op::LocStore(_)
+                                    | masm::Op::LocStorew(_)
+                                    | masm::Op::LocLoad(_)
+                                    | masm::Op::LocLoadw(_)
+                            )
+                        });
+                    if uses_locals {
+                        errors.push(UnsupportedError::LocalsInInlineAsm { inst });
+                    }
+                }
+                hir::Instruction::BinaryOp(hir::BinaryOp { op, overflow, args })
+                    if check_operators =>
+                {
+                    let ty = f.dfg.value_type(args[0]);
+                    if let Some(err) = check_binary_op(inst, *op, *overflow, ty) {
+                        errors.push(err);
+                    }
+                }
+                hir::Instruction::BinaryOpImm(hir::BinaryOpImm {
+                    op, overflow, arg, ..
+                }) if check_operators => {
+                    let ty = f.dfg.value_type(*arg);
+                    if let Some(err) = check_binary_op(inst, *op, *overflow, ty) {
+                        errors.push(err);
+                    }
+                }
+                hir::Instruction::Test(hir::Test { op, .. }) if check_operators => {
+                    errors.push(UnsupportedError::Opcode { inst, opcode: *op });
+                }
+                _ => (),
+            }
+        }
+    }
+
+    errors
+}
+
+/// Returns the reason code cannot be generated for the binary operator `op` with `overflow`
+/// semantics and operands of type `ty`, if any
+fn check_binary_op(
+    inst: hir::Inst,
+    op: hir::Opcode,
+    overflow: Option<hir::Overflow>,
+    ty: &hir::Type,
+) -> Option<UnsupportedError> {
+    if !is_binary_op_supported(op, ty) {
+        return Some(UnsupportedError::OperandType {
+            inst,
+            opcode: op,
+            ty: ty.clone(),
+        });
+    }
+    let overflow = overflow.unwrap_or(hir::Overflow::Checked);
+    if !is_overflow_supported(op, overflow, ty) {
+        return Some(UnsupportedError::Overflow {
+            inst,
+            opcode: op,
+            overflow,
+            ty: ty.clone(),
+        });
+    }
+    None
+}
+
+/// Returns false if code cannot be generated for the binary operator `op` with operands of type
+/// `ty`, see the emitters in `codegen::emit::binary`, which this must be kept in sync with
+fn is_binary_op_supported(op: hir::Opcode, ty: &hir::Type) -> bool {
+    use hir::{Opcode, Type};
+
+    match op {
+        Opcode::Eq | Opcode::Neq => matches!(
+            ty,
+            Type::I128
+                | Type::I64
+                | Type::U64
+                | Type::Felt
+                | Type::Ptr(_)
+                | Type::U32
+                | Type::I32
+                | Type::U16
+                | Type::I16
+                | Type::U8
+                | Type::I8
+                | Type::I1
+        ),
+        Opcode::Gt | Opcode::Gte | Opcode::Lt | Opcode::Lte => matches!(
+            ty,
+            Type::Felt | Type::U64 | Type::U32 | Type::I32 | Type::U16 | Type::U8 | Type::I1
+        ),
+        Opcode::Add | Opcode::Sub => matches!(
+            ty,
+            Type::Felt
+                | Type::U64
+                | Type::I64
+                | Type::U32
+                | Type::I32
+                | Type::U16
+                | Type::U8
+                | Type::I1
+        ),
+        Opcode::Mul => matches!(
+            ty,
+            Type::Felt | Type::U64 | Type::I64 | Type::U32 | Type::I32 | Type::U16 | Type::U8
+        ),
+        Opcode::Div => {
+            matches!(ty, Type::Felt | Type::U64 | Type::U32 | Type::I32 | Type::U16 | Type::U8)
+        }
+        Opcode::Mod | Opcode::DivMod => {
+            matches!(ty, Type::U64 | Type::U32 | Type::U16 | Type::U8)
+        }
+        Opcode::Exp => matches!(ty, Type::Felt | Type::U32 | Type::I32 | Type::U16 | Type::U8),
+        Opcode::And | Opcode::Or | Opcode::Xor => matches!(ty, Type::I1),
+        Opcode::Band | Opcode::Bor | Opcode::Bxor => matches!(
+            ty,
+            Type::U64
+                | Type::I64
+                | Type::U32
+                | Type::I32
+                | Type::U16
+                | Type::I16
+                | Type::U8
+                | Type::I8
+                | Type::I1
+        ),
+        Opcode::Shl | Opcode::Shr => {
+            matches!(ty, Type::U64 | Type::U32 | Type::I32 | Type::U16 | Type::U8)
+        }
+        Opcode::Rotl | Opcode::Rotr => matches!(ty, Type::U64 | Type::U32),
+        Opcode::Min | Opcode::Max => {
+            matches!(ty, Type::U64 | Type::U32 | Type::I32 | Type::U16 | Type::U8 | Type::I1)
+        }
+        _ => false,
+    }
+}
+
+/// Returns false if `op` is not implemented with `overflow` semantics for operands of type `ty`
+fn is_overflow_supported(op: hir::Opcode, overflow: hir::Overflow, ty: &hir::Type) -> bool {
+    match (op, ty) {
+        (hir::Opcode::Add | hir::Opcode::Sub | hir::Opcode::Mul, hir::Type::I64)
+        | (hir::Opcode::Mul, hir::Type::Felt) => {
+            matches!(overflow, hir::Overflow::Unchecked | hir::Overflow::Wrapping)
+        }
+        _ => true,
+    }
+}
+
+impl<'a> ConvertHirToMasm<&'a hir::Function> {
+    /// Convert `f` to Miden Assembly.
+    ///
+    /// If `outlined` is given, the code of loop headers which is duplicated for each loopback edge
+    /// is factored out into procedures of its own, which are appended to `outlined`, and must be
+    /// added to the module prior to the converted function. These are given names for which
+    /// `is_defined` returns false, i.e. which are not already defined in the module.
+    ///
+    /// When arguments are passed in memory, this is only done for callees for which `is_defined`
+    /// returns true, i.e. which are defined in the program being compiled, rather than provided at
+    /// runtime.
+    ///
+    /// If `observer` is given, statistics about the emitted code are reported to it.
+    pub(crate) fn convert_function(
         &mut self,
-        f: Self::From,
+        f: &hir::Function,
         analyses: &mut AnalysisManager,
         session: &Session,
-    ) -> ConversionResult<Self::To> {
+        outlined: Option<&mut Vec<masm::Function>>,
+        is_defined: &dyn Fn(hir::FunctionIdent) -> bool,
+        observer: Option<&mut (dyn SchedulerObserver + '_)>,
+    ) -> Result<masm::Function, CodegenError> {
+        // Report everything we are unable to generate code for, rather than just the first. The
+        // operators used are left to the emitters, see `unsupported_constructs`
+        let errors = unsupported_constructs(f, false);
+        if !errors.is_empty() {
+            let diagnostics = &session.diagnostics;
+            for err in errors.iter() {
+                diagnostic!(
+                    diagnostics,
+                    Severity::Error,
+                    "unsupported construct",
+                    f.id.span(),
+                    format!("unable to generate code for '{}': {err}", f.id)
+                );
+            }
+            return Err(CodegenError::Unsupported {
+                function: f.id,
+                errors,
+            });
+        }
+
+        let f_prime = self.emit_function(f, analyses, session, outlined, is_defined, observer)?;
+
+        // Reject functions whose control flow is nested too deeply, as this may exceed the
+        // limits of the assembler or VM
+        if let Some((depth, max_depth)) = excess_nesting_depth(&f_prime, session) {
+            let diagnostics = &session.diagnostics;
+            diagnostic!(
+                diagnostics,
+                Severity::Error,
+                "control flow is nested too deeply",
+                f.id.span(),
+                format!(
+                    "'{}::{}' nests loops and conditionals {depth} levels deep, but the maximum \
+                     is {max_depth}",
+                    f.id.module.as_str(),
+                    f.id.function.as_str()
+                ),
+                "Each loop and conditional adds a level of nesting to the generated code, \
+                 consider moving deeply nested control flow into separate functions."
+            );
+            return Err(CodegenError::NestingDepth {
+                function: f.id,
+                depth,
+                max_depth,
+            });
+        }
+
+        // Warn if the function uses more locals than allowed, e.g. due to excessive spilling
+        if let Some(budget) = session.options.locals_budget {
+            let num_locals = f_prime.locals().len();
+            if num_locals > budget {
+                let diagnostics = &session.diagnostics;
+                diagnostic!(
+                    diagnostics,
+                    Severity::Warning,
+                    "function exceeds the budget for procedure locals",
+                    f.id.span(),
+                    format!(
+                        "'{}::{}' uses {num_locals} locals, but the budget is {budget}",
+                        f.id.module.as_str(),
+                        f.id.function.as_str()
+                    ),
+                    "Locals are mostly allocated when spilling values from the operand stack, so \
+                     this may indicate that the spill threshold is too low."
+                );
+            }
+        }
+
+        Ok(f_prime)
+    }
+
+    /// Check that code can be generated for `f`, and that the generated code is within the limits
+    /// configured in `session`, e.g. `max_nesting_depth`, without reporting diagnostics for either.
+    ///
+    /// This generates code for `f` and discards it, so the limits are only checked if `f` passes
+    /// [Self::validate]. Otherwise, the constructs which code cannot be generated for are
+    /// reported as [CodegenError::Unsupported].
+    pub fn dry_run(
+        &mut self,
+        f: &hir::Function,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> Result<(), CodegenError> {
+        self.validate(f).map_err(|errors| CodegenError::Unsupported {
+            function: f.id,
+            errors,
+        })?;
+
+        let f_prime = self.emit_function(f, analyses, session, None, &|_| false, None)?;
+        match excess_nesting_depth(&f_prime, session) {
+            Some((depth, max_depth)) => Err(CodegenError::NestingDepth {
+                function: f.id,
+                depth,
+                max_depth,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Generate code for `f`, which must have passed [Self::validate], see [Self::convert_function]
+    fn emit_function(
+        &mut self,
+        f: &hir::Function,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+        outlined: Option<&mut Vec<masm::Function>>,
+        is_defined: &dyn Fn(hir::FunctionIdent) -> bool,
+        observer: Option<&mut (dyn SchedulerObserver + '_)>,
+    ) -> Result<masm::Function, CodegenError> {
         use miden_hir::ProgramAnalysisKey;
 
         let mut f_prime = masm::Function::new(f.id, f.signature.clone());
+        let mut header_copies = LoopHeaderCopies::default();
 
         // Start at the function entry
         {
             let entry = f.dfg.entry_block();
 
-            let globals = analyses
-                .get::<ProgramGlobalVariableAnalysis>(&ProgramAnalysisKey)
-                .map(|result| result.layout().clone())
-                .unwrap_or_else(|| {
-                    let result = analyses.expect::<ModuleGlobalVariableAnalysis>(
-                        &f.id.module,
-                        "expected global variable analysis to be available",
-                    );
-                    result.layout().clone()
-                });
+            let globals = match analyses.get::<ProgramGlobalVariableAnalysis>(&ProgramAnalysisKey) {
+                Some(result) => result.layout().clone(),
+                None => analyses
+                    .get::<ModuleGlobalVariableAnalysis>(&f.id.module)
+                    .ok_or(CodegenError::MissingGlobalVariableAnalysis { function: f.id })?
+                    .layout()
+                    .clone(),
+            };
 
             let domtree = analyses.get_or_compute::<analysis::DominatorTree>(f, session)?;
             let loops = analyses.get_or_compute::<analysis::LoopAnalysis>(f, session)?;
@@ -170,14 +661,110 @@ impl<'a> ConversionPass for ConvertHirToMasm<&'a hir::Function> {
                 stack.push(TypedValue { value: arg, ty });
             }
 
+            // When arguments are passed in memory, load them onto the operand stack on entry, so
+            // that the body is emitted just as if they had been passed on the operand stack. The
+            // entrypoint, functions whose parameters do not fit in the argument region, and those
+            // invoked in another context, e.g. via `call` or `syscall`, are always invoked with
+            // their arguments on the operand stack.
+            if session.options.memory_call_args
+                && f.signature.cc == hir::CallConv::SystemV
+                && !f.has_attribute(&symbols::Entrypoint)
+                && masm::fits_call_args_region(&f.signature)
+            {
+                let num_elements = stack.raw_len() as u32;
+                let prologue = f_prime.body.id();
+                for offset in (0..num_elements).rev() {
+                    let addr = masm::CALL_ARGS_ADDR + offset;
+                    f_prime.block_mut(prologue).push(masm::Op::MemLoadImm(addr));
+                }
+            }
+
             let scheduler = Scheduler::new(f, &mut f_prime, &domtree, &loops, &liveness);
             let schedule = scheduler.build();
 
-            let emitter =
-                FunctionEmitter::new(f, &mut f_prime, &domtree, &loops, &liveness, &globals);
-            emitter.emit(schedule, stack);
+            let mut emitter =
+                FunctionEmitter::new(f, &mut f_prime, &domtree, &loops, &liveness, &globals)
+                    .with_spill_threshold(session.options.spill_threshold)
+                    .with_block_annotations(session.options.annotate_blocks)
+                    .with_strict_returns(session.options.strict_returns);
+            if session.options.memory_call_args {
+                emitter = emitter.with_memory_call_args(is_defined);
+            }
+            if outlined.is_some() {
+                emitter = emitter.with_loop_header_copies(&mut header_copies);
+            }
+            if let Some(observer) = observer {
+                emitter = emitter.with_observer(observer);
+            }
+            let loop_balance =
+                emitter.emit(schedule, stack).map_err(|error| CodegenError::Emit {
+                    function: f.id,
+                    error,
+                })?;
+            loop_balance.report(f, &session.diagnostics).map_err(|_| {
+                let unbalanced = loop_balance.unbalanced()[0];
+                CodegenError::UnbalancedLoop {
+                    function: f.id,
+                    header: unbalanced.header,
+                    expected: unbalanced.expected,
+                    actual: unbalanced.actual,
+                }
+            })?;
         }
 
+        // Factor out the code duplicated for each loopback edge, while it is still exactly as it
+        // was emitted
+        if let Some(outlined) = outlined {
+            let is_defined = |function| {
+                is_defined(hir::FunctionIdent {
+                    module: f.id.module,
+                    function,
+                })
+            };
+            for mut procedure in outline_loop_headers(&mut f_prime, &header_copies, &is_defined) {
+                for block in procedure.body.blocks.values_mut() {
+                    peephole::optimize(block);
+                }
+                procedure.update_may_trap();
+                outlined.push(procedure);
+            }
+        }
+
+        // Drop values dead along all paths through a conditional once, after the conditional,
+        // then clean up any redundant control flow and stack manipulation left behind by code
+        // generation
+        drops::optimize(&mut f_prime.body);
+        nops::optimize(&mut f_prime.body);
+        for block in f_prime.body.blocks.values_mut() {
+            peephole::optimize(block);
+        }
+
+        // Now that the body is final, determine whether the function may trap
+        f_prime.update_may_trap();
+
         Ok(f_prime)
     }
 }
+
+/// Returns the depth the control flow of `f` is nested to, along with the maximum allowed by
+/// `session`, if it is nested too deeply
+fn excess_nesting_depth(f: &masm::Function, session: &Session) -> Option<(usize, usize)> {
+    let max_depth = session.options.max_nesting_depth?;
+    let depth = f.body.nesting_depth();
+    (depth > max_depth).then_some((depth, max_depth))
+}
+
+impl<'a> ConversionPass for ConvertHirToMasm<&'a hir::Function> {
+    type From = &'a hir::Function;
+    type To = masm::Function;
+
+    fn convert(
+        &mut self,
+        f: Self::From,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> ConversionResult<Self::To> {
+        // A single function has no module to add procedures factored out of it to
+        Ok(self.convert_function(f, analyses, session, None, &|_| false, None)?)
+    }
+}
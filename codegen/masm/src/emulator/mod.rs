@@ -1412,6 +1412,7 @@ impl Emulator {
                     debug_assert!(addr < self.memory.len());
                     let value = pop!(self);
                     self.memory[addr][0] = value;
+                    self.callstack.push(state);
                     return Ok(EmulatorEvent::MemoryWrite {
                         addr: addr as u32,
                         size: 4,
@@ -1423,11 +1424,23 @@ impl Emulator {
                     let word =
                         self.stack.peekw().expect("operand stack does not contain a full word");
                     self.memory[addr] = word;
+                    self.callstack.push(state);
                     return Ok(EmulatorEvent::MemoryWrite {
                         addr: addr as u32,
                         size: 16,
                     });
                 }
+                Op::LocLoad(id) => {
+                    let addr = (state.fp() + id.as_usize() as u32) as usize;
+                    debug_assert!(addr < self.memory.len());
+                    self.stack.push(self.memory[addr][0]);
+                }
+                Op::LocLoadw(id) => {
+                    let addr = (state.fp() + id.as_usize() as u32) as usize;
+                    assert!(addr < self.memory.len() - 4, "out of bounds memory access");
+                    self.stack.dropw();
+                    self.stack.pushw(self.memory[addr]);
+                }
                 Op::MemLoad => {
                     let addr = pop_addr!(self);
                     self.stack.push(self.memory[addr][0]);
@@ -1576,7 +1589,7 @@ impl Emulator {
                         Stub::Native(_function) => unimplemented!(),
                     }
                 }
-                Op::Syscall(_callee) => unimplemented!(),
+                Op::Call(_callee) | Op::Syscall(_callee) => unimplemented!(),
                 Op::Add => binop!(self, add),
                 Op::AddImm(imm) => binop!(self, add, imm),
                 Op::Sub => binop!(self, sub),
@@ -1724,14 +1737,37 @@ impl Emulator {
                 Op::U32OverflowingAddImm(imm) => binop_overflowing_u32!(self, add, imm),
                 Op::U32WrappingAdd => binop_wrapping_u32!(self, add),
                 Op::U32WrappingAddImm(imm) => binop_wrapping_u32!(self, add, imm),
-                Op::U32OverflowingAdd3 => todo!(),
-                Op::U32WrappingAdd3 => todo!(),
+                Op::U32OverflowingAdd3 => {
+                    let c = pop_u32!(self) as u64;
+                    let b = pop_u32!(self) as u64;
+                    let a = pop_u32!(self) as u64;
+                    let result = a + b + c;
+                    self.stack.push(Felt::new(result % 2u64.pow(32)));
+                    self.stack.push(Felt::new(result / 2u64.pow(32)));
+                }
+                Op::U32WrappingAdd3 => {
+                    let c = pop_u32!(self) as u64;
+                    let b = pop_u32!(self) as u64;
+                    let a = pop_u32!(self) as u64;
+                    self.stack.push(Felt::new((a + b + c) % 2u64.pow(32)));
+                }
                 Op::U32OverflowingSub => binop_overflowing_u32!(self, sub),
                 Op::U32OverflowingSubImm(imm) => binop_overflowing_u32!(self, sub, imm),
                 Op::U32WrappingSub => binop_wrapping_u32!(self, sub),
                 Op::U32WrappingSubImm(imm) => binop_wrapping_u32!(self, sub, imm),
-                Op::U32OverflowingMul => binop_overflowing_u32!(self, mul),
-                Op::U32OverflowingMulImm(imm) => binop_overflowing_u32!(self, mul, imm),
+                Op::U32OverflowingMul => {
+                    let b = pop_u32!(self) as u64;
+                    let a = pop_u32!(self) as u64;
+                    let result = a * b;
+                    self.stack.push(Felt::new(result % 2u64.pow(32)));
+                    self.stack.push(Felt::new(result / 2u64.pow(32)));
+                }
+                Op::U32OverflowingMulImm(imm) => {
+                    let a = pop_u32!(self) as u64;
+                    let result = a * imm as u64;
+                    self.stack.push(Felt::new(result % 2u64.pow(32)));
+                    self.stack.push(Felt::new(result / 2u64.pow(32)));
+                }
                 Op::U32WrappingMul => binop_wrapping_u32!(self, mul),
                 Op::U32WrappingMulImm(imm) => binop_wrapping_u32!(self, mul, imm),
                 Op::U32OverflowingMadd => {
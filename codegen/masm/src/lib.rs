@@ -8,11 +8,18 @@ mod masm;
 #[cfg(test)]
 mod tests;
 
+use std::collections::BTreeSet;
+
 use miden_hir as hir;
 use midenc_session::Session;
 
 pub use self::{
-    convert::ConvertHirToMasm,
+    codegen::{
+        compute_schedule, BlockInfo, Constraint, DivergentJoinError, EmitError, InstInfo,
+        OperandStack, OperandStackSnapshot, Schedule, ScheduleOp, SchedulerObserver, SnapshotError,
+        StackDepthError,
+    },
+    convert::{CodegenError, ConvertHirToMasm, UnsupportedError},
     emulator::{
         Breakpoint, BreakpointEvent, CallFrame, DebugInfo, DebugInfoWithStack, EmulationError,
         Emulator, EmulatorEvent, InstructionPointer, WatchMode, Watchpoint, WatchpointId,
@@ -38,6 +45,12 @@ pub enum CompilerError {
     /// An error occurred during application of a conversion
     #[error(transparent)]
     Conversion(#[from] hir::pass::ConversionError),
+    /// The function requested for compilation is not defined
+    #[error("undefined function '{}::{}'", .module.as_str(), .function.as_str())]
+    UndefinedFunction {
+        module: hir::Ident,
+        function: hir::Ident,
+    },
 }
 
 pub type CompilerResult<T> = Result<T, CompilerError>;
@@ -54,24 +67,47 @@ pub type CompilerResult<T> = Result<T, CompilerError>;
 pub struct MasmCompiler<'a> {
     session: &'a Session,
     analyses: hir::pass::AnalysisManager,
+    observer: Option<&'a mut dyn SchedulerObserver>,
 }
 impl<'a> MasmCompiler<'a> {
     pub fn new(session: &'a Session) -> Self {
         Self {
             session,
             analyses: hir::pass::AnalysisManager::new(),
+            observer: None,
         }
     }
 
+    /// Create a [MasmCompiler] which uses `analyses` as its analysis cache.
+    ///
+    /// This allows analyses to be computed ahead of time, e.g. when compiling many functions, or
+    /// shared between compilations. Cached analyses are only used if they are still valid after
+    /// the rewrites applied during compilation, otherwise they are recomputed.
+    pub fn with_analyses(session: &'a Session, analyses: hir::pass::AnalysisManager) -> Self {
+        Self {
+            session,
+            analyses,
+            observer: None,
+        }
+    }
+
+    /// Report statistics about the code emitted for each function compiled to `observer`, e.g.
+    /// to profile the quality of instruction schedules, see [SchedulerObserver]
+    pub fn with_observer(mut self, observer: &'a mut dyn SchedulerObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Consume this compiler, returning its analysis cache, so that it can be reused
+    pub fn into_analyses(self) -> hir::pass::AnalysisManager {
+        self.analyses
+    }
+
     /// Compile an [hir::Program] that has been linked and is ready to be compiled.
     pub fn compile(&mut self, mut input: Box<hir::Program>) -> CompilerResult<Box<Program>> {
-        use miden_hir::pass::{ConversionPass, ModuleRewritePassAdapter, RewritePass, RewriteSet};
-        use miden_hir_transform as transforms;
+        use miden_hir::pass::RewritePass;
 
-        let mut rewrites = RewriteSet::default();
-        rewrites.push(ModuleRewritePassAdapter::new(transforms::SplitCriticalEdges));
-        rewrites.push(ModuleRewritePassAdapter::new(transforms::Treeify));
-        rewrites.push(ModuleRewritePassAdapter::new(transforms::InlineBlocks));
+        let mut rewrites = default_rewrites();
 
         let modules = input.modules_mut().take();
         for mut module in modules.into_iter() {
@@ -80,7 +116,12 @@ impl<'a> MasmCompiler<'a> {
         }
 
         let mut convert_to_masm = ConvertHirToMasm::<hir::Program>::default();
-        let mut program = convert_to_masm.convert(input, &mut self.analyses, self.session)?;
+        let mut program = convert_to_masm.convert_program(
+            input,
+            &mut self.analyses,
+            self.session,
+            self.observer.as_deref_mut(),
+        )?;
 
         // Ensure intrinsics modules are linked
         program.insert(Box::new(
@@ -95,6 +136,72 @@ impl<'a> MasmCompiler<'a> {
         Ok(program)
     }
 
+    /// Compile the function `name` of `module` in isolation, returning just that function.
+    ///
+    /// Only `name` and the functions of `module` it transitively calls are retained, all other
+    /// functions are removed from the module before compilation. This is primarily intended for
+    /// tests which are only interested in the code generated for a specific function.
+    pub fn compile_function(
+        &mut self,
+        mut module: Box<hir::Module>,
+        name: hir::Ident,
+    ) -> CompilerResult<Function> {
+        use miden_hir::pass::RewritePass;
+        use miden_hir_analysis::GlobalVariableAnalysis;
+
+        if !module.contains(name) {
+            return Err(CompilerError::UndefinedFunction {
+                module: module.name,
+                function: name,
+            });
+        }
+
+        // Compute the set of functions reachable from `name` within this module
+        let mut reachable = BTreeSet::from([name]);
+        let mut worklist = vec![name];
+        while let Some(caller) = worklist.pop() {
+            // Only functions defined in this module are added to the worklist
+            let caller = module.function(caller).unwrap();
+            for callee in caller.imports() {
+                if callee.id.module == module.name
+                    && module.contains(callee.id.function)
+                    && reachable.insert(callee.id.function)
+                {
+                    worklist.push(callee.id.function);
+                }
+            }
+        }
+
+        let unreachable = module
+            .functions()
+            .map(|f| f.id.function)
+            .filter(|f| !reachable.contains(f))
+            .collect::<Vec<_>>();
+        for function in unreachable.into_iter() {
+            module.unlink(function);
+        }
+
+        let mut rewrites = default_rewrites();
+        rewrites.apply(&mut module, &mut self.analyses, self.session)?;
+
+        self.analyses
+            .get_or_compute::<GlobalVariableAnalysis<hir::Module>>(&module, self.session)?;
+
+        let function = module.function(name).unwrap();
+        let mut convert_to_masm = ConvertHirToMasm::<&hir::Function>::default();
+        let function = convert_to_masm
+            .convert_function(
+                function,
+                &mut self.analyses,
+                self.session,
+                None,
+                &|id| id.module == module.name && module.contains(id.function),
+                self.observer.as_deref_mut(),
+            )
+            .map_err(hir::pass::ConversionError::from)?;
+        Ok(function)
+    }
+
     /// Compile a single [hir::Module] as a program.
     ///
     /// It is assumed that the given module has been validated, and that all necessary
@@ -126,3 +233,22 @@ impl<'a> MasmCompiler<'a> {
         self.compile(program)
     }
 }
+
+/// The set of rewrites applied to each module prior to conversion to MASM
+fn default_rewrites() -> hir::pass::RewriteSet<hir::Module> {
+    use miden_hir::pass::{ModuleRewritePassAdapter, RewriteSet};
+    use miden_hir_transform as transforms;
+
+    let mut rewrites = RewriteSet::default();
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::TrapInfiniteLoops));
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::ElideBoundsChecks));
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::FoldConstants));
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::FoldConstantOperands));
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::EliminateDeadBlockParams));
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::EliminateDeadCode));
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::SplitCriticalEdges));
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::HoistLoopInvariants));
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::Treeify));
+    rewrites.push(ModuleRewritePassAdapter::new(transforms::InlineBlocks));
+    rewrites
+}
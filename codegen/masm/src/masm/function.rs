@@ -30,6 +30,8 @@ pub struct Function {
     locals: SmallVec<[Local; 1]>,
     /// The next available local index
     next_local_id: usize,
+    /// Whether this function may trap when executed, see [Function::may_trap]
+    may_trap: bool,
 }
 impl Function {
     pub fn new(name: FunctionIdent, signature: Signature) -> Self {
@@ -42,6 +44,7 @@ impl Function {
             body: Default::default(),
             locals: Default::default(),
             next_local_id: 0,
+            may_trap: true,
         }
     }
 
@@ -52,6 +55,27 @@ impl Function {
         self.attrs.has(&symbols::Entrypoint)
     }
 
+    /// Returns true if this function may trap when executed
+    ///
+    /// This is conservative, and is only refined once [Function::update_may_trap] has been
+    /// called on the completed function body, until then it is assumed that the function may
+    /// trap.
+    #[inline]
+    pub fn may_trap(&self) -> bool {
+        self.may_trap
+    }
+
+    /// Recompute whether this function may trap, based on the operations in its body
+    pub fn update_may_trap(&mut self) {
+        self.may_trap = self.body.may_trap();
+    }
+
+    /// Estimate the cost of executing this function, assuming each `while.true` loop iterates
+    /// `loop_iterations` times, see [CostEstimate]
+    pub fn cost_estimate(&self, loop_iterations: usize) -> CostEstimate {
+        self.body.cost_estimate(loop_iterations)
+    }
+
     /// Return the number of arguments expected on the operand stack
     #[inline]
     pub fn arity(&self) -> usize {
@@ -143,6 +167,7 @@ impl Function {
         }
 
         function.body = Region::from_code_body(&proc.body, locals, imported);
+        function.update_may_trap();
 
         function
     }
@@ -189,6 +214,7 @@ impl fmt::Debug for Function {
             .field("signature", &self.signature)
             .field("attrs", &self.attrs)
             .field("locals", &self.locals)
+            .field("may_trap", &self.may_trap)
             .field("body", &self.body)
             .finish()
     }
@@ -255,6 +281,13 @@ impl Functions {
         }
     }
 
+    pub fn pop_front(&mut self) -> Option<Box<Function>> {
+        match self {
+            Self::Open(ref mut list) => list.pop_front(),
+            Self::Frozen(_) => panic!("cannot remove function from frozen module"),
+        }
+    }
+
     pub fn freeze(&mut self) {
         if let Self::Open(ref mut functions) = self {
             let mut frozen = FrozenFunctionList::default();
@@ -13,9 +13,33 @@ pub use self::{
     function::{FrozenFunctionList, Function, FunctionList},
     module::{FrozenModuleTree, LoadModuleError, Module, ModuleTree},
     program::Program,
-    region::{Begin, Region},
+    region::{Begin, CostEstimate, Region},
 };
 
+/// The number of words in the region of memory through which procedure arguments are passed,
+/// when the memory calling convention is in use, see [CALL_ARGS_ADDR].
+pub const CALL_ARGS_WORDS: u32 = 64;
+
+/// The native address of the first word of the region of memory through which procedure
+/// arguments are passed when the memory calling convention is in use, rather than the operand
+/// stack. The region occupies the last [CALL_ARGS_WORDS] words of the address space.
+///
+/// The caller stores the field elements of its arguments, starting from the top of the operand
+/// stack, into the first element of consecutive words of this region, and the callee loads them
+/// back onto the operand stack on entry. This layout is fixed, so that procedures compiled
+/// separately agree on how arguments are passed, regardless of how each was compiled.
+pub const CALL_ARGS_ADDR: u32 = u32::MAX - CALL_ARGS_WORDS + 1;
+
+/// Returns true if the arguments of a procedure with the given signature fit in the region of
+/// memory through which arguments are passed, see [CALL_ARGS_ADDR].
+///
+/// The arguments of procedures for which this returns false are passed on the operand stack, by
+/// both the caller and the callee, even when the memory calling convention is in use.
+pub fn fits_call_args_region(signature: &miden_hir::Signature) -> bool {
+    let num_elements = signature.params.iter().map(|param| param.ty.size_in_felts()).sum::<usize>();
+    num_elements <= CALL_ARGS_WORDS as usize
+}
+
 /// This represents a descriptor for a pointer translated from the IR into a form suitable for
 /// referencing data in Miden's linear memory.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
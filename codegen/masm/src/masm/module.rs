@@ -169,6 +169,13 @@ impl Module {
         self.functions.push_back(function);
     }
 
+    /// Remove the first function in this module, and return it, if present
+    ///
+    /// NOTE: This function will panic if the module has been frozen
+    pub fn pop_front(&mut self) -> Option<Box<Function>> {
+        self.functions.pop_front()
+    }
+
     /// Convert this module into its [miden_assembly::Module] representation.
     pub fn to_module_ast(&self, codemap: &miden_diagnostics::CodeMap) -> miden_assembly::Module {
         use miden_assembly::{self as masm, ast::ModuleImports};
@@ -1,5 +1,9 @@
 use core::fmt;
-use std::{collections::BTreeMap, path::Path, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    sync::Arc,
+};
 
 use miden_hir::{self as hir, DataSegmentTable, FunctionIdent, Ident};
 use rustc_hash::FxHashMap;
@@ -75,6 +79,84 @@ impl Program {
         self.modules.iter().any(|m| m.name == name)
     }
 
+    /// Returns the set of functions referenced by this program which are not defined by any of its
+    /// modules, i.e. those which must be provided by the environment it is executed in, such as
+    /// the transaction kernel, the Miden SDK, or the standard library.
+    ///
+    /// This serves as a manifest of the dependencies of a compiled program, e.g. to determine the
+    /// host environment required to deploy it.
+    pub fn dependencies(&self) -> BTreeSet<FunctionIdent> {
+        let functions = self
+            .modules()
+            .flat_map(|module| module.functions().map(move |f| (&module.imports, &f.body)));
+        let body = self.body.iter().map(|begin| (&begin.imports, &begin.body));
+
+        let mut dependencies = BTreeSet::default();
+        for (imports, region) in functions.chain(body) {
+            for op in region.blocks.values().flat_map(|block| block.ops.iter()) {
+                let callee = match op {
+                    Op::Exec(callee)
+                    | Op::Call(callee)
+                    | Op::Syscall(callee)
+                    | Op::ProcRef(callee) => callee,
+                    _ => continue,
+                };
+                // Callees may be referenced via the alias of the module they are imported from
+                let module = imports.unalias(&callee.module).unwrap_or(callee.module);
+                if !self.contains(module) {
+                    dependencies.insert(FunctionIdent {
+                        module,
+                        function: callee.function,
+                    });
+                }
+            }
+        }
+        dependencies
+    }
+
+    /// Returns the instructions which initialize the memory reserved for `segments`, by writing
+    /// the initializer of each segment to the memory it occupies.
+    ///
+    /// This is the prologue of the entrypoint of an executable program, which is executed before
+    /// control is transferred to the entrypoint function.
+    ///
+    /// Byte addresses are mapped to words of Miden's memory as described in
+    /// [NativePtr::from_ptr], with each element of a word holding 4 bytes in little-endian order.
+    /// Memory is written a word at a time, so segments which share a word are written together,
+    /// and words which are entirely zero are skipped, as memory is zeroed on startup. Words in
+    /// which only the first element is non-zero are written with a scalar store instead.
+    pub fn data_segment_prologue(segments: &DataSegmentTable) -> Vec<Op> {
+        let mut words = BTreeMap::<u32, [u8; 16]>::default();
+        for segment in segments.iter() {
+            let offset = segment.offset();
+            for (i, byte) in segment.init().as_slice().iter().copied().enumerate() {
+                let addr = offset + i as u32;
+                words.entry(addr / 16).or_insert([0; 16])[(addr % 16) as usize] = byte;
+            }
+        }
+
+        let mut ops = vec![];
+        for (waddr, bytes) in words.into_iter() {
+            if bytes.iter().all(|byte| *byte == 0) {
+                continue;
+            }
+            // If only the first element of the word is non-zero, e.g. the tail of a segment which
+            // ends one element into the word, a scalar store is sufficient
+            if bytes[4..].iter().all(|byte| *byte == 0) {
+                ops.push(Op::PushU32(u32::from_le_bytes(bytes[..4].try_into().unwrap())));
+                ops.push(Op::MemStoreImm(waddr));
+                continue;
+            }
+            // The first element of the word must be on top of the stack
+            for element in bytes.chunks_exact(4).rev() {
+                ops.push(Op::PushU32(u32::from_le_bytes(element.try_into().unwrap())));
+            }
+            ops.push(Op::MemStorewImm(waddr));
+            ops.push(Op::Dropw);
+        }
+        ops
+    }
+
     /// Write this [Program] to the given output directory.
     ///
     /// The provided [miden_diagnostics::CodeMap] is used for computing source locations.
@@ -150,7 +232,8 @@ impl Program {
             let invoked = BTreeMap::from([(entry_id, (entry_name, entry_module_path))]);
             let imports = ModuleImports::new(imported, invoked);
 
-            // TODO: Write data segments, initialize function table
+            // NOTE: Programs with data segments are always constructed with a body, which writes
+            // them in its prologue, see `data_segment_prologue`, so there is nothing to write here
             let body = vec![Node::Instruction(Instruction::ExecImported(entry_id))];
 
             ProgramAst::new(body, vec![])
@@ -197,8 +280,6 @@ impl Program {
         root_ns: S,
         codemap: &miden_diagnostics::CodeMap,
     ) -> Result<miden_assembly::MaslLibrary, miden_assembly::LibraryError> {
-        use std::collections::BTreeSet;
-
         use miden_assembly::{LibraryNamespace, MaslLibrary, Version};
 
         let ns = LibraryNamespace::new(root_ns)?;
@@ -256,7 +337,9 @@ impl From<&hir::Program> for Program {
             let mut begin = Begin::default();
             begin.imports.add(entry);
             let entry_module = begin.imports.alias(&entry.module);
-            begin.body.block_mut(begin.body.body).ops.push(Op::Exec(FunctionIdent {
+            let body = begin.body.block_mut(begin.body.body);
+            body.ops.extend(Self::data_segment_prologue(&segments));
+            body.ops.push(Op::Exec(FunctionIdent {
                 module: entry_module.unwrap_or(entry.module),
                 function: entry.function,
             }));
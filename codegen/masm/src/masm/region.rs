@@ -27,6 +27,35 @@ impl fmt::Display for Begin {
     }
 }
 
+/// An estimate of the cost of executing some Miden Assembly, see [Region::cost_estimate].
+///
+/// Instructions are counted once per occurrence in the code, regardless of how many times they
+/// are executed, while cycles are counted per execution. Each instruction is assumed to take a
+/// single cycle, as in the emulator, and calls are assumed to take a single cycle, i.e. the cost
+/// of the callee is not included.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CostEstimate {
+    /// The number of instructions in the code
+    pub ops: usize,
+    /// The number of `dropw` instructions in the code
+    pub dropws: usize,
+    /// The number of `swap` instructions in the code
+    pub swaps: usize,
+    /// The number of `movup` instructions in the code
+    pub movups: usize,
+    /// The estimated number of cycles needed to execute the code
+    pub cycles: usize,
+}
+impl fmt::Display for CostEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ops (dropw: {}, swap: {}, movup: {}), ~{} cycles",
+            self.ops, self.dropws, self.swaps, self.movups, self.cycles
+        )
+    }
+}
+
 /// This struct represents a region of code in Miden Assembly.
 ///
 /// A region is a tree of blocks with isolated scope. In many
@@ -48,6 +77,7 @@ impl Default for Region {
         let body = blocks.push(Block {
             id,
             ops: smallvec![],
+            origin: None,
         });
         Self { body, blocks }
     }
@@ -71,6 +101,88 @@ impl Region {
         &mut self.blocks[id]
     }
 
+    /// Returns true if any operation in this region may trap when executed
+    ///
+    /// See [Op::may_trap] for details on what is considered to trap.
+    pub fn may_trap(&self) -> bool {
+        self.blocks.values().any(|block| block.ops.iter().any(|op| op.may_trap()))
+    }
+
+    /// Returns the maximum depth to which control flow, i.e. `if.true`, `while.true` and `repeat`
+    /// blocks, is nested in this region, or zero if there is no control flow
+    pub fn nesting_depth(&self) -> usize {
+        self.block_nesting_depth(self.body)
+    }
+
+    fn block_nesting_depth(&self, id: BlockId) -> usize {
+        self.blocks[id]
+            .ops
+            .iter()
+            .map(|op| match op {
+                Op::If(then_blk, else_blk) => {
+                    let then_depth = self.block_nesting_depth(*then_blk);
+                    1 + then_depth.max(self.block_nesting_depth(*else_blk))
+                }
+                Op::While(body_blk) | Op::Repeat(_, body_blk) => {
+                    1 + self.block_nesting_depth(*body_blk)
+                }
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Estimate the cost of executing this region, see [CostEstimate].
+    ///
+    /// The number of iterations of a `while.true` loop is generally only known at runtime, so each
+    /// such loop is assumed to iterate `loop_iterations` times.
+    pub fn cost_estimate(&self, loop_iterations: usize) -> CostEstimate {
+        let mut estimate = CostEstimate::default();
+        estimate.cycles = self.block_cost(self.body, loop_iterations, &mut estimate);
+        estimate
+    }
+
+    /// Count the instructions of block `id` in `estimate`, and return the cycles needed to execute
+    /// it once
+    fn block_cost(
+        &self,
+        id: BlockId,
+        loop_iterations: usize,
+        estimate: &mut CostEstimate,
+    ) -> usize {
+        let mut cycles = 0;
+        for op in self.blocks[id].ops.iter() {
+            estimate.ops += 1;
+            cycles += match op {
+                // Only one of the branches is taken, so assume the most expensive one is
+                Op::If(then_blk, else_blk) => {
+                    let then_cycles = self.block_cost(*then_blk, loop_iterations, estimate);
+                    1 + then_cycles.max(self.block_cost(*else_blk, loop_iterations, estimate))
+                }
+                Op::While(body_blk) => {
+                    1 + loop_iterations * self.block_cost(*body_blk, loop_iterations, estimate)
+                }
+                Op::Repeat(n, body_blk) => {
+                    *n as usize * self.block_cost(*body_blk, loop_iterations, estimate)
+                }
+                Op::Dropw => {
+                    estimate.dropws += 1;
+                    1
+                }
+                Op::Swap(_) => {
+                    estimate.swaps += 1;
+                    1
+                }
+                Op::Movup(_) => {
+                    estimate.movups += 1;
+                    1
+                }
+                _ => 1,
+            };
+        }
+        cycles
+    }
+
     /// Get the instruction under `ip`, if valid
     pub fn get(&self, ip: InstructionPointer) -> Option<Op> {
         self.blocks[ip.block].ops.get(ip.index).copied()
@@ -82,6 +194,7 @@ impl Region {
         self.blocks.push(Block {
             id,
             ops: smallvec![],
+            origin: None,
         });
         id
     }
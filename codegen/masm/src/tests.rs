@@ -1,7 +1,7 @@
-use std::{cell::RefCell, sync::Arc};
+use std::{cell::RefCell, rc::Rc, sync::Arc};
 
 use miden_hir::{
-    pass::{AnalysisManager, ConversionPass},
+    pass::{AnalysisManager, ConversionError, ConversionPass},
     testing::{self, TestContext},
     AbiParam, CallConv, Felt, FieldElement, FunctionIdent, Immediate, InstBuilder, Linkage,
     OperandStack, ProgramBuilder, Signature, SourceSpan, Stack, StarkField, Type,
@@ -10,6 +10,10 @@ use proptest::prelude::*;
 use smallvec::{smallvec, SmallVec};
 
 use super::*;
+use crate::codegen::{
+    Constraint, EmitError, FunctionEmitter, Schedule, ScheduleOp, Scheduler, SchedulerObserver,
+    TypedValue,
+};
 
 #[cfg(test)]
 #[allow(unused_macros)]
@@ -154,6 +158,25 @@ impl TestByEmulationHarness {
         self.emulator.start()
     }
 
+    /// Compile `program`, execute its entrypoint on the emulator with `args`, and assert that the
+    /// operand stack holds exactly `expected` on exit, starting from the top of the stack.
+    pub fn assert_program_output(
+        &mut self,
+        program: Box<hir::Program>,
+        args: &[Felt],
+        expected: &[u64],
+    ) {
+        let mut compiler = MasmCompiler::new(&self.context.session);
+        let program = compiler.compile(program).expect("compilation failed");
+
+        let mut stack = self.execute_program(program.freeze(), args).expect("execution failed");
+        let mut results = vec![];
+        while let Some(result) = stack.pop() {
+            results.push(result.as_int());
+        }
+        assert_eq!(results.as_slice(), expected);
+    }
+
     #[allow(unused)]
     pub fn execute_program_with_entry(
         &mut self,
@@ -371,6 +394,352 @@ fn codegen_fundamental_loops() {
     assert_eq!(stack.pop().map(|e| e.as_int()), Some(7));
 }
 
+/// Test that a tail-controlled loop is lowered so that the loop condition controls the
+/// `while.true` directly, rather than via a conditional nested in an unconditional loop
+#[test]
+fn codegen_do_while_loops() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that sums the integers in `0..n`
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "sum_to",
+                Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let n = fb.block_params(entry)[0];
+        let loop_header_blk = fb.create_block();
+        let i1 = fb.append_block_param(loop_header_blk, Type::U32, SourceSpan::UNKNOWN);
+        let acc1 = fb.append_block_param(loop_header_blk, Type::U32, SourceSpan::UNKNOWN);
+        let n1 = fb.append_block_param(loop_header_blk, Type::U32, SourceSpan::UNKNOWN);
+        let loop_body_blk = fb.create_block();
+        let loop_exit_blk = fb.create_block();
+        let result0 = fb.append_block_param(loop_exit_blk, Type::U32, SourceSpan::UNKNOWN);
+        let zero = fb.ins().u32(0, SourceSpan::UNKNOWN);
+        fb.ins().br(loop_header_blk, &[zero, zero, n], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(loop_header_blk);
+        let is_lt = fb.ins().lt(i1, n1, SourceSpan::UNKNOWN);
+        fb.ins()
+            .cond_br(is_lt, loop_body_blk, &[], loop_exit_blk, &[acc1], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(loop_body_blk);
+        let acc2 = fb.ins().add_checked(acc1, i1, SourceSpan::UNKNOWN);
+        let i2 = fb.ins().incr_checked(i1, SourceSpan::UNKNOWN);
+        fb.ins().br(loop_header_blk, &[i2, acc2, n1], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(loop_exit_blk);
+        fb.ins().ret(Some(result0), SourceSpan::UNKNOWN);
+
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    // The loop is entered conditionally, and its body is emitted directly in the `while.true`
+    let function = program
+        .get("test")
+        .unwrap()
+        .functions()
+        .find(|f| f.name == id)
+        .expect("expected test::sum_to to have been compiled");
+    let mut loops = 0;
+    for block in function.body.blocks.values() {
+        for (i, op) in block.ops.iter().enumerate() {
+            if let masm::Op::While(body) = op {
+                loops += 1;
+                assert_ne!(i.checked_sub(1).map(|i| &block.ops[i]), Some(&masm::Op::PushU8(1)));
+                let body = function.block(*body);
+                assert!(!body.ops.iter().any(|op| matches!(op, masm::Op::If(..))));
+            }
+        }
+    }
+    assert_eq!(loops, 1);
+
+    let n = Felt::new(5);
+
+    let mut stack = harness.execute_program(program.freeze(), &[n]).expect("execution failed");
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(10));
+}
+
+/// Test the code generator on a multi-way branch, including multiple arms with the same
+/// destination, and a discriminant which falls through to the default destination
+#[test]
+fn codegen_switch() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that computes a different result for each arm
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "select",
+                Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let a = fb.block_params(entry)[0];
+        let add_blk = fb.create_block();
+        let mul_blk = fb.create_block();
+        let sub_blk = fb.create_block();
+        let default_blk = fb.create_block();
+        fb.ins().switch(
+            a,
+            vec![(0, add_blk), (1, mul_blk), (2, add_blk), (4, sub_blk)],
+            default_blk,
+            SourceSpan::UNKNOWN,
+        );
+
+        fb.switch_to_block(add_blk);
+        let result = fb.ins().add_imm_checked(a, Immediate::U32(100), SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(mul_blk);
+        let result = fb.ins().mul_imm_checked(a, Immediate::U32(3), SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(sub_blk);
+        let result = fb.ins().sub_imm_checked(a, Immediate::U32(1), SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(default_blk);
+        let result = fb.ins().u32(7, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    for (a, expected) in [(0, 100), (1, 3), (2, 102), (3, 7), (4, 3), (9, 7)] {
+        let mut stack = harness.invoke(id, &[Felt::new(a)]).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(expected), "unexpected result for {a}");
+    }
+}
+
+/// Test the code generator on a multi-way branch with a single arm
+#[test]
+fn codegen_switch_single_arm() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that adds to the selector only if it matches the arm
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "select",
+                Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let a = fb.block_params(entry)[0];
+        let add_blk = fb.create_block();
+        let default_blk = fb.create_block();
+        fb.ins().switch(a, vec![(3, add_blk)], default_blk, SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(add_blk);
+        let result = fb.ins().add_imm_checked(a, Immediate::U32(100), SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(default_blk);
+        let result = fb.ins().u32(7, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    for (a, expected) in [(0, 7), (2, 7), (3, 103), (4, 7)] {
+        let mut stack = harness.invoke(id, &[Felt::new(a)]).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(expected), "unexpected result for {a}");
+    }
+}
+
+/// Test that sign-extending the low bits of an integer, as done by the Wasm `extend8_s` family of
+/// instructions, discards the high bits, and extends the sign bit of the low bits
+#[test]
+fn codegen_sign_extension() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with functions that sign-extend the low 8 bits of an i32, and the low 32
+    // bits of an i64
+    let mut mb = builder.module("test");
+    let extend8 = {
+        let mut fb = mb
+            .function(
+                "extend8",
+                Signature::new([AbiParam::new(Type::I32)], [AbiParam::new(Type::I32)]),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let a = fb.block_params(entry)[0];
+        let narrow = fb.ins().trunc(a, Type::I8, SourceSpan::UNKNOWN);
+        let result = fb.ins().sext(narrow, Type::I32, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let extend32 = {
+        let mut fb = mb
+            .function(
+                "extend32",
+                Signature::new([AbiParam::new(Type::I64)], [AbiParam::new(Type::I64)]),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let a = fb.block_params(entry)[0];
+        let narrow = fb.ins().trunc(a, Type::I32, SourceSpan::UNKNOWN);
+        let result = fb.ins().sext(narrow, Type::I64, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(extend8).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    let minus_one = u32::MAX as u64;
+    let cases = [(0xff, minus_one), (0x1ff, minus_one), (0x7f, 0x7f), (0x180, 0xffffff80)];
+    for (a, expected) in cases {
+        let mut stack = harness.invoke(extend8, &[Felt::new(a)]).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(expected), "unexpected result for {a}");
+    }
+
+    // i64 values are represented as two 32-bit limbs, the most significant limb first
+    for (a, expected) in [([0, minus_one], [minus_one, minus_one]), ([1, 0x7f], [0, 0x7f])] {
+        let args = a.map(Felt::new);
+        let mut stack = harness.invoke(extend32, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 2);
+        let result = [stack.pop().unwrap().as_int(), stack.pop().unwrap().as_int()];
+        assert_eq!(result, expected, "unexpected result for {a:?}");
+    }
+}
+
+/// Test that sign-extending the low 8 or 16 bits of an i64, as done by the Wasm `i64.extend8_s`
+/// and `i64.extend16_s` instructions, discards all of the bits above them, including those of the
+/// low limb
+#[test]
+fn codegen_sign_extension_i64() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with functions that sign-extend the low 8 and 16 bits of an i64
+    let mut mb = builder.module("test");
+    let signature = Signature::new([AbiParam::new(Type::I64)], [AbiParam::new(Type::I64)]);
+    let extend8 = {
+        let mut fb = mb.function("extend8", signature.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let a = fb.block_params(entry)[0];
+        let narrow = fb.ins().trunc(a, Type::I8, SourceSpan::UNKNOWN);
+        let result = fb.ins().sext(narrow, Type::I64, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let extend16 = {
+        let mut fb = mb.function("extend16", signature).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let a = fb.block_params(entry)[0];
+        let narrow = fb.ins().trunc(a, Type::I16, SourceSpan::UNKNOWN);
+        let result = fb.ins().sext(narrow, Type::I64, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(extend8).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    // i64 values are represented as two 32-bit limbs, the most significant limb first
+    let minus_one = u32::MAX as u64;
+    let cases = [
+        (extend8, [0x12345678, 0x1ff], [minus_one, minus_one]),
+        (extend8, [minus_one, 0xffff017f], [0, 0x7f]),
+        (extend8, [0, 0x80], [minus_one, 0xffffff80]),
+        (extend16, [1, 0x18000], [minus_one, 0xffff8000]),
+        (extend16, [minus_one, 0xffff7fff], [0, 0x7fff]),
+        (extend16, [0, 0x1ffff], [minus_one, minus_one]),
+    ];
+    for (function, a, expected) in cases {
+        let args = a.map(Felt::new);
+        let mut stack = harness.invoke(function, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 2);
+        let result = [stack.pop().unwrap().as_int(), stack.pop().unwrap().as_int()];
+        assert_eq!(result, expected, "unexpected result of {function} for {a:?}");
+    }
+}
+
 /// Test the code generator on a simple program containing [testing::sum_matrix].
 #[test]
 fn codegen_sum_matrix() {
@@ -424,27 +793,3371 @@ fn codegen_sum_matrix() {
     assert_eq!(stack.pop().map(|e| e.as_int()), Some(6));
 }
 
+/// Test that loads and stores of integers smaller than 32 bits only access the bytes of the
+/// element which hold the value, and that loaded values can be sign-extended.
 #[test]
-#[should_panic(expected = "assertion failed: expected false, got true")]
-fn i32_checked_neg() {
+fn codegen_sub_word_load_store() {
     let mut harness = TestByEmulationHarness::default();
 
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with a function equivalent to the following:
+    //
+    //     i32.load8_s (local.get 0)
+    //     i32.store8 (local.get 0) (i32.const 0xAA)
+    //     i32.load (local.get 0)
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "narrow",
+                Signature::new(
+                    [AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::I32), AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let addr = fb.block_params(entry)[0];
+        let byte_ptr = fb.ins().inttoptr(addr, Type::Ptr(Box::new(Type::U8)), SourceSpan::UNKNOWN);
+        let byte = fb.ins().load(byte_ptr, SourceSpan::UNKNOWN);
+        let signed = fb.ins().sext(byte, Type::I32, SourceSpan::UNKNOWN);
+        let value = fb.ins().u8(0xaa, SourceSpan::UNKNOWN);
+        fb.ins().store(byte_ptr, value, SourceSpan::UNKNOWN);
+        let word_ptr = fb.ins().inttoptr(addr, Type::Ptr(Box::new(Type::U32)), SourceSpan::UNKNOWN);
+        let word = fb.ins().load(word_ptr, SourceSpan::UNKNOWN);
+        fb.ins().ret_many(&[signed, word], SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
     harness
         .emulator
-        .load_module(
-            Box::new(
-                intrinsics::load("intrinsics::i32", &harness.context.session.codemap)
-                    .expect("undefined intrinsic module"),
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    // The low byte of the element is 0xFF, which is -1 when sign-extended
+    let addr = harness.malloc(core::mem::size_of::<u32>());
+    harness.store(addr as usize, Felt::new(0x112233ff));
+
+    let mut stack = harness.invoke(id, &[Felt::new(addr as u64)]).expect("execution failed");
+    harness.emulator.stop();
+    assert_eq!(stack.len(), 2);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(u32::MAX as u64));
+    // Only the low byte of the element was replaced by the store
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(0x112233aa));
+}
+
+/// Test that a 16-bit load from the last byte of an element is assembled from the bytes of two
+/// elements
+#[test]
+fn codegen_sub_word_load_spanning_elements() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with a function equivalent to the following:
+    //
+    //     i32.load16_u align=2 offset=3 (local.get 0)
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "spanning",
+                Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U16)]),
             )
-            .freeze(),
-        )
-        .expect("failed to load intrinsics::i32");
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let addr = fb.block_params(entry)[0];
+        let addr = fb.ins().add_imm_wrapping(addr, Immediate::U32(3), SourceSpan::UNKNOWN);
+        let ptr = fb.ins().inttoptr(addr, Type::Ptr(Box::new(Type::U16)), SourceSpan::UNKNOWN);
+        let value = fb.ins().load(ptr, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(value), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
 
-    let min = Felt::new(i32::MIN as u32 as u64);
+    mb.build().expect("unexpected error constructing test module");
 
-    let neg = "intrinsics::i32::checked_neg".parse().unwrap();
-    // i32::MIN
-    harness.invoke(neg, &[min]).expect("execution failed");
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    // The value is made up of the high byte of the first element, and the low byte of the next
+    let addr = harness.malloc(core::mem::size_of::<u32>() * 2);
+    harness.store(addr as usize, Felt::new(0x112233ff));
+    harness.store(addr as usize + 4, Felt::new(0x55667788));
+
+    harness.assert_program_output(program, &[Felt::new(addr as u64)], &[0x8811]);
+}
+
+/// Test that a 32-bit load from an address which is not a multiple of four is loaded correctly,
+/// as the alignment of a Wasm load is only a hint
+#[test]
+fn codegen_misaligned_load() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with a function equivalent to the following:
+    //
+    //     i32.load align=4 (local.get 0)
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "misaligned",
+                Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let addr = fb.block_params(entry)[0];
+        let ptr = fb.ins().inttoptr(addr, Type::Ptr(Box::new(Type::U32)), SourceSpan::UNKNOWN);
+        let value = fb.ins().load(ptr, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(value), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    let addr = harness.malloc(core::mem::size_of::<u32>() * 2);
+    harness.store(addr as usize, Felt::new(0x112233ff));
+    harness.store(addr as usize + 4, Felt::new(0x55667788));
+
+    // An address one byte past an element spans the high bytes of the first element, and the low
+    // byte of the next
+    for (addr, expected) in [(addr, 0x112233ff), (addr + 1, 0x88112233)] {
+        let mut stack = harness.invoke(id, &[Felt::new(addr as u64)]).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(expected), "load at {addr:#x}");
+    }
+}
+
+/// Test that a function with no results leaves the operand stack empty when it returns, even
+/// when it computes intermediate values along the way
+#[test]
+fn codegen_zero_results() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that asserts that the sum of two numbers is at least as
+    // large as the first number, and returns nothing
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "assert_sum_gte",
+                Signature::new(
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U64), AbiParam::new(Type::U32)],
+                    [],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b, c) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1], args[2])
+        };
+        let b32 = fb.ins().trunc(b, Type::U32, SourceSpan::UNKNOWN);
+        let sum = fb.ins().add_checked(a, b32, SourceSpan::UNKNOWN);
+        let sum = fb.ins().add_checked(sum, c, SourceSpan::UNKNOWN);
+        let is_gte = fb.ins().gte(sum, a, SourceSpan::UNKNOWN);
+        fb.ins().assert(is_gte, SourceSpan::UNKNOWN);
+        fb.ins().ret(None, SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    let a = Felt::new(1);
+    let b = [Felt::new(0), Felt::new(2)];
+    let c = Felt::new(3);
+    let stack = harness.invoke(id, &[a, b[0], b[1], c]).expect("execution failed");
+    assert_eq!(stack.len(), 0);
+}
+
+/// Test that functions are flagged as potentially trapping based on the code generated for them
+#[test]
+fn codegen_may_trap() {
+    let harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with one function that asserts, and one which only does arithmetic
+    let mut mb = builder.module("test");
+    let assert_gte = {
+        let mut fb = mb
+            .function(
+                "assert_gte",
+                Signature::new([AbiParam::new(Type::U32), AbiParam::new(Type::U32)], []),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let is_gte = fb.ins().gte(a, b, SourceSpan::UNKNOWN);
+        fb.ins().assert(is_gte, SourceSpan::UNKNOWN);
+        fb.ins().ret(None, SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let mul_add = {
+        let mut fb = mb
+            .function(
+                "mul_add",
+                Signature::new(
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let product = fb.ins().mul_wrapping(a, b, SourceSpan::UNKNOWN);
+        let sum = fb.ins().add_wrapping(product, a, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(assert_gte).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let module = program.get("test").unwrap();
+    let function = |id| module.functions().find(|f| f.name == id).expect("missing function");
+    assert!(function(assert_gte).may_trap());
+    assert!(!function(mul_add).may_trap());
+}
+
+/// Test that when optimizations are enabled, an `eq` with two constant operands is lowered using
+/// the immediate form of the instruction, i.e. `push.3 eq.5` rather than `push.5 push.3 eq`
+#[test]
+fn codegen_fold_constant_operands() {
+    let options = midenc_session::Options {
+        optimize: midenc_session::OptLevel::Basic,
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, None);
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with a function that compares two constants
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function("eq_consts", Signature::new([], [AbiParam::new(Type::I1)]))
+            .expect("unexpected symbol conflict");
+        let a = fb.ins().u32(3, SourceSpan::UNKNOWN);
+        let b = fb.ins().u32(5, SourceSpan::UNKNOWN);
+        let is_equal = fb.ins().eq(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(is_equal), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let module = program.get("test").unwrap();
+    let function = module.functions().find(|f| f.name == id).expect("missing function");
+    let ops = function
+        .body
+        .blocks
+        .values()
+        .flat_map(|block| block.ops.iter().cloned())
+        .collect::<Vec<_>>();
+    assert!(ops.contains(&masm::Op::EqImm(Felt::new(5))), "expected eq.5 in {ops:?}");
+    assert!(!ops.contains(&masm::Op::Eq), "expected no eq in {ops:?}");
+}
+
+/// Test that when `trap_infinite_loops` is set, a panic handler which loops forever, as is typical
+/// of `no_std` crates, traps as soon as it is called, rather than spinning in a `while.true` loop
+#[test]
+fn codegen_trap_infinite_loops() {
+    let options = midenc_session::Options {
+        trap_infinite_loops: true,
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, None);
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with a division which panics when the divisor is zero
+    let mut mb = builder.module("test");
+    let panic_signature = Signature::new([AbiParam::new(Type::I32)], []);
+    let panic_handler = {
+        let mut fb = mb
+            .function("rust_begin_unwind", panic_signature.clone())
+            .expect("unexpected symbol conflict");
+        let loop_blk = fb.create_block();
+        fb.ins().br(loop_blk, &[], SourceSpan::UNKNOWN);
+        fb.switch_to_block(loop_blk);
+        fb.ins().br(loop_blk, &[], SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let id = {
+        let mut fb = mb
+            .function(
+                "div",
+                Signature::new(
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        fb.import_function(panic_handler.module, panic_handler.function, panic_signature)
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let panic_blk = fb.create_block();
+        let div_blk = fb.create_block();
+        let is_zero = fb.ins().eq_imm(b, Immediate::U32(0), SourceSpan::UNKNOWN);
+        fb.ins().cond_br(is_zero, panic_blk, &[], div_blk, &[], SourceSpan::UNKNOWN);
+        fb.switch_to_block(panic_blk);
+        let info = fb.ins().i32(0, SourceSpan::UNKNOWN);
+        fb.ins().call(panic_handler, &[info], SourceSpan::UNKNOWN);
+        fb.ins().unreachable(SourceSpan::UNKNOWN);
+        fb.switch_to_block(div_blk);
+        let quotient = fb.ins().div_unchecked(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(quotient), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let module = program.get("test").unwrap();
+    let function = module.functions().find(|f| f.name == panic_handler).expect("missing function");
+    let ops = function
+        .body
+        .blocks
+        .values()
+        .flat_map(|block| block.ops.iter().cloned())
+        .collect::<Vec<_>>();
+    assert!(ops.contains(&masm::Op::Assert), "expected a trap in {ops:?}");
+    assert!(
+        !ops.iter().any(|op| matches!(op, masm::Op::While(_))),
+        "expected no loop in {ops:?}"
+    );
+}
+
+/// Test that when optimizations are enabled, the code of a loop header, which is otherwise
+/// duplicated on entry to the loop and on each loopback edge, is emitted once, in a procedure of
+/// its own, which is executed in place of each copy
+#[test]
+fn codegen_outline_loop_headers() {
+    let options = midenc_session::Options {
+        optimize: midenc_session::OptLevel::Basic,
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, None);
+    let mut harness = TestByEmulationHarness {
+        context,
+        emulator: Emulator::default(),
+    };
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with a function that adds `n` to `a`, by twos while `n` is greater than
+    // two, and by ones otherwise, so that the loop header has two loopback edges
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "add_until",
+                Signature::new(
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, n) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let loop_header_blk = fb.create_block();
+        let a1 = fb.append_block_param(loop_header_blk, Type::U32, SourceSpan::UNKNOWN);
+        let n1 = fb.append_block_param(loop_header_blk, Type::U32, SourceSpan::UNKNOWN);
+        let loop_body_blk = fb.create_block();
+        let by_two_blk = fb.create_block();
+        let by_one_blk = fb.create_block();
+        let loop_exit_blk = fb.create_block();
+        let result0 = fb.append_block_param(loop_exit_blk, Type::U32, SourceSpan::UNKNOWN);
+        fb.ins().br(loop_header_blk, &[a, n], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(loop_header_blk);
+        let is_zero = fb.ins().eq_imm(n1, Immediate::U32(0), SourceSpan::UNKNOWN);
+        fb.ins()
+            .cond_br(is_zero, loop_exit_blk, &[a1], loop_body_blk, &[], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(loop_body_blk);
+        let is_gt_two = fb.ins().gt_imm(n1, Immediate::U32(2), SourceSpan::UNKNOWN);
+        fb.ins()
+            .cond_br(is_gt_two, by_two_blk, &[], by_one_blk, &[], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(by_two_blk);
+        let a2 = fb.ins().add_imm_checked(a1, Immediate::U32(2), SourceSpan::UNKNOWN);
+        let n2 = fb.ins().sub_imm_checked(n1, Immediate::U32(2), SourceSpan::UNKNOWN);
+        fb.ins().br(loop_header_blk, &[a2, n2], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(by_one_blk);
+        let a3 = fb.ins().incr_checked(a1, SourceSpan::UNKNOWN);
+        let n3 = fb.ins().sub_imm_checked(n1, Immediate::U32(1), SourceSpan::UNKNOWN);
+        fb.ins().br(loop_header_blk, &[a3, n3], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(loop_exit_blk);
+        fb.ins().ret(Some(result0), SourceSpan::UNKNOWN);
+
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    // The header is emitted once, and executed on entry, and on both loopback edges
+    let module = program.get("test").unwrap();
+    let headers = module
+        .functions()
+        .filter(|f| f.name.function.as_str().starts_with("add_until_loop_header"))
+        .map(|f| f.name)
+        .collect::<Vec<_>>();
+    assert_eq!(headers.len(), 1, "expected a single loop header procedure");
+    let function = module.functions().find(|f| f.name == id).expect("missing function");
+    let execs = function
+        .body
+        .blocks
+        .values()
+        .flat_map(|block| block.ops.iter())
+        .filter(|op| **op == masm::Op::Exec(headers[0]))
+        .count();
+    assert_eq!(execs, 3);
+
+    let a = Felt::new(3);
+    let n = Felt::new(5);
+
+    let mut stack = harness.execute_program(program.freeze(), &[a, n]).expect("execution failed");
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(8));
+}
+
+/// Test that a value whose only use in another block is dead is not copied for its last live use,
+/// as it would be if liveness still reflected the dead use, i.e. if dead code was only eliminated
+/// during scheduling, rather than prior to code generation.
+#[test]
+fn codegen_no_copies_for_dead_uses() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with a function which computes `a + b`, and then along one of two paths,
+    // computes `a * 2` for nothing
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "add_or_not",
+                Signature::new(
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U32), AbiParam::new(Type::I1)],
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b, flag) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1], args[2])
+        };
+        let dead_blk = fb.create_block();
+        let exit_blk = fb.create_block();
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        fb.ins().cond_br(flag, dead_blk, &[], exit_blk, &[], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(dead_blk);
+        fb.ins().mul_imm_wrapping(a, Immediate::U32(2), SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(exit_blk);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    // No value is used more than once, so nothing should be copied
+    let module = program.get("test").unwrap();
+    let function = module.functions().find(|f| f.name == id).expect("missing function");
+    let ops = function
+        .body
+        .blocks
+        .values()
+        .flat_map(|block| block.ops.iter().cloned())
+        .collect::<Vec<_>>();
+    assert!(
+        !ops.iter().any(|op| matches!(op, masm::Op::Dup(_))),
+        "unexpected dup in {ops:?}"
+    );
+
+    let args = [3, 4, 1].map(Felt::new);
+    let mut stack = harness.execute_program(program.freeze(), &args).expect("execution failed");
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(7));
+}
+
+/// Test that the operands of a commutative operator are used in the order in which they are found
+/// on the operand stack, rather than swapped into the order in which they are given in the IR
+#[test]
+fn codegen_commutative_operands() {
+    let context = TestContext::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with functions which compute `((a op b) op c) op d`, so that the operands
+    // of each instruction are on top of the operand stack, but in the opposite order to that of
+    // the IR
+    let mut mb = builder.module("test");
+    let signature = Signature::new(
+        [
+            AbiParam::new(Type::U32),
+            AbiParam::new(Type::U32),
+            AbiParam::new(Type::U32),
+            AbiParam::new(Type::U32),
+        ],
+        [AbiParam::new(Type::U32)],
+    );
+    let sum = {
+        let mut fb = mb.function("sum", signature.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let args = fb.block_params(entry).to_vec();
+        let mut acc = args[0];
+        for arg in args[1..].iter().copied() {
+            acc = fb.ins().add_wrapping(acc, arg, SourceSpan::UNKNOWN);
+        }
+        fb.ins().ret(Some(acc), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let diff = {
+        let mut fb = mb.function("diff", signature).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let args = fb.block_params(entry).to_vec();
+        let mut acc = args[0];
+        for arg in args[1..].iter().copied() {
+            acc = fb.ins().sub_wrapping(acc, arg, SourceSpan::UNKNOWN);
+        }
+        fb.ins().ret(Some(acc), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(sum).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let module = program.get("test").unwrap();
+    let swaps = |id| {
+        let function = module.functions().find(|f| f.name == id).expect("missing function");
+        function
+            .body
+            .blocks
+            .values()
+            .flat_map(|block| block.ops.iter())
+            .filter(|op| matches!(op, masm::Op::Swap(_)))
+            .count()
+    };
+
+    // The subtractions must swap their operands, but none of the additions need to
+    assert!(swaps(diff) > 0);
+    assert_eq!(swaps(sum), 0);
+}
+
+/// Test that when requested, each block of emitted code is annotated with the block of the
+/// source function it was emitted from
+#[test]
+fn codegen_annotate_blocks() {
+    let options = midenc_session::Options {
+        annotate_blocks: true,
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, None);
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with function that adds two numbers if the
+    // first number is odd, and multiplies them if the first number is even
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "add_odd_mul_even",
+                Signature::new(
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let is_odd_blk = fb.create_block();
+        let is_even_blk = fb.create_block();
+        let is_odd = fb.ins().is_odd(a, SourceSpan::UNKNOWN);
+        fb.ins().cond_br(is_odd, is_odd_blk, &[], is_even_blk, &[], SourceSpan::UNKNOWN);
+        fb.switch_to_block(is_odd_blk);
+        let c = fb.ins().add_checked(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(c), SourceSpan::UNKNOWN);
+        fb.switch_to_block(is_even_blk);
+        let d = fb.ins().mul_checked(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(d), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let module = program.get("test").unwrap();
+    let function = module.functions().find(|f| f.name == id).expect("missing function");
+    let masm = function.display(&module.imports).to_string();
+    let lines = masm.lines().map(str::trim).collect::<Vec<_>>();
+
+    // The body of the function is the entry block, and each branch of the conditional is the
+    // block it branches to
+    assert_eq!(lines[1], "# from block0", "unexpected output: {masm}");
+    let then_idx = lines.iter().position(|line| *line == "if.true").expect("missing if.true");
+    assert_eq!(lines[then_idx + 1], "# from block1", "unexpected output: {masm}");
+    let else_idx = lines.iter().position(|line| *line == "else").expect("missing else");
+    assert_eq!(lines[else_idx + 1], "# from block2", "unexpected output: {masm}");
+}
+
+/// Test that code generation produces the same output whether the analyses it depends on are
+/// computed ahead of time, or on demand
+#[test]
+fn codegen_precomputed_analyses() {
+    use miden_hir::pass::RewritePass;
+    use miden_hir_analysis::{
+        DominatorTree, GlobalVariableAnalysis, LivenessAnalysis, LoopAnalysis,
+    };
+
+    let context = TestContext::default();
+    let session = &context.session;
+    let name = hir::Ident::from("sum_matrix");
+
+    // Build the test module, and apply the rewrites required for code generation
+    let build_module = |analyses: &mut AnalysisManager| {
+        let mut mb = hir::ModuleBuilder::new("test");
+        testing::sum_matrix(&mut mb, &context);
+        let mut module = mb.build();
+        default_rewrites()
+            .apply(&mut module, analyses, session)
+            .expect("failed to apply rewrites");
+        analyses
+            .get_or_compute::<GlobalVariableAnalysis<hir::Module>>(&module, session)
+            .expect("global variable analysis failed");
+        module
+    };
+    let codegen = |module: &hir::Module, analyses: &mut AnalysisManager| {
+        let function = module.function(name).unwrap();
+        let mut convert_to_masm = ConvertHirToMasm::<&hir::Function>::default();
+        let function = convert_to_masm
+            .convert(function, analyses, session)
+            .expect("code generation failed");
+        function.display(&module.imports()).to_string()
+    };
+
+    // Compute analyses on demand
+    let mut analyses = AnalysisManager::new();
+    let module = build_module(&mut analyses);
+    let expected = codegen(&module, &mut analyses);
+
+    // Compute analyses ahead of time
+    let mut analyses = AnalysisManager::new();
+    let module = build_module(&mut analyses);
+    let function = module.function(name).unwrap();
+    let domtree = analyses.get_or_compute::<DominatorTree>(function, session).unwrap();
+    let loops = analyses.get_or_compute::<LoopAnalysis>(function, session).unwrap();
+    let liveness = analyses.get_or_compute::<LivenessAnalysis>(function, session).unwrap();
+    let actual = codegen(&module, &mut analyses);
+
+    assert_eq!(actual, expected);
+    // The precomputed analyses were used, rather than recomputed
+    let id = function.id;
+    assert!(Rc::ptr_eq(&domtree, &analyses.get::<DominatorTree>(&id).unwrap()));
+    assert!(Rc::ptr_eq(&loops, &analyses.get::<LoopAnalysis>(&id).unwrap()));
+    assert!(Rc::ptr_eq(&liveness, &analyses.get::<LivenessAnalysis>(&id).unwrap()));
+}
+
+/// Test that a compiler given an analysis cache reuses the analyses already in it, and hands the
+/// cache back along with the analyses computed during compilation
+#[test]
+fn codegen_compiler_with_analyses() {
+    use miden_hir::ProgramAnalysisKey;
+    use miden_hir_analysis::{GlobalVariableAnalysis, LivenessAnalysis};
+
+    let context = TestContext::default();
+    let session = &context.session;
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&session.diagnostics);
+
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function("main", Signature::new([], [AbiParam::new(Type::I32)]))
+            .expect("unexpected symbol conflict");
+        let a = fb.ins().i32(3, SourceSpan::UNKNOWN);
+        let b = fb.ins().i32(5, SourceSpan::UNKNOWN);
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    // Compute the global variable layout of the program ahead of time
+    let mut analyses = AnalysisManager::new();
+    let globals = analyses
+        .get_or_compute::<GlobalVariableAnalysis<hir::Program>>(&program, session)
+        .expect("global variable analysis failed");
+    assert!(!analyses.is_available::<LivenessAnalysis>(&id));
+
+    let mut compiler = MasmCompiler::with_analyses(session, analyses);
+    compiler.compile(program).expect("compilation failed");
+    let analyses = compiler.into_analyses();
+
+    // The precomputed analysis was used, rather than recomputed
+    let cached = analyses
+        .get::<GlobalVariableAnalysis<hir::Program>>(&ProgramAnalysisKey)
+        .expect("missing global variable analysis");
+    assert!(Rc::ptr_eq(&globals, &cached));
+    // The analyses computed during compilation are returned with the cache
+    assert!(analyses.is_available::<LivenessAnalysis>(&id));
+}
+
+/// Test that the dependencies of a compiled program include the kernel functions it calls, but
+/// not the functions it defines
+#[test]
+fn program_dependencies() {
+    let context = TestContext::default();
+    let session = &context.session;
+
+    let account = hir::Ident::from("miden:tx_kernel/account");
+    let get_id = FunctionIdent {
+        module: account,
+        function: hir::Ident::from("get_id"),
+    };
+    let add_asset = FunctionIdent {
+        module: account,
+        function: hir::Ident::from("add_asset"),
+    };
+    let asset = [Type::Felt, Type::Felt, Type::Felt, Type::Felt];
+
+    // Build a module with a function which adds an asset to the account, and returns its id
+    let mut mb = hir::ModuleBuilder::new("test");
+    {
+        let sig = Signature::new(asset.clone().map(AbiParam::new), [AbiParam::new(Type::Felt)]);
+        let mut fb = mb.function("receive_asset", sig).expect("unexpected symbol conflict");
+        let sig = Signature::new([], [AbiParam::new(Type::Felt)]);
+        fb.import_function(get_id.module, get_id.function, sig)
+            .expect("unexpected symbol conflict");
+        let sig = Signature::new(asset.clone().map(AbiParam::new), asset.map(AbiParam::new));
+        fb.import_function(add_asset.module, add_asset.function, sig)
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let args = fb.block_params(entry).to_vec();
+        fb.ins().call(add_asset, &args, SourceSpan::UNKNOWN);
+        let call = fb.ins().call(get_id, &[], SourceSpan::UNKNOWN);
+        let account_id = fb.first_result(call);
+        fb.ins().ret(Some(account_id), SourceSpan::UNKNOWN);
+        fb.build(&session.diagnostics).expect("unexpected error building function");
+    }
+    let mut module = mb.build();
+
+    let mut analyses = AnalysisManager::new();
+    default_rewrites()
+        .apply(&mut module, &mut analyses, session)
+        .expect("failed to apply rewrites");
+    let mut convert_to_masm = ConvertHirToMasm::<hir::Module>::default();
+    let module = convert_to_masm
+        .convert(module, &mut analyses, session)
+        .expect("code generation failed");
+    let mut program = Program::new();
+    program.insert(module);
+
+    let dependencies = program.dependencies().into_iter().collect::<Vec<_>>();
+    assert_eq!(dependencies, [add_asset, get_id]);
+}
+
+/// Test that a function using the account calling convention is invoked with `call`, so that it
+/// executes in its own memory context, while other functions are invoked with `exec`
+#[test]
+fn codegen_call_account_function() {
+    let context = TestContext::default();
+    let session = &context.session;
+
+    let wallet = hir::Ident::from("wallet");
+    let get_balance = FunctionIdent {
+        module: wallet,
+        function: hir::Ident::from("get_balance"),
+    };
+    let get_fee = FunctionIdent {
+        module: wallet,
+        function: hir::Ident::from("get_fee"),
+    };
+
+    // Build a module with a function which returns the balance of an account, less a fee
+    let mut mb = hir::ModuleBuilder::new("test");
+    {
+        let sig = Signature::new([], [AbiParam::new(Type::Felt)]);
+        let mut fb = mb.function("net_balance", sig).expect("unexpected symbol conflict");
+        let sig = Signature {
+            cc: CallConv::Account,
+            ..Signature::new([], [AbiParam::new(Type::Felt)])
+        };
+        fb.import_function(get_balance.module, get_balance.function, sig)
+            .expect("unexpected symbol conflict");
+        let sig = Signature::new([], [AbiParam::new(Type::Felt)]);
+        fb.import_function(get_fee.module, get_fee.function, sig)
+            .expect("unexpected symbol conflict");
+        let call = fb.ins().call(get_balance, &[], SourceSpan::UNKNOWN);
+        let balance = fb.first_result(call);
+        let call = fb.ins().call(get_fee, &[], SourceSpan::UNKNOWN);
+        let fee = fb.first_result(call);
+        let net = fb.ins().sub_unchecked(balance, fee, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(net), SourceSpan::UNKNOWN);
+        fb.build(&session.diagnostics).expect("unexpected error building function");
+    }
+    let mut module = mb.build();
+
+    let mut analyses = AnalysisManager::new();
+    default_rewrites()
+        .apply(&mut module, &mut analyses, session)
+        .expect("failed to apply rewrites");
+    let mut convert_to_masm = ConvertHirToMasm::<hir::Module>::default();
+    let module = convert_to_masm
+        .convert(module, &mut analyses, session)
+        .expect("code generation failed");
+
+    let ops = module
+        .functions()
+        .flat_map(|function| function.body.blocks.values())
+        .flat_map(|block| block.ops.iter())
+        .collect::<Vec<_>>();
+    assert!(ops.contains(&&masm::Op::Call(get_balance)));
+    assert!(!ops.contains(&&masm::Op::Exec(get_balance)));
+    assert!(ops.contains(&&masm::Op::Exec(get_fee)));
+    assert!(!ops.contains(&&masm::Op::Call(get_fee)));
+}
+
+/// Test that the data segments of a program are written to the words of memory they occupy
+#[test]
+fn program_data_segment_prologue() {
+    let mut segments = hir::DataSegmentTable::default();
+    // Occupies the first two elements of the word at address 1
+    segments
+        .declare(16, 8, vec![1u8, 2, 3, 4, 5, 6, 7, 8].into(), true)
+        .expect("invalid data segment");
+    // Occupies the second element of the word at address 2
+    segments
+        .declare(36, 4, vec![0xaau8, 0xbb, 0xcc, 0xdd].into(), false)
+        .expect("invalid data segment");
+
+    let prologue = Program::data_segment_prologue(&segments);
+    assert_eq!(
+        prologue.as_slice(),
+        &[
+            masm::Op::PushU32(0),
+            masm::Op::PushU32(0),
+            masm::Op::PushU32(0x08070605),
+            masm::Op::PushU32(0x04030201),
+            masm::Op::MemStorewImm(1),
+            masm::Op::Dropw,
+            masm::Op::PushU32(0),
+            masm::Op::PushU32(0),
+            masm::Op::PushU32(0xddccbbaa),
+            masm::Op::PushU32(0),
+            masm::Op::MemStorewImm(2),
+            masm::Op::Dropw,
+        ]
+    );
+}
+
+/// Test that a data segment is initialized a word at a time, with a scalar store for a trailing
+/// element, and that the initialized memory matches the segment data
+#[test]
+fn program_data_segment_prologue_batched() {
+    // 17 elements, i.e. four words and a single element of a fifth
+    let init = (1..=68u8).collect::<Vec<_>>();
+    let mut segments = hir::DataSegmentTable::default();
+    segments
+        .declare(32, init.len() as u32, init.clone().into(), true)
+        .expect("invalid data segment");
+
+    let prologue = Program::data_segment_prologue(&segments);
+    let word_stores = prologue.iter().filter(|op| matches!(op, masm::Op::MemStorewImm(_))).count();
+    let scalar_stores = prologue.iter().filter(|op| matches!(op, masm::Op::MemStoreImm(_))).count();
+    assert_eq!(word_stores, 4, "{prologue:?}");
+    assert_eq!(scalar_stores, 1, "{prologue:?}");
+
+    // Execute the prologue against a model of memory, and read back the segment
+    let mut stack = vec![];
+    let mut memory = std::collections::BTreeMap::<u32, [u32; 4]>::default();
+    for op in prologue.iter() {
+        match op {
+            masm::Op::PushU32(value) => stack.push(*value),
+            masm::Op::MemStorewImm(waddr) => {
+                let word = memory.entry(*waddr).or_default();
+                for (i, element) in stack.iter().rev().take(4).enumerate() {
+                    word[i] = *element;
+                }
+            }
+            masm::Op::MemStoreImm(waddr) => {
+                memory.entry(*waddr).or_default()[0] = stack.pop().unwrap();
+            }
+            masm::Op::Dropw => stack.truncate(stack.len() - 4),
+            op => panic!("unexpected instruction in prologue: {op:?}"),
+        }
+    }
+    assert!(stack.is_empty());
+    let bytes = memory
+        .values()
+        .flat_map(|word| word.iter().flat_map(|element| element.to_le_bytes()))
+        .collect::<Vec<_>>();
+    assert_eq!(memory.keys().next(), Some(&2));
+    assert_eq!(&bytes[..init.len()], init.as_slice());
+    assert!(bytes[init.len()..].iter().all(|byte| *byte == 0));
+}
+
+/// Test that the global variables are allocated above the configured memory base, and that data
+/// segments, which cannot be moved, are rejected only if they overlap the memory below it
+#[test]
+fn program_memory_base() {
+    use miden_hir::{pass::Analysis, DataSegmentError, LinkerError};
+    use miden_hir_analysis::GlobalVariableAnalysis;
+
+    const BASE: u32 = 0x10000;
+
+    let context = TestContext::default();
+    let link = |base: u32, segment: Option<u32>| {
+        let mut builder = ProgramBuilder::new(&context.session.diagnostics).with_memory_base(base);
+        let mut mb = builder.module("test");
+        if let Some(offset) = segment {
+            mb.declare_data_segment(offset, 4, vec![1u8, 2, 3, 4], true)
+                .expect("invalid data segment");
+        }
+        mb.declare_global_variable(
+            "counter",
+            Type::U32,
+            Linkage::External,
+            None,
+            SourceSpan::UNKNOWN,
+        )
+        .expect("unexpected global variable conflict");
+        mb.build().expect("unexpected error constructing test module");
+        builder.link()
+    };
+    let global_table_offset = |program: &hir::Program| {
+        let mut analyses = AnalysisManager::new();
+        GlobalVariableAnalysis::<hir::Program>::analyze(program, &mut analyses, &context.session)
+            .expect("global variable analysis failed")
+            .layout()
+            .global_table_offset()
+    };
+
+    let program = link(0, None).expect("failed to link program");
+    let rebased = link(BASE, None).expect("failed to link program");
+    assert_eq!(rebased.segments().base(), BASE);
+    assert_eq!(
+        global_table_offset(&rebased),
+        global_table_offset(&program) + BASE,
+        "expected global variables to be shifted by the memory base"
+    );
+
+    // A data segment at or above the memory base is kept at its address, and the global variables
+    // are allocated after it
+    let program = link(BASE, Some(BASE)).expect("failed to link program");
+    let prologue = Program::data_segment_prologue(program.segments());
+    assert!(
+        prologue
+            .iter()
+            .any(|op| matches!(op, masm::Op::MemStoreImm(0x1000) | masm::Op::MemStorewImm(0x1000))),
+        "{prologue:?}"
+    );
+    assert!(global_table_offset(&program) >= BASE + 4);
+
+    // A data segment which overlaps the memory below the base is rejected
+    let err = link(BASE, Some(0x100)).expect_err("expected data segment to be rejected");
+    assert!(
+        matches!(
+            err,
+            LinkerError::SegmentError(DataSegmentError::BelowBase {
+                offset: 0x100,
+                size: 4,
+                base: BASE
+            })
+        ),
+        "unexpected error: {err}"
+    );
+
+    // The memory base must be word-aligned
+    let err = link(BASE + 4, None).expect_err("expected unaligned memory base to be rejected");
+    assert!(
+        matches!(err, LinkerError::SegmentError(DataSegmentError::UnalignedBase(0x10004))),
+        "unexpected error: {err}"
+    );
+}
+
+/// Test that validation reports every construct code cannot be generated for, not just the first
+#[test]
+fn validate_unsupported_constructs() {
+    let id: FunctionIdent = "test::rec".parse().unwrap();
+    let signature = Signature::new(
+        [AbiParam::new(Type::I64), AbiParam::new(Type::I64)],
+        [AbiParam::new(Type::I64)],
+    );
+    let mut function = hir::Function::new(id, signature.clone());
+    let call = {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        fb.import_function("test", "rec", signature, SourceSpan::UNKNOWN)
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        // Checked arithmetic is not implemented for i64, nor are recursive calls
+        let sum = fb.ins().add_checked(a, b, SourceSpan::UNKNOWN);
+        let call = fb.ins().call(id, &[sum, b], SourceSpan::UNKNOWN);
+        let result = fb.first_result(call);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        call
+    };
+
+    let errors = ConvertHirToMasm::<&hir::Function>::default()
+        .validate(&function)
+        .expect_err("expected validation to fail");
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(
+        matches!(
+            errors[0],
+            UnsupportedError::Overflow {
+                opcode: hir::Opcode::Add,
+                overflow: hir::Overflow::Checked,
+                ty: Type::I64,
+                ..
+            }
+        ),
+        "{errors:?}"
+    );
+    assert_eq!(
+        errors[1],
+        UnsupportedError::RecursiveCall {
+            inst: call,
+            callee: id
+        }
+    );
+}
+
+/// Test that validation reports operators used with operand types code cannot be generated for
+#[test]
+fn validate_unsupported_operand_types() {
+    let id: FunctionIdent = "test::rotate".parse().unwrap();
+    let signature = Signature::new(
+        [AbiParam::new(Type::I32), AbiParam::new(Type::I64)],
+        [AbiParam::new(Type::I64)],
+    );
+    let mut function = hir::Function::new(id, signature);
+    let (rotl, shr) = {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        // Neither rotations of i32, nor shifts of i64, are implemented
+        let rotated = fb.ins().rotl(a, a, SourceSpan::UNKNOWN);
+        let rotl = fb.func.dfg.value_data(rotated).unwrap_inst();
+        let shifted = fb.ins().shr_wrapping(b, b, SourceSpan::UNKNOWN);
+        let shr = fb.func.dfg.value_data(shifted).unwrap_inst();
+        fb.ins().ret(Some(shifted), SourceSpan::UNKNOWN);
+        (rotl, shr)
+    };
+
+    let errors = ConvertHirToMasm::<&hir::Function>::default()
+        .validate(&function)
+        .expect_err("expected validation to fail");
+    assert_eq!(
+        errors,
+        vec![
+            UnsupportedError::OperandType {
+                inst: rotl,
+                opcode: hir::Opcode::Rotl,
+                ty: Type::I32,
+            },
+            UnsupportedError::OperandType {
+                inst: shr,
+                opcode: hir::Opcode::Shr,
+                ty: Type::I64,
+            },
+        ]
+    );
+}
+
+/// Test that validation accepts a function containing both an indirect call and a switch, which
+/// code can be generated for
+#[test]
+fn validate_indirect_call_and_switch() {
+    let id: FunctionIdent = "test::dispatch".parse().unwrap();
+    let callee: FunctionIdent = "test::callee".parse().unwrap();
+    let signature = Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]);
+    let mut function = hir::Function::new(id, signature);
+    {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let a = fb.block_params(entry)[0];
+        let call_blk = fb.create_block();
+        let default_blk = fb.create_block();
+        fb.ins().switch(a, vec![(0, call_blk)], default_blk, SourceSpan::UNKNOWN);
+
+        // Call `callee` via the hash of its MAST root, rather than by name
+        fb.switch_to_block(call_blk);
+        let mut asm_builder = fb.ins().inline_asm(&[], [], SourceSpan::UNKNOWN);
+        asm_builder.ins().procref(callee);
+        asm_builder.ins().dynexec();
+        asm_builder.build();
+        fb.ins().ret(Some(a), SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(default_blk);
+        let result = fb.ins().u32(7, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+    }
+
+    ConvertHirToMasm::<&hir::Function>::default()
+        .validate(&function)
+        .expect("expected indirect calls and switches to be supported");
+}
+
+/// Test that a dry run rejects a function whose generated code is nested too deeply, without
+/// reporting any diagnostics
+#[test]
+fn dry_run_max_nesting_depth() {
+    use miden_hir::pass::RewritePass;
+    use miden_hir_analysis::GlobalVariableAnalysis;
+
+    let options = midenc_session::Options {
+        max_nesting_depth: Some(1),
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, None);
+    let session = &context.session;
+
+    let mut analyses = AnalysisManager::new();
+    let mut mb = hir::ModuleBuilder::new("test");
+    let id = testing::sum_matrix(&mut mb, &context);
+    let mut module = mb.build();
+    default_rewrites()
+        .apply(&mut module, &mut analyses, session)
+        .expect("failed to apply rewrites");
+    analyses
+        .get_or_compute::<GlobalVariableAnalysis<hir::Module>>(&module, session)
+        .expect("global variable analysis failed");
+
+    let function = module.function(id.function).unwrap();
+    let err = ConvertHirToMasm::<&hir::Function>::default()
+        .dry_run(function, &mut analyses, session)
+        .expect_err("expected deeply nested loops to be rejected");
+    assert!(
+        matches!(
+            err,
+            CodegenError::NestingDepth { function, depth, max_depth: 1 }
+                if function == id && depth > 1
+        ),
+        "unexpected error: {err:?}"
+    );
+    assert!(!session.diagnostics.has_errors());
+}
+
+/// Test that the reason code cannot be generated for a function can be recovered from the error
+/// returned by the conversion pass
+#[test]
+fn codegen_error_unsupported() {
+    let context = TestContext::default();
+    let id: FunctionIdent = "test::rec".parse().unwrap();
+    let signature = Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]);
+    let mut function = hir::Function::new(id, signature.clone());
+    {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        fb.import_function("test", "rec", signature, SourceSpan::UNKNOWN)
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let n = fb.block_params(entry)[0];
+        let call = fb.ins().call(id, &[n], SourceSpan::UNKNOWN);
+        let result = fb.first_result(call);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+    }
+
+    let mut analyses = AnalysisManager::default();
+    let err = ConvertHirToMasm::<&hir::Function>::default()
+        .convert(&function, &mut analyses, &context.session)
+        .expect_err("expected conversion to fail");
+    let ConversionError::Failed(err) = err else {
+        panic!("expected conversion to fail with a code generation error");
+    };
+    let err = err.downcast_ref::<CodegenError>().expect("expected a code generation error");
+    assert!(
+        matches!(
+            err,
+            CodegenError::Unsupported { function, errors }
+                if *function == id
+                    && matches!(errors.as_slice(), [UnsupportedError::RecursiveCall { .. }])
+        ),
+        "unexpected error: {err:?}"
+    );
+}
+
+/// Test that converting a function without a global variable analysis for its module or program is
+/// reported as an error, rather than a panic
+#[test]
+fn codegen_error_missing_analysis() {
+    let context = TestContext::default();
+    let id: FunctionIdent = "test::id".parse().unwrap();
+    let signature = Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]);
+    let mut function = hir::Function::new(id, signature);
+    {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let n = fb.block_params(entry)[0];
+        fb.ins().ret(Some(n), SourceSpan::UNKNOWN);
+    }
+
+    let mut analyses = AnalysisManager::default();
+    let err = ConvertHirToMasm::<&hir::Function>::default()
+        .convert(&function, &mut analyses, &context.session)
+        .expect_err("expected conversion to fail");
+    let ConversionError::Failed(err) = err else {
+        panic!("expected conversion to fail with a code generation error");
+    };
+    let err = err.downcast_ref::<CodegenError>().expect("expected a code generation error");
+    assert!(
+        matches!(err, CodegenError::MissingGlobalVariableAnalysis { function } if *function == id),
+        "unexpected error: {err:?}"
+    );
+}
+
+/// Test that compiling a function which is not defined by the module is reported as an error,
+/// rather than a panic
+#[test]
+fn codegen_error_undefined_function() {
+    let context = TestContext::default();
+    let module = Box::new(hir::Module::new("test"));
+    let name = hir::Ident::from("missing");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let err = compiler
+        .compile_function(module, name)
+        .expect_err("expected compilation to fail");
+    assert!(
+        matches!(err, CompilerError::UndefinedFunction { function, .. } if function == name),
+        "unexpected error: {err:?}"
+    );
+    assert_eq!(err.to_string(), "undefined function 'test::missing'");
+}
+
+/// Test the code generator on an instruction with multiple results, where one of the results is
+/// used more than once by the same instruction, and so must be copied before it is consumed.
+#[test]
+fn codegen_multiple_results() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that squares the sum of two numbers, and adds 1 if the
+    // sum overflowed
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "square_sum",
+                Signature::new(
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let inst = fb.ins().add_overflowing(a, b, SourceSpan::UNKNOWN);
+        let (overflowed, sum) = {
+            let results = fb.inst_results(inst);
+            (results[0], results[1])
+        };
+        let squared = fb.ins().mul_wrapping(sum, sum, SourceSpan::UNKNOWN);
+        let carry = fb.ins().zext(overflowed, Type::U32, SourceSpan::UNKNOWN);
+        let result = fb.ins().add_wrapping(squared, carry, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    // No overflow
+    let a = Felt::new(3);
+    let b = Felt::new(4);
+    let mut stack = harness.invoke(id, &[a, b]).expect("execution failed");
+    harness.emulator.stop();
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(49));
+
+    // Overflow
+    let a = Felt::new(u32::MAX as u64);
+    let b = Felt::new(2);
+    let mut stack = harness.invoke(id, &[a, b]).expect("execution failed");
+    harness.emulator.stop();
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(2));
+}
+
+/// Test the code generator on a function which returns multiple values, ensuring that all of the
+/// results are left on the operand stack in order, with the first result on top.
+#[test]
+fn codegen_multiple_return_values() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that returns both the successor and the double of a number
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "incr_and_double",
+                Signature::new(
+                    [AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let n = fb.block_params(entry)[0];
+        let incremented = fb.ins().incr_wrapping(n, SourceSpan::UNKNOWN);
+        let doubled = fb.ins().mul_imm_wrapping(n, Immediate::U32(2), SourceSpan::UNKNOWN);
+        fb.ins().ret_many(&[incremented, doubled], SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    let n = Felt::new(5);
+    let mut stack = harness.invoke(id, &[n]).expect("execution failed");
+    assert_eq!(stack.len(), 2);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(6));
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(10));
+}
+
+/// Test the code generator on a function which returns a `(value, status)` pair, where the value
+/// is beneath the status on the operand stack when returning, so that the results must be
+/// reordered to leave the first result on top.
+#[test]
+fn codegen_return_value_and_status() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that returns a number, along with whether it is zero
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "with_is_zero",
+                Signature::new(
+                    [AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::I1)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let n = fb.block_params(entry)[0];
+        let is_zero = fb.ins().eq_imm(n, Immediate::U32(0), SourceSpan::UNKNOWN);
+        fb.ins().ret_many(&[n, is_zero], SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    for (n, is_zero) in [(5, 0), (0, 1)] {
+        let mut stack = harness.invoke(id, &[Felt::new(n)]).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(n));
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(is_zero));
+    }
+}
+
+/// Test that a function returning a value wider than one field element, e.g. a word, leaves
+/// exactly the elements of that value on the operand stack, in order, both when returning it from
+/// the entrypoint, and when returning it from a callee
+#[test]
+fn codegen_return_word() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with a function which returns a word found beneath another value on the
+    // operand stack, and an entrypoint which calls it, leaving yet another value beneath the word
+    let mut mb = builder.module("test");
+    let word = Type::Array(Box::new(Type::Felt), 4);
+    let pick_signature = Signature::new(
+        [AbiParam::new(Type::Felt), AbiParam::new(word.clone())],
+        [AbiParam::new(word.clone())],
+    );
+    let pick = {
+        let mut fb =
+            mb.function("pick", pick_signature.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let w = fb.block_params(entry)[1];
+        fb.ins().ret(Some(w), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let id = {
+        let mut fb = mb
+            .function(
+                "main",
+                Signature::new(
+                    [
+                        AbiParam::new(Type::Felt),
+                        AbiParam::new(word.clone()),
+                        AbiParam::new(Type::Felt),
+                    ],
+                    [AbiParam::new(word)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        fb.import_function(pick.module, pick.function, pick_signature)
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (w, b) = {
+            let args = fb.block_params(entry);
+            (args[1], args[2])
+        };
+        let call = fb.ins().call(pick, &[b, w], SourceSpan::UNKNOWN);
+        let result = fb.first_result(call);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let args = [1, 2, 3, 4, 5, 6].map(Felt::new);
+    harness.assert_program_output(program, &args, &[2, 3, 4, 5]);
+}
+
+/// Test that values are spilled to procedure locals when more values are live than can be kept
+/// within reach on the operand stack, and reloaded when they are used.
+#[test]
+fn codegen_spill_to_locals() {
+    let options = midenc_session::Options {
+        spill_threshold: Some(12),
+        ..Default::default()
+    };
+    let mut harness = TestByEmulationHarness {
+        context: TestContext::default_with_opts_and_emitter(options, None),
+        emulator: Emulator::default(),
+    };
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that derives 20 values from its argument, and then sums
+    // them twice, in opposite orders. As each value is used by both sums, regardless of how
+    // the sums are scheduled, at some point all 20 values must be live at the same time.
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "sum_twice",
+                Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let a = fb.block_params(entry)[0];
+        let values = (1..=20u32)
+            .map(|i| fb.ins().add_imm_wrapping(a, Immediate::U32(i), SourceSpan::UNKNOWN))
+            .collect::<Vec<_>>();
+        let mut forward = values[0];
+        for value in values.iter().skip(1).copied() {
+            forward = fb.ins().add_wrapping(forward, value, SourceSpan::UNKNOWN);
+        }
+        let mut backward = values[19];
+        for value in values.iter().rev().skip(1).copied() {
+            backward = fb.ins().add_wrapping(backward, value, SourceSpan::UNKNOWN);
+        }
+        let result = fb.ins().add_wrapping(forward, backward, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let function = program
+        .modules()
+        .flat_map(|module| module.functions())
+        .find(|function| function.name == id)
+        .expect("missing test function");
+    assert!(!function.locals().is_empty(), "expected values to be spilled to locals");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    // Each sum is 20a + 210
+    let a = Felt::new(1);
+    let mut stack = harness.invoke(id, &[a]).expect("execution failed");
+    harness.emulator.stop();
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(460));
+}
+
+/// Test that a function whose loops are nested more deeply than the configured maximum is
+/// rejected with a diagnostic naming the function
+#[test]
+fn codegen_max_nesting_depth() {
+    use miden_diagnostics::CaptureEmitter;
+
+    let emitter = Arc::new(CaptureEmitter::default());
+    let options = midenc_session::Options {
+        max_nesting_depth: Some(1),
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, Some(emitter.clone()));
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with a function containing a loop nested in another loop
+    let mut mb = builder.module("test");
+    testing::sum_matrix(mb.as_mut(), &context);
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder
+        .with_entrypoint("test::sum_matrix".parse().unwrap())
+        .link()
+        .expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let result = compiler.compile(program);
+    assert!(result.is_err(), "expected deeply nested loops to be rejected");
+
+    assert!(context.session.diagnostics.has_errors());
+    let captured = emitter.captured();
+    assert!(
+        captured.contains("control flow is nested too deeply"),
+        "unexpected diagnostics: {captured}"
+    );
+    assert!(
+        captured.contains("'test::sum_matrix' nests loops and conditionals"),
+        "unexpected diagnostics: {captured}"
+    );
+    assert!(captured.contains("but the maximum is 1"), "unexpected diagnostics: {captured}");
+}
+
+/// Test that a warning is emitted when a function uses more procedure locals than allowed
+#[test]
+fn codegen_locals_budget_warning() {
+    use miden_diagnostics::CaptureEmitter;
+
+    let emitter = Arc::new(CaptureEmitter::default());
+    let options = midenc_session::Options {
+        spill_threshold: Some(12),
+        locals_budget: Some(1),
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, Some(emitter.clone()));
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with function that derives 20 values from its argument, all of which are
+    // live at the same time, so that many of them must be spilled
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "sum_twice",
+                Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let a = fb.block_params(entry)[0];
+        let values = (1..=20u32)
+            .map(|i| fb.ins().add_imm_wrapping(a, Immediate::U32(i), SourceSpan::UNKNOWN))
+            .collect::<Vec<_>>();
+        let mut forward = values[0];
+        for value in values.iter().skip(1).copied() {
+            forward = fb.ins().add_wrapping(forward, value, SourceSpan::UNKNOWN);
+        }
+        let mut backward = values[19];
+        for value in values.iter().rev().skip(1).copied() {
+            backward = fb.ins().add_wrapping(backward, value, SourceSpan::UNKNOWN);
+        }
+        let result = fb.ins().add_wrapping(forward, backward, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    compiler.compile(program).expect("compilation failed");
+
+    assert!(!context.session.diagnostics.has_errors());
+    let captured = emitter.captured();
+    assert!(
+        captured.contains("function exceeds the budget for procedure locals"),
+        "unexpected diagnostics: {captured}"
+    );
+    assert!(
+        captured.contains("'test::sum_twice' uses"),
+        "unexpected diagnostics: {captured}"
+    );
+}
+
+/// Test that chained 64-bit arithmetic leaves both limbs of each intermediate result as valid u32
+/// values, propagating carries from the low limb into the high limb, and discarding the carry out
+/// of the high limb.
+#[test]
+fn codegen_i64_add_chain() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that computes `(a + b) + c` using wrapping i64 addition
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "add3",
+                Signature::new(
+                    [AbiParam::new(Type::I64), AbiParam::new(Type::I64), AbiParam::new(Type::I64)],
+                    [AbiParam::new(Type::I64)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b, c) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1], args[2])
+        };
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        let result = fb.ins().add_wrapping(sum, c, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    let cases: [(i64, i64, i64); 5] = [
+        (1, 2, 3),
+        // The low limb carries into the high limb in both additions
+        (u32::MAX as i64, 1, u32::MAX as i64),
+        // The first addition overflows the signed range
+        (i64::MAX, 1, -1),
+        // The high limb wraps in the second addition
+        (-1, 0, 2),
+        (i64::MIN, i64::MIN, i64::MIN),
+    ];
+    for (a, b, c) in cases {
+        let mut args = a.canonicalize();
+        args.extend(b.canonicalize());
+        args.extend(c.canonicalize());
+        let mut stack = harness.invoke(id, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 2);
+        // Both limbs must be valid u32 values, which is asserted when they are read back
+        let result = <i64 as ToCanonicalRepr>::from_stack(&mut stack);
+        assert_eq!(result, a.wrapping_add(b).wrapping_add(c), "{a} + {b} + {c}");
+    }
+}
+
+/// Test that u64 addition and comparisons are lowered to operations on the two u32 limbs of each
+/// value, propagating the carry (or borrow) from the low limb into the high limb
+#[test]
+fn codegen_u64_add_and_compare() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with functions that compute `a + b`, `a < b` and `a > b` for u64 values
+    let mut mb = builder.module("test");
+    let params = [AbiParam::new(Type::U64), AbiParam::new(Type::U64)];
+    let add = {
+        let mut fb = mb
+            .function("add", Signature::new(params.clone(), [AbiParam::new(Type::U64)]))
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let lt = {
+        let mut fb = mb
+            .function("lt", Signature::new(params.clone(), [AbiParam::new(Type::I1)]))
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let is_lt = fb.ins().lt(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(is_lt), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let gt = {
+        let mut fb = mb
+            .function("gt", Signature::new(params, [AbiParam::new(Type::I1)]))
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let is_gt = fb.ins().gt(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(is_gt), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(add).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    let cases: [(u64, u64); 6] = [
+        (1, 2),
+        // The low limb carries into the high limb
+        (u32::MAX as u64, 1),
+        (u32::MAX as u64, u32::MAX as u64),
+        // The high limbs are equal, so the low limbs decide the comparison
+        ((1 << 32) | 5, (1 << 32) | 3),
+        // The high limb wraps
+        (u64::MAX, 2),
+        (3, 3),
+    ];
+    for (a, b) in cases {
+        let mut args = a.canonicalize();
+        args.extend(b.canonicalize());
+
+        let mut stack = harness.invoke(add, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 2);
+        let sum = <u64 as ToCanonicalRepr>::from_stack(&mut stack);
+        assert_eq!(sum, a.wrapping_add(b), "{a} + {b}");
+
+        let mut stack = harness.invoke(lt, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some((a < b) as u64), "{a} < {b}");
+
+        let mut stack = harness.invoke(gt, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some((a > b) as u64), "{a} > {b}");
+    }
+}
+
+/// Test that eq/neq of u64 values compare both limbs, and leave the values beneath their operands
+/// on the operand stack untouched
+#[test]
+fn codegen_u64_eq_neq() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with functions that compute `a == b` and `a != b` for u64 values, and
+    // return them along with a third argument, which is beneath the u64 operands on the stack
+    let mut mb = builder.module("test");
+    let signature = Signature::new(
+        [AbiParam::new(Type::U64), AbiParam::new(Type::U64), AbiParam::new(Type::U32)],
+        [AbiParam::new(Type::I1), AbiParam::new(Type::U32)],
+    );
+    let eq = {
+        let mut fb = mb.function("eq", signature.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b, c) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1], args[2])
+        };
+        let is_eq = fb.ins().eq(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret_many(&[is_eq, c], SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let neq = {
+        let mut fb = mb.function("neq", signature).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b, c) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1], args[2])
+        };
+        let is_neq = fb.ins().neq(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret_many(&[is_neq, c], SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(eq).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    let cases: [(u64, u64); 4] = [
+        (3, 3),
+        // The high limbs are equal, the low limbs differ
+        ((1 << 32) | 5, (1 << 32) | 3),
+        // The low limbs are equal, the high limbs differ
+        ((2 << 32) | 5, (1 << 32) | 5),
+        (u64::MAX, u64::MAX),
+    ];
+    for (a, b) in cases {
+        let mut args = a.canonicalize();
+        args.extend(b.canonicalize());
+        args.push(Felt::new(7));
+
+        let mut stack = harness.invoke(eq, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some((a == b) as u64), "{a} == {b}");
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(7));
+
+        let mut stack = harness.invoke(neq, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some((a != b) as u64), "{a} != {b}");
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(7));
+    }
+}
+
+/// Test that wrapping subtraction and multiplication of u64 values borrow and carry between limbs,
+/// and wrap around on overflow
+#[test]
+fn codegen_u64_wrapping_sub_and_mul() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with functions that compute `a - b` and `a * b` for u64 values
+    let mut mb = builder.module("test");
+    let signature = Signature::new(
+        [AbiParam::new(Type::U64), AbiParam::new(Type::U64)],
+        [AbiParam::new(Type::U64)],
+    );
+    let sub = {
+        let mut fb = mb.function("sub", signature.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let diff = fb.ins().sub_wrapping(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(diff), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let mul = {
+        let mut fb = mb.function("mul", signature).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let product = fb.ins().mul_wrapping(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(product), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(sub).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    harness
+        .emulator
+        .load_program(program.freeze())
+        .expect("failed to load test program");
+
+    let cases: [(u64, u64); 7] = [
+        (5, 3),
+        // The result wraps below zero
+        (0, 1),
+        // The low limb borrows from the high limb
+        (1 << 32, 1),
+        // The low limb carries into the high limb
+        (u32::MAX as u64, u32::MAX as u64),
+        ((1 << 32) | 3, (1 << 32) | 7),
+        // The product wraps
+        (u64::MAX, u64::MAX),
+        (u64::MAX, 2),
+    ];
+    for (a, b) in cases {
+        let mut args = a.canonicalize();
+        args.extend(b.canonicalize());
+
+        let mut stack = harness.invoke(sub, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 2);
+        let diff = <u64 as ToCanonicalRepr>::from_stack(&mut stack);
+        assert_eq!(diff, a.wrapping_sub(b), "{a} - {b}");
+
+        let mut stack = harness.invoke(mul, &args).expect("execution failed");
+        harness.emulator.stop();
+        assert_eq!(stack.len(), 2);
+        let product = <u64 as ToCanonicalRepr>::from_stack(&mut stack);
+        assert_eq!(product, a.wrapping_mul(b), "{a} * {b}");
+    }
+}
+
+/// Test the code generator end-to-end on the HIR of the `func_call` lit test, i.e. a call to a
+/// function in the same module, which adds its two arguments
+#[test]
+fn codegen_func_call() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    let mut mb = builder.module("func_call");
+    let add_signature = Signature::new(
+        [AbiParam::new(Type::I32), AbiParam::new(Type::I32)],
+        [AbiParam::new(Type::I32)],
+    );
+    let add = {
+        let mut fb = mb.function("add", add_signature.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let exit_blk = fb.create_block();
+        let result = fb.append_block_param(exit_blk, Type::I32, SourceSpan::UNKNOWN);
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        fb.ins().br(exit_blk, &[sum], SourceSpan::UNKNOWN);
+        fb.switch_to_block(exit_blk);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let id = {
+        let mut fb = mb
+            .function("main", Signature::new([], [AbiParam::new(Type::I32)]))
+            .expect("unexpected symbol conflict");
+        fb.import_function(add.module, add.function, add_signature)
+            .expect("unexpected symbol conflict");
+        let exit_blk = fb.create_block();
+        let result = fb.append_block_param(exit_blk, Type::I32, SourceSpan::UNKNOWN);
+        let a = fb.ins().i32(3, SourceSpan::UNKNOWN);
+        let b = fb.ins().i32(5, SourceSpan::UNKNOWN);
+        let call = fb.ins().call(add, &[a, b], SourceSpan::UNKNOWN);
+        let sum = fb.first_result(call);
+        fb.ins().br(exit_blk, &[sum], SourceSpan::UNKNOWN);
+        fb.switch_to_block(exit_blk);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    harness.assert_program_output(program, &[], &[8]);
+}
+
+/// Test that when arguments are passed in memory, the caller stores each argument in the argument
+/// region rather than leaving it on the operand stack, and the callee loads them back on entry
+#[test]
+fn codegen_memory_call_args() {
+    let options = midenc_session::Options {
+        memory_call_args: true,
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, None);
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with a function which calls another function to add two numbers
+    let mut mb = builder.module("test");
+    let add_signature = Signature::new(
+        [AbiParam::new(Type::I32), AbiParam::new(Type::I32)],
+        [AbiParam::new(Type::I32)],
+    );
+    let add = {
+        let mut fb = mb.function("add", add_signature.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let id = {
+        let mut fb = mb
+            .function("main", Signature::new([], [AbiParam::new(Type::I32)]))
+            .expect("unexpected symbol conflict");
+        fb.import_function(add.module, add.function, add_signature)
+            .expect("unexpected symbol conflict");
+        let a = fb.ins().i32(3, SourceSpan::UNKNOWN);
+        let b = fb.ins().i32(5, SourceSpan::UNKNOWN);
+        let call = fb.ins().call(add, &[a, b], SourceSpan::UNKNOWN);
+        let sum = fb.first_result(call);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let module = program.get("test").unwrap();
+    let body = |name: FunctionIdent| {
+        let function = module.functions().find(|f| f.name == name).expect("missing function");
+        function
+            .body
+            .blocks
+            .values()
+            .flat_map(|block| block.ops.iter().cloned())
+            .collect::<Vec<_>>()
+    };
+
+    // The first argument is on top of the operand stack, and is stored first
+    let main = body(id);
+    let expected = [
+        masm::Op::MemStoreImm(masm::CALL_ARGS_ADDR),
+        masm::Op::MemStoreImm(masm::CALL_ARGS_ADDR + 1),
+        masm::Op::Exec(add),
+    ];
+    assert!(
+        main.windows(3).any(|ops| ops == expected),
+        "expected arguments to be stored before exec in {main:?}"
+    );
+
+    // The callee loads the arguments in reverse, leaving the first argument on top
+    let add = body(add);
+    assert_eq!(
+        &add[..2],
+        &[
+            masm::Op::MemLoadImm(masm::CALL_ARGS_ADDR + 1),
+            masm::Op::MemLoadImm(masm::CALL_ARGS_ADDR)
+        ]
+    );
+}
+
+/// Test that when arguments are passed in memory, the arguments of calls to the standard library
+/// are still passed on the operand stack, as it is not compiled under that convention
+#[test]
+fn codegen_memory_call_args_runtime_callee() {
+    let options = midenc_session::Options {
+        memory_call_args: true,
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, None);
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with a function which calls a standard library procedure
+    let mut mb = builder.module("test");
+    let callee = "std::math::u64::wrapping_add".parse::<FunctionIdent>().unwrap();
+    let callee_signature = Signature::new(
+        [AbiParam::new(Type::U64), AbiParam::new(Type::U64)],
+        [AbiParam::new(Type::U64)],
+    );
+    let id = {
+        let mut fb = mb
+            .function(
+                "main",
+                Signature::new(
+                    [AbiParam::new(Type::U64), AbiParam::new(Type::U64)],
+                    [AbiParam::new(Type::U64)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        fb.import_function(callee.module, callee.function, callee_signature)
+            .expect("unexpected symbol conflict");
+        let call = fb.ins().call(callee, &[a, b], SourceSpan::UNKNOWN);
+        let sum = fb.first_result(call);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let module = program.get("test").unwrap();
+    let main = module.functions().find(|f| f.name == id).expect("missing function");
+    let ops = main
+        .body
+        .blocks
+        .values()
+        .flat_map(|block| block.ops.iter().cloned())
+        .collect::<Vec<_>>();
+    assert!(ops.contains(&masm::Op::Exec(callee)), "expected exec of {callee} in {ops:?}");
+    assert!(
+        !ops.iter().any(|op| matches!(op, masm::Op::MemStoreImm(_))),
+        "expected arguments to be passed on the operand stack in {ops:?}"
+    );
+}
+
+/// Test that when arguments are passed in memory, the arguments of calls to the transaction
+/// kernel are still passed on the operand stack, as it is imported rather than compiled with the
+/// program, even though it uses the `SystemV` calling convention
+#[test]
+fn codegen_memory_call_args_tx_kernel_callee() {
+    let options = midenc_session::Options {
+        memory_call_args: true,
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, None);
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with a function which adds an asset to the account via the tx kernel
+    let mut mb = builder.module("test");
+    let callee = FunctionIdent {
+        module: miden_hir::Ident::from("miden:tx_kernel/account"),
+        function: miden_hir::Ident::from("add_asset"),
+    };
+    let callee_signature = Signature::new(
+        [
+            AbiParam::new(Type::Felt),
+            AbiParam::new(Type::Felt),
+            AbiParam::new(Type::Felt),
+            AbiParam::new(Type::Felt),
+        ],
+        [
+            AbiParam::new(Type::Felt),
+            AbiParam::new(Type::Felt),
+            AbiParam::new(Type::Felt),
+            AbiParam::new(Type::Felt),
+        ],
+    );
+    assert_eq!(callee_signature.cc, CallConv::SystemV);
+    let id = {
+        let mut fb = mb
+            .function("main", callee_signature.clone())
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let args = fb.block_params(entry).to_vec();
+        fb.import_function(callee.module, callee.function, callee_signature)
+            .expect("unexpected symbol conflict");
+        let call = fb.ins().call(callee, &args, SourceSpan::UNKNOWN);
+        let results = fb.inst_results(call).to_vec();
+        fb.ins().ret_many(&results, SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let module = program.get("test").unwrap();
+    let main = module.functions().find(|f| f.name == id).expect("missing function");
+    let ops = main
+        .body
+        .blocks
+        .values()
+        .flat_map(|block| block.ops.iter().cloned())
+        .collect::<Vec<_>>();
+    assert!(ops.contains(&masm::Op::Exec(callee)), "expected exec of {callee} in {ops:?}");
+    assert!(
+        !ops.iter().any(|op| matches!(op, masm::Op::MemStoreImm(_))),
+        "expected arguments to be passed on the operand stack in {ops:?}"
+    );
+}
+
+/// Test that the arguments of a procedure are only passed in memory if they fit in the argument
+/// region
+#[test]
+fn codegen_memory_call_args_region_size() {
+    let fits = Signature::new(vec![AbiParam::new(Type::I32); masm::CALL_ARGS_WORDS as usize], []);
+    assert!(masm::fits_call_args_region(&fits));
+
+    let overflows =
+        Signature::new(vec![AbiParam::new(Type::I64); masm::CALL_ARGS_WORDS as usize / 2 + 1], []);
+    assert!(!masm::fits_call_args_region(&overflows));
+}
+
+/// Test that when arguments are passed in memory, a function using the account calling convention
+/// still receives its arguments on the operand stack, as it is invoked with `call`
+#[test]
+fn codegen_memory_call_args_account_callee() {
+    let options = midenc_session::Options {
+        memory_call_args: true,
+        ..Default::default()
+    };
+    let context = TestContext::default_with_opts_and_emitter(options, None);
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+
+    // Build test module with a function which calls an account function to add two numbers
+    let mut mb = builder.module("test");
+    let add_signature = Signature {
+        cc: CallConv::Account,
+        ..Signature::new(
+            [AbiParam::new(Type::I32), AbiParam::new(Type::I32)],
+            [AbiParam::new(Type::I32)],
+        )
+    };
+    let add = {
+        let mut fb = mb.function("add", add_signature.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    let id = {
+        let mut fb = mb
+            .function("main", Signature::new([], [AbiParam::new(Type::I32)]))
+            .expect("unexpected symbol conflict");
+        fb.import_function(add.module, add.function, add_signature)
+            .expect("unexpected symbol conflict");
+        let a = fb.ins().i32(3, SourceSpan::UNKNOWN);
+        let b = fb.ins().i32(5, SourceSpan::UNKNOWN);
+        let call = fb.ins().call(add, &[a, b], SourceSpan::UNKNOWN);
+        let sum = fb.first_result(call);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    let module = program.get("test").unwrap();
+    let body = |name: FunctionIdent| {
+        let function = module.functions().find(|f| f.name == name).expect("missing function");
+        function
+            .body
+            .blocks
+            .values()
+            .flat_map(|block| block.ops.iter().cloned())
+            .collect::<Vec<_>>()
+    };
+
+    let main = body(id);
+    assert!(main.contains(&masm::Op::Call(add)), "expected call of {add} in {main:?}");
+    assert!(
+        !main.iter().any(|op| matches!(op, masm::Op::MemStoreImm(_))),
+        "expected arguments to be passed on the operand stack in {main:?}"
+    );
+    let add = body(add);
+    assert!(
+        !add.iter().any(|op| matches!(op, masm::Op::MemLoadImm(_))),
+        "expected arguments to be received on the operand stack in {add:?}"
+    );
+}
+
+/// Test the code generator end-to-end on the HIR of the `loop_br_if` lit test, i.e. a loop which
+/// sums the numbers from 2 down to 1, and is exited via a conditional branch at its end
+#[test]
+fn codegen_loop_br_if() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    let mut mb = builder.module("loop_br_if");
+    let id = {
+        let mut fb = mb
+            .function("main", Signature::new([], [AbiParam::new(Type::I32)]))
+            .expect("unexpected symbol conflict");
+        let exit_blk = fb.create_block();
+        let result = fb.append_block_param(exit_blk, Type::I32, SourceSpan::UNKNOWN);
+        let loop_blk = fb.create_block();
+        let n = fb.append_block_param(loop_blk, Type::I32, SourceSpan::UNKNOWN);
+        let acc = fb.append_block_param(loop_blk, Type::I32, SourceSpan::UNKNOWN);
+        let loop_exit_blk = fb.create_block();
+        let break_blk = fb.create_block();
+
+        let zero = fb.ins().i32(0, SourceSpan::UNKNOWN);
+        let two = fb.ins().i32(2, SourceSpan::UNKNOWN);
+        fb.ins().br(loop_blk, &[two, zero], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(exit_blk);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(loop_blk);
+        let acc2 = fb.ins().add_wrapping(n, acc, SourceSpan::UNKNOWN);
+        let one = fb.ins().i32(1, SourceSpan::UNKNOWN);
+        let n2 = fb.ins().sub_wrapping(n, one, SourceSpan::UNKNOWN);
+        let is_nonzero = fb.ins().neq_imm(n2, Immediate::I32(0), SourceSpan::UNKNOWN);
+        fb.ins()
+            .cond_br(is_nonzero, loop_blk, &[n2, acc2], break_blk, &[], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(loop_exit_blk);
+        fb.ins().br(exit_blk, &[acc2], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(break_blk);
+        fb.ins().br(loop_exit_blk, &[], SourceSpan::UNKNOWN);
+
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    harness.assert_program_output(program, &[], &[3]);
+}
+
+/// Test that the cost estimate of a function without loops counts each instruction once
+#[test]
+fn codegen_cost_estimate_straight_line() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function("main", Signature::new([], [AbiParam::new(Type::I32)]))
+            .expect("unexpected symbol conflict");
+        let a = fb.ins().i32(3, SourceSpan::UNKNOWN);
+        let b = fb.ins().i32(5, SourceSpan::UNKNOWN);
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    // There is no control flow, so each instruction executes exactly once, however many loop
+    // iterations are assumed
+    let module = program.get("test").unwrap();
+    let main = module.functions().find(|f| f.name == id).expect("missing function");
+    let estimate = main.cost_estimate(10);
+    assert!(estimate.ops > 0, "expected instructions in {}", main.name);
+    assert_eq!(estimate.cycles, estimate.ops, "unexpected estimate: {estimate}");
+    assert_eq!(estimate.dropws, 0, "unexpected estimate: {estimate}");
+    assert_eq!(main.cost_estimate(0), estimate);
+
+    let mut stack = harness.execute_program(program.freeze(), &[]).expect("execution failed");
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(8));
+}
+
+/// Test that the cost estimate of a function with a loop counts the instructions of the loop body
+/// once, but the cycles of the loop body once per iteration
+#[test]
+fn codegen_cost_estimate_loop() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build a function which counts down from 3 to 0 in a loop
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function("main", Signature::new([], [AbiParam::new(Type::I32)]))
+            .expect("unexpected symbol conflict");
+        let loop_blk = fb.create_block();
+        let n = fb.append_block_param(loop_blk, Type::I32, SourceSpan::UNKNOWN);
+        let exit_blk = fb.create_block();
+
+        let three = fb.ins().i32(3, SourceSpan::UNKNOWN);
+        fb.ins().br(loop_blk, &[three], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(loop_blk);
+        let one = fb.ins().i32(1, SourceSpan::UNKNOWN);
+        let n2 = fb.ins().sub_wrapping(n, one, SourceSpan::UNKNOWN);
+        let is_nonzero = fb.ins().neq_imm(n2, Immediate::I32(0), SourceSpan::UNKNOWN);
+        fb.ins()
+            .cond_br(is_nonzero, loop_blk, &[n2], exit_blk, &[], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(exit_blk);
+        fb.ins().ret(Some(n2), SourceSpan::UNKNOWN);
+
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed");
+
+    // The number of instructions is fixed, but the cycles grow with each iteration of the loop
+    let module = program.get("test").unwrap();
+    let main = module.functions().find(|f| f.name == id).expect("missing function");
+    let never = main.cost_estimate(0);
+    let once = main.cost_estimate(1);
+    let many = main.cost_estimate(10);
+    assert_eq!(never.ops, many.ops);
+    assert!(once.cycles > never.cycles, "expected loop body to cost cycles: {once}");
+    assert_eq!(many.cycles - never.cycles, 10 * (once.cycles - never.cycles));
+
+    let mut stack = harness.execute_program(program.freeze(), &[]).expect("execution failed");
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(0));
+}
+
+/// Test the code generator end-to-end on the HIR of the `if_then_else` lit test, i.e. a
+/// conditional which selects one of two constants
+#[test]
+fn codegen_if_then_else() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    let mut mb = builder.module("if_then_else");
+    let id = {
+        let mut fb = mb
+            .function("main", Signature::new([], [AbiParam::new(Type::I32)]))
+            .expect("unexpected symbol conflict");
+        let exit_blk = fb.create_block();
+        let result = fb.append_block_param(exit_blk, Type::I32, SourceSpan::UNKNOWN);
+        let then_blk = fb.create_block();
+        let join_blk = fb.create_block();
+        let selected = fb.append_block_param(join_blk, Type::I32, SourceSpan::UNKNOWN);
+        let else_blk = fb.create_block();
+
+        let two = fb.ins().i32(2, SourceSpan::UNKNOWN);
+        let is_nonzero = fb.ins().neq_imm(two, Immediate::I32(0), SourceSpan::UNKNOWN);
+        fb.ins().cond_br(is_nonzero, then_blk, &[], else_blk, &[], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(exit_blk);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(then_blk);
+        let three = fb.ins().i32(3, SourceSpan::UNKNOWN);
+        fb.ins().br(join_blk, &[three], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(join_blk);
+        fb.ins().br(exit_blk, &[selected], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(else_blk);
+        let five = fb.ins().i32(5, SourceSpan::UNKNOWN);
+        fb.ins().br(join_blk, &[five], SourceSpan::UNKNOWN);
+
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    harness.assert_program_output(program, &[], &[3]);
+}
+
+/// Test that a load from an address observes a preceding store to that address, i.e. the store
+/// is scheduled before the load, even though the load does not depend on any result of the store
+#[test]
+fn codegen_store_before_load() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that stores a value, and loads it back again
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "store_load",
+                Signature::new(
+                    [AbiParam::new(Type::Ptr(Box::new(Type::U32))), AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (ptr, value) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        fb.ins().store(ptr, value, SourceSpan::UNKNOWN);
+        let loaded = fb.ins().load(ptr, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(loaded), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let addr = harness.malloc(core::mem::size_of::<u32>());
+    harness.store(addr as usize, Felt::ZERO);
+    let ptr = Felt::new(addr as u64);
+    let value = Felt::new(42);
+
+    harness.assert_program_output(program, &[ptr, value], &[42]);
+}
+
+/// Test that a store to an address does not clobber the value read by a preceding load from that
+/// address, i.e. the load is scheduled before the store, even though the store does not depend on
+/// the result of the load
+#[test]
+fn codegen_load_before_store() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that loads a value, and then overwrites it
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "load_store",
+                Signature::new(
+                    [AbiParam::new(Type::Ptr(Box::new(Type::U32))), AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (ptr, value) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let loaded = fb.ins().load(ptr, SourceSpan::UNKNOWN);
+        fb.ins().store(ptr, value, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(loaded), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let addr = harness.malloc(core::mem::size_of::<u32>());
+    harness.store(addr as usize, Felt::new(7));
+    let ptr = Felt::new(addr as u64);
+    let value = Felt::new(42);
+
+    harness.assert_program_output(program, &[ptr, value], &[7]);
+}
+
+/// Test that a 32-bit value stored to an address which is not element-aligned replaces the high
+/// bytes of the element containing the address, and the low bytes of the next one
+#[test]
+fn codegen_unaligned_store() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with function that stores a value 2 bytes past an address, and loads the
+    // two elements at that address back again
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "unaligned_store",
+                Signature::new(
+                    [AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let addr = fb.block_params(entry)[0];
+        let ty = Type::Ptr(Box::new(Type::U32));
+        let dst = fb.ins().add_imm_wrapping(addr, Immediate::U32(2), SourceSpan::UNKNOWN);
+        let dst = fb.ins().inttoptr(dst, ty.clone(), SourceSpan::UNKNOWN);
+        let value = fb.ins().u32(0xaabbccdd, SourceSpan::UNKNOWN);
+        fb.ins().store(dst, value, SourceSpan::UNKNOWN);
+        let lo = fb.ins().inttoptr(addr, ty.clone(), SourceSpan::UNKNOWN);
+        let lo = fb.ins().load(lo, SourceSpan::UNKNOWN);
+        let hi = fb.ins().add_imm_wrapping(addr, Immediate::U32(4), SourceSpan::UNKNOWN);
+        let hi = fb.ins().inttoptr(hi, ty, SourceSpan::UNKNOWN);
+        let hi = fb.ins().load(hi, SourceSpan::UNKNOWN);
+        fb.ins().ret_many(&[lo, hi], SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    // Link the program
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let addr = harness.malloc(core::mem::size_of::<u32>() * 2);
+    harness.store(addr as usize, Felt::new(0x11223344));
+    harness.store(addr as usize + 4, Felt::new(0x55667788));
+
+    harness.assert_program_output(program, &[Felt::new(addr as u64)], &[0xccdd3344, 0x5566aabb]);
+}
+
+/// Compute the instruction schedule of `function`
+fn schedule_function(function: &hir::Function, context: &TestContext) -> Schedule {
+    let mut analyses = AnalysisManager::new();
+    compute_schedule(function, &mut analyses, &context.session).expect("analysis failed")
+}
+
+/// Test that the schedule of a block places an instruction after the instructions defining its
+/// operands, whether they are defined in the same block or a predecessor
+#[test]
+fn schedule_operands_before_use() {
+    use miden_hir_analysis::dependency_graph::Node;
+
+    let context = TestContext::default();
+
+    // Build a function that adds a value computed in the entry block to one computed in its
+    // successor
+    let id = "test::inter_block".parse().unwrap();
+    let mut function = hir::Function::new(
+        id,
+        Signature::new(
+            [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+            [AbiParam::new(Type::U32)],
+        ),
+    );
+    let (lhs, rhs, sum, blk) = {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let blk = fb.create_block();
+        let lhs = fb.ins().mul_imm_wrapping(a, Immediate::U32(2), SourceSpan::UNKNOWN);
+        fb.ins().br(blk, &[], SourceSpan::UNKNOWN);
+        fb.switch_to_block(blk);
+        let rhs = fb.ins().mul_imm_wrapping(b, Immediate::U32(3), SourceSpan::UNKNOWN);
+        let sum = fb.ins().add_wrapping(lhs, rhs, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        (lhs, rhs, sum, blk)
+    };
+    let def = |value| function.dfg.value_data(value).unwrap_inst();
+
+    let schedule = schedule_function(&function, &context);
+    let position = |block, inst| {
+        schedule
+            .get(block)
+            .iter()
+            .position(|op| matches!(op, ScheduleOp::Inst(info) if info.inst == inst))
+    };
+
+    // The left operand is scheduled in the entry block, and the right operand before the add
+    assert!(position(function.dfg.entry_block(), def(lhs)).is_some());
+    let rhs_pos = position(blk, def(rhs)).expect("expected rhs to be scheduled in its block");
+    let sum_pos = position(blk, def(sum)).expect("expected add to be scheduled in its block");
+    assert!(rhs_pos < sum_pos);
+
+    // The dependency graph of the block treats the left operand as available on the operand
+    // stack on entry, and the right operand as a result of an instruction in the block
+    let depgraph = &schedule.block_info(blk).depgraph;
+    assert!(depgraph.contains(&Node::Stack(lhs)));
+    assert!(depgraph.contains(&Node::Result {
+        value: rhs,
+        index: 0,
+    }));
+}
+
+/// Test the rendering of the dependency graph and tree graph of a block in DOT format, where one
+/// of the operands of the block is a local which is live across blocks, i.e. the IR of the
+/// `locals_inter_block` lit test:
+///
+/// ```text,ignore
+/// block0:
+///     v1 = const.i32 3; br block2
+/// block2:
+///     v2 = const.i32 5; v3 = add.wrapping v1 v2; br block3
+/// block3:
+///     v4 = const.i32 7; v5 = add.wrapping v4 v3; br block1(v5)
+/// block1(v0):
+///     ret v0
+/// ```
+#[test]
+fn schedule_locals_inter_block_to_dot() {
+    let context = TestContext::default();
+
+    let id = "locals_inter_block::main".parse().unwrap();
+    let mut function = hir::Function::new(id, Signature::new([], [AbiParam::new(Type::I32)]));
+    let (local, blk3) = {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let exit = fb.create_block();
+        let result = fb.append_block_param(exit, Type::I32, SourceSpan::UNKNOWN);
+        let blk2 = fb.create_block();
+        let blk3 = fb.create_block();
+        let three = fb.ins().i32(3, SourceSpan::UNKNOWN);
+        fb.ins().br(blk2, &[], SourceSpan::UNKNOWN);
+        fb.switch_to_block(blk2);
+        let five = fb.ins().i32(5, SourceSpan::UNKNOWN);
+        let local = fb.ins().add_wrapping(three, five, SourceSpan::UNKNOWN);
+        fb.ins().br(blk3, &[], SourceSpan::UNKNOWN);
+        fb.switch_to_block(blk3);
+        let seven = fb.ins().i32(7, SourceSpan::UNKNOWN);
+        let sum = fb.ins().add_wrapping(seven, local, SourceSpan::UNKNOWN);
+        fb.ins().br(exit, &[sum], SourceSpan::UNKNOWN);
+        fb.switch_to_block(exit);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        (local, blk3)
+    };
+
+    let schedule = schedule_function(&function, &context);
+    let block_info = schedule.block_info(blk3);
+    let br = function.dfg.last_inst(blk3).unwrap();
+
+    // The local computed in the preceding block is available on the operand stack on entry, and
+    // the edge from the argument of the add which uses it is labeled with it
+    let dot = block_info.depgraph.to_dot();
+    assert!(dot.starts_with("digraph DependencyGraph {\n"), "unexpected dot: {dot}");
+    assert!(dot.contains(&format!("    \"{br}\" [shape=box];\n")), "unexpected dot: {dot}");
+    assert!(dot.contains(&format!("    \"{local}\";\n")), "unexpected dot: {dot}");
+    assert!(
+        dot.contains(&format!(" -> \"{local}\" [label=\"{local}\"];\n")),
+        "unexpected dot: {dot}"
+    );
+
+    // The branch is the root of a tree
+    let dot = block_info.treegraph.to_dot();
+    assert!(dot.starts_with("digraph TreeGraph {\n"), "unexpected dot: {dot}");
+    assert!(dot.contains(&format!("    \"{br}\" [shape=box];\n")), "unexpected dot: {dot}");
+}
+
+/// Test that a load from an address is scheduled after a preceding store to that address, even
+/// though the load does not depend on any result of the store
+#[test]
+fn schedule_store_before_load() {
+    let context = TestContext::default();
+
+    // Build a function that stores a value, and loads it back again
+    let id = "test::store_load".parse().unwrap();
+    let mut function = hir::Function::new(
+        id,
+        Signature::new(
+            [AbiParam::new(Type::Ptr(Box::new(Type::U32))), AbiParam::new(Type::U32)],
+            [AbiParam::new(Type::U32)],
+        ),
+    );
+    {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let (ptr, value) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        fb.ins().store(ptr, value, SourceSpan::UNKNOWN);
+        let loaded = fb.ins().load(ptr, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(loaded), SourceSpan::UNKNOWN);
+    }
+
+    let schedule = schedule_function(&function, &context);
+    let scheduled = schedule
+        .get(function.dfg.entry_block())
+        .iter()
+        .filter_map(|op| match op {
+            ScheduleOp::Inst(info) => Some(function.dfg.inst(info.inst).opcode()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(scheduled, [hir::Opcode::Store, hir::Opcode::Load, hir::Opcode::Ret]);
+}
+
+/// Test that a load is only ordered after a preceding store if they may access the same memory,
+/// i.e. accesses to distinct global variables are scheduled independently of one another
+#[test]
+fn schedule_disjoint_memory_accesses() {
+    use miden_hir_analysis::dependency_graph::Node;
+
+    let context = TestContext::default();
+
+    // Build a function that stores a value to global `a`, and then loads from globals `b` and `a`
+    let id = "test::globals".parse().unwrap();
+    let mut function = hir::Function::new(
+        id,
+        Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+    );
+    {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let value = fb.block_params(entry)[0];
+        let ptr_ty = Type::Ptr(Box::new(Type::U32));
+        let a = fb.ins().symbol_addr("a", ptr_ty.clone(), SourceSpan::UNKNOWN);
+        fb.ins().store(a, value, SourceSpan::UNKNOWN);
+        let b = fb.ins().symbol_addr("b", ptr_ty.clone(), SourceSpan::UNKNOWN);
+        let b_value = fb.ins().load(b, SourceSpan::UNKNOWN);
+        let a = fb.ins().symbol_addr("a", ptr_ty, SourceSpan::UNKNOWN);
+        let a_value = fb.ins().load(a, SourceSpan::UNKNOWN);
+        let sum = fb.ins().add_wrapping(a_value, b_value, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+    }
+
+    let entry = function.dfg.entry_block();
+    let nodes = function
+        .dfg
+        .block_insts(entry)
+        .enumerate()
+        .map(|(pos, id)| {
+            Node::Inst {
+                id,
+                pos: pos as u16,
+            }
+            .id()
+        })
+        .collect::<Vec<_>>();
+    let (store, load_b, load_a) = (nodes[1], nodes[3], nodes[5]);
+
+    let schedule = schedule_function(&function, &context);
+    let depgraph = &schedule.block_info(entry).depgraph;
+    assert!(!depgraph.successor_ids(load_b).any(|node| node == store));
+    assert!(depgraph.successor_ids(load_a).any(|node| node == store));
+}
+
+/// Tallies the statistics reported by a [FunctionEmitter] to its [SchedulerObserver]
+#[derive(Default)]
+struct ScheduleStats {
+    blocks: usize,
+    treegraph_nodes: usize,
+    ops: usize,
+    moves: usize,
+    copies: usize,
+    max_stack_depth: usize,
+}
+impl SchedulerObserver for ScheduleStats {
+    fn enter_block(&mut self, _block: hir::Block, treegraph_nodes: usize) {
+        self.blocks += 1;
+        self.treegraph_nodes += treegraph_nodes;
+    }
+
+    fn exit_block(&mut self, _block: hir::Block, ops: usize) {
+        self.ops += ops;
+    }
+
+    fn operand(&mut self, _value: hir::Value, constraint: Constraint) {
+        match constraint {
+            Constraint::Move => self.moves += 1,
+            Constraint::Copy => self.copies += 1,
+        }
+    }
+
+    fn stack_depth(&mut self, depth: usize) {
+        self.max_stack_depth = self.max_stack_depth.max(depth);
+    }
+}
+
+/// Test that an observer installed on the emitter is told how each operand is moved into place,
+/// i.e. a value used more than once is copied for all but its last use
+#[test]
+fn schedule_observer_tallies() {
+    use miden_hir_analysis as analysis;
+
+    let context = TestContext::default();
+
+    // Build a function that computes `(a + b) * a`
+    let id = "test::add_mul".parse().unwrap();
+    let mut function = hir::Function::new(
+        id,
+        Signature::new(
+            [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+            [AbiParam::new(Type::U32)],
+        ),
+    );
+    {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        let product = fb.ins().mul_wrapping(sum, a, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(product), SourceSpan::UNKNOWN);
+    }
+
+    let mut analyses = AnalysisManager::new();
+    let domtree = analyses
+        .get_or_compute::<analysis::DominatorTree>(&function, &context.session)
+        .expect("dominator tree analysis failed");
+    let loops = analyses
+        .get_or_compute::<analysis::LoopAnalysis>(&function, &context.session)
+        .expect("loop analysis failed");
+    let liveness = analyses
+        .get_or_compute::<analysis::LivenessAnalysis>(&function, &context.session)
+        .expect("liveness analysis failed");
+    let globals = analysis::GlobalVariableLayout::default();
+
+    let mut f_prime = Function::new(function.id, function.signature.clone());
+    let schedule = Scheduler::new(&function, &mut f_prime, &domtree, &loops, &liveness).build();
+
+    let mut stack = crate::codegen::OperandStack::default();
+    for arg in function.dfg.block_args(function.dfg.entry_block()).iter().rev().copied() {
+        let ty = function.dfg.value_type(arg).clone();
+        stack.push(TypedValue { value: arg, ty });
+    }
+
+    let mut stats = ScheduleStats::default();
+    FunctionEmitter::new(&function, &mut f_prime, &domtree, &loops, &liveness, &globals)
+        .with_observer(&mut stats)
+        .emit(schedule, stack)
+        .expect("code generation failed");
+
+    // `a` is copied for the addition, and moved for the multiplication, all other operands,
+    // i.e. `b`, the sum and the product, are only used once, and are moved
+    assert_eq!(stats.copies, 1);
+    assert_eq!(stats.moves, 4);
+    assert_eq!(stats.blocks, 1);
+    assert!(stats.treegraph_nodes > 0);
+    assert_eq!(stats.ops, f_prime.block(f_prime.body.id()).ops.len());
+    // After the addition, both the sum and `a` are on the operand stack
+    assert_eq!(stats.max_stack_depth, 2);
+}
+
+/// Test that an observer installed on the compiler is told about the code emitted for each
+/// function compiled
+#[test]
+fn schedule_observer_compiler() {
+    let context = TestContext::default();
+
+    // Build a module with a function that computes `(a + b) * a`
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "add_mul",
+                Signature::new(
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let sum = fb.ins().add_wrapping(a, b, SourceSpan::UNKNOWN);
+        let product = fb.ins().mul_wrapping(sum, a, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(product), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    mb.build().expect("unexpected error constructing test module");
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut stats = ScheduleStats::default();
+    MasmCompiler::new(&context.session)
+        .with_observer(&mut stats)
+        .compile(program)
+        .expect("compilation failed");
+
+    assert_eq!(stats.blocks, 1);
+    assert_eq!(stats.copies, 1);
+    assert_eq!(stats.moves, 4);
+}
+
+/// Test that in strict mode, a value which the schedule fails to drop after its last use is
+/// reported when the function returns, rather than silently dropped
+#[test]
+#[should_panic(expected = "v2 was left on the operand stack on return from 'test::leak'")]
+fn codegen_strict_returns() {
+    use miden_hir_analysis as analysis;
+
+    let context = TestContext::default();
+
+    // Build a function that adds its arguments, ignoring whether the addition overflowed
+    let id = "test::leak".parse().unwrap();
+    let mut function = hir::Function::new(
+        id,
+        Signature::new(
+            [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+            [AbiParam::new(Type::U32)],
+        ),
+    );
+    let overflowed = {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let (a, b) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let inst = fb.ins().add_overflowing(a, b, SourceSpan::UNKNOWN);
+        let (overflowed, sum) = {
+            let results = fb.inst_results(inst);
+            (results[0], results[1])
+        };
+        fb.ins().ret(Some(sum), SourceSpan::UNKNOWN);
+        overflowed
+    };
+
+    let mut analyses = AnalysisManager::new();
+    let domtree = analyses
+        .get_or_compute::<analysis::DominatorTree>(&function, &context.session)
+        .expect("dominator tree analysis failed");
+    let loops = analyses
+        .get_or_compute::<analysis::LoopAnalysis>(&function, &context.session)
+        .expect("loop analysis failed");
+    let liveness = analyses
+        .get_or_compute::<analysis::LivenessAnalysis>(&function, &context.session)
+        .expect("liveness analysis failed");
+    let globals = analysis::GlobalVariableLayout::default();
+
+    let mut f_prime = Function::new(function.id, function.signature.clone());
+    let mut schedule = Scheduler::new(&function, &mut f_prime, &domtree, &loops, &liveness).build();
+
+    // Introduce a bug in the schedule, by removing the drop of the unused overflow flag, `v2`
+    let entry = function.dfg.entry_block();
+    let block_schedule = &mut schedule.block_schedules[entry];
+    let num_ops = block_schedule.len();
+    block_schedule.retain(|op| !matches!(op, ScheduleOp::Drop(value) if *value == overflowed));
+    assert_eq!(block_schedule.len(), num_ops - 1, "expected the overflow flag to be dropped");
+
+    let mut stack = crate::codegen::OperandStack::default();
+    for arg in function.dfg.block_args(entry).iter().rev().copied() {
+        let ty = function.dfg.value_type(arg).clone();
+        stack.push(TypedValue { value: arg, ty });
+    }
+
+    let _ = FunctionEmitter::new(&function, &mut f_prime, &domtree, &loops, &liveness, &globals)
+        .with_strict_returns(true)
+        .emit(schedule, stack);
+}
+
+/// Test that emitting code for a control flow graph which was not treeified is reported as an
+/// error, rather than a panic
+#[test]
+fn codegen_unexpected_cycle() {
+    use miden_hir_analysis as analysis;
+
+    let context = TestContext::default();
+
+    // Build a function in which both branches of a conditional join in the same block
+    let id = "test::diamond".parse().unwrap();
+    let mut function = hir::Function::new(
+        id,
+        Signature::new(
+            [AbiParam::new(Type::I1), AbiParam::new(Type::U32)],
+            [AbiParam::new(Type::U32)],
+        ),
+    );
+    let join_blk = {
+        let mut fb = hir::FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let (cond, a) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let then_blk = fb.create_block();
+        let else_blk = fb.create_block();
+        let join_blk = fb.create_block();
+        let result = fb.append_block_param(join_blk, Type::U32, SourceSpan::UNKNOWN);
+        fb.ins().cond_br(cond, then_blk, &[], else_blk, &[], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(then_blk);
+        let one = fb.ins().add_imm_wrapping(a, Immediate::U32(1), SourceSpan::UNKNOWN);
+        fb.ins().br(join_blk, &[one], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(else_blk);
+        let two = fb.ins().add_imm_wrapping(a, Immediate::U32(2), SourceSpan::UNKNOWN);
+        fb.ins().br(join_blk, &[two], SourceSpan::UNKNOWN);
+
+        fb.switch_to_block(join_blk);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        join_blk
+    };
+
+    let mut analyses = AnalysisManager::new();
+    let domtree = analyses
+        .get_or_compute::<analysis::DominatorTree>(&function, &context.session)
+        .expect("dominator tree analysis failed");
+    let loops = analyses
+        .get_or_compute::<analysis::LoopAnalysis>(&function, &context.session)
+        .expect("loop analysis failed");
+    let liveness = analyses
+        .get_or_compute::<analysis::LivenessAnalysis>(&function, &context.session)
+        .expect("liveness analysis failed");
+    let globals = analysis::GlobalVariableLayout::default();
+
+    let mut f_prime = Function::new(function.id, function.signature.clone());
+    let schedule = Scheduler::new(&function, &mut f_prime, &domtree, &loops, &liveness).build();
+
+    let mut stack = crate::codegen::OperandStack::default();
+    for arg in function.dfg.block_args(function.dfg.entry_block()).iter().rev().copied() {
+        let ty = function.dfg.value_type(arg).clone();
+        stack.push(TypedValue { value: arg, ty });
+    }
+
+    // Without treeification, the join block is emitted once for each branch of the conditional
+    let err = FunctionEmitter::new(&function, &mut f_prime, &domtree, &loops, &liveness, &globals)
+        .emit(schedule, stack)
+        .expect_err("expected emitting a non-treeified control flow graph to fail");
+    assert!(
+        matches!(err, EmitError::UnexpectedCycle { block } if block == join_blk),
+        "unexpected error: {err}"
+    );
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "unexpected cycle at {join_blk}: only loop headers may be reached more than once, is \
+             the control flow graph treeified?"
+        )
+    );
+}
+
+#[test]
+#[should_panic(expected = "assertion failed: expected false, got true")]
+fn i32_checked_neg() {
+    let mut harness = TestByEmulationHarness::default();
+
+    harness
+        .emulator
+        .load_module(
+            Box::new(
+                intrinsics::load("intrinsics::i32", &harness.context.session.codemap)
+                    .expect("undefined intrinsic module"),
+            )
+            .freeze(),
+        )
+        .expect("failed to load intrinsics::i32");
+
+    let min = Felt::new(i32::MIN as u32 as u64);
+
+    let neg = "intrinsics::i32::checked_neg".parse().unwrap();
+    // i32::MIN
+    harness.invoke(neg, &[min]).expect("execution failed");
+}
+
+#[test]
+fn mem_memory_grow() {
+    let mut harness = TestByEmulationHarness::default();
+
+    harness
+        .emulator
+        .load_module(
+            Box::new(
+                intrinsics::load("intrinsics::mem", &harness.context.session.codemap)
+                    .expect("undefined intrinsic module"),
+            )
+            .freeze(),
+        )
+        .expect("failed to load intrinsics::mem");
+
+    let memory_grow = "intrinsics::mem::memory_grow".parse().unwrap();
+
+    // Growing by zero pages returns the current size of linear memory
+    let mut stack = harness.invoke(memory_grow, &[Felt::ZERO]).expect("execution failed");
+    harness.emulator.stop();
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(1048575));
+
+    // Linear memory is already at its maximum size, so it cannot grow any further
+    let mut stack = harness.invoke(memory_grow, &[Felt::new(1)]).expect("execution failed");
+    harness.emulator.stop();
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(u32::MAX as u64));
 }
 
 macro_rules! proptest_unary_numeric_op {
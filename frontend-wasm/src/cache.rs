@@ -0,0 +1,308 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use miden_diagnostics::{
+    term::termcolor::Buffer, CaptureEmitter, CodeMap, DiagnosticsConfig, DiagnosticsHandler,
+    Emitter, Severity, Verbosity,
+};
+use rustc_hash::FxHashMap;
+
+use crate::{error::WasmResult, translate_module, WasmTranslationConfig};
+
+/// A cache of translated Wasm core modules, keyed on the content of the Wasm binary, and the
+/// configuration it was translated with.
+///
+/// This is intended for build systems which translate the same dependency many times, to avoid
+/// re-parsing, re-validating and re-translating it each time, see [translate_module_cached].
+#[derive(Default)]
+pub struct TranslationCache {
+    modules: FxHashMap<u64, CachedModule>,
+    hits: usize,
+    misses: usize,
+}
+
+struct CachedModule {
+    wasm: Box<[u8]>,
+    module: miden_hir::Module,
+    /// The diagnostics reported while translating `module`, which are reported again each time it
+    /// is answered from the cache
+    diagnostics: Vec<RecordedDiagnostic>,
+}
+
+impl TranslationCache {
+    /// Create a new, empty [TranslationCache]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of modules in this cache
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    /// Returns true if this cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Returns the number of translations which were answered from this cache
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Returns the number of translations which were not found in this cache, and were performed
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Remove all modules from this cache
+    pub fn clear(&mut self) {
+        self.modules.clear();
+    }
+}
+
+/// Like [translate_module], but returns a copy of the module from `cache`, if the same Wasm binary
+/// was previously translated with an identical configuration.
+///
+/// Any diagnostics reported while translating the module are recorded along with it, and are
+/// reported to `diagnostics` again each time it is answered from `cache`. Modules which fail to
+/// translate are not cached.
+pub fn translate_module_cached(
+    wasm: &[u8],
+    config: &WasmTranslationConfig,
+    diagnostics: &DiagnosticsHandler,
+    cache: &mut TranslationCache,
+) -> WasmResult<miden_hir::Module> {
+    let key = cache_key(wasm, config);
+    if let Some(cached) = cache.modules.get(&key) {
+        // Guard against hash collisions
+        if cached.wasm.as_ref() == wasm {
+            cache.hits += 1;
+            replay(&cached.diagnostics, diagnostics);
+            return Ok(cached.module.clone());
+        }
+    }
+
+    cache.misses += 1;
+    let recorder = Arc::new(DiagnosticsRecorder::default());
+    let result = translate_module(wasm, config, &recorder.handler());
+    let recorded = recorder.take();
+    replay(&recorded, diagnostics);
+    let module = result?;
+    cache.modules.insert(
+        key,
+        CachedModule {
+            wasm: wasm.into(),
+            module: module.clone(),
+            diagnostics: recorded,
+        },
+    );
+    Ok(module)
+}
+
+/// A diagnostic reported while translating a module, as rendered when it was reported
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RecordedDiagnostic {
+    severity: Severity,
+    message: String,
+    /// Everything rendered after the message, i.e. the labels, along with the source locations
+    /// they refer to, and any notes
+    details: Option<String>,
+}
+
+/// Report each of the `recorded` diagnostics to `diagnostics`
+fn replay(recorded: &[RecordedDiagnostic], diagnostics: &DiagnosticsHandler) {
+    for diagnostic in recorded {
+        let in_flight = diagnostics
+            .diagnostic(diagnostic.severity)
+            .with_message(diagnostic.message.as_str());
+        match diagnostic.details.as_deref() {
+            Some(details) => in_flight.with_note(details).emit(),
+            None => in_flight.emit(),
+        }
+    }
+}
+
+/// An [Emitter] which records each diagnostic emitted to it, rather than printing it
+#[derive(Default)]
+struct DiagnosticsRecorder {
+    capture: CaptureEmitter,
+    recorded: Mutex<Vec<RecordedDiagnostic>>,
+}
+impl DiagnosticsRecorder {
+    /// Get a [DiagnosticsHandler] which records every diagnostic reported to it in this recorder.
+    ///
+    /// Filtering by verbosity and the treatment of warnings is left to the handler the recorded
+    /// diagnostics are replayed to. Wasm modules have no source in the code map, so a fresh one is
+    /// used here, and the labels of a diagnostic are recorded as they are rendered.
+    fn handler(self: &Arc<Self>) -> DiagnosticsHandler {
+        DiagnosticsHandler::new(
+            DiagnosticsConfig {
+                verbosity: Verbosity::Debug,
+                warnings_as_errors: false,
+                no_warn: false,
+                display: Default::default(),
+            },
+            Arc::new(CodeMap::new()),
+            self.clone(),
+        )
+    }
+
+    /// Take the diagnostics recorded so far, in the order they were emitted
+    fn take(&self) -> Vec<RecordedDiagnostic> {
+        core::mem::take(&mut *self.recorded.lock().unwrap())
+    }
+}
+impl Emitter for DiagnosticsRecorder {
+    #[inline]
+    fn buffer(&self) -> Buffer {
+        self.capture.buffer()
+    }
+
+    fn print(&self, buffer: Buffer) -> std::io::Result<()> {
+        let rendered = String::from_utf8_lossy(buffer.as_slice());
+        // Diagnostics are rendered as `<severity>: <message>`, followed by any labels and notes on
+        // the lines after it
+        let (header, details) = match rendered.trim_end().split_once('\n') {
+            Some((header, details)) => (header, Some(details.to_string())),
+            None => (rendered.trim_end(), None),
+        };
+        let (severity, message) = match header.split_once(": ") {
+            Some(("bug", message)) => (Severity::Bug, message),
+            Some(("error", message)) => (Severity::Error, message),
+            Some(("warning", message)) => (Severity::Warning, message),
+            Some(("note", message)) => (Severity::Note, message),
+            Some(("help", message)) => (Severity::Help, message),
+            _ => (Severity::Note, header),
+        };
+        self.recorded.lock().unwrap().push(RecordedDiagnostic {
+            severity,
+            message: message.to_string(),
+            details,
+        });
+        Ok(())
+    }
+}
+
+/// Hash `wasm`, along with every field of `config` which can affect its translation
+fn cache_key(wasm: &[u8], config: &WasmTranslationConfig) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    wasm.hash(&mut hasher);
+    config.source_name.hash(&mut hasher);
+    config.override_name.hash(&mut hasher);
+    config.generate_native_debuginfo.hash(&mut hasher);
+    config.parse_wasm_debuginfo.hash(&mut hasher);
+    for (id, metadata) in config.import_metadata.iter() {
+        id.hash(&mut hasher);
+        metadata.digest.as_bytes().hash(&mut hasher);
+    }
+    config.abort_imports.hash(&mut hasher);
+    config.features.hash(&mut hasher);
+    (config.readonly_data_segment as usize).hash(&mut hasher);
+    config.intrinsics_modules.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+
+    #[test]
+    fn translation_cache_hit() {
+        let wat = r#"
+        (module
+            (func $add (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.add
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let config = WasmTranslationConfig::default();
+        let mut cache = TranslationCache::new();
+
+        let module = translate_module_cached(&wasm, &config, &diagnostics, &mut cache)
+            .expect("failed to translate module");
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+
+        // The second translation is answered from the cache, and is identical to the first
+        let cached = translate_module_cached(&wasm, &config, &diagnostics, &mut cache)
+            .expect("failed to translate module");
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        assert_eq!(cached.to_string(), module.to_string());
+
+        // A different configuration is translated separately
+        let config = WasmTranslationConfig {
+            override_name: Some(Cow::Borrowed("renamed")),
+            ..Default::default()
+        };
+        let renamed = translate_module_cached(&wasm, &config, &diagnostics, &mut cache)
+            .expect("failed to translate module");
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+        assert_eq!(renamed.name.as_str(), "renamed");
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn translation_cache_replays_diagnostics() {
+        let (diagnostics, capture) = crate::test_utils::capture_diagnostics();
+
+        // Diagnostics recorded during translation are reported as if emitted directly
+        let recorder = Arc::new(DiagnosticsRecorder::default());
+        let recording = recorder.handler();
+        recording
+            .diagnostic(Severity::Warning)
+            .with_message("unused import 'foo'")
+            .with_note("remove the import")
+            .emit();
+        recording.diagnostic(Severity::Error).with_message("unsupported").emit();
+        let recorded = recorder.take();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].severity, Severity::Warning);
+        assert_eq!(recorded[0].message, "unused import 'foo'");
+        assert!(
+            recorded[0]
+                .details
+                .as_ref()
+                .is_some_and(|details| details.contains("remove the import")),
+            "{recorded:?}"
+        );
+        assert_eq!(
+            recorded[1],
+            RecordedDiagnostic {
+                severity: Severity::Error,
+                message: "unsupported".to_string(),
+                details: None,
+            }
+        );
+        replay(&recorded, &diagnostics);
+        assert!(diagnostics.has_errors());
+        let replayed = capture.captured();
+        assert!(replayed.contains("warning: unused import 'foo'"), "{replayed}");
+        assert!(replayed.contains("remove the import"), "{replayed}");
+        assert!(replayed.contains("error: unsupported"), "{replayed}");
+
+        // A module which fails to translate reports its errors to the caller, and is not cached
+        let wat = r#"
+        (module
+            (func $f (param i32) (result i32)
+                local.get 0
+                f32.convert_i32_s
+                i32.reinterpret_f32
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let mut cache = TranslationCache::new();
+        let config = WasmTranslationConfig::default();
+        assert!(translate_module_cached(&wasm, &config, &diagnostics, &mut cache).is_err());
+        assert!(diagnostics.has_errors());
+        assert!(cache.is_empty());
+    }
+}
@@ -17,8 +17,8 @@ use std::{collections::hash_map, u64};
 
 use miden_diagnostics::{DiagnosticsHandler, SourceSpan};
 use miden_hir::{
-    cranelift_entity::packed_option::ReservedValue, Block, Immediate, Inst, InstBuilder, Type,
-    Type::*, Value,
+    cranelift_entity::packed_option::ReservedValue, Block, Felt, FieldElement, Immediate, Inst,
+    InstBuilder, Instruction, Opcode, Type, Type::*, UnaryOpImm, Value, ValueData,
 };
 use rustc_hash::FxHashMap;
 use wasmparser::{MemArg, Operator};
@@ -74,10 +74,12 @@ pub fn translate_operator(
         }
         Operator::LocalSet { local_index } => {
             let val = state.pop1();
+            name_local_value(builder, state, module, *local_index, val);
             builder.def_var(Variable::from_u32(*local_index), val);
         }
         Operator::LocalTee { local_index } => {
             let val = state.peek1();
+            name_local_value(builder, state, module, *local_index, val);
             builder.def_var(Variable::from_u32(*local_index), val);
         }
         /********************************** Globals ****************************************/
@@ -89,6 +91,11 @@ pub fn translate_operator(
         }
         Operator::GlobalSet { global_index } => {
             let global_index = GlobalIndex::from_u32(*global_index);
+            // Setting an immutable global is rejected when the function body is validated
+            debug_assert!(
+                module.globals[global_index].mutability,
+                "unexpected global.set of an immutable global"
+            );
             let name = module.global_name(global_index);
             let ty = ir_type(module.globals[global_index].ty)?;
             let ptr = builder
@@ -99,7 +106,10 @@ pub fn translate_operator(
         }
         /********************************* Stack misc **************************************/
         Operator::Drop => _ = state.pop1(),
-        Operator::Select => {
+        // A typed `select` only differs in that the type of its operands is given explicitly,
+        // rather than inferred, so both forms are translated the same way. Code generation
+        // lowers `select` to conditional drops, so no branches are introduced for it.
+        Operator::Select | Operator::TypedSelect { .. } => {
             let (arg1, arg2, cond) = state.pop3();
             // if cond is not 0, return arg1, else return arg2
             // https://www.w3.org/TR/wasm-core-1/#-hrefsyntax-instr-parametricmathsfselect%E2%91%A0
@@ -123,7 +133,7 @@ pub fn translate_operator(
         Operator::Br { relative_depth } => translate_br(state, relative_depth, builder, span),
         Operator::BrIf { relative_depth } => translate_br_if(*relative_depth, builder, state, span)?,
         Operator::BrTable { targets } => translate_br_table(targets, state, builder, span)?,
-        Operator::Return => translate_return(state, builder, diagnostics, span)?,
+        Operator::Return => translate_return(state, builder, span)?,
         /************************************ Calls ****************************************/
         Operator::Call { function_index } => {
             translate_call(
@@ -135,6 +145,13 @@ pub fn translate_operator(
                 diagnostics,
             )?;
         }
+        Operator::ReturnCall { function_index } => translate_return_call(
+            state,
+            builder,
+            FuncIndex::from_u32(*function_index),
+            span,
+            diagnostics,
+        )?,
         Operator::CallIndirect { type_index: _, table_index: _, table_byte: _ } => {
             // TODO:
         }
@@ -223,9 +240,22 @@ pub fn translate_operator(
             let val = state.pop1();
             state.push1(builder.ins().popcnt(val, span));
         }
-        Operator::I32Extend8S | Operator::I32Extend16S => {
+        Operator::I32Extend8S
+        | Operator::I32Extend16S
+        | Operator::I64Extend8S
+        | Operator::I64Extend16S
+        | Operator::I64Extend32S => {
             let val = state.pop1();
-            state.push1(builder.ins().sext(val, I32, span));
+            // Sign-extend the low N bits of the operand to the full width of the operand type
+            let (narrow_ty, ty) = match op {
+                Operator::I32Extend8S => (I8, I32),
+                Operator::I32Extend16S => (I16, I32),
+                Operator::I64Extend8S => (I8, I64),
+                Operator::I64Extend16S => (I16, I64),
+                _ => (I32, I64),
+            };
+            let narrow = builder.ins().trunc(val, narrow_ty, span);
+            state.push1(builder.ins().sext(narrow, ty, span));
         }
         Operator::I64ExtendI32S => {
             let val = state.pop1();
@@ -240,7 +270,13 @@ pub fn translate_operator(
         }
         Operator::I32WrapI64 => {
             let val = state.pop1();
-            state.push1(builder.ins().trunc(val, I32, span));
+            // When the low half of a 64-bit constant is extracted, emit it directly as a 32-bit
+            // constant, rather than truncating the 64-bit value. The now-unused 64-bit constant is
+            // removed as dead code during code generation.
+            match i64_constant(builder, val) {
+                Some(imm) => state.push1(builder.ins().i32(imm as i32, span)),
+                None => state.push1(builder.ins().trunc(val, I32, span)),
+            }
         }
         /****************************** Binary Operators ************************************/
         Operator::I32Add | Operator::I64Add => {
@@ -274,6 +310,15 @@ pub fn translate_operator(
             let val = builder.ins().shr_wrapping(arg1, arg2, span);
             state.push1(builder.ins().cast(val, I32, span));
         }
+        Operator::I64ShrU
+            if state.peekn(2).iter().all(|arg| i64_constant(builder, *arg).is_some()) =>
+        {
+            // Fold shifts of 64-bit constants, e.g. when extracting the high half of a constant
+            let (arg1, arg2) = state.pop2();
+            let lhs = i64_constant(builder, arg1).unwrap() as u64;
+            let rhs = i64_constant(builder, arg2).unwrap() as u32;
+            state.push1(builder.ins().i64(lhs.wrapping_shr(rhs) as i64, span));
+        }
         Operator::I64ShrU => {
             let (arg1, arg2) = state.pop2_casted(U64, builder, span);
             // wrapping shift semantics drop any bits that would cause
@@ -484,7 +529,7 @@ fn translate_br_table(
     let mut data = Vec::with_capacity(targets.len() as usize);
     if jump_args_count == 0 {
         // No jump arguments
-        for depth in targets.targets() {
+        for (index, depth) in targets.targets().enumerate() {
             let depth = depth?;
             let block = {
                 let i = state.control_stack.len() - 1 - (depth as usize);
@@ -492,7 +537,7 @@ fn translate_br_table(
                 frame.set_branched_to_exit();
                 frame.br_destination()
             };
-            data.push((depth, block));
+            data.push((index as u32, block));
         }
         let def_block = {
             let i = state.control_stack.len() - 1 - (default as usize);
@@ -507,7 +552,7 @@ fn translate_br_table(
         let return_count = jump_args_count;
         let mut dest_block_sequence = vec![];
         let mut dest_block_map = FxHashMap::default();
-        for depth in targets.targets() {
+        for (index, depth) in targets.targets().enumerate() {
             let depth = depth?;
             let branch_block = match dest_block_map.entry(depth as usize) {
                 hash_map::Entry::Occupied(entry) => *entry.get(),
@@ -517,7 +562,7 @@ fn translate_br_table(
                     *entry.insert(block)
                 }
             };
-            data.push((depth, branch_block));
+            data.push((index as u32, branch_block));
         }
         let default_branch_block = match dest_block_map.entry(default as usize) {
             hash_map::Entry::Occupied(entry) => *entry.get(),
@@ -555,6 +600,41 @@ const fn mem_total_pages() -> i32 {
     (MEMORY_SIZE / PAGE_SIZE) as i32
 }
 
+/// Returns the value of the 64-bit integer constant which defines `value`, if it is one
+fn i64_constant(builder: &FunctionBuilderExt, value: Value) -> Option<i64> {
+    let dfg = builder.data_flow_graph();
+    let ValueData::Inst { inst, .. } = dfg.value_data(value) else {
+        return None;
+    };
+    match dfg.inst(*inst) {
+        Instruction::UnaryOpImm(UnaryOpImm {
+            op: Opcode::ImmI64,
+            imm: Immediate::I64(imm),
+            ..
+        }) => Some(*imm),
+        _ => None,
+    }
+}
+
+/// Give `value` the name of the local `local_index` it is being assigned to, if the local has one.
+///
+/// A value may be assigned to more than one local, e.g. when copying one local to another, in
+/// which case it keeps the name of the first local it was assigned to.
+fn name_local_value(
+    builder: &mut FunctionBuilderExt,
+    state: &FuncTranslationState,
+    module: &Module,
+    local_index: u32,
+    value: Value,
+) {
+    if builder.data_flow_graph().value_name(value).is_some() {
+        return;
+    }
+    if let Some(name) = state.local_name(module, local_index) {
+        builder.data_flow_graph_mut().set_value_name(value, name);
+    }
+}
+
 fn translate_load(
     ptr_ty: Type,
     memarg: &MemArg,
@@ -649,6 +729,11 @@ fn translate_call(
     span: SourceSpan,
     diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<()> {
+    if let Some(error_code) = module_state.abort_code(function_index) {
+        let num_wasm_args = module_state.signature(function_index).params().len();
+        translate_abort(func_state, builder, num_wasm_args, error_code, span);
+        return Ok(());
+    }
     let func_id =
         module_state.get_direct_func(builder.data_flow_graph_mut(), function_index, diagnostics)?;
     let wasm_sig = module_state.signature(function_index);
@@ -690,10 +775,62 @@ fn translate_call(
     Ok(())
 }
 
-fn translate_return(
+/// Translates a tail call of the function being translated into a branch back to the start of its
+/// body, with the arguments of the call as the new values of its parameters.
+///
+/// Tail calls of any other function are not supported, as Miden does not permit recursion, and
+/// they cannot be lowered to a loop in general.
+fn translate_return_call(
     state: &mut FuncTranslationState,
     builder: &mut FunctionBuilderExt,
+    function_index: FuncIndex,
+    span: SourceSpan,
     diagnostics: &DiagnosticsHandler,
+) -> WasmResult<()> {
+    let header = match state.tail_call_header {
+        Some(header) if state.func_index() == Some(function_index) => header,
+        _ => {
+            unsupported_diag!(
+                diagnostics,
+                "Tail call of function {} is not supported, only functions which tail call \
+                 themselves can be translated, as recursion is not permitted",
+                function_index.as_u32()
+            );
+        }
+    };
+    let num_params = builder.signature().params().len();
+    for (i, arg) in state.peekn(num_params).iter().enumerate() {
+        builder.def_var(Variable::from_u32(i as u32), *arg);
+    }
+    state.popn(num_params);
+    builder.ins().br(header, &[], span);
+    state.reachable = false;
+    Ok(())
+}
+
+/// Translates a call to an imported function which is known to abort execution into a trap,
+/// raising `error_code`, if given.
+fn translate_abort(
+    state: &mut FuncTranslationState,
+    builder: &mut FunctionBuilderExt,
+    num_args: usize,
+    error_code: Option<u32>,
+    span: SourceSpan,
+) {
+    state.popn(num_args);
+    if let Some(error_code) = error_code {
+        let mut asm_builder = builder.ins().inline_asm(&[], [], span);
+        asm_builder.ins().push(Felt::ZERO);
+        asm_builder.ins().assert(Some(error_code));
+        asm_builder.build();
+    }
+    builder.ins().unreachable(span);
+    state.reachable = false;
+}
+
+fn translate_return(
+    state: &mut FuncTranslationState,
+    builder: &mut FunctionBuilderExt,
     span: SourceSpan,
 ) -> WasmResult<()> {
     let return_count = {
@@ -701,15 +838,8 @@ fn translate_return(
         frame.num_return_values()
     };
     {
-        let return_args = match return_count {
-            0 => None,
-            1 => Some(state.peekn_mut(return_count).first().unwrap().clone()),
-            _ => {
-                unsupported_diag!(diagnostics, "Multiple values are not supported");
-            }
-        };
-
-        builder.ins().ret(return_args, span);
+        let return_args = state.peekn(return_count);
+        builder.ins().ret_many(return_args, span);
     }
     state.popn(return_count);
     state.reachable = false;
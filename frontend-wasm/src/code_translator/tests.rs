@@ -1,9 +1,12 @@
+use alloc::borrow::Cow;
 use core::fmt::Write;
 
 use expect_test::expect;
-use miden_hir::Ident;
+use miden_hir::{
+    BranchInfo, CallInfo, Felt, FieldElement, FunctionIdent, Ident, Instruction, MasmOp, Opcode,
+};
 
-use crate::{test_utils::test_diagnostics, translate_module, WasmTranslationConfig};
+use crate::{test_utils::test_diagnostics, translate_module, WasmError, WasmTranslationConfig};
 
 /// Check IR generated for a Wasm op(s).
 /// Wrap Wasm ops in a function and check the IR generated for the entry block of that function.
@@ -362,6 +365,35 @@ fn i32_store8() {
     )
 }
 
+#[test]
+fn i32_store8_load8_s() {
+    check_op(
+        r#"
+            i32.const 1024
+            i32.const 255
+            i32.store8 offset=3
+            i32.const 1024
+            i32.load8_s offset=3
+            drop
+        "#,
+        expect![[r#"
+            (let (v0 i32) (const.i32 1024))
+            (let (v1 i32) (const.i32 255))
+            (let (v2 u8) (trunc v1))
+            (let (v3 u32) (cast v0))
+            (let (v4 u32) (add.checked v3 3))
+            (let (v5 (ptr u8)) (inttoptr v4))
+            (store v5 v2)
+            (let (v6 i32) (const.i32 1024))
+            (let (v7 u32) (cast v6))
+            (let (v8 u32) (add.checked v7 3))
+            (let (v9 (ptr i8)) (inttoptr v8))
+            (let (v10 i8) (load v9))
+            (let (v11 i32) (sext v10))
+        "#]],
+    )
+}
+
 #[test]
 fn i32_store16() {
     check_op(
@@ -516,6 +548,70 @@ fn i64_extend_i32_s() {
     )
 }
 
+#[test]
+fn i32_extend8_s() {
+    check_op(
+        r#"
+            i32.const 255
+            i32.extend8_s
+            drop
+        "#,
+        expect![[r#"
+            (let (v0 i32) (const.i32 255))
+            (let (v1 i8) (trunc v0))
+            (let (v2 i32) (sext v1))
+        "#]],
+    )
+}
+
+#[test]
+fn i64_extend32_s() {
+    check_op(
+        r#"
+            i64.const 4294967295
+            i64.extend32_s
+            drop
+        "#,
+        expect![[r#"
+            (let (v0 i64) (const.i64 4294967295))
+            (let (v1 i32) (trunc v0))
+            (let (v2 i64) (sext v1))
+        "#]],
+    )
+}
+
+#[test]
+fn i64_extend8_s() {
+    check_op(
+        r#"
+            i64.const 511
+            i64.extend8_s
+            drop
+        "#,
+        expect![[r#"
+            (let (v0 i64) (const.i64 511))
+            (let (v1 i8) (trunc v0))
+            (let (v2 i64) (sext v1))
+        "#]],
+    )
+}
+
+#[test]
+fn i64_extend16_s() {
+    check_op(
+        r#"
+            i64.const 98303
+            i64.extend16_s
+            drop
+        "#,
+        expect![[r#"
+            (let (v0 i64) (const.i64 98303))
+            (let (v1 i16) (trunc v0))
+            (let (v2 i64) (sext v1))
+        "#]],
+    )
+}
+
 #[test]
 fn i64_extend_i32_u() {
     check_op(
@@ -543,7 +639,50 @@ fn i32_wrap_i64() {
         "#,
         expect![[r#"
             (let (v0 i64) (const.i64 1))
-            (let (v1 i32) (trunc v0))
+            (let (v1 i32) (const.i32 1))
+        "#]],
+    )
+}
+
+#[test]
+fn i32_wrap_i64_non_constant() {
+    check_op(
+        r#"
+            i32.const 1024
+            i64.load
+            i32.wrap_i64
+            drop
+        "#,
+        expect![[r#"
+            (let (v0 i32) (const.i32 1024))
+            (let (v1 u32) (cast v0))
+            (let (v2 (ptr i64)) (inttoptr v1))
+            (let (v3 i64) (load v2))
+            (let (v4 i32) (trunc v3))
+        "#]],
+    )
+}
+
+#[test]
+fn i64_const_split() {
+    check_op(
+        r#"
+            i64.const 0x0123456789abcdef
+            i32.wrap_i64
+            drop
+            i64.const 0x0123456789abcdef
+            i64.const 32
+            i64.shr_u
+            i32.wrap_i64
+            drop
+        "#,
+        expect![[r#"
+            (let (v0 i64) (const.i64 81985529216486895))
+            (let (v1 i32) (const.i32 -1985229329))
+            (let (v2 i64) (const.i64 81985529216486895))
+            (let (v3 i64) (const.i64 32))
+            (let (v4 i64) (const.i64 19088743))
+            (let (v5 i32) (const.i32 19088743))
         "#]],
     )
 }
@@ -784,10 +923,31 @@ fn i64_shr_u() {
         expect![[r#"
             (let (v0 i64) (const.i64 2))
             (let (v1 i64) (const.i64 1))
-            (let (v2 u64) (cast v0))
-            (let (v3 u64) (cast v1))
-            (let (v4 u64) (shr.wrapping v2 v3))
-            (let (v5 i64) (cast v4))
+            (let (v2 i64) (const.i64 1))
+        "#]],
+    )
+}
+
+#[test]
+fn i64_shr_u_non_constant() {
+    check_op(
+        r#"
+            i32.const 1024
+            i64.load
+            i64.const 1
+            i64.shr_u
+            drop
+        "#,
+        expect![[r#"
+            (let (v0 i32) (const.i32 1024))
+            (let (v1 u32) (cast v0))
+            (let (v2 (ptr i64)) (inttoptr v1))
+            (let (v3 i64) (load v2))
+            (let (v4 i64) (const.i64 1))
+            (let (v5 u64) (cast v3))
+            (let (v6 u64) (cast v4))
+            (let (v7 u64) (shr.wrapping v5 v6))
+            (let (v8 i64) (cast v7))
         "#]],
     )
 }
@@ -1503,3 +1663,357 @@ fn select_i32() {
         "#]],
     )
 }
+
+#[test]
+fn typed_select_i32() {
+    check_op(
+        r#"
+            i32.const 3
+            i32.const 7
+            i32.const 1
+            select (result i32)
+            drop
+        "#,
+        expect![[r#"
+            (let (v0 i32) (const.i32 3))
+            (let (v1 i32) (const.i32 7))
+            (let (v2 i32) (const.i32 1))
+            (let (v3 i1) (neq v2 0))
+            (let (v4 i32) (select v3 v0 v1))
+        "#]],
+    )
+}
+
+#[test]
+fn call_abort_import() {
+    let wat = r#"
+        (module
+            (import "env" "abort" (func $abort))
+            (func $test_wrapper
+                call $abort
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let diagnostics = test_diagnostics();
+    let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics).unwrap();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    let entry_block = func.dfg.entry_block();
+    let opcodes = func
+        .dfg
+        .block(entry_block)
+        .insts()
+        .map(|inst| func.dfg[inst].opcode())
+        .collect::<Vec<_>>();
+    // The call is lowered to a trap, rather than an `exec` of the unresolved import
+    assert_eq!(opcodes, vec![Opcode::Unreachable]);
+    assert_eq!(func.dfg.imports().count(), 0);
+}
+
+#[test]
+fn call_abort_import_with_error_code() {
+    let wat = r#"
+        (module
+            (import "env" "abort" (func $abort))
+            (func $test_wrapper
+                call $abort
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let diagnostics = test_diagnostics();
+    let config = WasmTranslationConfig {
+        abort_imports: [(
+            Cow::Borrowed("env"),
+            [(Cow::Borrowed("abort"), Some(42))].into_iter().collect(),
+        )]
+        .into_iter()
+        .collect(),
+        ..Default::default()
+    };
+    let module = translate_module(&wasm, &config, &diagnostics).unwrap();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    let entry_block = func.dfg.entry_block();
+    let insts = func.dfg.block(entry_block).insts().collect::<Vec<_>>();
+    assert_eq!(insts.len(), 2);
+    // The error code is raised by a failing assertion, before the trap
+    let Instruction::InlineAsm(asm) = &func.dfg[insts[0]] else {
+        panic!("expected inline assembly, got {:?}", func.dfg[insts[0]].opcode());
+    };
+    assert_eq!(
+        asm.blocks[asm.body].ops.as_slice(),
+        &[MasmOp::Push(Felt::ZERO), MasmOp::AssertWithError(42)]
+    );
+    assert_eq!(func.dfg[insts[1]].opcode(), Opcode::Unreachable);
+    assert_eq!(func.dfg.imports().count(), 0);
+}
+
+#[test]
+fn call_abort_from_other_module() {
+    // Only `abort` from the `env` module is known to abort execution
+    let wat = r#"
+        (module
+            (import "other" "abort" (func $abort))
+            (func $test_wrapper
+                call $abort
+            )
+        )"#;
+    let callees = check_calls(wat);
+    assert_eq!(callees.len(), 1);
+    assert_eq!(callees[0].module.as_str(), "other");
+    assert_eq!(callees[0].function.as_str(), "abort");
+}
+
+#[test]
+fn unreachable_dead_tail() {
+    let wat = r#"
+        (module
+            (func $test_wrapper (param i32) (result i32)
+                block
+                    br 0
+                    local.get 0
+                    i32.const 2
+                    i32.add
+                    drop
+                end
+                unreachable
+                local.get 0
+                i32.const 3
+                i32.mul
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let diagnostics = test_diagnostics();
+    let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics).unwrap();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    let opcodes = func
+        .dfg
+        .blocks()
+        .flat_map(|(_, block)| block.insts())
+        .map(|inst| func.dfg[inst].opcode())
+        .collect::<Vec<_>>();
+    // `unreachable` is lowered to a trap, and the code following it, or an unconditional branch,
+    // up to the end of the enclosing Wasm block, is never translated
+    assert_eq!(opcodes.iter().filter(|op| **op == Opcode::Unreachable).count(), 1);
+    assert!(!opcodes.contains(&Opcode::Add));
+    assert!(!opcodes.contains(&Opcode::Mul));
+    assert!(!opcodes.contains(&Opcode::ImmI32));
+}
+
+fn tail_call_config() -> WasmTranslationConfig {
+    WasmTranslationConfig {
+        features: wasmparser::WasmFeatures {
+            tail_call: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn return_call_self_as_loop() {
+    let wat = r#"
+        (module
+            (func $fact (param i32 i32) (result i32)
+                local.get 0
+                i32.eqz
+                if (result i32)
+                    local.get 1
+                else
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    local.get 0
+                    local.get 1
+                    i32.mul
+                    return_call $fact
+                end
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let diagnostics = test_diagnostics();
+    let module = translate_module(&wasm, &tail_call_config(), &diagnostics).unwrap();
+    let func = module.function(Ident::from("fact")).unwrap();
+    let insts = func.dfg.blocks().flat_map(|(_, block)| block.insts()).collect::<Vec<_>>();
+    // The tail call is lowered to a loop, rather than a recursive call
+    assert!(insts.iter().all(|inst| func.dfg[*inst].opcode() != Opcode::Call));
+
+    // The entry block branches to the loop header, passing the function parameters, which the
+    // tail call replaces with its arguments on the back edge
+    let entry = func.dfg.entry_block();
+    let header = match func.dfg.analyze_branch(func.dfg.last_inst(entry).unwrap()) {
+        BranchInfo::SingleDest(header, args) => {
+            assert_eq!(args, func.dfg.block_params(entry));
+            header
+        }
+        _ => panic!("expected the entry block to branch to the loop header"),
+    };
+    assert_eq!(func.dfg.block_insts(entry).count(), 1);
+    assert_eq!(func.dfg.block_params(header).len(), 2);
+    let back_edges = insts
+        .iter()
+        .filter(|inst| **inst != func.dfg.last_inst(entry).unwrap())
+        .filter(|inst| match func.dfg.analyze_branch(**inst) {
+            BranchInfo::SingleDest(dest, args) => {
+                assert!(dest != header || args.len() == 2);
+                dest == header
+            }
+            BranchInfo::MultiDest(targets) => targets.iter().any(|jt| jt.destination == header),
+            BranchInfo::NotABranch => false,
+        })
+        .count();
+    assert_eq!(back_edges, 1);
+}
+
+#[test]
+fn return_call_other_unsupported() {
+    let wat = r#"
+        (module
+            (func $callee (param i32) (result i32)
+                local.get 0
+            )
+            (func $test_wrapper (param i32) (result i32)
+                local.get 0
+                return_call $callee
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let diagnostics = test_diagnostics();
+    let err = translate_module(&wasm, &tail_call_config(), &diagnostics)
+        .expect_err("expected a tail call of another function to be rejected");
+    assert!(matches!(err, WasmError::Unsupported(_)), "unexpected error: {err}");
+}
+
+/// Translate `wat` and return the callees of the `call` instructions in `test_wrapper`
+fn check_calls(wat: &str) -> Vec<FunctionIdent> {
+    let wasm = wat::parse_str(wat).unwrap();
+    let diagnostics = test_diagnostics();
+    let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics).unwrap();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    let entry_block = func.dfg.entry_block();
+    func.dfg
+        .block(entry_block)
+        .insts()
+        .filter_map(|inst| match func.dfg.analyze_call(inst) {
+            CallInfo::Direct(callee, _) => Some(callee),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn call_tx_kernel_account_ids() {
+    let wat = r#"
+        (module
+            (import "miden:tx_kernel/account" "get_id<0x0000000000000000000000000000000000000000000000000000000000000000>" (func $get_id (result f64)))
+            (import "miden:tx_kernel/note" "get_sender<0x0000000000000000000000000000000000000000000000000000000000000000>" (func $get_sender (result f64)))
+            (func $test_wrapper
+                call $get_id
+                drop
+                call $get_sender
+                drop
+            )
+        )"#;
+    let callees = check_calls(wat);
+    // The executing account and the note sender are read via distinct kernel procedures
+    assert_eq!(
+        callees,
+        vec![
+            FunctionIdent {
+                module: Ident::from("miden:tx_kernel/account"),
+                function: Ident::from("get_id"),
+            },
+            FunctionIdent {
+                module: Ident::from("miden:tx_kernel/note"),
+                function: Ident::from("get_sender"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn word_intrinsics_assert_eqw() {
+    let wat = r#"
+        (module
+            (import "miden:prelude/intrinsics_word" "assert_eqw" (func $assert_eqw (param f64 f64 f64 f64 f64 f64 f64 f64)))
+            (import "miden:prelude/intrinsics_word" "assert_word_zero" (func $assert_word_zero (param f64 f64 f64 f64)))
+            (func $test_wrapper (param f64 f64 f64 f64)
+                local.get 0
+                local.get 1
+                local.get 2
+                local.get 3
+                local.get 0
+                local.get 1
+                local.get 2
+                local.get 3
+                call $assert_eqw
+                local.get 0
+                local.get 1
+                local.get 2
+                local.get 3
+                call $assert_word_zero
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let diagnostics = test_diagnostics();
+    let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics).unwrap();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    let entry_block = func.dfg.entry_block();
+    // Both words are compared at once, rather than element by element
+    assert!(check_calls(wat).is_empty());
+    let asm = func
+        .dfg
+        .block(entry_block)
+        .insts()
+        .filter_map(|inst| match &func.dfg[inst] {
+            Instruction::InlineAsm(asm) => {
+                assert!(asm.results.is_empty());
+                Some(asm.blocks[asm.body].ops.to_vec())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(asm, vec![vec![MasmOp::AssertEqw], vec![MasmOp::Padw, MasmOp::AssertEqw]]);
+}
+
+#[test]
+fn felt_intrinsics_shifts() {
+    let wat = r#"
+        (module
+            (import "miden:prelude/intrinsics_felt" "shl" (func $shl (param f64 i32) (result f64)))
+            (import "miden:prelude/intrinsics_felt" "rotr" (func $rotr (param f64 i32) (result f64)))
+            (func $test_wrapper (param f64 i32) (result f64)
+                local.get 0
+                local.get 1
+                call $shl
+                local.get 1
+                call $rotr
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let diagnostics = test_diagnostics();
+    let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics).unwrap();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    let entry_block = func.dfg.entry_block();
+    assert!(check_calls(wat).is_empty());
+    let opcodes = func
+        .dfg
+        .block(entry_block)
+        .insts()
+        .map(|inst| func.dfg[inst].opcode())
+        .take_while(|opcode| !opcode.is_branch())
+        .collect::<Vec<_>>();
+    // Both operands are cast to u32, which traps if either is out of range, and the result is
+    // extended back to a felt
+    assert_eq!(
+        opcodes,
+        vec![
+            Opcode::Cast,
+            Opcode::Cast,
+            Opcode::Shl,
+            Opcode::Zext,
+            Opcode::Cast,
+            Opcode::Cast,
+            Opcode::Rotr,
+            Opcode::Zext,
+        ]
+    );
+}
@@ -1,7 +1,13 @@
-use alloc::{borrow::Cow, collections::BTreeMap};
+use alloc::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+};
 
 use miden_core::crypto::hash::RpoDigest;
-use miden_hir::InterfaceFunctionIdent;
+use miden_hir::{InterfaceFunctionIdent, Symbol};
+use wasmparser::WasmFeatures;
+
+use crate::intrinsics::is_miden_intrinsics_module;
 
 /// Represents Miden VM codegen metadata for a function import.
 /// This struct will have more fields in the future e.g. where the function
@@ -33,6 +39,32 @@ pub struct WasmTranslationConfig {
     /// each imported function. Having it here might be a temporary solution,
     /// later we might want to move it to Wasm custom section.
     pub import_metadata: BTreeMap<InterfaceFunctionIdent, ImportMetadata>,
+
+    /// Imported functions which are known to abort execution, e.g. those used by Rust's panic
+    /// machinery, keyed by the name of the module they are imported from, and then by their own
+    /// name, along with the error code to raise when they are called, if any.
+    ///
+    /// Calls to these functions are lowered directly to a trap, rather than treated as calls to
+    /// an unresolved import.
+    pub abort_imports: BTreeMap<Cow<'static, str>, BTreeMap<Cow<'static, str>, Option<u32>>>,
+
+    /// The WebAssembly proposals, e.g. sign extension or bulk memory, which a core module is
+    /// permitted to use. Modules using a proposal which is not enabled here fail validation.
+    pub features: WasmFeatures,
+
+    /// Decides whether a data segment should be read-only, given the segment's name from the
+    /// name section.
+    ///
+    /// By default, segments with `.rodata` in their name are read-only, see [is_rodata_segment].
+    pub readonly_data_segment: fn(&str) -> bool,
+
+    /// Names of modules, in addition to the built-in Miden intrinsics modules, whose functions are
+    /// treated as intrinsics.
+    ///
+    /// Functions imported from these modules are not added to the imports of a component built
+    /// by [crate::translate_module_as_component], as they are expected to be lowered by the
+    /// consumer of the translated IR, rather than resolved at link time.
+    pub intrinsics_modules: BTreeSet<Cow<'static, str>>,
 }
 
 impl Default for WasmTranslationConfig {
@@ -43,6 +75,31 @@ impl Default for WasmTranslationConfig {
             generate_native_debuginfo: false,
             parse_wasm_debuginfo: false,
             import_metadata: Default::default(),
+            abort_imports: [(
+                Cow::Borrowed("env"),
+                [(Cow::Borrowed("abort"), None), (Cow::Borrowed("__rust_panic"), None)]
+                    .into_iter()
+                    .collect(),
+            )]
+            .into_iter()
+            .collect(),
+            features: WasmFeatures::default(),
+            readonly_data_segment: is_rodata_segment,
+            intrinsics_modules: Default::default(),
         }
     }
 }
+
+impl WasmTranslationConfig {
+    /// Returns true if functions imported from `module` are treated as intrinsics, either because
+    /// it is a built-in Miden intrinsics module, or because it is one of [Self::intrinsics_modules]
+    pub fn is_intrinsics_module(&self, module: Symbol) -> bool {
+        is_miden_intrinsics_module(module) || self.intrinsics_modules.contains(module.as_str())
+    }
+}
+
+/// The default classification of read-only data segments, i.e. those whose name contains
+/// `.rodata`, as is the case for the segments emitted by LLVM for read-only data.
+pub fn is_rodata_segment(name: &str) -> bool {
+    name.contains(".rodata")
+}
@@ -1,6 +1,6 @@
 use miden_diagnostics::Diagnostic;
 use miden_diagnostics::ToDiagnostic;
-use miden_hir::SymbolConflictError;
+use miden_hir::{FunctionIdent, SymbolConflictError};
 use thiserror::Error;
 
 /// A WebAssembly translation error.
@@ -51,6 +51,10 @@ pub enum WasmError {
 
     #[error("Export metadata is missing: {0}")]
     MissingExportMetadata(String),
+
+    /// The MAST root hash of an imported Miden SDK function is unknown
+    #[error("Missing MAST root hash for imported function '{0}'")]
+    MissingImportDigest(FunctionIdent),
 }
 
 impl From<wasmparser::BinaryReaderError> for WasmError {
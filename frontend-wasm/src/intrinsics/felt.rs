@@ -68,6 +68,21 @@ pub(crate) fn convert_felt_intrinsics(
             let inst = builder.ins().exp(args[0], args[1], span);
             vec![inst]
         }
+        // Bitwise operations, on the 32-bit sub-range of the field
+        "shl" | "shr" | "rotl" | "rotr" => {
+            assert_eq!(args.len(), 2, "{} takes exactly two arguments", func_id);
+            // both casts trap if the value does not fit in a u32
+            let a = builder.ins().cast(args[0], U32, span);
+            let b = builder.ins().cast(args[1], U32, span);
+            let inst = match func_id.function.as_symbol().as_str() {
+                "shl" => builder.ins().shl_unchecked(a, b, span),
+                "shr" => builder.ins().shr_unchecked(a, b, span),
+                "rotl" => builder.ins().rotl(a, b, span),
+                _ => builder.ins().rotr(a, b, span),
+            };
+            let zext = builder.ins().zext(inst, Felt, span);
+            vec![zext]
+        }
         // Comparison operations
         "eq" => {
             assert_eq!(args.len(), 2, "{} takes exactly two arguments", func_id);
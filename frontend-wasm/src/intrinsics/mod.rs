@@ -1,4 +1,5 @@
 mod felt;
+mod word;
 
 use std::{collections::HashSet, sync::OnceLock};
 
@@ -16,6 +17,7 @@ fn modules() -> &'static HashSet<&'static str> {
     MODULES.get_or_init(|| {
         let mut s = HashSet::default();
         s.insert(felt::PRELUDE_INTRINSICS_FELT_MODULE_NAME);
+        s.insert(word::PRELUDE_INTRINSICS_WORD_MODULE_NAME);
         s
     })
 }
@@ -31,6 +33,9 @@ pub fn convert_intrinsics_call(
         felt::PRELUDE_INTRINSICS_FELT_MODULE_NAME => {
             felt::convert_felt_intrinsics(func_id, args, builder, span)
         }
+        word::PRELUDE_INTRINSICS_WORD_MODULE_NAME => {
+            word::convert_word_intrinsics(func_id, args, builder, span)
+        }
         _ => panic!("No intrinsics found for {}", func_id),
     }
 }
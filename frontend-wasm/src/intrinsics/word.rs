@@ -0,0 +1,38 @@
+use std::vec;
+
+use miden_hir::{FunctionIdent, InstBuilder, SourceSpan, Value};
+
+use crate::module::function_builder_ext::FunctionBuilderExt;
+
+pub(crate) const PRELUDE_INTRINSICS_WORD_MODULE_NAME: &str = "miden:prelude/intrinsics_word";
+
+/// Convert a call to a word op intrinsic function into instruction(s)
+///
+/// Words are passed to these intrinsics as four separate felts each, in the order of their
+/// elements.
+pub(crate) fn convert_word_intrinsics(
+    func_id: FunctionIdent,
+    args: &[Value],
+    builder: &mut FunctionBuilderExt<'_, '_, '_>,
+    span: SourceSpan,
+) -> Vec<Value> {
+    match func_id.function.as_symbol().as_str() {
+        // Assert operations
+        "assert_eqw" => {
+            assert_eq!(args.len(), 8, "{} takes exactly two words", func_id);
+            let mut asm_builder = builder.ins().inline_asm(args, [], span);
+            asm_builder.ins().assert_eqw(None);
+            asm_builder.build();
+            vec![]
+        }
+        "assert_word_zero" => {
+            assert_eq!(args.len(), 4, "{} takes exactly one word", func_id);
+            let mut asm_builder = builder.ins().inline_asm(args, [], span);
+            asm_builder.ins().padw();
+            asm_builder.ins().assert_eqw(None);
+            asm_builder.build();
+            vec![]
+        }
+        _ => panic!("No word op intrinsics found for {}", func_id),
+    }
+}
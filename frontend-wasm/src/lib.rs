@@ -7,6 +7,7 @@
 
 extern crate alloc;
 
+mod cache;
 mod code_translator;
 mod component;
 mod config;
@@ -20,7 +21,10 @@ mod translation_utils;
 #[cfg(test)]
 mod test_utils;
 
+pub use wasmparser::WasmFeatures;
+
 pub use self::{
+    cache::{translate_module_cached, TranslationCache},
     component::build_ir::translate_component,
     config::*,
     error::WasmError,
@@ -18,6 +18,7 @@ enum TransformStrategy {
 fn get_transform_strategy(function_id: &str) -> TransformStrategy {
     match function_id {
         tx_kernel::NOTE_GET_INPUTS => TransformStrategy::ListReturn,
+        tx_kernel::NOTE_GET_SENDER => TransformStrategy::NoTransform,
         tx_kernel::ACCOUNT_ADD_ASSET => TransformStrategy::ReturnViaPointer,
         tx_kernel::ACCOUNT_GET_ID => TransformStrategy::NoTransform,
         _ => panic!("No transform strategy found for function {}", function_id),
@@ -9,6 +9,7 @@ use rustc_hash::FxHashMap;
 
 pub const NOTE_MODULE_NAME: &str = "miden:tx_kernel/note";
 pub const NOTE_GET_INPUTS: &str = "get_inputs";
+pub const NOTE_GET_SENDER: &str = "get_sender";
 
 pub const ACCOUNT_MODULE_NAME: &str = "miden:tx_kernel/account";
 pub const ACCOUNT_ADD_ASSET: &str = "add_asset";
@@ -24,6 +25,7 @@ pub(crate) fn types() -> &'static ModuleFunctionTypeMap {
 
         let mut note: FunctionTypeMap = Default::default();
         note.insert(NOTE_GET_INPUTS, FunctionType::new_miden([Felt], [I32, Felt]));
+        note.insert(NOTE_GET_SENDER, FunctionType::new_miden([], [Felt]));
         m.insert(NOTE_MODULE_NAME, note);
 
         let mut account: FunctionTypeMap = Default::default();
@@ -1,20 +1,23 @@
 use core::mem;
 
 use miden_diagnostics::{DiagnosticsHandler, SourceSpan};
-use miden_hir::{CallConv, ConstantData, Linkage, MidenAbiImport, ModuleBuilder, Symbol};
-use wasmparser::{Validator, WasmFeatures};
+use miden_hir::{
+    symbols, CallConv, ConstantData, Linkage, MidenAbiImport, ModuleBuilder, Symbol, Type,
+};
+use wasmparser::Validator;
 
 use super::{module_translation_state::ModuleTranslationState, Module};
 use crate::{
     error::WasmResult,
-    intrinsics::is_miden_intrinsics_module,
     miden_abi::miden_sdk_function_type,
     module::{
         func_translator::FuncTranslator,
         module_env::{FunctionBodyData, ModuleEnvironment, ParsedModule},
-        types::{ir_func_sig, ir_func_type, ir_type, ModuleTypes},
+        types::{
+            ir_func_sig, ir_func_type, ir_type, EntityIndex, GlobalIndex, GlobalInit, ModuleTypes,
+        },
     },
-    WasmError, WasmTranslationConfig,
+    unsupported_diag, WasmError, WasmTranslationConfig,
 };
 
 /// Translate a valid Wasm core module binary into Miden IR module
@@ -23,8 +26,7 @@ pub fn translate_module(
     config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<miden_hir::Module> {
-    let wasm_features = WasmFeatures::default();
-    let mut validator = Validator::new_with_features(wasm_features);
+    let mut validator = Validator::new_with_features(config.features);
     let parser = wasmparser::Parser::new(0);
     let mut module_types_builder = Default::default();
     let mut parsed_module = ModuleEnvironment::new(
@@ -55,8 +57,7 @@ pub fn translate_module_as_component(
     config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<miden_hir::Component> {
-    let wasm_features = WasmFeatures::default();
-    let mut validator = Validator::new_with_features(wasm_features);
+    let mut validator = Validator::new_with_features(config.features);
     let parser = wasmparser::Parser::new(0);
     let mut module_types_builder = Default::default();
     let mut parsed_module = ModuleEnvironment::new(
@@ -77,28 +78,32 @@ pub fn translate_module_as_component(
         build_ir_module(&mut parsed_module, &module_types, &mut module_state, config, diagnostics)?;
     let mut cb = miden_hir::ComponentBuilder::new(&diagnostics);
     let module_imports = module.imports();
-    for import_module_id in module_imports.iter_module_names() {
-        if let Some(imports) = module_imports.imported(import_module_id) {
-            for ext_func in imports {
-                if is_miden_intrinsics_module(ext_func.module.as_symbol()) {
-                    // ignore intrinsics imports
-                    continue;
-                }
-                let function_ty = miden_sdk_function_type(
-                    ext_func.module.as_symbol(),
-                    ext_func.function.as_symbol(),
-                );
-                let digest = *module_state.digest(ext_func).expect(
-                    format!("failed to find MAST root hash for function {}", ext_func.function)
-                        .as_str(),
-                );
-                let component_import = miden_hir::ComponentImport::MidenAbiImport(MidenAbiImport {
-                    function_ty,
-                    digest,
-                });
-                cb.add_import(*ext_func, component_import);
-            }
+    // The component keeps its imports ordered by function, so the order they are added in here
+    // does not affect the component which is built
+    let imported_functions = module_imports
+        .iter_module_names()
+        .filter_map(|import_module_id| module_imports.imported(import_module_id))
+        .flatten();
+    for ext_func in imported_functions {
+        if config.is_intrinsics_module(ext_func.module.as_symbol()) {
+            // ignore intrinsics imports
+            continue;
         }
+        let Some(digest) = module_state.digest(ext_func).copied() else {
+            let err = WasmError::MissingImportDigest(*ext_func);
+            diagnostics
+                .diagnostic(miden_diagnostics::Severity::Error)
+                .with_message(err.to_string())
+                .emit();
+            return Err(err);
+        };
+        let function_ty =
+            miden_sdk_function_type(ext_func.module.as_symbol(), ext_func.function.as_symbol());
+        let component_import = miden_hir::ComponentImport::MidenAbiImport(MidenAbiImport {
+            function_ty,
+            digest,
+        });
+        cb.add_import(*ext_func, component_import);
     }
     cb.add_module(module.into()).expect("module is already added");
     Ok(cb.build())
@@ -108,13 +113,23 @@ pub fn build_ir_module(
     parsed_module: &mut ParsedModule,
     module_types: &ModuleTypes,
     module_state: &mut ModuleTranslationState,
-    _config: &WasmTranslationConfig,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<miden_hir::Module> {
+    module_state.recognize_abort_imports(&parsed_module.module, &config.abort_imports);
     let name = parsed_module.module.name();
     let mut module_builder = ModuleBuilder::new(name.clone().as_str());
     build_globals(&parsed_module.module, &mut module_builder, diagnostics)?;
-    build_data_segments(parsed_module, &mut module_builder, diagnostics)?;
+    build_data_segments(parsed_module, &mut module_builder, config, diagnostics)?;
+    if let Some(start_func) = parsed_module.module.start_func {
+        if parsed_module.module.is_imported_function(start_func) {
+            unsupported_diag!(
+                diagnostics,
+                "imported start function '{}' is not supported",
+                parsed_module.module.func_name(start_func)
+            );
+        }
+    }
     let mut func_translator = FuncTranslator::new();
     // Although this renders this parsed module invalid(without functiong
     // bodies), we don't support multiple module instances. Thus, this
@@ -131,6 +146,7 @@ pub fn build_ir_module(
         let FunctionBodyData { validator, body } = body_data;
         let mut func_validator = validator.into_validator(Default::default());
         func_translator.translate_body(
+            *func_index,
             &body,
             &mut module_func_builder,
             module_state,
@@ -139,6 +155,11 @@ pub fn build_ir_module(
             diagnostics,
             &mut func_validator,
         )?;
+        // The start function must run before any export is called, so it is made the entrypoint of
+        // the program, which is executed once the data segments have been initialized
+        if parsed_module.module.start_func == Some(*func_index) {
+            module_func_builder.set_attribute(symbols::Entrypoint, ());
+        }
         module_func_builder
             .build(diagnostics)
             .map_err(|_| WasmError::InvalidFunctionError)?;
@@ -152,18 +173,27 @@ fn build_globals(
     module_builder: &mut ModuleBuilder,
     diagnostics: &DiagnosticsHandler,
 ) -> Result<(), WasmError> {
-    Ok(for (global_idx, global) in &wasm_module.globals {
+    // Report every global whose initializer is unsupported, rather than just the first one
+    let mut unsupported = None;
+    for (global_idx, global) in &wasm_module.globals {
         let global_name = wasm_module
             .name_section
             .globals_names
             .get(&global_idx)
             .cloned()
             .unwrap_or(Symbol::intern(format!("gv{}", global_idx.as_u32())));
-        let global_init = wasm_module.try_global_initializer(global_idx, diagnostics)?;
-        let init = ConstantData::from(global_init.to_le_bytes(&wasm_module, diagnostics)?);
+        let init = match global_initializer(wasm_module, global_idx, global_name, diagnostics) {
+            Ok(init) => init,
+            Err(err) => {
+                unsupported.get_or_insert(err);
+                continue;
+            }
+        };
+        let global_ty = ir_type(global.ty.clone())?;
+        check_global_initializer_size(global_name, &global_ty, &init, diagnostics)?;
         if let Err(e) = module_builder.declare_global_variable(
             global_name.as_str(),
-            ir_type(global.ty.clone())?,
+            global_ty,
             Linkage::External,
             Some(init.clone()),
             SourceSpan::default(),
@@ -179,18 +209,79 @@ fn build_globals(
                 .emit();
             return Err(WasmError::Unexpected(message));
         }
-    })
+    }
+    unsupported.map_or(Ok(()), Err)
+}
+
+/// Evaluate the initializer of the global variable `global_name` to a constant.
+///
+/// Emits a diagnostic naming the global if it, or its initializer, cannot be evaluated at compile
+/// time, e.g. because it is imported, or initialized from an imported global.
+fn global_initializer(
+    wasm_module: &Module,
+    global_idx: GlobalIndex,
+    global_name: Symbol,
+    diagnostics: &DiagnosticsHandler,
+) -> WasmResult<ConstantData> {
+    let Some(defined_global_idx) = wasm_module.defined_global_index(global_idx) else {
+        unsupported_diag!(
+            diagnostics,
+            "Unsupported global variable '{global_name}': imported globals are not supported yet"
+        );
+    };
+    let global_init = wasm_module.global_initializers[defined_global_idx];
+    if let GlobalInit::GetGlobal(source_idx) = global_init {
+        if wasm_module.is_imported_global(source_idx) {
+            let source = wasm_module
+                .imports
+                .iter()
+                .find(|import| import.index == EntityIndex::Global(source_idx))
+                .map(|import| format!("{}.{}", import.module, import.field))
+                .unwrap_or_else(|| format!("gv{}", source_idx.as_u32()));
+            unsupported_diag!(
+                diagnostics,
+                "Unsupported initializer for global variable '{global_name}': `global.get` of the \
+                 imported global '{source}' cannot be evaluated at compile time"
+            );
+        }
+    }
+    Ok(ConstantData::from(global_init.to_le_bytes(wasm_module, diagnostics)?))
+}
+
+/// Verify that the initializer of the global variable `global_name` is exactly the size of its
+/// declared type, as otherwise the global would be laid out incorrectly in memory
+fn check_global_initializer_size(
+    global_name: Symbol,
+    global_ty: &Type,
+    init: &ConstantData,
+    diagnostics: &DiagnosticsHandler,
+) -> WasmResult<()> {
+    let expected_size = global_ty.size_in_bytes();
+    if init.len() != expected_size {
+        let message = format!(
+            "Invalid initializer for global variable '{global_name}': expected {expected_size} \
+             bytes for type '{global_ty}', but the initializer is {} bytes",
+            init.len()
+        );
+        diagnostics
+            .diagnostic(miden_diagnostics::Severity::Error)
+            .with_message(message.clone())
+            .emit();
+        return Err(WasmError::Unexpected(message));
+    }
+    Ok(())
 }
 
 fn build_data_segments(
     translation: &ParsedModule,
     module_builder: &mut ModuleBuilder,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
 ) -> Result<(), WasmError> {
     for (data_segment_idx, data_segment) in &translation.data_segments {
         let data_segment_name =
             translation.module.name_section.data_segment_names[&data_segment_idx].clone();
-        let readonly = data_segment_name.as_str().contains(".rodata");
+        let readonly = (config.readonly_data_segment)(data_segment_name.as_str());
         let init = ConstantData::from(data_segment.data);
         let offset = data_segment.offset.as_i32(&translation.module, diagnostics)? as u32;
         let size = init.len() as u32;
@@ -209,3 +300,448 @@ fn build_data_segments(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module::types::NULL_REF;
+
+    #[test]
+    fn global_initializer_size_mismatch() {
+        let (diagnostics, emitter) = crate::test_utils::capture_diagnostics();
+        let global_name = Symbol::intern("__stack_pointer");
+        let init = ConstantData::from(1048576i32.to_le_bytes().to_vec());
+
+        check_global_initializer_size(global_name, &Type::I32, &init, &diagnostics)
+            .expect("expected initializer of matching size to be accepted");
+        assert!(!diagnostics.has_errors());
+
+        let err = check_global_initializer_size(global_name, &Type::I64, &init, &diagnostics)
+            .expect_err("expected initializer of mismatched size to be rejected");
+        assert!(matches!(err, WasmError::Unexpected(_)));
+        assert!(diagnostics.has_errors());
+        let captured = emitter.captured();
+        assert!(
+            captured.contains("Invalid initializer for global variable '__stack_pointer'"),
+            "unexpected diagnostics: {captured}"
+        );
+    }
+
+    #[test]
+    fn sign_extension_feature() {
+        let wat = r#"
+        (module
+            (func $sext (param i32) (result i32)
+                local.get 0
+                i32.extend8_s
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+
+        // Sign extension is enabled by default
+        let config = WasmTranslationConfig::default();
+        translate_module(&wasm, &config, &diagnostics)
+            .expect("expected sign extension to be accepted by default");
+
+        let config = WasmTranslationConfig {
+            features: wasmparser::WasmFeatures {
+                sign_extension: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let err = translate_module(&wasm, &config, &diagnostics)
+            .expect_err("expected sign extension to be rejected when disabled");
+        assert!(matches!(err, WasmError::InvalidWebAssembly { .. }), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn multi_value_results() {
+        let wat = r#"
+        (module
+            (func $swap (param i32 i32) (result i32 i32)
+                local.get 1
+                local.get 0
+            )
+            (func $swap_early (param i32 i32) (result i32 i32)
+                local.get 1
+                local.get 0
+                return
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+            .expect("expected multi-value results to be supported");
+
+        for name in ["swap", "swap_early"] {
+            let func = module.function(miden_hir::Ident::from(name)).unwrap();
+            assert_eq!(func.signature.results.len(), 2);
+            let rets = func
+                .dfg
+                .blocks()
+                .flat_map(|(_, block)| block.insts())
+                .filter(|inst| func.dfg[*inst].opcode() == miden_hir::Opcode::Ret)
+                .collect::<Vec<_>>();
+            assert_eq!(rets.len(), 1, "expected a single return in {name}");
+            let args = func.dfg.inst_args(rets[0]);
+            assert_eq!(args.len(), 2);
+            assert!(args.iter().all(|arg| func.dfg.value_type(*arg) == &Type::I32));
+        }
+    }
+
+    #[test]
+    fn readonly_data_segments() {
+        let wat = r#"
+        (module
+            (memory 1)
+            (data $my.rodata.table (i32.const 0) "\01\02\03\04")
+            (data $constants (i32.const 1024) "\05\06\07\08")
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let readonly_offsets = |config: &WasmTranslationConfig| {
+            let module =
+                translate_module(&wasm, config, &diagnostics).expect("failed to translate module");
+            module
+                .segments()
+                .iter()
+                .map(|segment| (segment.offset(), segment.is_readonly()))
+                .collect::<Vec<_>>()
+        };
+
+        // By default, only segments with `.rodata` in their name are read-only
+        let config = WasmTranslationConfig::default();
+        assert_eq!(readonly_offsets(&config), vec![(0, true), (1024, false)]);
+
+        // A custom classification overrides the default
+        let config = WasmTranslationConfig {
+            readonly_data_segment: |name| name == "constants",
+            ..Default::default()
+        };
+        assert_eq!(readonly_offsets(&config), vec![(0, false), (1024, true)]);
+    }
+
+    #[test]
+    fn reference_typed_globals() {
+        let wat = r#"
+        (module
+            (global $null_func funcref (ref.null func))
+            (global $null_extern (mut externref) (ref.null extern))
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+            .expect("failed to translate module");
+
+        // References are represented as felts, initialized to null
+        for name in ["null_func", "null_extern"] {
+            let global = module
+                .find_global(miden_hir::Ident::with_empty_span(Symbol::intern(name)))
+                .unwrap_or_else(|| panic!("expected global '{name}' to be declared"));
+            assert_eq!(global.ty, Type::Felt);
+            let init = global.initializer().expect("expected global to be initialized");
+            assert_eq!(module.globals().get_constant(init).as_slice(), &NULL_REF.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn globals_without_memory() {
+        let wat = r#"
+        (module
+            (global $counter (mut i32) (i32.const 7))
+            (global $total i64 (i64.const 42))
+            (func $incr (result i32)
+                global.get $counter
+                i32.const 1
+                i32.add
+                global.set $counter
+                global.get $counter
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let (diagnostics, emitter) = crate::test_utils::capture_diagnostics();
+        let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+            .expect("expected module without memory to be translated");
+
+        let captured = emitter.captured();
+        assert!(captured.is_empty(), "unexpected diagnostics: {captured}");
+        assert!(module.segments().is_empty());
+
+        // The globals are laid out in declaration order, with their initializers
+        let globals = module
+            .globals()
+            .iter()
+            .map(|global| {
+                let init = global.initializer().expect("expected global to be initialized");
+                (global.ty.clone(), module.globals().get_constant(init).as_slice().to_vec())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            globals,
+            vec![
+                (Type::I32, 7i32.to_le_bytes().to_vec()),
+                (Type::I64, 42i64.to_le_bytes().to_vec())
+            ]
+        );
+
+        // The function accesses the global via its symbol
+        let func = module.function(miden_hir::Ident::from("incr")).unwrap();
+        let opcodes = func
+            .dfg
+            .blocks()
+            .flat_map(|(_, block)| block.insts())
+            .map(|inst| func.dfg[inst].opcode())
+            .collect::<Vec<_>>();
+        assert!(opcodes.contains(&miden_hir::Opcode::GlobalValue), "{opcodes:?}");
+        assert!(opcodes.contains(&miden_hir::Opcode::Store), "{opcodes:?}");
+    }
+
+    #[test]
+    fn global_set_immutable() {
+        let wat = r#"
+        (module
+            (global $total i32 (i32.const 42))
+            (func $reset
+                i32.const 0
+                global.set $total
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let result = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics);
+        assert!(
+            matches!(result, Err(WasmError::InvalidWebAssembly { .. })),
+            "expected setting an immutable global to be rejected"
+        );
+    }
+
+    #[test]
+    fn local_names() {
+        let wat = r#"
+        (module
+            (func $add_one (param $x i32) (result i32)
+                (local $sum i32)
+                local.get $x
+                i32.const 1
+                i32.add
+                local.set $sum
+                local.get $sum
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+            .expect("failed to translate module");
+
+        let func = module.function(miden_hir::Ident::from("add_one")).unwrap();
+        let param = func.dfg.block_params(func.dfg.entry_block())[0];
+        assert_eq!(func.dfg.value_name(param), Some(Symbol::intern("x")));
+        let sum = func
+            .dfg
+            .value_names
+            .iter()
+            .find_map(|(value, name)| (name.as_str() == "sum").then_some(*value))
+            .expect("expected a value named after the local 'sum'");
+        assert_eq!(func.dfg.value_type(sum), &Type::I32);
+        assert_ne!(sum, param);
+    }
+
+    #[test]
+    fn local_names_shared_value() {
+        let wat = r#"
+        (module
+            (func $copy (param $x i32) (result i32)
+                (local $y i32)
+                local.get $x
+                local.set $y
+                local.get $y
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+            .expect("failed to translate module");
+
+        // The parameter is also assigned to `y`, but keeps the name it was given first
+        let func = module.function(miden_hir::Ident::from("copy")).unwrap();
+        let param = func.dfg.block_params(func.dfg.entry_block())[0];
+        assert_eq!(func.dfg.value_name(param), Some(Symbol::intern("x")));
+        assert!(func.dfg.value_names.values().all(|name| name.as_str() != "y"));
+    }
+
+    #[test]
+    fn global_initialized_from_imported_global() {
+        let (diagnostics, emitter) = crate::test_utils::capture_diagnostics();
+        let wat = r#"
+        (module
+            (import "env" "base" (global $base i32))
+            (global $offset i32 (global.get $base))
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+
+        let err = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+            .expect_err("expected global initialized from an imported global to be rejected");
+        assert!(matches!(err, WasmError::Unsupported(_)), "unexpected error: {err}");
+        let captured = emitter.captured();
+        assert!(
+            captured.contains(
+                "Unsupported initializer for global variable 'offset': `global.get` of the \
+                 imported global 'env.base' cannot be evaluated at compile time"
+            ),
+            "unexpected diagnostics: {captured}"
+        );
+    }
+
+    #[test]
+    fn component_import_missing_digest() {
+        let wat = r#"
+        (module
+            (import "miden:tx_kernel/account" "get_id" (func $get_id (result f64)))
+            (func $test_wrapper
+                call $get_id
+                drop
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let err =
+            translate_module_as_component(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+                .expect_err("expected an import without a MAST root hash to be rejected");
+        let WasmError::MissingImportDigest(function) = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(function.module.as_str(), "miden:tx_kernel/account");
+        assert_eq!(function.function.as_str(), "get_id");
+        assert!(diagnostics.has_errors());
+    }
+
+    #[test]
+    fn custom_intrinsics_module() {
+        let wat = r#"
+        (module
+            (import "my:custom/intrinsics" "fold" (func $fold (param i32) (result i32)))
+            (func $test_wrapper (param i32) (result i32)
+                local.get 0
+                call $fold
+            )
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+
+        // By default, the import is expected to be a Miden SDK function with a MAST root hash
+        let err =
+            translate_module_as_component(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+                .expect_err("expected an import from an unknown module to be rejected");
+        assert!(matches!(err, WasmError::MissingImportDigest(_)), "unexpected error: {err}");
+
+        // Imports from custom intrinsics modules are left to the consumer of the IR to lower
+        let config = WasmTranslationConfig {
+            intrinsics_modules: ["my:custom/intrinsics".into()].into_iter().collect(),
+            ..Default::default()
+        };
+        let component = translate_module_as_component(&wasm, &config, &diagnostics)
+            .expect("expected imports from a custom intrinsics module to be ignored");
+        assert!(component.imports().is_empty());
+    }
+
+    #[test]
+    fn module_name_qualifies_functions() {
+        use miden_hir::{CallInfo, FunctionIdent};
+
+        let wat = |name: &str| {
+            format!(
+                r#"
+        (module {name}
+            (func $f (result i32)
+                call $g
+            )
+            (func $g (result i32)
+                i32.const 1
+            )
+        )"#
+            )
+        };
+        let diagnostics = crate::test_utils::test_diagnostics();
+        // Returns the identifiers of the functions of the translated module, as well as the
+        // callee of `f`
+        let translate = |wat: String, config: &WasmTranslationConfig| {
+            let wasm = wat::parse_str(wat).unwrap();
+            let module = translate_module(&wasm, config, &diagnostics).unwrap();
+            let ids = module.functions().map(|f| f.id).collect::<Vec<_>>();
+            let f = module.function("f".into()).unwrap();
+            let callee = f
+                .dfg
+                .block(f.dfg.entry_block())
+                .insts()
+                .find_map(|inst| match f.dfg.analyze_call(inst) {
+                    CallInfo::Direct(callee, _) => Some(callee),
+                    _ => None,
+                })
+                .expect("expected f to call g");
+            (ids, callee)
+        };
+        let id = |id: &str| id.parse::<FunctionIdent>().unwrap();
+
+        // Without a name section, the source name is used
+        let config = WasmTranslationConfig {
+            source_name: "a".into(),
+            ..Default::default()
+        };
+        let (a_ids, a_callee) = translate(wat(""), &config);
+        assert_eq!(a_ids, [id("a::f"), id("a::g")]);
+        assert_eq!(a_callee, id("a::g"));
+
+        // The name section takes precedence over the source name
+        let (b_ids, b_callee) = translate(wat("$b"), &config);
+        assert_eq!(b_ids, [id("b::f"), id("b::g")]);
+        assert_eq!(b_callee, id("b::g"));
+
+        // An override takes precedence over both
+        let config = WasmTranslationConfig {
+            override_name: Some("c".into()),
+            ..config
+        };
+        let (c_ids, c_callee) = translate(wat("$b"), &config);
+        assert_eq!(c_ids, [id("c::f"), id("c::g")]);
+        assert_eq!(c_callee, id("c::g"));
+
+        // Functions with the same name in different modules are distinct
+        assert!(a_ids.iter().all(|a| !b_ids.contains(a) && !c_ids.contains(a)));
+    }
+
+    #[test]
+    fn start_function_entrypoint() {
+        let wat = r#"
+        (module
+            (global $initialized (mut i32) (i32.const 0))
+            (func $init
+                i32.const 1
+                global.set $initialized
+            )
+            (func $get (export "get") (result i32)
+                global.get $initialized
+            )
+            (start $init)
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let diagnostics = crate::test_utils::test_diagnostics();
+        let module = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+            .expect("failed to translate module");
+        let entrypoint =
+            module.entrypoint().expect("expected the start function to be the entrypoint");
+        assert_eq!(entrypoint.function.as_str(), "init");
+
+        // An imported start function cannot be made the entrypoint
+        let wat = r#"
+        (module
+            (import "env" "init" (func $init))
+            (start $init)
+        )"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let err = translate_module(&wasm, &WasmTranslationConfig::default(), &diagnostics)
+            .expect_err("expected an imported start function to be rejected");
+        assert!(matches!(err, WasmError::Unsupported(_)), "unexpected error: {err}");
+    }
+}
@@ -6,11 +6,14 @@
 //! Based on Cranelift's Wasm -> CLIF translator v11.0.0
 
 use miden_diagnostics::SourceSpan;
-use miden_hir::{Block, Inst, InstBuilder, Signature, Value};
+use miden_hir::{Block, Inst, InstBuilder, Signature, Symbol, Value};
 use miden_hir_type::Type;
 
 use super::function_builder_ext::FunctionBuilderExt;
-use crate::module::types::BlockType;
+use crate::module::{
+    types::{BlockType, FuncIndex},
+    Module,
+};
 
 /// Information about the presence of an associated `else` for an `if`, or the
 /// lack thereof.
@@ -230,6 +233,11 @@ pub struct FuncTranslationState {
     /// Is the current translation state still reachable? This is false when translating operators
     /// like End, Return, or Unreachable.
     pub(crate) reachable: bool,
+    /// The function being translated, used to look up the names of its locals
+    func_index: Option<FuncIndex>,
+    /// If the function being translated tail calls itself, this is the block which each such
+    /// call branches back to, i.e. the start of the function body, following its entry block
+    pub(crate) tail_call_header: Option<Block>,
 }
 
 impl FuncTranslationState {
@@ -239,6 +247,8 @@ impl FuncTranslationState {
             stack: Vec::new(),
             control_stack: Vec::new(),
             reachable: true,
+            func_index: None,
+            tail_call_header: None,
         }
     }
 
@@ -252,11 +262,32 @@ impl FuncTranslationState {
     ///
     /// This resets the state to containing only a single block representing the whole function.
     /// The exit block is the last block in the function which will contain the return instruction.
-    pub(crate) fn initialize(&mut self, sig: &Signature, exit_block: Block) {
+    ///
+    /// If the function tail calls itself, `tail_call_header` is the block those calls branch to.
+    pub(crate) fn initialize(
+        &mut self,
+        sig: &Signature,
+        exit_block: Block,
+        func_index: FuncIndex,
+        tail_call_header: Option<Block>,
+    ) {
         self.clear();
+        self.func_index = Some(func_index);
+        self.tail_call_header = tail_call_header;
         self.push_block(exit_block, 0, sig.results().len());
     }
 
+    /// Returns the index of the function being translated
+    pub(crate) fn func_index(&self) -> Option<FuncIndex> {
+        self.func_index
+    }
+
+    /// Returns the name of the local variable `local` of the function being translated, if it has
+    /// one
+    pub(crate) fn local_name(&self, module: &Module, local: u32) -> Option<Symbol> {
+        self.func_index.and_then(|func| module.local_name(func, local))
+    }
+
     /// Push a value.
     pub(crate) fn push1(&mut self, val: Value) {
         self.stack.push(val);
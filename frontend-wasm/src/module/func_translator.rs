@@ -8,7 +8,7 @@
 
 use miden_diagnostics::{DiagnosticsHandler, SourceSpan};
 use miden_hir::{cranelift_entity::EntityRef, Block, InstBuilder, ModuleFunctionBuilder};
-use wasmparser::{BinaryReader, FuncValidator, FunctionBody, WasmModuleResources};
+use wasmparser::{BinaryReader, FuncValidator, FunctionBody, Operator, WasmModuleResources};
 
 use super::{module_translation_state::ModuleTranslationState, Module};
 use crate::{
@@ -17,7 +17,7 @@ use crate::{
     module::{
         func_translation_state::FuncTranslationState,
         function_builder_ext::{FunctionBuilderContext, FunctionBuilderExt},
-        types::{convert_valtype, ir_type, ModuleTypes},
+        types::{convert_valtype, ir_type, FuncIndex, ModuleTypes},
     },
     ssa::Variable,
     translation_utils::emit_zero,
@@ -43,8 +43,12 @@ impl FuncTranslator {
     }
 
     /// Translate a binary WebAssembly function from a `FunctionBody`.
+    ///
+    /// If the name section names the locals of `func_index`, the values bound to them are given
+    /// those names for debugging purposes.
     pub fn translate_body(
         &mut self,
+        func_index: FuncIndex,
         body: &FunctionBody<'_>,
         mod_func_builder: &mut ModuleFunctionBuilder,
         module_state: &mut ModuleTranslationState,
@@ -59,13 +63,27 @@ impl FuncTranslator {
         let entry_block = builder.current_block();
         builder.seal_block(entry_block); // Declare all predecessors known.
 
-        let num_params = declare_parameters(&mut builder, entry_block);
+        let num_params = declare_parameters(&mut builder, entry_block, module, func_index);
+
+        // A function which tail calls itself is translated as a loop, with each tail call
+        // reassigning the parameters, and branching back to the start of the body. The locals are
+        // initialized in the loop header, so that they are reset on each iteration, as they would
+        // be by a call.
+        let tail_call_header = if has_self_tail_call(body, func_index)? {
+            let header = builder.create_block();
+            builder.ins().br(header, &[], SourceSpan::default());
+            builder.switch_to_block(header);
+            Some(header)
+        } else {
+            None
+        };
 
         // Set up the translation state with a single pushed control block representing the whole
         // function and its return values.
         let exit_block = builder.create_block();
         builder.append_block_params_for_function_returns(exit_block);
-        self.state.initialize(&builder.signature(), exit_block);
+        self.state
+            .initialize(&builder.signature(), exit_block, func_index, tail_call_header);
 
         parse_local_decls(&mut reader, &mut builder, num_params, func_validator)?;
         parse_function_body(
@@ -79,15 +97,38 @@ impl FuncTranslator {
             func_validator,
         )?;
 
+        // All of the tail calls, and thus predecessors of the loop header, are now known
+        if let Some(header) = tail_call_header {
+            builder.seal_block(header);
+        }
+
         builder.finalize();
         Ok(())
     }
 }
 
+/// Returns true if the function body contains a `return_call` of the function itself
+fn has_self_tail_call(body: &FunctionBody<'_>, func_index: FuncIndex) -> WasmResult<bool> {
+    let mut reader = body.get_operators_reader()?;
+    while !reader.eof() {
+        if let Operator::ReturnCall { function_index } = reader.read()? {
+            if FuncIndex::from_u32(function_index) == func_index {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
 /// Declare local variables for the signature parameters that correspond to WebAssembly locals.
 ///
 /// Return the number of local variables declared.
-fn declare_parameters(builder: &mut FunctionBuilderExt, entry_block: Block) -> usize {
+fn declare_parameters(
+    builder: &mut FunctionBuilderExt,
+    entry_block: Block,
+    module: &Module,
+    func_index: FuncIndex,
+) -> usize {
     let sig_len = builder.signature().params().len();
     let mut next_local = 0;
     for i in 0..sig_len {
@@ -97,6 +138,9 @@ fn declare_parameters(builder: &mut FunctionBuilderExt, entry_block: Block) -> u
         next_local += 1;
 
         let param_value = builder.block_params(entry_block)[i];
+        if let Some(name) = module.local_name(func_index, i as u32) {
+            builder.data_flow_graph_mut().set_value_name(param_value, name);
+        }
         builder.def_var(local, param_value);
     }
     next_local
@@ -188,7 +232,7 @@ fn parse_function_body(
     // generate a return instruction that doesn't match the signature.
     if state.reachable {
         if !builder.is_unreachable() {
-            builder.ins().ret(state.stack.first().cloned(), SourceSpan::default());
+            builder.ins().ret_many(&state.stack, SourceSpan::default());
         }
     }
 
@@ -284,6 +284,16 @@ impl Module {
             .unwrap_or(Symbol::intern(format!("global{}", index.as_u32()).as_str()))
     }
 
+    /// Returns the name of the local variable `local` of the function `func`, if the name section
+    /// gives it one
+    pub fn local_name(&self, func: FuncIndex, local: u32) -> Option<Symbol> {
+        self.name_section
+            .locals_names
+            .get(&func)
+            .and_then(|names| names.get(&local))
+            .copied()
+    }
+
     /// Returns the type of an item based on its index
     pub fn type_of(&self, index: EntityIndex) -> EntityType {
         match index {
@@ -178,7 +178,7 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                 // never get here.
                 unreachable!();
             }
-            Payload::GlobalSection(globals) => self.global_section(globals)?,
+            Payload::GlobalSection(globals) => self.global_section(globals, diagnostics)?,
             Payload::ExportSection(exports) => self.export_section(exports)?,
             Payload::StartSection { func, range } => self.start_section(func, range)?,
             Payload::ElementSection(elements) => self.element_section(elements)?,
@@ -345,13 +345,15 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
     ) -> Result<(), WasmError> {
         self.validator.memory_section(&memories)?;
         let cnt = usize::try_from(memories.count()).unwrap();
-        assert_eq!(cnt, 1, "only one memory per module is supported");
+        // An empty memory section is equivalent to a module without linear memory
+        assert!(cnt <= 1, "only one memory per module is supported");
         Ok(())
     }
 
     fn global_section(
         &mut self,
         globals: wasmparser::GlobalSectionReader<'data>,
+        diagnostics: &DiagnosticsHandler,
     ) -> Result<(), WasmError> {
         self.validator.global_section(&globals)?;
         let cnt = usize::try_from(globals.count()).unwrap();
@@ -370,11 +372,14 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                 Operator::GlobalGet { global_index } => {
                     GlobalInit::GetGlobal(GlobalIndex::from_u32(global_index))
                 }
+                Operator::RefNull { .. } => GlobalInit::RefNullConst,
                 s => {
-                    return Err(WasmError::Unsupported(format!(
-                        "unsupported init expr in global section: {:?}",
-                        s
-                    )));
+                    let global_idx = self.result.module.globals.len();
+                    unsupported_diag!(
+                        diagnostics,
+                        "Unsupported initializer for global variable 'gv{global_idx}': {s:?} \
+                         cannot be evaluated at compile time"
+                    );
                 }
             };
             let ty = convert_global_type(&ty);
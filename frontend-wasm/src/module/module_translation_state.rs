@@ -1,3 +1,5 @@
+use alloc::{borrow::Cow, collections::BTreeMap};
+
 use miden_core::crypto::hash::RpoDigest;
 use miden_diagnostics::DiagnosticsHandler;
 use miden_hir::{AbiParam, CallConv, DataFlowGraph, FunctionIdent, Ident, Linkage, Signature};
@@ -18,6 +20,8 @@ pub struct ModuleTranslationState {
     functions: FxHashMap<FuncIndex, (FunctionIdent, Signature)>,
     /// Parsed MAST root hash for imported functions for Miden SDK
     digests: FxHashMap<FunctionIdent, RpoDigest>,
+    /// Imported functions which are known to abort execution, and the error code to raise, if any
+    aborts: FxHashMap<FuncIndex, Option<u32>>,
     /// Number of imported or aliased functions in the module.
     pub num_imported_funcs: usize,
     // stable_imported_miden_abi_functions: FxHashMap<FunctionIdent, String>,
@@ -89,6 +93,7 @@ impl ModuleTranslationState {
         Self {
             functions,
             digests,
+            aborts: FxHashMap::default(),
             num_imported_funcs: module.num_imported_funcs,
         }
     }
@@ -104,6 +109,33 @@ impl ModuleTranslationState {
         self.digests.get(func_id)
     }
 
+    /// Record which of the function imports of `module` are known to abort execution, given the
+    /// module and function names of such functions, and the error codes they raise, in
+    /// `abort_imports`.
+    pub fn recognize_abort_imports(
+        &mut self,
+        module: &Module,
+        abort_imports: &BTreeMap<Cow<'static, str>, BTreeMap<Cow<'static, str>, Option<u32>>>,
+    ) {
+        for import in module.imports.iter() {
+            let EntityIndex::Function(func_idx) = import.index else {
+                continue;
+            };
+            let error_code = abort_imports
+                .get(import.module.as_str())
+                .and_then(|functions| functions.get(import.field.as_str()));
+            if let Some(error_code) = error_code {
+                self.aborts.insert(func_idx, *error_code);
+            }
+        }
+    }
+
+    /// Returns `Some` if the function `index` is an import known to abort execution, along with
+    /// the error code it raises, if any.
+    pub fn abort_code(&self, index: FuncIndex) -> Option<Option<u32>> {
+        self.aborts.get(&index).copied()
+    }
+
     /// Get the `FunctionIdent` that should be used to make a direct call to function
     /// `index`.
     ///
@@ -312,6 +312,8 @@ pub enum GlobalInit {
     V128Const(u128),
     /// A `global.get` of another global.
     GetGlobal(GlobalIndex),
+    /// A `ref.null`.
+    RefNullConst,
 }
 
 impl GlobalInit {
@@ -327,6 +329,7 @@ impl GlobalInit {
             GlobalInit::F32Const(x) => x.to_le_bytes().to_vec(),
             GlobalInit::F64Const(x) => x.to_le_bytes().to_vec(),
             GlobalInit::V128Const(x) => x.to_le_bytes().to_vec(),
+            GlobalInit::RefNullConst => NULL_REF.to_le_bytes().to_vec(),
             GlobalInit::GetGlobal(global_idx) => {
                 let global_init = module.try_global_initializer(global_idx, diagnostics)?;
                 global_init.to_le_bytes(module, diagnostics)?
@@ -572,7 +575,13 @@ pub fn ir_func_type(ty: &WasmFuncType) -> WasmResult<hir::FunctionType> {
     })
 }
 
+/// The representation of a null reference, see [ir_type]
+pub const NULL_REF: u32 = 0;
+
 /// Converts a Wasm type into a Miden IR type
+///
+/// References, i.e. `funcref` and `externref`, are opaque to Miden IR, and are represented as a
+/// field element, with [NULL_REF] representing the null reference.
 pub fn ir_type(ty: WasmType) -> WasmResult<hir::Type> {
     Ok(match ty {
         WasmType::I32 => hir::Type::I32,
@@ -582,9 +591,7 @@ pub fn ir_type(ty: WasmType) -> WasmResult<hir::Type> {
         WasmType::V128 => {
             return Err(WasmError::Unsupported("V128 type is not supported".to_string()));
         }
-        WasmType::Ref(_) => {
-            return Err(WasmError::Unsupported("Ref type is not supported".to_string()));
-        }
+        WasmType::Ref(_) => hir::Type::Felt,
     })
 }
 
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use miden_diagnostics::term::termcolor::ColorChoice;
+use miden_diagnostics::CaptureEmitter;
 use miden_diagnostics::CodeMap;
 use miden_diagnostics::DiagnosticsConfig;
 use miden_diagnostics::DiagnosticsHandler;
@@ -15,8 +16,19 @@ pub fn default_emitter(verbosity: Verbosity, color: ColorChoice) -> Arc<dyn Emit
 }
 
 pub fn test_diagnostics() -> DiagnosticsHandler {
+    diagnostics_with_emitter(default_emitter(Verbosity::Debug, ColorChoice::Auto))
+}
+
+/// Like [test_diagnostics], but the diagnostics are captured, so that tests can check what was
+/// reported
+pub fn capture_diagnostics() -> (DiagnosticsHandler, Arc<CaptureEmitter>) {
+    let emitter = Arc::new(CaptureEmitter::default());
+    (diagnostics_with_emitter(emitter.clone()), emitter)
+}
+
+fn diagnostics_with_emitter(emitter: Arc<dyn Emitter>) -> DiagnosticsHandler {
     let codemap = Arc::new(CodeMap::new());
-    let diagnostics = DiagnosticsHandler::new(
+    DiagnosticsHandler::new(
         DiagnosticsConfig {
             verbosity: Verbosity::Debug,
             warnings_as_errors: false,
@@ -24,7 +36,6 @@ pub fn test_diagnostics() -> DiagnosticsHandler {
             display: Default::default(),
         },
         codemap,
-        default_emitter(Verbosity::Debug, ColorChoice::Auto),
-    );
-    diagnostics
+        emitter,
+    )
 }
@@ -691,6 +691,32 @@ impl DependencyGraph {
         edges.push(edge);
     }
 
+    /// Add a control dependency from instruction `a` to instruction `b`, i.e. `b` must be executed
+    /// before `a`, even though `a` does not use any of the results of `b`.
+    ///
+    /// This is used to preserve the relative order of instructions whose effects are observable by
+    /// one another, e.g. a store and a later load from the same address. If `b` produces results,
+    /// the dependency is added on its first result instead, as such instructions are only ever
+    /// materialized via their results. The result nodes of `b` must already be in the graph.
+    pub fn add_control_dependency(&mut self, a: NodeId, b: NodeId) {
+        assert!(
+            a.is_instruction() && b.is_instruction(),
+            "invalid control dependency {} -> {}: control dependencies may only be added between \
+             instructions",
+            a.expand(),
+            b.expand()
+        );
+        let first_result = self
+            .predecessors(b)
+            .map(|pred| pred.dependent)
+            .filter(|node| node.is_result())
+            .min_by_key(|node| match node.expand() {
+                Node::Result { index, .. } => index,
+                _ => unreachable!(),
+            });
+        self.add_dependency(a, first_result.unwrap_or(b));
+    }
+
     /// Get a [Dependency] corresponding to the edge from `from` to `to`
     ///
     /// This will panic if there is no edge between the two nodes given.
@@ -1004,7 +1030,49 @@ impl DependencyGraph {
         }
         self.add_dependency(dependent_id, dependency_id);
     }
+
+    /// Render this graph in Graphviz DOT format, for use when debugging instruction scheduling.
+    ///
+    /// Each node is labeled with the instruction, argument, or value it represents, and edges
+    /// pointing to a value are labeled with that value, i.e. the value being used by the dependent.
+    pub fn to_dot(&self) -> String {
+        use core::fmt::Write;
+
+        let mut dot = String::from("digraph DependencyGraph {\n");
+        for node in self.nodes.iter().copied() {
+            write_dot_node(&mut dot, node);
+        }
+        for node in self.nodes.iter().copied() {
+            for Dependency {
+                dependent,
+                dependency,
+            } in self.successors(node)
+            {
+                let label = dependency
+                    .expand()
+                    .as_value()
+                    .map(|value| format!(" [label=\"{value}\"]"))
+                    .unwrap_or_default();
+                writeln!(dot, "    \"{dependent}\" -> \"{dependency}\"{label};").unwrap();
+            }
+        }
+        dot.push('}');
+        dot
+    }
 }
+
+/// Writes the DOT declaration for `node` to `dot`, drawing instructions as boxes, so that they
+/// stand out from their arguments and results.
+pub(crate) fn write_dot_node(dot: &mut String, node: NodeId) {
+    use core::fmt::Write;
+
+    if node.is_instruction() {
+        writeln!(dot, "    \"{node}\" [shape=box];").unwrap();
+    } else {
+        writeln!(dot, "    \"{node}\";").unwrap();
+    }
+}
+
 impl fmt::Debug for DependencyGraph {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("DependencyGraph")
@@ -1470,4 +1538,29 @@ mod tests {
         assert_eq!(indices.get(inst0_node), Some(1));
         assert_eq!(indices.get(v0_node), Some(0));
     }
+
+    /// See [simple_dependency_graph] for details on the input dependency graph.
+    #[test]
+    fn dependency_graph_to_dot() {
+        let graph = simple_dependency_graph();
+
+        let dot = graph.to_dot();
+
+        assert!(dot.starts_with("digraph DependencyGraph {\n"));
+        assert!(dot.ends_with('}'));
+        // Instructions, arguments and results are all represented
+        assert!(dot.contains("    \"inst1\" [shape=box];\n"));
+        assert!(dot.contains("    \"arg(1 of inst1)\";\n"));
+        assert!(dot.contains("    \"result(v2)\";\n"));
+        assert!(dot.contains("    \"v0\";\n"));
+        // Edges to values are labeled with the value used
+        assert!(dot.contains("    \"arg(1 of inst1)\" -> \"v0\" [label=\"v0\"];\n"));
+        assert!(
+            dot.contains("    \"block_arg(0 to 1 of inst2)\" -> \"result(v1)\" [label=\"v1\"];\n")
+        );
+        // Other edges are unlabeled
+        assert!(dot.contains("    \"inst1\" -> \"arg(0 of inst1)\";\n"));
+        assert!(dot.contains("    \"result(v2)\" -> \"inst1\";\n"));
+        assert_eq!(dot.matches(" -> ").count(), 17);
+    }
 }
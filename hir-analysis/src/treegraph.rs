@@ -589,6 +589,39 @@ impl TreeGraph {
             Ok(output)
         }
     }
+
+    /// Render this graph in Graphviz DOT format, for use when debugging instruction scheduling.
+    ///
+    /// Each node represents a tree, and is labeled with the root of that tree. Each edge is
+    /// labeled with the values used by the dependencies it carries.
+    pub fn to_dot(&self) -> String {
+        use core::fmt::Write;
+
+        let mut dot = String::from("digraph TreeGraph {\n");
+        for node in self.nodes.iter().copied() {
+            write_dot_node(&mut dot, node);
+        }
+        for (
+            EdgeId {
+                predecessor,
+                successor,
+            },
+            edges,
+        ) in self.edges.iter()
+        {
+            let label = edges
+                .iter()
+                .filter_map(|edge| edge.successor.expand().as_value())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(dot, "    \"{predecessor}\" -> \"{successor}\" [label=\"{label}\"];").unwrap();
+        }
+        dot.push('}');
+        dot
+    }
 }
 impl From<DependencyGraph> for TreeGraph {
     fn from(mut depgraph: DependencyGraph) -> Self {
@@ -658,6 +691,7 @@ impl fmt::Debug for TreeGraph {
         f.debug_struct("TreeGraph")
             .field("nodes", &DebugNodes(self))
             .field("edges", &DebugEdges(self))
+            .field("trees", &DebugTrees(self))
             .finish()
     }
 }
@@ -684,6 +718,25 @@ impl<'a> fmt::Debug for DebugEdges<'a> {
     }
 }
 
+/// Renders each tree as its root, followed by every node of the dependency graph which was
+/// condensed into that tree, including the root itself
+struct DebugTrees<'a>(&'a TreeGraph);
+impl<'a> fmt::Debug for DebugTrees<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut trees = f.debug_map();
+        for root in self.0.nodes.iter().copied() {
+            let members = self
+                .0
+                .condensed
+                .iter()
+                .filter_map(|(node, tree)| (*tree == root).then_some(*node))
+                .collect::<Vec<_>>();
+            trees.entry(&format_args!("{root}"), &members);
+        }
+        trees.finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use miden_hir as hir;
@@ -764,4 +817,49 @@ mod tests {
         // Results are scheduled before instructions which produce them
         assert!(treegraph.is_scheduled_before(v2_node, inst1_node));
     }
+
+    /// See [treegraph_construction] for details on the expected treegraph.
+    #[test]
+    fn treegraph_to_dot() {
+        let graph = simple_dependency_graph();
+        let treegraph = TreeGraph::from(graph);
+
+        let dot = treegraph.to_dot();
+
+        assert!(dot.starts_with("digraph TreeGraph {\n"));
+        assert!(dot.ends_with('}'));
+        // Only tree roots are represented as nodes
+        assert!(dot.contains("    \"inst2\" [shape=box];\n"));
+        assert!(dot.contains("    \"result(v1)\";\n"));
+        assert!(dot.contains("    \"result(v3)\";\n"));
+        assert!(dot.contains("    \"v0\";\n"));
+        assert!(!dot.contains("\"inst1\""));
+        // Edges are labeled with the values used by the dependencies they carry
+        assert!(dot.contains("    \"inst2\" -> \"result(v1)\" [label=\"v1\"];\n"));
+        assert!(dot.contains("    \"inst2\" -> \"v0\" [label=\"v0\"];\n"));
+        assert!(dot.contains("    \"result(v1)\" -> \"v0\" [label=\"v0\"];\n"));
+        assert_eq!(dot.matches(" -> ").count(), 3);
+    }
+
+    /// See [treegraph_construction] for details on the expected treegraph.
+    #[test]
+    fn treegraph_debug_trees() {
+        let graph = simple_dependency_graph();
+        let treegraph = TreeGraph::from(graph);
+
+        let debug = format!("{treegraph:?}");
+
+        // Each tree lists the nodes condensed into it, in node order
+        assert!(debug.contains("v0: [v0]"), "{debug}");
+        assert!(debug.contains("result(v1): [arg(0 of inst0), inst0, result(v1)]"), "{debug}");
+        assert!(debug.contains("result(v3): [inst3, result(v3)]"), "{debug}");
+        assert!(
+            debug.contains(
+                "inst2: [arg(0 of inst1), arg(1 of inst1), arg(0 of inst2), block_arg(0 to 0 of \
+                 inst2), block_arg(0 to 1 of inst2), conditional_block_arg(1 to 1 of inst2), \
+                 inst1, inst2, result(v2)]"
+            ),
+            "{debug}"
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use cranelift_entity::packed_option::ReservedValue;
 use miden_diagnostics::{DiagnosticsHandler, Severity, Spanned};
 use miden_hir::*;
 use rustc_hash::FxHashSet;
@@ -122,6 +123,8 @@ impl<'a> Rule<BlockData> for DefsDominateUses<'a> {
 ///   the function body (i.e. it is not valid to reference detached blocks)
 /// * A multi-way branch instruction must have at least one successor
 /// * A multi-way branch instruction must not specify the same block as a successor multiple times.
+/// * A switch instruction must not have more than one arm for the same discriminant, and must have
+///   a default destination
 ///
 /// This rule does not perform type checking, or verify use/def dominance.
 pub struct BlockValidator<'a> {
@@ -158,6 +161,28 @@ impl<'a> Rule<BlockData> for BlockValidator<'a> {
             );
         }
 
+        // Verify that there are no terminator instructions in any other position than last, i.e.
+        // that there is no unreachable code following the terminator of this block, which would
+        // indicate a bug in the frontend which produced it
+        let mut insts = block_data.insts.iter();
+        if let Some(node) = insts.by_ref().find(|node| node.opcode().is_terminator()) {
+            let op = node.opcode();
+            let num_unreachable = insts.count();
+            if num_unreachable > 0 {
+                invalid_block!(
+                    diagnostics,
+                    id,
+                    self.span,
+                    "terminator found in middle of block",
+                    format!(
+                        "A block may only have a terminator instruction as the last instruction \
+                         in the block, but {id} uses {op} before the end of the block, followed \
+                         by {num_unreachable} unreachable instruction(s)"
+                    )
+                );
+            }
+        }
+
         let terminator = terminator.unwrap();
         let op = terminator.opcode();
         if !op.is_terminator() {
@@ -172,6 +197,39 @@ impl<'a> Rule<BlockData> for BlockValidator<'a> {
                 )
             );
         }
+        if let Instruction::Switch(Switch {
+            ref arms, default, ..
+        }) = *terminator.as_ref()
+        {
+            let mut discriminants = SmallVec::<[u32; 4]>::default();
+            for (discriminant, _) in arms.iter() {
+                if discriminants.contains(discriminant) {
+                    invalid_instruction!(
+                        diagnostics,
+                        terminator.key,
+                        terminator.span(),
+                        "invalid {op} instruction",
+                        format!(
+                            "Each discriminant of a switch may only be handled by a single arm, \
+                             but the switch in {id} has more than one arm for {discriminant}"
+                        )
+                    );
+                }
+                discriminants.push(*discriminant);
+            }
+
+            if default.is_reserved_value() {
+                invalid_instruction!(
+                    diagnostics,
+                    terminator.key,
+                    terminator.span(),
+                    "incomplete {op} instruction",
+                    "A switch must have a default destination for discriminants which are not \
+                     handled by any of its arms, but none was given."
+                );
+            }
+        }
+
         match terminator.analyze_branch(&self.dfg.value_lists) {
             BranchInfo::SingleDest(destination, _) => {
                 if !self.dfg.is_block_linked(destination) {
@@ -236,23 +294,6 @@ impl<'a> Rule<BlockData> for BlockValidator<'a> {
             BranchInfo::NotABranch => (),
         }
 
-        // Verify that there are no terminator instructions in any other position than last
-        for node in block_data.insts.iter() {
-            let op = node.opcode();
-            if op.is_terminator() && node.key != terminator.key {
-                invalid_block!(
-                    diagnostics,
-                    id,
-                    self.span,
-                    "terminator found in middle of block",
-                    format!(
-                        "A block may only have a terminator instruction as the last instruction \
-                         in the block, but {id} uses {op} before the end of the block"
-                    )
-                );
-            }
-        }
-
         Ok(())
     }
 }
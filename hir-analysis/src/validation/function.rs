@@ -9,6 +9,7 @@ use crate::{ControlFlowGraph, DominatorTree};
 /// This validation rule ensures that function-local invariants are upheld:
 ///
 /// * A function may not be empty
+/// * A function must have exactly one entry block
 /// * All blocks in the function body must be valid
 /// * All uses of values must be dominated by their definitions
 /// * All value uses must type check, i.e. branching to a block with a value
@@ -27,6 +28,10 @@ impl Rule<Function> for FunctionValidator {
         function: &Function,
         diagnostics: &DiagnosticsHandler,
     ) -> Result<(), ValidationError> {
+        // Ensure the function has a well-defined entry before anything else inspects it
+        let mut rules = SingleEntry;
+        rules.validate(function, diagnostics)?;
+
         // Validate the function declaration
         let mut rules = NamingConventions.chain(CoherentSignature::new(self.in_kernel_module));
         rules.validate(function, diagnostics)?;
@@ -52,6 +57,83 @@ impl Rule<Function> for FunctionValidator {
     }
 }
 
+/// This validation rule ensures that a function has exactly one entry block
+///
+/// The entry block is the block designated as such by the [DataFlowGraph], it must:
+///
+/// 1. Be present in the function layout
+/// 2. Be the first block in the function layout
+/// 3. Have no predecessors, i.e. no branch instruction in the function may target it
+///
+/// Codegen relies on this to establish the initial state of the operand stack from the
+/// entry block parameters, so a function which violates these invariants cannot be compiled.
+struct SingleEntry;
+impl Rule<Function> for SingleEntry {
+    fn validate(
+        &mut self,
+        function: &Function,
+        diagnostics: &DiagnosticsHandler,
+    ) -> Result<(), ValidationError> {
+        let entry = function.dfg.entry_block();
+
+        // 1
+        if !function.dfg.is_block_linked(entry) {
+            invalid_function!(
+                diagnostics,
+                function.id,
+                function.id.span(),
+                "this function has no entry block",
+                format!(
+                    "The entry block of a function must be present in the function layout, but \
+                     {entry} has been detached from the body of this function"
+                )
+            );
+        }
+
+        // 2
+        let first = function.dfg.blocks().next().map(|(block, _)| block);
+        if first != Some(entry) {
+            let first = first.unwrap();
+            invalid_function!(
+                diagnostics,
+                function.id,
+                function.id.span(),
+                "the entry block of this function is not the first block in the layout",
+                format!(
+                    "The entry block, {entry}, must be the first block in the function layout, \
+                     but {first} precedes it"
+                )
+            );
+        }
+
+        // 3
+        for (block, block_data) in function.dfg.blocks() {
+            let Some(terminator) = block_data.insts.back().get() else {
+                continue;
+            };
+            let targets_entry = match terminator.analyze_branch(&function.dfg.value_lists) {
+                BranchInfo::NotABranch => false,
+                BranchInfo::SingleDest(destination, _) => destination == entry,
+                BranchInfo::MultiDest(ref jts) => jts.iter().any(|jt| jt.destination == entry),
+            };
+            if targets_entry {
+                invalid_function!(
+                    diagnostics,
+                    function.id,
+                    terminator.span(),
+                    "this function has multiple entries",
+                    format!(
+                        "The entry block of a function may not have predecessors, but {block} \
+                         branches to the entry block, {entry}"
+                    )
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// This validation rule ensures that a [Signature] is coherent
 ///
 /// A signature is coherent if:
@@ -433,10 +433,115 @@ impl From<ModuleValidationAnalysis> for Result<(), ValidationError> {
 
 #[cfg(test)]
 mod tests {
+    use cranelift_entity::packed_option::ReservedValue;
     use miden_hir::testing::TestContext;
 
     use super::*;
 
+    /// Build a function `test::switch` which dispatches on its argument using a switch with the
+    /// given arms and default destination, where `arms` and `default` are indices of the blocks
+    /// to use as successors, or `None` if the default destination should be left unset.
+    fn switch_function(
+        context: &TestContext,
+        arms: &[(u32, usize)],
+        default: Option<usize>,
+    ) -> (Function, Inst) {
+        let id = "test::switch".parse::<FunctionIdent>().unwrap();
+        let mut function = Function::new(id, Signature::new([AbiParam::new(Type::U32)], []));
+        let inst = {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let selector = builder.block_params(entry)[0];
+            let blocks = (0..3).map(|_| builder.create_block()).collect::<Vec<_>>();
+            for block in blocks.iter().copied() {
+                builder.switch_to_block(block);
+                builder.ins().ret(None, context.current_span());
+            }
+            builder.switch_to_block(entry);
+            let arms = arms.iter().map(|(discriminant, i)| (*discriminant, blocks[*i])).collect();
+            let default = default.map(|i| blocks[i]).unwrap_or_else(Block::reserved_value);
+            builder.ins().switch(selector, arms, default, context.current_span())
+        };
+        (function, inst)
+    }
+
+    #[test]
+    fn block_validator_accepts_switch_test() {
+        let context = TestContext::default();
+
+        let (function, _) = switch_function(&context, &[(0, 0), (1, 1)], Some(2));
+        let entry = function.dfg.entry_block();
+        let mut rules = BlockValidator::new(&function.dfg, function.id.span());
+        rules
+            .validate(function.dfg.block(entry), &context.session.diagnostics)
+            .expect("switch was expected to be valid");
+    }
+
+    #[test]
+    fn block_validator_rejects_duplicate_switch_arms_test() {
+        let context = TestContext::default();
+
+        let (function, inst) = switch_function(&context, &[(0, 0), (1, 1), (0, 1)], Some(2));
+        let entry = function.dfg.entry_block();
+        let mut rules = BlockValidator::new(&function.dfg, function.id.span());
+        let result = rules.validate(function.dfg.block(entry), &context.session.diagnostics);
+        assert_eq!(
+            result,
+            Err(ValidationError::InvalidInstruction {
+                span: SourceSpan::UNKNOWN,
+                inst,
+                reason: "invalid switch instruction".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn block_validator_rejects_switch_without_default_test() {
+        let context = TestContext::default();
+
+        let (function, inst) = switch_function(&context, &[(0, 0), (1, 1)], None);
+        let entry = function.dfg.entry_block();
+        let mut rules = BlockValidator::new(&function.dfg, function.id.span());
+        let result = rules.validate(function.dfg.block(entry), &context.session.diagnostics);
+        assert_eq!(
+            result,
+            Err(ValidationError::InvalidInstruction {
+                span: SourceSpan::UNKNOWN,
+                inst,
+                reason: "incomplete switch instruction".to_string(),
+            })
+        );
+    }
+
+    /// Instructions following the terminator of a block can never be executed, and indicate a
+    /// bug in the frontend which produced the block
+    #[test]
+    fn block_validator_rejects_code_after_terminator_test() {
+        let context = TestContext::default();
+
+        let id = "test::dead_code".parse::<FunctionIdent>().unwrap();
+        let mut function = Function::new(id, Signature::new([], [AbiParam::new(Type::U32)]));
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let one = builder.ins().u32(1, context.current_span());
+            builder.ins().ret(Some(one), context.current_span());
+            // These instructions can never be executed
+            let two = builder.ins().u32(2, context.current_span());
+            builder.ins().add_wrapping(one, two, context.current_span());
+        }
+
+        let entry = function.dfg.entry_block();
+        let mut rules = BlockValidator::new(&function.dfg, function.id.span());
+        let result = rules.validate(function.dfg.block(entry), &context.session.diagnostics);
+        assert_eq!(
+            result,
+            Err(ValidationError::InvalidBlock {
+                block: entry,
+                reason: "terminator found in middle of block".to_string(),
+            })
+        );
+    }
+
     #[test]
     fn module_validator_test() {
         let context = TestContext::default();
@@ -450,4 +555,50 @@ mod tests {
         let analysis = ModuleValidationAnalysis::validate(&module, &context.session);
         analysis.expect("module was expected to be valid")
     }
+
+    #[test]
+    fn function_validator_rejects_missing_entry_test() {
+        let context = TestContext::default();
+
+        let id = "test::degenerate".parse::<FunctionIdent>().unwrap();
+        let mut function = Function::new(id, Signature::new([], []));
+        let entry = function.dfg.entry_block();
+        function.dfg.detach_block(entry);
+
+        let mut rules = FunctionValidator::new(false);
+        let result = rules.validate(&function, &context.session.diagnostics);
+        assert_eq!(
+            result,
+            Err(ValidationError::InvalidFunction {
+                function: id,
+                reason: "this function has no entry block".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn function_validator_rejects_multiple_entries_test() {
+        let context = TestContext::default();
+
+        let id = "test::degenerate".parse::<FunctionIdent>().unwrap();
+        let mut function = Function::new(id, Signature::new([], []));
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let loop_body = builder.create_block();
+            builder.ins().br(loop_body, &[], context.current_span());
+            builder.switch_to_block(loop_body);
+            builder.ins().br(entry, &[], context.current_span());
+        }
+
+        let mut rules = FunctionValidator::new(false);
+        let result = rules.validate(&function, &context.session.diagnostics);
+        assert_eq!(
+            result,
+            Err(ValidationError::InvalidFunction {
+                function: id,
+                reason: "this function has multiple entries".to_string(),
+            })
+        );
+    }
 }
@@ -0,0 +1,557 @@
+use miden_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use miden_hir_analysis::{ControlFlowGraph, DominatorTree};
+use midenc_session::Session;
+use smallvec::SmallVec;
+
+/// This pass removes redundant bounds checks, such as those emitted by Rust when indexing into an
+/// array, when the `remove_redundant_bounds_checks` option is enabled. Otherwise, the IR is left
+/// unchanged, i.e. bounds checks are kept by default.
+///
+/// A bounds check is recognized by the idiom the frontend produces for them:
+///
+/// * A conditional branch whose condition is an integer comparison, i.e. `lt`, `lte`, `gt` or
+/// `gte`, either directly, or as translated from a Wasm `br_if`, i.e. `neq (cast cmp) 0`
+/// * One of the successors of that branch calls `panic_bounds_check`, and then traps, i.e. it has
+/// no block arguments, only materializes constants for the call, and is terminated by
+/// `unreachable`
+///
+/// Other guarded traps, e.g. user assertions, do not match this idiom, and are never removed.
+///
+/// A bounds check is only removed if it can be proven to always succeed, which is the case when:
+///
+/// * Both operands of the comparison are constants, and the comparison holds
+/// * It is dominated by the success path of an identical bounds check, i.e. one which compares
+/// the same values in the same way
+///
+/// Such branches are rewritten to branch unconditionally to the non-trapping successor, and the
+/// trapping block is removed if it has no other predecessors. The comparison itself is left in
+/// place, but as it is no longer used, it will not be materialized during code generation.
+///
+/// This is distinct from overflow checks, i.e. the checked variants of arithmetic instructions,
+/// which are not affected by this pass.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct ElideBoundsChecks;
+impl RewritePass for ElideBoundsChecks {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        if !session.options.remove_redundant_bounds_checks {
+            analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+            analyses.mark_preserved::<DominatorTree>(&function.id);
+            return Ok(());
+        }
+
+        let mut cfg = analyses
+            .take::<ControlFlowGraph>(&function.id)
+            .unwrap_or_else(|| ControlFlowGraph::with_function(function));
+        let domtree = DominatorTree::with_function(function, &cfg);
+
+        // Find all of the bounds checks up front, as the facts established by a check still hold
+        // once it has been removed as redundant, by virtue of the check which made it redundant
+        let checks = domtree
+            .cfg_postorder()
+            .iter()
+            .rev()
+            .filter_map(|block| match_bounds_check(function, &cfg, *block))
+            .collect::<SmallVec<[BoundsCheck; 4]>>();
+
+        let mut changed = false;
+        for check in checks.iter() {
+            if !is_redundant(function, &domtree, check, &checks) {
+                continue;
+            }
+
+            let terminator = function.dfg.last_inst(check.block).unwrap();
+            let span = function.dfg.inst_span(terminator);
+            function.dfg.replace(terminator).br(check.destination, &check.args, span);
+            cfg.recompute_block(&function.dfg, check.block);
+
+            if cfg.num_predecessors(check.trap) == 0 {
+                cfg.detach_block(check.trap);
+                function.dfg.detach_block(check.trap);
+            }
+
+            changed = true;
+        }
+
+        analyses.insert(function.id, cfg);
+        if !changed {
+            analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+            analyses.mark_preserved::<DominatorTree>(&function.id);
+        }
+
+        Ok(())
+    }
+}
+
+/// A bounds check terminating `block`
+struct BoundsCheck {
+    block: Block,
+    /// The comparison which must hold for the check to succeed
+    condition: Condition,
+    /// The successor taken when the check fails
+    trap: Block,
+    /// The successor taken when the check succeeds
+    destination: Block,
+    args: SmallVec<[Value; 2]>,
+}
+
+/// An integer comparison `lhs <op> rhs`, which is expected to evaluate to `holds`
+#[derive(PartialEq, Eq)]
+struct Condition {
+    op: Opcode,
+    lhs: Value,
+    rhs: Value,
+    holds: bool,
+}
+
+/// If `block` is terminated by the conditional branch of a bounds check, returns it
+fn match_bounds_check(
+    function: &hir::Function,
+    cfg: &ControlFlowGraph,
+    block: Block,
+) -> Option<BoundsCheck> {
+    let inst = function.dfg.last_inst(block)?;
+    let Instruction::CondBr(CondBr {
+        cond,
+        then_dest: (then_dest, then_args),
+        else_dest: (else_dest, else_args),
+        ..
+    }) = function.dfg.inst(inst)
+    else {
+        return None;
+    };
+
+    if then_dest == else_dest {
+        return None;
+    }
+
+    let (op, lhs, rhs) = match_comparison(function, *cond)?;
+    let pool = &function.dfg.value_lists;
+    let (holds, trap, destination, args) = if is_bounds_check_trap(function, *else_dest) {
+        (true, *else_dest, *then_dest, then_args.as_slice(pool))
+    } else if is_bounds_check_trap(function, *then_dest) {
+        (false, *then_dest, *else_dest, else_args.as_slice(pool))
+    } else {
+        return None;
+    };
+
+    // The comparison is only known to hold in `destination` if it can't be reached another way
+    if cfg.num_predecessors(destination) != 1 {
+        return None;
+    }
+
+    Some(BoundsCheck {
+        block,
+        condition: Condition {
+            op,
+            lhs,
+            rhs,
+            holds,
+        },
+        trap,
+        destination,
+        args: SmallVec::from_slice(args),
+    })
+}
+
+/// Returns true if `check` is known to succeed, either because it compares constants, or because
+/// an identical check has already succeeded on every path to it
+fn is_redundant(
+    function: &hir::Function,
+    domtree: &DominatorTree,
+    check: &BoundsCheck,
+    checks: &[BoundsCheck],
+) -> bool {
+    let condition = &check.condition;
+    if let (Some(lhs), Some(rhs)) =
+        (constant_value(function, condition.lhs), constant_value(function, condition.rhs))
+    {
+        let result = match condition.op {
+            Opcode::Lt => lhs < rhs,
+            Opcode::Lte => lhs <= rhs,
+            Opcode::Gt => lhs > rhs,
+            Opcode::Gte => lhs >= rhs,
+            _ => unreachable!(),
+        };
+        return result == condition.holds;
+    }
+
+    checks.iter().any(|other| {
+        other.block != check.block
+            && &other.condition == condition
+            && domtree.dominates(other.destination, check.block, &function.dfg)
+    })
+}
+
+/// Returns the opcode and operands of the integer comparison producing `value`, looking through
+/// the `neq (cast cmp) 0` produced when translating a Wasm `br_if`
+fn match_comparison(function: &hir::Function, value: Value) -> Option<(Opcode, Value, Value)> {
+    let ValueData::Inst { inst, .. } = function.dfg.value_data(value) else {
+        return None;
+    };
+    match function.dfg.inst(*inst) {
+        Instruction::BinaryOp(BinaryOp {
+            op: op @ (Opcode::Lt | Opcode::Lte | Opcode::Gt | Opcode::Gte),
+            args,
+            ..
+        }) => Some((*op, args[1], args[0])),
+        Instruction::BinaryOpImm(BinaryOpImm {
+            op: Opcode::Neq,
+            arg,
+            imm,
+            ..
+        }) if imm.as_u64() == Some(0) => {
+            let ValueData::Inst { inst, .. } = function.dfg.value_data(*arg) else {
+                return None;
+            };
+            match function.dfg.inst(*inst) {
+                Instruction::UnaryOp(UnaryOp {
+                    op: Opcode::Cast,
+                    arg,
+                    ..
+                }) => match_comparison(function, *arg),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the value of `value` if it is a constant, looking through casts
+fn constant_value(function: &hir::Function, value: Value) -> Option<i128> {
+    let ValueData::Inst { inst, .. } = function.dfg.value_data(value) else {
+        return None;
+    };
+    match function.dfg.inst(*inst) {
+        Instruction::UnaryOpImm(UnaryOpImm { imm, .. }) => imm.as_i128(),
+        Instruction::UnaryOp(UnaryOp {
+            op: Opcode::Cast,
+            arg,
+            ..
+        }) => constant_value(function, *arg),
+        _ => None,
+    }
+}
+
+/// Returns true if `block` does nothing but call `panic_bounds_check` and trap
+fn is_bounds_check_trap(function: &hir::Function, block: Block) -> bool {
+    if !function.dfg.block_params(block).is_empty() {
+        return false;
+    }
+
+    let mut panics = false;
+    let mut insts = function.dfg.block_insts(block).peekable();
+    while let Some(inst) = insts.next() {
+        let is_last = insts.peek().is_none();
+        let is_valid = match function.dfg.inst(inst) {
+            data if is_last => data.opcode() == Opcode::Unreachable,
+            Instruction::UnaryOpImm(_) => true,
+            Instruction::Call(Call {
+                op: Opcode::Call,
+                callee,
+                ..
+            }) if !panics && callee.function.as_str().contains("panic_bounds_check") => {
+                panics = true;
+                true
+            }
+            _ => false,
+        };
+        if !is_valid {
+            return false;
+        }
+    }
+
+    panics
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Block, Function, FunctionBuilder, InstBuilder, Opcode, Signature, SourceSpan,
+        Type,
+    };
+    use midenc_session::Options;
+    use pretty_assertions::assert_eq;
+
+    use crate::ElideBoundsChecks;
+
+    /// Construct a function which contains the following pair of identical bounds checks, where
+    /// the second is redundant, as it is dominated by the first:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32, u32) -> u32 {
+    /// entry(index: u32, len: u32):
+    ///    in_bounds = lt index, len;
+    ///    condbr in_bounds, blk1, blk2;
+    ///
+    /// blk1:
+    ///    in_bounds2 = lt index, len;
+    ///    condbr in_bounds2, blk3, blk4;
+    ///
+    /// blk2:
+    ///    call core::panic_bounds_check();
+    ///    unreachable;
+    ///
+    /// blk3:
+    ///    ret index;
+    ///
+    /// blk4:
+    ///    call core::panic_bounds_check();
+    ///    unreachable;
+    /// }
+    /// ```
+    ///
+    /// If `panics` is false, the trapping blocks do not call `panic_bounds_check`, i.e. the
+    /// checks are ordinary assertions rather than bounds checks.
+    ///
+    /// Returns the function, along with the blocks containing the first and second checks, and
+    /// the trapping block of the second check.
+    fn bounds_check_function(panics: bool) -> (Function, [Block; 3]) {
+        let id = "test::ebc".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        let blocks = {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let panic_bounds_check = builder
+                .import_function(
+                    "core",
+                    "panic_bounds_check",
+                    Signature::new([], []),
+                    SourceSpan::UNKNOWN,
+                )
+                .expect("unexpected symbol conflict");
+            let entry = builder.current_block();
+            let (index, len) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+
+            let check_blk = builder.create_block();
+            let trap_blk = builder.create_block();
+            let in_bounds_blk = builder.create_block();
+            let redundant_trap_blk = builder.create_block();
+
+            let in_bounds = builder.ins().lt(index, len, SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(in_bounds, check_blk, &[], trap_blk, &[], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(check_blk);
+            let in_bounds = builder.ins().lt(index, len, SourceSpan::UNKNOWN);
+            builder.ins().cond_br(
+                in_bounds,
+                in_bounds_blk,
+                &[],
+                redundant_trap_blk,
+                &[],
+                SourceSpan::UNKNOWN,
+            );
+
+            for blk in [trap_blk, redundant_trap_blk] {
+                builder.switch_to_block(blk);
+                if panics {
+                    builder.ins().call(panic_bounds_check, &[], SourceSpan::UNKNOWN);
+                }
+                builder.ins().unreachable(SourceSpan::UNKNOWN);
+            }
+
+            builder.switch_to_block(in_bounds_blk);
+            builder.ins().ret(Some(index), SourceSpan::UNKNOWN);
+
+            [entry, check_blk, redundant_trap_blk]
+        };
+
+        (function, blocks)
+    }
+
+    fn remove_bounds_checks_context() -> TestContext {
+        let options = Options {
+            remove_redundant_bounds_checks: true,
+            ..Default::default()
+        };
+        TestContext::default_with_opts_and_emitter(options, None)
+    }
+
+    /// Bounds checks are kept unless explicitly requested otherwise
+    #[test]
+    fn elide_bounds_checks_default_test() {
+        let context = TestContext::default();
+        let (mut function, _) = bounds_check_function(true);
+
+        let original = function.to_string();
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = ElideBoundsChecks;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("eliding bounds checks failed");
+
+        assert_eq!(function.to_string(), original);
+    }
+
+    /// Redundant bounds checks are removed when `remove_redundant_bounds_checks` is set, but the
+    /// check which makes them redundant is kept
+    #[test]
+    fn elide_bounds_checks_test() {
+        let context = remove_bounds_checks_context();
+        let (mut function, [entry, check_blk, redundant_trap_blk]) = bounds_check_function(true);
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = ElideBoundsChecks;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("eliding bounds checks failed");
+
+        let terminator = |block| function.dfg.inst(function.dfg.last_inst(block).unwrap()).opcode();
+        assert_eq!(terminator(entry), Opcode::CondBr);
+        assert_eq!(terminator(check_blk), Opcode::Br);
+        assert!(!function.dfg.is_block_linked(redundant_trap_blk));
+    }
+
+    /// A lone bounds check on values which are not constants is kept, even when
+    /// `remove_redundant_bounds_checks` is set, as it is not known to succeed
+    #[test]
+    fn elide_bounds_checks_lone_check_test() {
+        let context = remove_bounds_checks_context();
+        let id = "test::ebc".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let panic_bounds_check = builder
+                .import_function(
+                    "core",
+                    "panic_bounds_check",
+                    Signature::new([], []),
+                    SourceSpan::UNKNOWN,
+                )
+                .expect("unexpected symbol conflict");
+            let entry = builder.current_block();
+            let (index, len) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+            let in_bounds_blk = builder.create_block();
+            let trap_blk = builder.create_block();
+
+            let in_bounds = builder.ins().lt(index, len, SourceSpan::UNKNOWN);
+            builder.ins().cond_br(
+                in_bounds,
+                in_bounds_blk,
+                &[],
+                trap_blk,
+                &[],
+                SourceSpan::UNKNOWN,
+            );
+
+            builder.switch_to_block(trap_blk);
+            builder.ins().call(panic_bounds_check, &[], SourceSpan::UNKNOWN);
+            builder.ins().unreachable(SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(in_bounds_blk);
+            builder.ins().ret(Some(index), SourceSpan::UNKNOWN);
+        }
+
+        let original = function.to_string();
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = ElideBoundsChecks;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("eliding bounds checks failed");
+
+        assert_eq!(function.to_string(), original);
+    }
+
+    /// A bounds check on constants which is known to succeed is removed
+    #[test]
+    fn elide_bounds_checks_constant_test() {
+        let context = remove_bounds_checks_context();
+        let id = "test::ebc".parse().unwrap();
+        let mut function = Function::new(id, Signature::new([], [AbiParam::new(Type::U32)]));
+
+        let entry = {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let panic_bounds_check = builder
+                .import_function(
+                    "core",
+                    "panic_bounds_check",
+                    Signature::new([], []),
+                    SourceSpan::UNKNOWN,
+                )
+                .expect("unexpected symbol conflict");
+            let entry = builder.current_block();
+            let in_bounds_blk = builder.create_block();
+            let trap_blk = builder.create_block();
+
+            let index = builder.ins().u32(1, SourceSpan::UNKNOWN);
+            let len = builder.ins().u32(4, SourceSpan::UNKNOWN);
+            let out_of_bounds = builder.ins().gte(index, len, SourceSpan::UNKNOWN);
+            builder.ins().cond_br(
+                out_of_bounds,
+                trap_blk,
+                &[],
+                in_bounds_blk,
+                &[],
+                SourceSpan::UNKNOWN,
+            );
+
+            builder.switch_to_block(trap_blk);
+            builder.ins().call(panic_bounds_check, &[], SourceSpan::UNKNOWN);
+            builder.ins().unreachable(SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(in_bounds_blk);
+            builder.ins().ret(Some(index), SourceSpan::UNKNOWN);
+
+            entry
+        };
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = ElideBoundsChecks;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("eliding bounds checks failed");
+
+        let terminator = function.dfg.last_inst(entry).unwrap();
+        assert_eq!(function.dfg.inst(terminator).opcode(), Opcode::Br);
+    }
+
+    /// A guarded trap which is not a bounds check, e.g. a user assertion, is kept, even when it is
+    /// redundant
+    #[test]
+    fn elide_bounds_checks_unrelated_trap_test() {
+        let context = remove_bounds_checks_context();
+        let (mut function, _) = bounds_check_function(false);
+
+        let original = function.to_string();
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = ElideBoundsChecks;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("eliding bounds checks failed");
+
+        assert_eq!(function.to_string(), original);
+    }
+}
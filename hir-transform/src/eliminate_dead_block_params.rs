@@ -0,0 +1,307 @@
+use miden_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use miden_hir_analysis::{ControlFlowGraph, DominatorTree, LivenessAnalysis, LoopAnalysis};
+use midenc_session::Session;
+use rustc_hash::FxHashSet;
+use smallvec::SmallVec;
+
+/// This pass removes block parameters which are never used, along with the corresponding
+/// argument of every branch to the block.
+///
+/// Code generation already drops a block parameter with no uses on entry to the block, but the
+/// value must still be materialized by each predecessor, and passed along to the block, only to
+/// be discarded. Removing the parameter avoids this, and may leave the instructions which computed
+/// the arguments without any uses, so this pass is most effective when run before
+/// [EliminateDeadCode].
+///
+/// A block parameter is considered used if it is an operand of an instruction other than a branch,
+/// or if it is passed as a branch argument to another parameter which is used. Parameters which
+/// are only passed around a loop, without ever being used, are thus removed as well.
+///
+/// The parameters of the entry block are the parameters of the function, and are always kept.
+///
+/// [EliminateDeadCode]: crate::EliminateDeadCode
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct EliminateDeadBlockParams;
+impl RewritePass for EliminateDeadBlockParams {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        _session: &Session,
+    ) -> RewriteResult {
+        // Removing block parameters and branch arguments never modifies the control flow graph
+        analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+        analyses.mark_preserved::<DominatorTree>(&function.id);
+        analyses.mark_preserved::<LoopAnalysis>(&function.id);
+
+        let entry = function.dfg.entry_block();
+        let blocks = function.dfg.blocks().map(|(b, _)| b).collect::<SmallVec<[Block; 8]>>();
+
+        // Find the values used by instructions other than branches, and the branches which pass
+        // arguments to other blocks
+        let mut used = FxHashSet::<Value>::default();
+        used.extend(function.dfg.block_params(entry).iter().copied());
+        let mut branches = SmallVec::<[Inst; 8]>::new();
+        for block in blocks.iter().copied() {
+            for inst in function.dfg.block_insts(block) {
+                used.extend(function.dfg.inst_args(inst).iter().copied());
+                if matches!(function.dfg.inst(inst), Instruction::Br(_) | Instruction::CondBr(_)) {
+                    branches.push(inst);
+                }
+            }
+        }
+
+        // An argument passed to a used block parameter is itself used, which may in turn make the
+        // parameters of another block used, so iterate until no new uses are found
+        loop {
+            let mut changed = false;
+            for inst in branches.iter().copied() {
+                let jts = match function.dfg.analyze_branch(inst) {
+                    BranchInfo::SingleDest(destination, args) => {
+                        vec![JumpTable::new(destination, args)]
+                    }
+                    BranchInfo::MultiDest(jts) => jts,
+                    BranchInfo::NotABranch => continue,
+                };
+                for jt in jts {
+                    let params = function.dfg.block_params(jt.destination);
+                    for (param, arg) in params.iter().zip(jt.args.iter()) {
+                        if used.contains(param) {
+                            changed |= used.insert(*arg);
+                        }
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut changed = false;
+        for block in blocks.into_iter().filter(|b| *b != entry) {
+            let unused = function
+                .dfg
+                .block_params(block)
+                .iter()
+                .copied()
+                .enumerate()
+                .filter(|(_, param)| !used.contains(param))
+                .collect::<SmallVec<[(usize, Value); 2]>>();
+            // Remove the parameters from last to first, so that the indices of the remaining
+            // parameters are unaffected by each removal
+            for (index, param) in unused.into_iter().rev() {
+                for inst in branches.iter().copied() {
+                    function.dfg.remove_branch_destination_argument(inst, block, index);
+                }
+                function.dfg.remove_block_param(param);
+                changed = true;
+            }
+        }
+
+        // Any liveness computed prior to this pass refers to the values we removed
+        if changed {
+            analyses.mark_invalid::<LivenessAnalysis>(&function.id);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use pretty_assertions::assert_eq;
+
+    use crate::EliminateDeadBlockParams;
+
+    /// Construct a function in which the result of a phi is never used:
+    ///
+    /// ```text,ignore
+    /// pub fn test(i1, u32) -> u32 {
+    /// entry(c: i1, x: u32):
+    ///    a = add.wrapping x, 1;
+    ///    condbr c, blk1, blk2;
+    ///
+    /// blk1:
+    ///    br blk3(x);
+    ///
+    /// blk2:
+    ///    br blk3(a);
+    ///
+    /// blk3(phi: u32):
+    ///    ret x;
+    /// }
+    /// ```
+    ///
+    /// We expect the parameter of `blk3` to be removed, along with the arguments passed to it.
+    #[test]
+    fn eliminate_dead_block_params_test() {
+        let context = TestContext::default();
+        let id = "test::params".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::I1), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (c, x) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+
+            let a = builder.create_block(); // blk1
+            let b = builder.create_block(); // blk2
+            let d = builder.create_block(); // blk3(phi: u32)
+            builder.append_block_param(d, Type::U32, SourceSpan::UNKNOWN);
+
+            // entry
+            let x1 = builder.ins().add_imm_wrapping(x, Immediate::U32(1), SourceSpan::UNKNOWN);
+            builder.ins().cond_br(c, a, &[], b, &[], SourceSpan::UNKNOWN);
+
+            // blk1
+            builder.switch_to_block(a);
+            builder.ins().br(d, &[x], SourceSpan::UNKNOWN);
+
+            // blk2
+            builder.switch_to_block(b);
+            builder.ins().br(d, &[x1], SourceSpan::UNKNOWN);
+
+            // blk3
+            builder.switch_to_block(d);
+            builder.ins().ret(Some(x), SourceSpan::UNKNOWN);
+        }
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = EliminateDeadBlockParams;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("eliminating dead block parameters failed");
+
+        let expected = "\
+(func (export #params) (param i1) (param u32) (result u32)
+    (block 0 (param v0 i1) (param v1 u32)
+        (let (v3 u32) (add.wrapping v1 1))
+        (condbr v0 (block 1) (block 2)))
+
+    (block 1
+        (br (block 3)))
+
+    (block 2
+        (br (block 3)))
+
+    (block 3
+        (ret v1))
+)";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// Construct a function with a loop which carries a value around, without ever using it:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32, u32) -> u32 {
+    /// entry(n0: u32, x: u32):
+    ///    br blk1(n0, x);
+    ///
+    /// blk1(n1: u32, carried: u32):
+    ///    is_zero = eq n1, 0;
+    ///    condbr is_zero, blk3(n1), blk2;
+    ///
+    /// blk2:
+    ///    n2 = sub.wrapping n1, 1;
+    ///    br blk1(n2, carried);
+    ///
+    /// blk3(result: u32):
+    ///    ret result;
+    /// }
+    /// ```
+    ///
+    /// The only use of `carried` is the argument passed back to itself along the loopback edge, so
+    /// we expect it to be removed, along with the arguments passed to it on entry to the loop and
+    /// along the loopback edge.
+    #[test]
+    fn eliminate_dead_loop_carried_block_params_test() {
+        let context = TestContext::default();
+        let id = "test::loop_params".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (n0, x) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+
+            let a = builder.create_block(); // blk1(n1: u32, carried: u32)
+            let n1 = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            let carried = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            let b = builder.create_block(); // blk2
+            let c = builder.create_block(); // blk3(result: u32)
+            let result = builder.append_block_param(c, Type::U32, SourceSpan::UNKNOWN);
+
+            // entry
+            builder.ins().br(a, &[n0, x], SourceSpan::UNKNOWN);
+
+            // blk1
+            builder.switch_to_block(a);
+            let is_zero = builder.ins().eq_imm(n1, Immediate::U32(0), SourceSpan::UNKNOWN);
+            builder.ins().cond_br(is_zero, c, &[n1], b, &[], SourceSpan::UNKNOWN);
+
+            // blk2
+            builder.switch_to_block(b);
+            let n2 = builder.ins().sub_imm_wrapping(n1, Immediate::U32(1), SourceSpan::UNKNOWN);
+            builder.ins().br(a, &[n2, carried], SourceSpan::UNKNOWN);
+
+            // blk3
+            builder.switch_to_block(c);
+            builder.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        }
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = EliminateDeadBlockParams;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("eliminating dead block parameters failed");
+
+        let expected = "\
+(func (export #loop_params) (param u32) (param u32) (result u32)
+    (block 0 (param v0 u32) (param v1 u32)
+        (br (block 1 v0)))
+
+    (block 1 (param v2 u32)
+        (let (v5 i1) (eq v2 0))
+        (condbr v5 (block 3 v2) (block 2)))
+
+    (block 2
+        (let (v6 u32) (sub.wrapping v2 1))
+        (br (block 1 v6)))
+
+    (block 3 (param v4 u32)
+        (ret v4))
+)";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
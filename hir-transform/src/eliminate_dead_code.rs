@@ -0,0 +1,169 @@
+use miden_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use miden_hir_analysis::{ControlFlowGraph, DominatorTree, LivenessAnalysis, LoopAnalysis};
+use midenc_session::Session;
+use smallvec::SmallVec;
+
+/// This pass removes instructions which have no side effects, and whose results are never used.
+///
+/// Code generation already skips such instructions, but only by looking at the uses within a
+/// single block, as the liveness of values across blocks is computed up front. If the only use
+/// of a value is itself dead, and is in another block, the value is still considered live, and
+/// is materialized for nothing.
+///
+/// This pass instead recomputes liveness over the whole function each time dead instructions have
+/// been removed, until no more instructions can be removed, so that chains of dead instructions
+/// are removed regardless of how many blocks they span.
+///
+/// Terminators, and other instructions with side effects, are always kept. Block arguments are
+/// not considered, i.e. a value passed to a block whose parameter is unused is still live.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct EliminateDeadCode;
+impl RewritePass for EliminateDeadCode {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        let cfg = analyses.get_or_compute::<ControlFlowGraph>(function, session)?;
+        let domtree = analyses.get_or_compute::<DominatorTree>(function, session)?;
+        let loops = analyses.get_or_compute::<LoopAnalysis>(function, session)?;
+
+        // Removing instructions other than terminators never modifies the control flow graph
+        analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+        analyses.mark_preserved::<DominatorTree>(&function.id);
+        analyses.mark_preserved::<LoopAnalysis>(&function.id);
+
+        let mut liveness = LivenessAnalysis::default();
+        let mut changed = false;
+        loop {
+            liveness.recompute(function, &cfg, &domtree, &loops);
+
+            // Liveness is only computed for blocks reachable from the entry
+            let mut dead = SmallVec::<[Inst; 8]>::new();
+            for block in domtree.cfg_postorder().iter().copied() {
+                dead.extend(
+                    function
+                        .dfg
+                        .block_insts(block)
+                        .filter(|inst| is_dead_instruction(*inst, function, &liveness)),
+                );
+            }
+
+            if dead.is_empty() {
+                break;
+            }
+
+            for inst in dead {
+                function.dfg.remove_inst(inst);
+            }
+            changed = true;
+        }
+
+        // Any liveness computed prior to this pass refers to the instructions we removed
+        if changed {
+            analyses.mark_invalid::<LivenessAnalysis>(&function.id);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns true if `inst` has no side effects, and none of its results are used after it
+fn is_dead_instruction(inst: Inst, function: &hir::Function, liveness: &LivenessAnalysis) -> bool {
+    if function.dfg.inst(inst).has_side_effects() {
+        return false;
+    }
+
+    // A value with no further uses has an infinite next-use distance
+    let pp = ProgramPoint::Inst(inst);
+    function
+        .dfg
+        .inst_results(inst)
+        .iter()
+        .all(|result| liveness.next_use_after(result, pp) == u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use pretty_assertions::assert_eq;
+
+    use crate::EliminateDeadCode;
+
+    /// Construct a function in which a value is computed in one block, and only used to compute
+    /// another value in a second block, which is itself never used:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32) -> u32 {
+    /// entry(n: u32):
+    ///    a = add.wrapping n, 1;
+    ///    b = mul.wrapping a, 2;
+    ///    br blk1;
+    ///
+    /// blk1:
+    ///    c = add.wrapping b, 3;
+    ///    d = mul.wrapping c, 4;
+    ///    ret n;
+    /// }
+    /// ```
+    fn dead_chain_function() -> Function {
+        let id = "test::dce".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let n = builder.block_params(entry)[0];
+            let blk1 = builder.create_block();
+
+            let a = builder.ins().add_imm_wrapping(n, Immediate::U32(1), SourceSpan::UNKNOWN);
+            let b = builder.ins().mul_imm_wrapping(a, Immediate::U32(2), SourceSpan::UNKNOWN);
+            builder.ins().br(blk1, &[], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk1);
+            let c = builder.ins().add_imm_wrapping(b, Immediate::U32(3), SourceSpan::UNKNOWN);
+            builder.ins().mul_imm_wrapping(c, Immediate::U32(4), SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(n), SourceSpan::UNKNOWN);
+        }
+
+        function
+    }
+
+    /// Values which are only used by dead instructions in other blocks are removed as well
+    #[test]
+    fn eliminate_dead_code_across_blocks_test() {
+        let context = TestContext::default();
+        let mut function = dead_chain_function();
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = EliminateDeadCode;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("eliminating dead code failed");
+
+        let expected = "\
+(func (export #dce) (param u32) (result u32)
+    (block 0 (param v0 u32)
+        (br (block 1)))
+
+    (block 1
+        (ret v0))
+)";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
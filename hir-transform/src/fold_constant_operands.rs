@@ -0,0 +1,253 @@
+use miden_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use miden_hir_analysis::ControlFlowGraph;
+use midenc_session::{OptLevel, Session};
+use smallvec::SmallVec;
+
+/// This pass rewrites binary operators with constant operands to use the immediate form of the
+/// operator, so that the constant is encoded in the instruction, rather than pushed on the operand
+/// stack separately, e.g. `push.1 eq` becomes `eq.1`.
+///
+/// * If the right-hand operand is a constant, it becomes the immediate. This is also the case when
+/// both operands are constants, as only one of them can be encoded in the instruction.
+/// * If only the left-hand operand is a constant, it becomes the immediate if the operator is
+/// commutative, or has a mirror image with the operands swapped, e.g. `lt` and `gt`.
+///
+/// Constants are only folded into operators whose immediate form accepts any value of the operand
+/// type, e.g. shifts are excluded, as their immediate forms require the shift to be in range, and
+/// division is only folded for non-zero divisors, so that division by zero still traps at runtime.
+///
+/// The constants themselves are left in place, but if they are no longer used, they will not be
+/// materialized during code generation.
+///
+/// This pass is only applied when optimizations are enabled, i.e. unless the optimization level is
+/// `none`, in which case the IR is left unchanged.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct FoldConstantOperands;
+impl RewritePass for FoldConstantOperands {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        // This pass never modifies the control flow graph
+        analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+
+        if matches!(session.options.optimize, OptLevel::None) {
+            return Ok(());
+        }
+
+        let blocks = function.dfg.blocks().map(|(b, _)| b).collect::<SmallVec<[Block; 8]>>();
+        for block in blocks {
+            let insts = function.dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                let Instruction::BinaryOp(BinaryOp {
+                    op,
+                    overflow,
+                    args: [rhs, lhs],
+                }) = *function.dfg.inst(inst)
+                else {
+                    continue;
+                };
+
+                let folded = match constant_operand(function, rhs, lhs) {
+                    Some(imm) => Some((op, lhs, imm)),
+                    None => constant_operand(function, lhs, rhs)
+                        .and_then(|imm| mirror(op).map(|op| (op, rhs, imm))),
+                };
+                let Some((op, arg, imm)) = folded else {
+                    continue;
+                };
+                if !has_immediate_form(op, imm) {
+                    continue;
+                }
+
+                *function.dfg.inst_mut(inst) = Instruction::BinaryOpImm(BinaryOpImm {
+                    op,
+                    overflow,
+                    arg,
+                    imm,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// If `value` is a constant of the same type as `other`, returns the constant as an immediate
+fn constant_operand(function: &hir::Function, value: Value, other: Value) -> Option<Immediate> {
+    let ValueData::Inst { inst, .. } = function.dfg.value_data(value) else {
+        return None;
+    };
+    let Instruction::UnaryOpImm(UnaryOpImm { op, imm, .. }) = function.dfg.inst(*inst) else {
+        return None;
+    };
+    let is_constant = matches!(
+        op,
+        Opcode::ImmI1
+            | Opcode::ImmU8
+            | Opcode::ImmI8
+            | Opcode::ImmU16
+            | Opcode::ImmI16
+            | Opcode::ImmU32
+            | Opcode::ImmI32
+            | Opcode::ImmU64
+            | Opcode::ImmI64
+            | Opcode::ImmFelt
+    );
+    if is_constant && &imm.ty() == function.dfg.value_type(other) {
+        Some(*imm)
+    } else {
+        None
+    }
+}
+
+/// Returns the operator equivalent to `op` with its operands swapped, if there is one
+fn mirror(op: Opcode) -> Option<Opcode> {
+    match op {
+        Opcode::Lt => Some(Opcode::Gt),
+        Opcode::Lte => Some(Opcode::Gte),
+        Opcode::Gt => Some(Opcode::Lt),
+        Opcode::Gte => Some(Opcode::Lte),
+        op if op.is_commutative() => Some(op),
+        _ => None,
+    }
+}
+
+/// Returns true if `op` has an immediate form which can encode `imm`
+fn has_immediate_form(op: Opcode, imm: Immediate) -> bool {
+    match op {
+        Opcode::Eq
+        | Opcode::Neq
+        | Opcode::Gt
+        | Opcode::Gte
+        | Opcode::Lt
+        | Opcode::Lte
+        | Opcode::Add
+        | Opcode::Sub
+        | Opcode::Mul
+        | Opcode::Min
+        | Opcode::Max
+        | Opcode::And
+        | Opcode::Band
+        | Opcode::Or
+        | Opcode::Bor
+        | Opcode::Xor
+        | Opcode::Bxor => true,
+        Opcode::Div | Opcode::Mod | Opcode::DivMod => imm.as_bool().unwrap_or(false),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use midenc_session::{OptLevel, Options};
+    use pretty_assertions::assert_eq;
+
+    use crate::FoldConstantOperands;
+
+    /// Construct a function which compares its argument against constants in various ways:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32) -> i1 {
+    /// entry(n: u32):
+    ///    one = const.u32 1;
+    ///    two = const.u32 2;
+    ///    is_one = eq n, one;
+    ///    is_gt_two = lt two, n;
+    ///    is_equal = eq one, two;
+    ///    diff = sub two, n;
+    ///    ...
+    /// }
+    /// ```
+    fn constant_operands_function() -> Function {
+        let id = "test::fco".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::I1)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let n = builder.block_params(entry)[0];
+
+            let one = builder.ins().u32(1, SourceSpan::UNKNOWN);
+            let two = builder.ins().u32(2, SourceSpan::UNKNOWN);
+            let is_one = builder.ins().eq(n, one, SourceSpan::UNKNOWN);
+            let is_gt_two = builder.ins().lt(two, n, SourceSpan::UNKNOWN);
+            let is_equal = builder.ins().eq(one, two, SourceSpan::UNKNOWN);
+            let diff = builder.ins().sub_wrapping(two, n, SourceSpan::UNKNOWN);
+            let is_zero = builder.ins().eq_imm(diff, Immediate::U32(0), SourceSpan::UNKNOWN);
+            let result = builder.ins().or(is_one, is_gt_two, SourceSpan::UNKNOWN);
+            let result = builder.ins().or(result, is_equal, SourceSpan::UNKNOWN);
+            let result = builder.ins().or(result, is_zero, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        }
+
+        function
+    }
+
+    /// Constant operands are left alone when optimizations are disabled
+    #[test]
+    fn fold_constant_operands_default_test() {
+        let context = TestContext::default();
+        let mut function = constant_operands_function();
+
+        let original = function.to_string();
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = FoldConstantOperands;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("folding constant operands failed");
+
+        assert_eq!(function.to_string(), original);
+    }
+
+    /// Constant operands are folded into immediates when optimizations are enabled
+    #[test]
+    fn fold_constant_operands_test() {
+        let options = Options {
+            optimize: OptLevel::Basic,
+            ..Default::default()
+        };
+        let context = TestContext::default_with_opts_and_emitter(options, None);
+        let mut function = constant_operands_function();
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = FoldConstantOperands;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("folding constant operands failed");
+
+        let expected = "\
+(func (export #fco) (param u32) (result i1)
+    (block 0 (param v0 u32)
+        (let (v1 u32) (const.u32 1))
+        (let (v2 u32) (const.u32 2))
+        (let (v3 i1) (eq v0 1))
+        (let (v4 i1) (gt v0 2))
+        (let (v5 i1) (eq v1 2))
+        (let (v6 u32) (sub.wrapping v2 v0))
+        (let (v7 i1) (eq v6 0))
+        (let (v8 i1) (or v3 v4))
+        (let (v9 i1) (or v8 v5))
+        (let (v10 i1) (or v9 v7))
+        (ret v10))
+)";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
@@ -0,0 +1,416 @@
+use miden_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use miden_hir_analysis::ControlFlowGraph;
+use midenc_session::{OptLevel, Session};
+use smallvec::SmallVec;
+
+/// This pass evaluates instructions whose operands are all constants at compile time, replacing
+/// each of them with the constant it produces, e.g. `(add.checked (const.u32 3) (const.u32 5))`
+/// becomes `(const.u32 8)`.
+///
+/// The following operations are folded:
+///
+/// * Integer arithmetic, i.e. `add`, `sub`, `mul`, `min` and `max`, as well as `div` and `mod` of
+/// unsigned integers. Checked and unchecked operations are only folded if the result is in range
+/// for the type, so that an operation which would trap at runtime is left in place. Wrapping
+/// operations are folded according to the range of the type.
+/// * Field arithmetic, i.e. `add`, `sub`, `mul`, `div` and `neg` of field elements, which wrap
+/// around the Goldilocks field modulus.
+/// * Comparisons of integers and field elements.
+/// * Boolean and bitwise operations, i.e. `and`, `or`, `xor`, `not`, `band`, `bor`, `bxor` and
+/// `bnot`.
+///
+/// Division by zero is never folded. The constant operands themselves are left in place, but if
+/// they are no longer used, they will not be materialized during code generation.
+///
+/// This pass is only applied when optimizations are enabled, i.e. unless the optimization level is
+/// `none`, in which case the IR is left unchanged.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct FoldConstants;
+impl RewritePass for FoldConstants {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        // This pass never modifies the control flow graph
+        analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+
+        if matches!(session.options.optimize, OptLevel::None) {
+            return Ok(());
+        }
+
+        // Visiting instructions in layout order means that the results of folded instructions
+        // are themselves available for folding by the instructions which use them
+        let blocks = function.dfg.blocks().map(|(b, _)| b).collect::<SmallVec<[Block; 8]>>();
+        for block in blocks {
+            let insts = function.dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                let folded = match *function.dfg.inst(inst) {
+                    Instruction::BinaryOp(BinaryOp {
+                        op,
+                        overflow,
+                        args: [rhs, lhs],
+                    }) => match (constant_value(function, lhs), constant_value(function, rhs)) {
+                        (Some(lhs), Some(rhs)) => {
+                            fold_binary(op, overflow.unwrap_or_default(), lhs, rhs)
+                        }
+                        _ => None,
+                    },
+                    Instruction::BinaryOpImm(BinaryOpImm {
+                        op,
+                        overflow,
+                        arg,
+                        imm,
+                    }) => constant_value(function, arg)
+                        .and_then(|lhs| fold_binary(op, overflow.unwrap_or_default(), lhs, imm)),
+                    Instruction::UnaryOp(UnaryOp { op, arg, .. }) => {
+                        constant_value(function, arg).and_then(|arg| fold_unary(op, arg))
+                    }
+                    _ => None,
+                };
+                let Some(imm) = folded else {
+                    continue;
+                };
+                let Some(op) = constant_opcode(imm) else {
+                    continue;
+                };
+
+                *function.dfg.inst_mut(inst) = Instruction::UnaryOpImm(UnaryOpImm {
+                    op,
+                    overflow: None,
+                    imm,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// If `value` is produced by a constant, returns the constant as an immediate
+fn constant_value(function: &hir::Function, value: Value) -> Option<Immediate> {
+    let ValueData::Inst { inst, .. } = function.dfg.value_data(value) else {
+        return None;
+    };
+    match function.dfg.inst(*inst) {
+        Instruction::UnaryOpImm(UnaryOpImm { op, imm, .. })
+            if constant_opcode(*imm) == Some(*op) =>
+        {
+            Some(*imm)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the opcode used to materialize `imm` as a constant, if it is a supported type
+fn constant_opcode(imm: Immediate) -> Option<Opcode> {
+    match imm {
+        Immediate::I1(_) => Some(Opcode::ImmI1),
+        Immediate::U8(_) => Some(Opcode::ImmU8),
+        Immediate::I8(_) => Some(Opcode::ImmI8),
+        Immediate::U16(_) => Some(Opcode::ImmU16),
+        Immediate::I16(_) => Some(Opcode::ImmI16),
+        Immediate::U32(_) => Some(Opcode::ImmU32),
+        Immediate::I32(_) => Some(Opcode::ImmI32),
+        Immediate::U64(_) => Some(Opcode::ImmU64),
+        Immediate::I64(_) => Some(Opcode::ImmI64),
+        Immediate::Felt(_) => Some(Opcode::ImmFelt),
+        Immediate::I128(_) | Immediate::F64(_) => None,
+    }
+}
+
+/// Evaluate the binary operator `op` on the constants `lhs` and `rhs`, if it can be folded
+fn fold_binary(
+    op: Opcode,
+    overflow: Overflow,
+    lhs: Immediate,
+    rhs: Immediate,
+) -> Option<Immediate> {
+    if lhs.ty() != rhs.ty() {
+        return None;
+    }
+
+    match (lhs, rhs) {
+        (Immediate::Felt(lhs), Immediate::Felt(rhs)) => fold_felt_binary(op, lhs, rhs),
+        (Immediate::I1(lhs), Immediate::I1(rhs)) => match op {
+            Opcode::And | Opcode::Band => Some(Immediate::I1(lhs & rhs)),
+            Opcode::Or | Opcode::Bor => Some(Immediate::I1(lhs | rhs)),
+            Opcode::Xor | Opcode::Bxor => Some(Immediate::I1(lhs ^ rhs)),
+            Opcode::Eq => Some(Immediate::I1(lhs == rhs)),
+            Opcode::Neq => Some(Immediate::I1(lhs != rhs)),
+            _ => None,
+        },
+        _ => {
+            let ty = lhs.ty();
+            let x = lhs.as_i128()?;
+            let y = rhs.as_i128()?;
+            // Arithmetic on 64-bit and wider operands can overflow an i128, which only has a
+            // well-defined result if it wraps around a type whose range fits in the low bits
+            let wrapping = matches!(overflow, Overflow::Wrapping) && ty.size_in_bits() <= 64;
+            let result = match op {
+                Opcode::Eq => return Some(Immediate::I1(x == y)),
+                Opcode::Neq => return Some(Immediate::I1(x != y)),
+                Opcode::Lt => return Some(Immediate::I1(x < y)),
+                Opcode::Lte => return Some(Immediate::I1(x <= y)),
+                Opcode::Gt => return Some(Immediate::I1(x > y)),
+                Opcode::Gte => return Some(Immediate::I1(x >= y)),
+                Opcode::Min => x.min(y),
+                Opcode::Max => x.max(y),
+                Opcode::Band => x & y,
+                Opcode::Bor => x | y,
+                Opcode::Bxor => x ^ y,
+                Opcode::Add if wrapping => x.wrapping_add(y),
+                Opcode::Sub if wrapping => x.wrapping_sub(y),
+                Opcode::Mul if wrapping => x.wrapping_mul(y),
+                Opcode::Add => x.checked_add(y)?,
+                Opcode::Sub => x.checked_sub(y)?,
+                Opcode::Mul => x.checked_mul(y)?,
+                // The semantics of signed division differ between frontends, so only unsigned
+                // division is folded
+                Opcode::Div if y != 0 && ty.is_unsigned_integer() => x / y,
+                Opcode::Mod if y != 0 && ty.is_unsigned_integer() => x % y,
+                _ => return None,
+            };
+            match overflow {
+                Overflow::Wrapping if wrapping => Some(wrap_integer(&ty, result)),
+                Overflow::Wrapping | Overflow::Checked | Overflow::Unchecked => {
+                    integer_in_range(&ty, result)
+                }
+                Overflow::Overflowing => None,
+            }
+        }
+    }
+}
+
+/// Evaluate the binary operator `op` on the field elements `lhs` and `rhs`, if it can be folded
+///
+/// Arithmetic wraps around the field modulus, e.g. `(p - 1) + 2 = 1`.
+fn fold_felt_binary(op: Opcode, lhs: Felt, rhs: Felt) -> Option<Immediate> {
+    let result = match op {
+        Opcode::Add => lhs + rhs,
+        Opcode::Sub => lhs - rhs,
+        Opcode::Mul => lhs * rhs,
+        Opcode::Div if rhs != Felt::ZERO => lhs / rhs,
+        Opcode::Eq => return Some(Immediate::I1(lhs == rhs)),
+        Opcode::Neq => return Some(Immediate::I1(lhs != rhs)),
+        Opcode::Lt => return Some(Immediate::I1(lhs.as_int() < rhs.as_int())),
+        Opcode::Lte => return Some(Immediate::I1(lhs.as_int() <= rhs.as_int())),
+        Opcode::Gt => return Some(Immediate::I1(lhs.as_int() > rhs.as_int())),
+        Opcode::Gte => return Some(Immediate::I1(lhs.as_int() >= rhs.as_int())),
+        _ => return None,
+    };
+    Some(Immediate::Felt(result))
+}
+
+/// Evaluate the unary operator `op` on the constant `arg`, if it can be folded
+fn fold_unary(op: Opcode, arg: Immediate) -> Option<Immediate> {
+    match (op, arg) {
+        (Opcode::Not | Opcode::Bnot, Immediate::I1(b)) => Some(Immediate::I1(!b)),
+        (Opcode::Neg, Immediate::Felt(felt)) => Some(Immediate::Felt(-felt)),
+        (Opcode::Bnot, imm) if imm.ty().is_integer() => {
+            Some(wrap_integer(&imm.ty(), !imm.as_i128()?))
+        }
+        _ => None,
+    }
+}
+
+/// Returns `value` as an immediate of type `ty`, if it is in range for that type
+fn integer_in_range(ty: &Type, value: i128) -> Option<Immediate> {
+    match ty {
+        Type::U8 => value.try_into().ok().map(Immediate::U8),
+        Type::I8 => value.try_into().ok().map(Immediate::I8),
+        Type::U16 => value.try_into().ok().map(Immediate::U16),
+        Type::I16 => value.try_into().ok().map(Immediate::I16),
+        Type::U32 => value.try_into().ok().map(Immediate::U32),
+        Type::I32 => value.try_into().ok().map(Immediate::I32),
+        Type::U64 => value.try_into().ok().map(Immediate::U64),
+        Type::I64 => value.try_into().ok().map(Immediate::I64),
+        _ => None,
+    }
+}
+
+/// Returns `value` as an immediate of type `ty`, wrapping around the range of that type
+fn wrap_integer(ty: &Type, value: i128) -> Immediate {
+    match ty {
+        Type::U8 => Immediate::U8(value as u8),
+        Type::I8 => Immediate::I8(value as i8),
+        Type::U16 => Immediate::U16(value as u16),
+        Type::I16 => Immediate::I16(value as i16),
+        Type::U32 => Immediate::U32(value as u32),
+        Type::I32 => Immediate::I32(value as i32),
+        Type::U64 => Immediate::U64(value as u64),
+        Type::I64 => Immediate::I64(value as i64),
+        ty => unreachable!("unexpected integer type for constant folding: {ty}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Felt, FieldElement, Function, FunctionBuilder, Immediate, InstBuilder, Opcode,
+        Overflow, Signature, SourceSpan, Type, FIELD_MODULUS,
+    };
+    use midenc_session::{OptLevel, Options};
+    use pretty_assertions::assert_eq;
+
+    use super::fold_binary;
+    use crate::FoldConstants;
+
+    fn fold_constants(function: &mut Function) {
+        let options = Options {
+            optimize: OptLevel::Basic,
+            ..Default::default()
+        };
+        let context = TestContext::default_with_opts_and_emitter(options, None);
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = FoldConstants;
+        rewrite
+            .apply(function, &mut analyses, &context.session)
+            .expect("constant folding failed");
+    }
+
+    /// Integer arithmetic and comparisons of constants are folded, unless they would trap
+    #[test]
+    fn fold_constants_integer_test() {
+        let id = "test::fold".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let n = builder.block_params(entry)[0];
+
+            let three = builder.ins().u32(3, SourceSpan::UNKNOWN);
+            let five = builder.ins().u32(5, SourceSpan::UNKNOWN);
+            let max = builder.ins().u32(u32::MAX, SourceSpan::UNKNOWN);
+            let eight = builder.ins().add_checked(three, five, SourceSpan::UNKNOWN);
+            let overflowed = builder.ins().add_checked(max, five, SourceSpan::UNKNOWN);
+            let wrapped = builder.ins().add_wrapping(max, five, SourceSpan::UNKNOWN);
+            let is_less = builder.ins().lt(three, eight, SourceSpan::UNKNOWN);
+            let is_true = builder.ins().not(is_less, SourceSpan::UNKNOWN);
+            let sum = builder.ins().add_wrapping(n, wrapped, SourceSpan::UNKNOWN);
+            let sum = builder.ins().add_wrapping(sum, overflowed, SourceSpan::UNKNOWN);
+            let result = builder.ins().select(is_true, n, sum, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        }
+
+        fold_constants(&mut function);
+
+        let expected = "\
+(func (export #fold) (param u32) (result u32)
+    (block 0 (param v0 u32)
+        (let (v1 u32) (const.u32 3))
+        (let (v2 u32) (const.u32 5))
+        (let (v3 u32) (const.u32 4294967295))
+        (let (v4 u32) (const.u32 8))
+        (let (v5 u32) (add.checked v3 v2))
+        (let (v6 u32) (const.u32 4))
+        (let (v7 i1) (const.i1 true))
+        (let (v8 i1) (const.i1 false))
+        (let (v9 u32) (add.wrapping v0 v6))
+        (let (v10 u32) (add.wrapping v9 v5))
+        (let (v11 u32) (select v8 v0 v10))
+        (ret v11))
+)";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// Arithmetic on 64-bit constants is folded without overflowing the intermediate result
+    #[test]
+    fn fold_constants_u64_test() {
+        let id = "test::fold_u64".parse().unwrap();
+        let mut function = Function::new(id, Signature::new([], [AbiParam::new(Type::U64)]));
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let max = builder.ins().u64(u64::MAX, SourceSpan::UNKNOWN);
+            let overflowed = builder.ins().mul_checked(max, max, SourceSpan::UNKNOWN);
+            let wrapped = builder.ins().mul_wrapping(max, max, SourceSpan::UNKNOWN);
+            let sum = builder.ins().add_wrapping(max, wrapped, SourceSpan::UNKNOWN);
+            let result = builder.ins().add_wrapping(sum, overflowed, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        }
+
+        fold_constants(&mut function);
+
+        // (2^64 - 1)^2 = 1 (mod 2^64), and (2^64 - 1) + 1 = 0 (mod 2^64)
+        let expected = "\
+(func (export #fold_u64) (result u64)
+    (block 0
+        (let (v0 u64) (const.u64 18446744073709551615))
+        (let (v1 u64) (mul.checked v0 v0))
+        (let (v2 u64) (const.u64 1))
+        (let (v3 u64) (const.u64 0))
+        (let (v4 u64) (add.wrapping v3 v1))
+        (ret v4))
+)";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// Arithmetic which overflows an i128 is never folded
+    #[test]
+    fn fold_constants_i128_overflow_test() {
+        let max = Immediate::I128(i128::MAX);
+        let min = Immediate::I128(i128::MIN);
+        for overflow in [Overflow::Checked, Overflow::Unchecked, Overflow::Wrapping] {
+            assert_eq!(fold_binary(Opcode::Add, overflow, max, max), None);
+            assert_eq!(fold_binary(Opcode::Sub, overflow, min, max), None);
+            assert_eq!(fold_binary(Opcode::Mul, overflow, min, max), None);
+            assert!(matches!(
+                fold_binary(Opcode::Lt, overflow, min, max),
+                Some(Immediate::I1(true))
+            ));
+        }
+    }
+
+    /// Field arithmetic on constants is folded modulo the Goldilocks prime
+    #[test]
+    fn fold_constants_felt_test() {
+        let id = "test::fold_felt".parse().unwrap();
+        let mut function = Function::new(id, Signature::new([], [AbiParam::new(Type::Felt)]));
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let max = builder.ins().felt(Felt::new(FIELD_MODULUS - 1), SourceSpan::UNKNOWN);
+            let two = builder.ins().felt(Felt::new(2), SourceSpan::UNKNOWN);
+            let sum = builder.ins().add_unchecked(max, two, SourceSpan::UNKNOWN);
+            let zero = builder.ins().felt(Felt::ZERO, SourceSpan::UNKNOWN);
+            let diff = builder.ins().sub_unchecked(zero, sum, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(diff), SourceSpan::UNKNOWN);
+        }
+
+        fold_constants(&mut function);
+
+        // (p - 1) + 2 = 1, and 0 - 1 = p - 1
+        let expected = format!(
+            "\
+(func (export #fold_felt) (result felt)
+    (block 0
+        (let (v0 felt) (const.felt {max}))
+        (let (v1 felt) (const.felt 2))
+        (let (v2 felt) (const.felt 1))
+        (let (v3 felt) (const.felt 0))
+        (let (v4 felt) (const.felt {max}))
+        (ret v4))
+)",
+            max = FIELD_MODULUS - 1
+        );
+
+        assert_eq!(function.to_string(), expected);
+    }
+}
@@ -0,0 +1,403 @@
+use miden_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use miden_hir_analysis::{ControlFlowGraph, DominatorTree, LivenessAnalysis, Loop, LoopAnalysis};
+use midenc_session::Session;
+use smallvec::SmallVec;
+
+/// This pass moves instructions which compute the same value on every iteration of a loop out of
+/// the loop, and into its preheader, so that the value is computed once, rather than on every
+/// iteration.
+///
+/// The preheader of a loop is the only predecessor of the loop header from outside the loop, and
+/// must unconditionally branch to the header. Loops without a preheader are left unchanged, but
+/// as [SplitCriticalEdges] introduces a block on the edge into any loop entered conditionally,
+/// this pass is most effective when run after it.
+///
+/// An instruction is loop-invariant when all of its arguments are defined outside the loop, or by
+/// other loop-invariant instructions. It is only hoisted if it has no side effects, does not read
+/// memory, and cannot trap, as it may be executed when the original instruction would not have
+/// been, e.g. when the loop body is conditionally executed.
+///
+/// Constants are not hoisted on their own, as they are cheaper to materialize where they are used
+/// than to keep on the operand stack for the duration of the loop. They are only hoisted along
+/// with an instruction which uses them.
+///
+/// Loops are visited from the innermost outwards, so that an instruction hoisted out of an inner
+/// loop may then be hoisted out of the loop containing it.
+///
+/// [SplitCriticalEdges]: crate::SplitCriticalEdges
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct HoistLoopInvariants;
+impl RewritePass for HoistLoopInvariants {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        let cfg = analyses.get_or_compute::<ControlFlowGraph>(function, session)?;
+        let domtree = analyses.get_or_compute::<DominatorTree>(function, session)?;
+        let loops = analyses.get_or_compute::<LoopAnalysis>(function, session)?;
+
+        // Moving instructions other than terminators never modifies the control flow graph
+        analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+        analyses.mark_preserved::<DominatorTree>(&function.id);
+        analyses.mark_preserved::<LoopAnalysis>(&function.id);
+
+        let mut innermost_first = loops.loops().collect::<SmallVec<[Loop; 4]>>();
+        innermost_first.sort_by_key(|lp| core::cmp::Reverse(loops.level(*lp)));
+
+        let mut changed = false;
+        for lp in innermost_first {
+            let Some(preheader) = find_preheader(lp, function, &cfg, &loops) else {
+                continue;
+            };
+
+            // Visit the blocks of the loop in reverse postorder, so that the definition of a value
+            // is always visited before its uses
+            let blocks = domtree
+                .cfg_postorder()
+                .iter()
+                .rev()
+                .copied()
+                .filter(|block| loops.is_in_loop(*block, lp))
+                .collect::<SmallVec<[Block; 8]>>();
+            for block in blocks {
+                let insts = function.dfg.block_insts(block).collect::<SmallVec<[Inst; 8]>>();
+                for inst in insts {
+                    if !is_hoistable(inst, function) {
+                        continue;
+                    }
+
+                    let mut constants = SmallVec::<[Inst; 2]>::new();
+                    let is_invariant = function.dfg.inst_args(inst).iter().all(|arg| {
+                        match function.dfg.value_data(*arg) {
+                            ValueData::Inst { inst: def, .. } => {
+                                let def_block = function.dfg.inst_block(*def).unwrap();
+                                if !loops.is_in_loop(def_block, lp) {
+                                    return true;
+                                }
+                                // Constants defined in the loop are hoisted along with their user
+                                if is_constant(*def, function) {
+                                    constants.push(*def);
+                                    true
+                                } else {
+                                    false
+                                }
+                            }
+                            ValueData::Param { block, .. } => !loops.is_in_loop(*block, lp),
+                        }
+                    });
+                    if !is_invariant {
+                        continue;
+                    }
+
+                    for constant in constants {
+                        // A constant used more than once by this instruction is only moved once
+                        if function.dfg.inst_block(constant) != Some(preheader.0) {
+                            function.dfg.move_inst_before(constant, preheader.1);
+                        }
+                    }
+                    function.dfg.move_inst_before(inst, preheader.1);
+                    changed = true;
+                }
+            }
+        }
+
+        // Any liveness computed prior to this pass refers to the original location of the
+        // instructions we moved
+        if changed {
+            analyses.mark_invalid::<LivenessAnalysis>(&function.id);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the preheader of `lp`, along with its terminator, if it has one
+fn find_preheader(
+    lp: Loop,
+    function: &hir::Function,
+    cfg: &ControlFlowGraph,
+    loops: &LoopAnalysis,
+) -> Option<(Block, Inst)> {
+    let header = loops.loop_header(lp);
+    let mut entries = cfg.pred_iter(header).filter(|pred| !loops.is_in_loop(pred.block, lp));
+    let entry = entries.next()?;
+    if entries.next().is_some() {
+        return None;
+    }
+
+    match function.dfg.inst(entry.inst) {
+        Instruction::Br(_) => Some((entry.block, entry.inst)),
+        _ => None,
+    }
+}
+
+/// Returns true if `inst` may be executed speculatively, i.e. it has no side effects, its result
+/// does not depend on the state of memory, and it cannot trap
+fn is_hoistable(inst: Inst, function: &hir::Function) -> bool {
+    let data = function.dfg.inst(inst);
+    if data.has_side_effects()
+        || is_constant(inst, function)
+        || matches!(data.overflow(), Some(Overflow::Checked))
+    {
+        return false;
+    }
+
+    // Only opcodes which are known not to trap are hoisted, e.g. `inttoptr` asserts that its
+    // argument is a valid address, and `div` that its divisor is non-zero
+    match data {
+        Instruction::GlobalValue(GlobalValueOp { global, .. }) => {
+            !matches!(function.dfg.global_value(*global), GlobalValueData::Load { .. })
+        }
+        _ => matches!(
+            data.opcode(),
+            Opcode::PtrToInt
+                | Opcode::Select
+                | Opcode::Add
+                | Opcode::Sub
+                | Opcode::Mul
+                | Opcode::Neg
+                | Opcode::Incr
+                | Opcode::Bnot
+                | Opcode::Band
+                | Opcode::Bor
+                | Opcode::Bxor
+                | Opcode::Popcnt
+                | Opcode::Clz
+                | Opcode::Ctz
+                | Opcode::Clo
+                | Opcode::Cto
+                | Opcode::Eq
+                | Opcode::Neq
+                | Opcode::Gt
+                | Opcode::Gte
+                | Opcode::Lt
+                | Opcode::Lte
+                | Opcode::IsOdd
+                | Opcode::Min
+                | Opcode::Max
+        ),
+    }
+}
+
+/// Returns true if `inst` materializes a constant
+fn is_constant(inst: Inst, function: &hir::Function) -> bool {
+    matches!(function.dfg.inst(inst), Instruction::UnaryOpImm(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Opcode, Signature, SourceSpan,
+        Type,
+    };
+    use pretty_assertions::assert_eq;
+
+    use crate::HoistLoopInvariants;
+
+    /// Construct a function with a loop whose body multiplies a function argument by a constant:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32, u32) -> u32 {
+    /// entry(n0: u32, x: u32):
+    ///    br blk1(n0, x);
+    ///
+    /// blk1(n1: u32, acc0: u32):
+    ///    is_zero = eq n1, 0;
+    ///    condbr is_zero, blk3(acc0), blk2;
+    ///
+    /// blk2:
+    ///    scaled = mul.wrapping x, 4;
+    ///    acc1 = add.wrapping acc0, scaled;
+    ///    n2 = sub.wrapping n1, 1;
+    ///    br blk1(n2, acc1);
+    ///
+    /// blk3(result: u32):
+    ///    ret result;
+    /// }
+    /// ```
+    ///
+    /// The multiply is the same on every iteration, so we expect it to be hoisted into the entry
+    /// block, while the other instructions of the loop depend on the loop parameters, and stay put.
+    #[test]
+    fn hoist_loop_invariants_test() {
+        let context = TestContext::default();
+        let id = "test::licm".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (n0, x) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+
+            let a = builder.create_block(); // blk1(n1: u32, acc0: u32)
+            let n1 = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            let acc0 = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            let b = builder.create_block(); // blk2
+            let c = builder.create_block(); // blk3(result: u32)
+            let result = builder.append_block_param(c, Type::U32, SourceSpan::UNKNOWN);
+
+            // entry
+            builder.ins().br(a, &[n0, x], SourceSpan::UNKNOWN);
+
+            // blk1
+            builder.switch_to_block(a);
+            let is_zero = builder.ins().eq_imm(n1, Immediate::U32(0), SourceSpan::UNKNOWN);
+            builder.ins().cond_br(is_zero, c, &[acc0], b, &[], SourceSpan::UNKNOWN);
+
+            // blk2
+            builder.switch_to_block(b);
+            let scaled = builder.ins().mul_imm_wrapping(x, Immediate::U32(4), SourceSpan::UNKNOWN);
+            let acc1 = builder.ins().add_wrapping(acc0, scaled, SourceSpan::UNKNOWN);
+            let n2 = builder.ins().sub_imm_wrapping(n1, Immediate::U32(1), SourceSpan::UNKNOWN);
+            builder.ins().br(a, &[n2, acc1], SourceSpan::UNKNOWN);
+
+            // blk3
+            builder.switch_to_block(c);
+            builder.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        }
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = HoistLoopInvariants;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("hoisting loop invariants failed");
+
+        let expected = "\
+(func (export #licm) (param u32) (param u32) (result u32)
+    (block 0 (param v0 u32) (param v1 u32)
+        (let (v6 u32) (mul.wrapping v1 4))
+        (br (block 1 v0 v1)))
+
+    (block 1 (param v2 u32) (param v3 u32)
+        (let (v5 i1) (eq v2 0))
+        (condbr v5 (block 3 v3) (block 2)))
+
+    (block 2
+        (let (v7 u32) (add.wrapping v3 v6))
+        (let (v8 u32) (sub.wrapping v2 1))
+        (br (block 1 v8 v7)))
+
+    (block 3 (param v4 u32)
+        (ret v4))
+)";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// Construct a function with a loop whose body loads a value from an address given as a
+    /// function argument, and accumulates it:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32, u32) -> u32 {
+    /// entry(n0: u32, addr: u32):
+    ///    br blk1(n0, 0);
+    ///
+    /// blk1(n1: u32, acc0: u32):
+    ///    is_zero = eq n1, 0;
+    ///    condbr is_zero, blk3(acc0), blk2;
+    ///
+    /// blk2:
+    ///    ptr = inttoptr addr : *mut u32;
+    ///    value = load ptr;
+    ///    acc1 = add.wrapping acc0, value;
+    ///    n2 = sub.wrapping n1, 1;
+    ///    br blk1(n2, acc1);
+    ///
+    /// blk3(result: u32):
+    ///    ret result;
+    /// }
+    /// ```
+    ///
+    /// The `inttoptr` and the load are loop-invariant, but the former traps on an invalid address,
+    /// and the latter depends on the state of memory, so we expect neither to be hoisted, as the
+    /// loop body is not executed when `n0` is zero.
+    #[test]
+    fn hoist_loop_invariants_trapping_test() {
+        let context = TestContext::default();
+        let id = "test::licm".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        let b = {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (n0, addr) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+
+            let a = builder.create_block(); // blk1(n1: u32, acc0: u32)
+            let n1 = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            let acc0 = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            let b = builder.create_block(); // blk2
+            let c = builder.create_block(); // blk3(result: u32)
+            let result = builder.append_block_param(c, Type::U32, SourceSpan::UNKNOWN);
+
+            // entry
+            let zero = builder.ins().u32(0, SourceSpan::UNKNOWN);
+            builder.ins().br(a, &[n0, zero], SourceSpan::UNKNOWN);
+
+            // blk1
+            builder.switch_to_block(a);
+            let is_zero = builder.ins().eq_imm(n1, Immediate::U32(0), SourceSpan::UNKNOWN);
+            builder.ins().cond_br(is_zero, c, &[acc0], b, &[], SourceSpan::UNKNOWN);
+
+            // blk2
+            builder.switch_to_block(b);
+            let ptr =
+                builder
+                    .ins()
+                    .inttoptr(addr, Type::Ptr(Box::new(Type::U32)), SourceSpan::UNKNOWN);
+            let value = builder.ins().load(ptr, SourceSpan::UNKNOWN);
+            let acc1 = builder.ins().add_wrapping(acc0, value, SourceSpan::UNKNOWN);
+            let n2 = builder.ins().sub_imm_wrapping(n1, Immediate::U32(1), SourceSpan::UNKNOWN);
+            builder.ins().br(a, &[n2, acc1], SourceSpan::UNKNOWN);
+
+            // blk3
+            builder.switch_to_block(c);
+            builder.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+            b
+        };
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = HoistLoopInvariants;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("hoisting loop invariants failed");
+
+        let opcodes = function
+            .dfg
+            .block_insts(b)
+            .map(|inst| function.dfg.inst(inst).opcode())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            opcodes,
+            vec![Opcode::IntToPtr, Opcode::Load, Opcode::Add, Opcode::Sub, Opcode::Br]
+        );
+    }
+}
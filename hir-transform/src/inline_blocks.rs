@@ -178,6 +178,10 @@ fn inline(
             worklist.push_back(*then_blk);
             worklist.push_back(*else_blk);
         }
+        Instruction::Switch(Switch { arms, default, .. }) => {
+            worklist.extend(arms.iter().map(|(_, dest)| *dest));
+            worklist.push_back(*default);
+        }
         _ => (),
     }
     function.dfg.blocks[from].insts.push_back(UnsafeRef::from_box(from_terminator));
@@ -259,6 +263,18 @@ fn rewrite_use(
                 }
             }
         }
+        Instruction::Switch(Switch {
+            ref mut arg,
+            ref arms,
+            default,
+            ..
+        }) => {
+            worklist.extend(arms.iter().map(|(_, dest)| *dest));
+            worklist.push(*default);
+            if let Some(replacement) = rewrites.get(arg).copied() {
+                *arg = replacement;
+            }
+        }
         op => {
             for arg in op.arguments_mut(pool) {
                 if let Some(replacement) = rewrites.get(arg).copied() {
@@ -1,8 +1,19 @@
 pub(crate) mod adt;
+mod elide_bounds_checks;
+mod eliminate_dead_block_params;
+mod eliminate_dead_code;
+mod fold_constant_operands;
+mod fold_constants;
+mod hoist_loop_invariants;
 mod inline_blocks;
 mod split_critical_edges;
+mod trap_infinite_loops;
 mod treeify;
 
 pub use self::{
-    inline_blocks::InlineBlocks, split_critical_edges::SplitCriticalEdges, treeify::Treeify,
+    elide_bounds_checks::ElideBoundsChecks, eliminate_dead_block_params::EliminateDeadBlockParams,
+    eliminate_dead_code::EliminateDeadCode, fold_constant_operands::FoldConstantOperands,
+    fold_constants::FoldConstants, hoist_loop_invariants::HoistLoopInvariants,
+    inline_blocks::InlineBlocks, split_critical_edges::SplitCriticalEdges,
+    trap_infinite_loops::TrapInfiniteLoops, treeify::Treeify,
 };
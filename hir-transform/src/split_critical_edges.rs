@@ -23,6 +23,9 @@ use smallvec::SmallVec;
 /// a new block, `B`, in which we insert a branch to `S` with whatever arguments were originally
 /// provided in `P`, and then rewriting the branch in `P` that went to `S`, to go to `B` instead.
 ///
+/// Additionally, when a switch has multiple edges to the same successor, each of those edges is
+/// split in the same way, so that each successor of a switch is reached by exactly one arm.
+///
 /// After this pass completes, no node in the control flow graph will have both multiple
 /// predecessors and multiple successors.
 #[derive(Default, PassInfo, ModuleRewritePassAdapter)]
@@ -70,64 +73,88 @@ impl RewritePass for SplitCriticalEdges {
 
             let succs = SmallVec::<[BlockId; 2]>::from_iter(cfg.succ_iter(p));
             for b in succs.into_iter() {
-                // Unless this successor has multiple predecessors, skip it
-                if cfg.num_predecessors(b) < 2 {
+                // A switch may have multiple edges to the same successor, and each of them is
+                // split, so that every successor of a switch is unique
+                let num_edges = match function.dfg.inst(function.dfg.last_inst(p).unwrap()) {
+                    Instruction::Switch(hir::Switch { arms, default, .. }) => {
+                        arms.iter().filter(|(_, dest)| *dest == b).count()
+                            + (*default == b) as usize
+                    }
+                    _ => 1,
+                };
+
+                // Unless this successor has multiple predecessors, or multiple edges from this
+                // block, skip it
+                if cfg.num_predecessors(b) < 2 && num_edges < 2 {
                     continue;
                 }
 
-                // We found a critical edge, so perform the following steps:
-                //
-                // * Create a new block, placed after the predecessor in the layout
-                // * Rewrite the terminator of the predecessor to refer to the new
-                // block, but without passing any block arguments
-                // * Insert an unconditional branch to the successor with the block
-                // arguments of the original terminator
-                // * Recompute the control flow graph for affected blocks
-                let split = function.dfg.create_block_after(p);
-                let terminator = function.dfg.last_inst(p).unwrap();
-                let span = function.dfg.inst_span(terminator);
-                let ix = function.dfg.inst_mut(terminator);
-                let args: ValueList;
-                match ix {
-                    Instruction::Br(hir::Br {
-                        ref mut destination,
-                        args: ref mut orig_args,
-                        ..
-                    }) => {
-                        args = orig_args.take();
-                        *destination = split;
-                    }
-                    Instruction::CondBr(hir::CondBr {
-                        then_dest: (ref mut then_dest, ref mut then_args),
-                        else_dest: (ref mut else_dest, ref mut else_args),
-                        ..
-                    }) => {
-                        if *then_dest == b {
-                            *then_dest = split;
-                            args = then_args.take();
-                        } else {
-                            *else_dest = split;
-                            args = else_args.take();
+                for _ in 0..num_edges {
+                    // We found a critical edge, so perform the following steps:
+                    //
+                    // * Create a new block, placed after the predecessor in the layout
+                    // * Rewrite the terminator of the predecessor to refer to the new
+                    // block, but without passing any block arguments
+                    // * Insert an unconditional branch to the successor with the block
+                    // arguments of the original terminator
+                    // * Recompute the control flow graph for affected blocks
+                    let split = function.dfg.create_block_after(p);
+                    let terminator = function.dfg.last_inst(p).unwrap();
+                    let span = function.dfg.inst_span(terminator);
+                    let ix = function.dfg.inst_mut(terminator);
+                    let args: ValueList;
+                    match ix {
+                        Instruction::Br(hir::Br {
+                            ref mut destination,
+                            args: ref mut orig_args,
+                            ..
+                        }) => {
+                            args = orig_args.take();
+                            *destination = split;
                         }
+                        Instruction::CondBr(hir::CondBr {
+                            then_dest: (ref mut then_dest, ref mut then_args),
+                            else_dest: (ref mut else_dest, ref mut else_args),
+                            ..
+                        }) => {
+                            if *then_dest == b {
+                                *then_dest = split;
+                                args = then_args.take();
+                            } else {
+                                *else_dest = split;
+                                args = else_args.take();
+                            }
+                        }
+                        Instruction::Switch(hir::Switch {
+                            ref mut arms,
+                            ref mut default,
+                            ..
+                        }) => {
+                            // Switch successors do not take block arguments
+                            args = ValueList::new();
+                            match arms.iter_mut().find(|(_, dest)| *dest == b) {
+                                Some((_, dest)) => *dest = split,
+                                None => *default = split,
+                            }
+                        }
+                        _ => unreachable!(),
                     }
-                    Instruction::Switch(_) => unimplemented!(),
-                    _ => unreachable!(),
+                    function.dfg.insert_inst(
+                        InsertionPoint {
+                            at: ProgramPoint::Block(split),
+                            action: Insert::After,
+                        },
+                        Instruction::Br(hir::Br {
+                            op: hir::Opcode::Br,
+                            destination: b,
+                            args,
+                        }),
+                        Type::Unknown,
+                        span,
+                    );
+
+                    cfg.recompute_block(&function.dfg, split);
                 }
-                function.dfg.insert_inst(
-                    InsertionPoint {
-                        at: ProgramPoint::Block(split),
-                        action: Insert::After,
-                    },
-                    Instruction::Br(hir::Br {
-                        op: hir::Opcode::Br,
-                        destination: b,
-                        args,
-                    }),
-                    Type::Unknown,
-                    span,
-                );
-
-                cfg.recompute_block(&function.dfg, split);
             }
 
             cfg.recompute_block(&function.dfg, p);
@@ -0,0 +1,171 @@
+use miden_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use miden_hir_analysis::ControlFlowGraph;
+use midenc_session::Session;
+use smallvec::SmallVec;
+
+/// This pass replaces loops which can never exit, and which do nothing on each iteration, with a
+/// trap, when the `trap_infinite_loops` option is enabled. Otherwise, the IR is left unchanged.
+///
+/// Such loops are typically found in the `#[panic_handler]` of a `no_std` crate, which must never
+/// return, and so is usually written as `loop {}`. When compiled as-is, a panic spins in an empty
+/// `while.true` loop until the VM runs out of cycles. Trapping instead aborts execution as soon as
+/// the panic occurs, deterministically, and without wasting cycles.
+///
+/// A loop is recognized when a block consists of nothing but an unconditional branch to itself.
+/// The branch is replaced with `unreachable`, which is lowered to an assertion that always fails.
+///
+/// NOTE: This changes a program which would never terminate into one which fails, so it is only
+/// appropriate when spinning forever is never the intended behavior.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct TrapInfiniteLoops;
+impl RewritePass for TrapInfiniteLoops {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        if !session.options.trap_infinite_loops {
+            analyses.mark_all_preserved::<hir::Function>(&function.id);
+            return Ok(());
+        }
+
+        let mut cfg = analyses
+            .take::<ControlFlowGraph>(&function.id)
+            .unwrap_or_else(|| ControlFlowGraph::with_function(function));
+
+        let mut changed = false;
+        let blocks = function.dfg.blocks().map(|(b, _)| b).collect::<SmallVec<[Block; 8]>>();
+        for block in blocks {
+            let Some(terminator) = function.dfg.last_inst(block) else {
+                continue;
+            };
+            if !is_infinite_loop(function, block, terminator) {
+                continue;
+            }
+
+            let span = function.dfg.inst_span(terminator);
+            function.dfg.replace(terminator).unreachable(span);
+            cfg.recompute_block(&function.dfg, block);
+
+            changed = true;
+        }
+
+        analyses.insert(function.id, cfg);
+        if !changed {
+            analyses.mark_all_preserved::<hir::Function>(&function.id);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns true if `terminator` is the only instruction in `block`, and branches back to `block`
+fn is_infinite_loop(function: &hir::Function, block: Block, terminator: Inst) -> bool {
+    let is_only_inst = function.dfg.block(block).first() == Some(terminator);
+    match function.dfg.inst(terminator) {
+        Instruction::Br(Br { destination, .. }) => is_only_inst && *destination == block,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Function, FunctionBuilder, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use miden_hir_analysis::DominatorTree;
+    use midenc_session::Options;
+    use pretty_assertions::assert_eq;
+
+    use crate::TrapInfiniteLoops;
+
+    /// Construct a function which loops forever, like the panic handler of a `no_std` crate:
+    ///
+    /// ```text,ignore
+    /// pub fn test(i32) {
+    /// entry(info: i32):
+    ///    br blk1;
+    ///
+    /// blk1:
+    ///    br blk1;
+    /// }
+    /// ```
+    fn panic_handler_function() -> Function {
+        let id = "test::panic".parse().unwrap();
+        let mut function = Function::new(id, Signature::new([AbiParam::new(Type::I32)], []));
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let a = builder.create_block(); // blk1
+
+            // entry
+            builder.ins().br(a, &[], SourceSpan::UNKNOWN);
+
+            // blk1
+            builder.switch_to_block(a);
+            builder.ins().br(a, &[], SourceSpan::UNKNOWN);
+        }
+
+        function
+    }
+
+    /// Infinite loops are kept unless explicitly requested otherwise
+    #[test]
+    fn trap_infinite_loops_default_test() {
+        let context = TestContext::default();
+        let mut function = panic_handler_function();
+
+        let original = function.to_string();
+        let mut analyses = AnalysisManager::default();
+        analyses
+            .get_or_compute::<DominatorTree>(&function, &context.session)
+            .expect("failed to compute dominator tree");
+        let mut rewrite = TrapInfiniteLoops;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("trapping infinite loops failed");
+
+        assert_eq!(function.to_string(), original);
+
+        // As the function is unchanged, every analysis of it remains valid
+        analyses.invalidate::<Function>(&function.id);
+        assert!(analyses.is_available::<DominatorTree>(&function.id));
+    }
+
+    /// Infinite loops are replaced with a trap when `trap_infinite_loops` is set
+    #[test]
+    fn trap_infinite_loops_test() {
+        let options = Options {
+            trap_infinite_loops: true,
+            ..Default::default()
+        };
+        let context = TestContext::default_with_opts_and_emitter(options, None);
+        let mut function = panic_handler_function();
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = TrapInfiniteLoops;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("trapping infinite loops failed");
+
+        let expected = "\
+(func (export #panic) (param i32)
+    (block 0 (param v0 i32)
+        (br (block 1)))
+
+    (block 1
+        (unreachable))
+)";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
@@ -574,6 +574,24 @@ fn copy_instructions(
                     }
                 }
             }
+            Instruction::Switch(hir::Switch {
+                ref mut arg,
+                ref mut arms,
+                ref mut default,
+                ..
+            }) => {
+                if let Some(arg_prime) = value_map.get(arg) {
+                    *arg = *arg_prime;
+                }
+                for (_, dest) in arms.iter_mut() {
+                    if let Some(new_dest) = block_map.get(dest) {
+                        *dest = *new_dest;
+                    }
+                }
+                if let Some(new_dest) = block_map.get(default) {
+                    *default = *new_dest;
+                }
+            }
             other => {
                 for arg in other.arguments_mut(&mut function.dfg.value_lists).iter_mut() {
                     if let Some(arg_prime) = value_map.get(arg) {
@@ -636,8 +654,17 @@ where
             callback(then_dest, then_args, value_lists);
             callback(else_dest, else_args, value_lists);
         }
-        Instruction::Switch(_) => {
-            panic!("expected switch instructions to have been simplified prior to treeification")
+        Instruction::Switch(hir::Switch {
+            ref mut arms,
+            ref mut default,
+            ..
+        }) => {
+            // Switch successors never take block arguments
+            let value_lists = &mut function.dfg.value_lists;
+            for (_, dest) in arms.iter_mut() {
+                callback(dest, &mut ValueList::new(), value_lists);
+            }
+            callback(default, &mut ValueList::new(), value_lists);
         }
         _ => unreachable!(),
     }
@@ -458,6 +458,11 @@ impl<'a> MasmOpBuilder<'a> {
         self.build(self.ip, MasmOp::Exec(id));
     }
 
+    /// Executes the named procedure in a new memory context.
+    pub fn call(mut self, id: FunctionIdent) {
+        self.build(self.ip, MasmOp::Call(id));
+    }
+
     /// Execute a procedure indirectly.
     ///
     /// Expects the hash of a function's MAST root on the stack, see `procref`
@@ -1402,6 +1407,7 @@ impl<'f> LoopBuilder<'f> {
                     MasmBlock {
                         id,
                         ops: smallvec![],
+                        origin: None,
                     },
                 );
                 self.asm.blocks[self.ip].append(&mut block.ops);
@@ -1699,7 +1705,11 @@ fn apply_op_stack_effects(
         MasmOp::AssertEqw | MasmOp::AssertEqwWithError(_) => {
             stack.dropn(8);
         }
-        MasmOp::LocAddr(_id) | MasmOp::LocStore(_id) | MasmOp::LocStorew(_id) => unreachable!(),
+        MasmOp::LocAddr(_id)
+        | MasmOp::LocStore(_id)
+        | MasmOp::LocStorew(_id)
+        | MasmOp::LocLoad(_id)
+        | MasmOp::LocLoadw(_id) => unreachable!(),
         MasmOp::MemLoad | MasmOp::MemLoadOffset => {
             let ty = stack.pop().expect("operand stack is empty");
             assert_matches!(
@@ -1821,7 +1831,7 @@ fn apply_op_stack_effects(
                 }
             }
         }
-        MasmOp::Exec(ref id) => {
+        MasmOp::Exec(ref id) | MasmOp::Call(ref id) => {
             execute_call(id, false, stack, dfg);
         }
         MasmOp::Syscall(ref id) => {
@@ -2059,7 +2069,7 @@ fn execute_call(
     }
     match import.signature.cc {
         // For now, we're treating all calling conventions the same as SystemV
-        CallConv::Fast | CallConv::SystemV | CallConv::Kernel => {
+        CallConv::Fast | CallConv::SystemV | CallConv::Kernel | CallConv::Account => {
             // Visit the argument list in reverse (so that the top of the stack on entry
             // is the first argument), and allocate elements based on the argument types.
             let mut elements_needed = 0;
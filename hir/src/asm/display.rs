@@ -132,6 +132,10 @@ impl<'a> PrettyPrint for DisplayMasmBlock<'a> {
             .reduce(|acc, e| acc + nl() + e)
             .unwrap_or_default();
 
+        if let Some(origin) = block.origin {
+            return text(format!("# from {origin}")) + nl() + multiline;
+        }
+
         if block.ops.len() < 5 && !block.ops.iter().any(|op| op.has_regions()) {
             let singleline = block
                 .ops
@@ -158,8 +162,11 @@ impl<'a> fmt::Display for DisplayMasmBlock<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let block = &self.blocks[self.block];
         let indent = self.indent;
+        if let Some(origin) = block.origin {
+            write!(f, "{}# from {origin}", DisplayIndent(indent))?;
+        }
         for (i, op) in block.ops.iter().enumerate() {
-            if i > 0 {
+            if i > 0 || block.origin.is_some() {
                 f.write_char('\n')?;
             }
             write!(
@@ -232,7 +239,11 @@ impl<'a> PrettyPrint for DisplayOp<'a> {
             | MasmOp::Movupw(idx)
             | MasmOp::Movdn(idx)
             | MasmOp::Movdnw(idx)) => text(format!("{op}")) + const_text(".") + display(*idx),
-            op @ (MasmOp::LocAddr(id) | MasmOp::LocStore(id) | MasmOp::LocStorew(id)) => {
+            op @ (MasmOp::LocAddr(id)
+            | MasmOp::LocStore(id)
+            | MasmOp::LocStorew(id)
+            | MasmOp::LocLoad(id)
+            | MasmOp::LocLoadw(id)) => {
                 text(format!("{op}")) + const_text(".") + display(id.as_usize())
             }
             op @ (MasmOp::MemLoadImm(addr)
@@ -305,7 +316,10 @@ impl<'a> PrettyPrint for DisplayOp<'a> {
                     + nl()
                     + const_text("end")
             }
-            op @ (MasmOp::Exec(id) | MasmOp::Syscall(id) | MasmOp::ProcRef(id)) => {
+            op @ (MasmOp::Exec(id)
+            | MasmOp::Call(id)
+            | MasmOp::Syscall(id)
+            | MasmOp::ProcRef(id)) => {
                 let FunctionIdent { module, function } = id;
                 if self.is_local_module(module) {
                     text(format!("{op}")) + const_text(".") + display(function)
@@ -369,7 +383,11 @@ impl<'a> fmt::Display for DisplayOp<'a> {
             | MasmOp::Movupw(idx)
             | MasmOp::Movdn(idx)
             | MasmOp::Movdnw(idx)) => write!(f, "{op}.{idx}"),
-            op @ (MasmOp::LocAddr(id) | MasmOp::LocStore(id) | MasmOp::LocStorew(id)) => {
+            op @ (MasmOp::LocAddr(id)
+            | MasmOp::LocStore(id)
+            | MasmOp::LocStorew(id)
+            | MasmOp::LocLoad(id)
+            | MasmOp::LocLoadw(id)) => {
                 write!(f, "{op}.{}", id.as_usize())
             }
             op @ (MasmOp::MemLoadImm(addr)
@@ -433,7 +451,10 @@ impl<'a> fmt::Display for DisplayOp<'a> {
                     DisplayIndent(self.indent),
                 )
             }
-            op @ (MasmOp::Exec(id) | MasmOp::Syscall(id) | MasmOp::ProcRef(id)) => {
+            op @ (MasmOp::Exec(id)
+            | MasmOp::Call(id)
+            | MasmOp::Syscall(id)
+            | MasmOp::ProcRef(id)) => {
                 let FunctionIdent { module, function } = id;
                 if self.is_local_module(module) {
                     write!(f, "{op}.{}", function.as_str())
@@ -16,6 +16,10 @@ entity_impl!(MasmBlockId, "blk");
 pub struct MasmBlock {
     pub id: MasmBlockId,
     pub ops: SmallVec<[MasmOp; 4]>,
+    /// The block of the function from which this code was emitted, if known
+    ///
+    /// When set, this is rendered as a comment preceding the code, e.g. `# from block2`
+    pub origin: Option<crate::Block>,
 }
 impl MasmBlock {
     /// Returns true if there are no instructions in this block
@@ -189,6 +193,12 @@ pub enum MasmOp {
     LocStore(LocalId),
     /// Writes a word to the address corresponding to the given local index
     LocStorew(LocalId),
+    /// Reads the first element of the word at the address corresponding to the given local
+    /// index, and places it on top of the stack
+    LocLoad(LocalId),
+    /// Overwrites the word on top of the stack with the word at the address corresponding to
+    /// the given local index
+    LocLoadw(LocalId),
     /// Pops `a`, representing a memory address, from the top of the stack, then loads the
     /// first element of the word starting at that address, placing it on top of the stack.
     ///
@@ -278,6 +288,9 @@ pub enum MasmOp {
     Repeat(u8, MasmBlockId),
     /// Pops `N` args off the stack, executes the procedure, results will be placed on the stack
     Exec(FunctionIdent),
+    /// Pops `N` args off the stack, executes the procedure in a new memory context, results will
+    /// be placed on the stack
+    Call(FunctionIdent),
     /// Pops `N` args off the stack, executes the procedure in the root context, results will be
     /// placed on the stack
     Syscall(FunctionIdent),
@@ -605,6 +618,57 @@ impl MasmOp {
         matches!(self, Self::If(_, _) | Self::While(_) | Self::Repeat(_, _))
     }
 
+    /// Returns true if executing this operation may cause the program to trap
+    ///
+    /// This is conservative: calls are always assumed to trap, as are operations which trap only
+    /// when given invalid operands (e.g. a zero divisor, or a non-boolean condition), or when the
+    /// advice stack is exhausted. Operations which are merely undefined on invalid operands, such
+    /// as the unchecked u32 operations, are not considered to trap.
+    ///
+    /// NOTE: Operations with nested regions are not considered to trap themselves, the blocks
+    /// they contain must be checked separately.
+    pub fn may_trap(&self) -> bool {
+        use crate::FieldElement;
+
+        match self {
+            Self::Assert
+            | Self::AssertWithError(_)
+            | Self::Assertz
+            | Self::AssertzWithError(_)
+            | Self::AssertEq
+            | Self::AssertEqWithError(_)
+            | Self::AssertEqw
+            | Self::AssertEqwWithError(_)
+            | Self::U32Assert
+            | Self::U32AssertWithError(_)
+            | Self::U32Assert2
+            | Self::U32Assert2WithError(_)
+            | Self::U32Assertw
+            | Self::U32AssertwWithError(_) => true,
+            Self::Cswap | Self::Cswapw | Self::Cdrop | Self::Cdropw => true,
+            Self::Not | Self::And | Self::Or | Self::Xor => true,
+            Self::MemLoad
+            | Self::MemLoadOffset
+            | Self::MemLoadw
+            | Self::MemStore
+            | Self::MemStoreOffset
+            | Self::MemStorew
+            | Self::MemStream
+            | Self::AdvPipe => true,
+            Self::AdvPush(_) | Self::AdvLoadw => true,
+            Self::Div | Self::Inv | Self::Pow2 | Self::Exp | Self::Ilog2 => true,
+            Self::DivImm(imm) => *imm == Felt::ZERO,
+            Self::ExpImm(imm) => *imm > 63,
+            Self::U32Div | Self::U32Mod | Self::U32DivMod => true,
+            Self::U32DivImm(imm) | Self::U32ModImm(imm) | Self::U32DivModImm(imm) => *imm == 0,
+            Self::U32And | Self::U32Or | Self::U32Xor | Self::U32Not => true,
+            Self::Exec(_) | Self::Call(_) | Self::Syscall(_) | Self::DynExec | Self::DynCall => {
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn from_masm(
         ix: miden_assembly::ast::Instruction,
         locals: &[FunctionIdent],
@@ -823,8 +887,11 @@ impl MasmOp {
             Instruction::MemStoreImm(addr) => Self::MemStoreImm(addr),
             Instruction::MemStoreW => Self::MemStorew,
             Instruction::MemStoreWImm(addr) => Self::MemStorewImm(addr),
-            Instruction::LocLoad(_) | Instruction::LocLoadW(_) => {
-                unimplemented!("load by local id")
+            Instruction::LocLoad(id) => {
+                Self::LocLoad(LocalId::from_u8(id.try_into().expect("invalid local id")))
+            }
+            Instruction::LocLoadW(id) => {
+                Self::LocLoadw(LocalId::from_u8(id.try_into().expect("invalid local id")))
             }
             Instruction::MemStream => Self::MemStream,
             Instruction::AdvPipe => Self::AdvPipe,
@@ -852,9 +919,21 @@ impl MasmOp {
                     function: Ident::with_empty_span(Symbol::intern(name.as_ref())),
                 })
             }
-            Instruction::CallLocal(_)
-            | Instruction::CallMastRoot(_)
-            | Instruction::CallImported(_) => unimplemented!("contract calls"),
+            Instruction::CallLocal(local_index) => Self::Call(locals[local_index as usize]),
+            Instruction::CallImported(ref proc_id) => {
+                let module = imported
+                    .get_procedure_path(proc_id)
+                    .expect("reference to import that doesn't exist")
+                    .last();
+                let name = imported
+                    .get_procedure_name(proc_id)
+                    .expect("reference to import that doesn't exist");
+                Self::Call(FunctionIdent {
+                    module: Ident::with_empty_span(Symbol::intern(module)),
+                    function: Ident::with_empty_span(Symbol::intern(name.as_ref())),
+                })
+            }
+            Instruction::CallMastRoot(_) => unimplemented!("calls by mast root"),
             Instruction::SysCall(ref proc_id) => {
                 let module = imported
                     .get_procedure_path(proc_id)
@@ -1027,6 +1106,8 @@ impl MasmOp {
             Self::LocAddr(id) => Instruction::Locaddr(id.as_usize() as u16),
             Self::LocStore(id) => Instruction::LocStore(id.as_usize() as u16),
             Self::LocStorew(id) => Instruction::LocStoreW(id.as_usize() as u16),
+            Self::LocLoad(id) => Instruction::LocLoad(id.as_usize() as u16),
+            Self::LocLoadw(id) => Instruction::LocLoadW(id.as_usize() as u16),
             Self::MemLoad => Instruction::MemLoad,
             Self::MemLoadImm(addr) => Instruction::MemLoadImm(addr),
             Self::MemLoadw => Instruction::MemLoadW,
@@ -1072,6 +1153,30 @@ impl MasmOp {
                     Instruction::ExecImported(id)
                 }
             }
+            Self::Call(ref callee) => {
+                if let Some(idx) = local_ids.get(callee).copied() {
+                    Instruction::CallLocal(idx)
+                } else {
+                    let aliased = if let Some(alias) = imports.alias(&callee.module) {
+                        FunctionIdent {
+                            module: alias,
+                            function: callee.function,
+                        }
+                    } else {
+                        let module_as_import = super::MasmImport::try_from(callee.module)
+                            .expect("invalid module name");
+                        FunctionIdent {
+                            module: Ident::with_empty_span(module_as_import.alias),
+                            function: callee.function,
+                        }
+                    };
+                    let id = proc_ids
+                        .get(&aliased)
+                        .copied()
+                        .unwrap_or_else(|| miden_assembly::ProcedureId::new(aliased.to_string()));
+                    Instruction::CallImported(id)
+                }
+            }
             Self::Syscall(ref callee) => {
                 let aliased = if let Some(alias) = imports.alias(&callee.module) {
                     FunctionIdent {
@@ -1295,6 +1400,8 @@ impl fmt::Display for MasmOp {
             Self::LocAddr(_) => f.write_str("locaddr"),
             Self::LocStore(_) => f.write_str("loc_store"),
             Self::LocStorew(_) => f.write_str("loc_storew"),
+            Self::LocLoad(_) => f.write_str("loc_load"),
+            Self::LocLoadw(_) => f.write_str("loc_loadw"),
             Self::MemLoad
             | Self::MemLoadOffset
             | Self::MemLoadImm(_)
@@ -1313,6 +1420,7 @@ impl fmt::Display for MasmOp {
             Self::While(_) => f.write_str("while.true"),
             Self::Repeat(..) => f.write_str("repeat"),
             Self::Exec(_) => f.write_str("exec"),
+            Self::Call(_) => f.write_str("call"),
             Self::Syscall(_) => f.write_str("syscall"),
             Self::DynExec => f.write_str("dynexec"),
             Self::DynCall => f.write_str("dyncall"),
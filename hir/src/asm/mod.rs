@@ -57,6 +57,7 @@ impl InlineAsm {
         let body = blocks.push(MasmBlock {
             id,
             ops: smallvec![],
+            origin: None,
         });
         Self {
             op: Opcode::InlineAsm,
@@ -73,6 +74,7 @@ impl InlineAsm {
         self.blocks.push(MasmBlock {
             id,
             ops: smallvec![],
+            origin: None,
         });
         id
     }
@@ -1219,6 +1219,16 @@ pub trait InstBuilder<'f>: InstBuilderBase<'f> {
         self.Ret(vlist, span).0
     }
 
+    /// Return from the current function with all of the given values as its results, in order
+    fn ret_many(mut self, returning: &[Value], span: SourceSpan) -> Inst {
+        let mut vlist = ValueList::default();
+        {
+            let pool = &mut self.data_flow_graph_mut().value_lists;
+            vlist.extend(returning.iter().copied(), pool);
+        }
+        self.Ret(vlist, span).0
+    }
+
     fn ret_imm(self, arg: Immediate, span: SourceSpan) -> Inst {
         let data = Instruction::RetImm(RetImm {
             op: Opcode::Ret,
@@ -139,7 +139,7 @@ impl FromStr for ConstantData {
 }
 
 /// This maintains the storage for constants used within a function
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ConstantPool {
     /// This mapping maintains the insertion order as long as Constants are created with
     /// sequentially increasing integers.
@@ -18,6 +18,8 @@ pub struct DataFlowGraph {
     pub imports: FxHashMap<FunctionIdent, ExternalFunction>,
     pub globals: PrimaryMap<GlobalValue, GlobalValueData>,
     pub constants: ConstantPool,
+    /// Debug names for values, e.g. the names of the source-level variables they were bound to
+    pub value_names: FxHashMap<Value, Symbol>,
 }
 impl Default for DataFlowGraph {
     fn default() -> Self {
@@ -28,6 +30,39 @@ impl Default for DataFlowGraph {
         dfg
     }
 }
+impl Clone for DataFlowGraph {
+    /// Creates a deep copy of this graph, in which all entities have the same keys as the original
+    ///
+    /// NOTE: Instructions which are not attached to a block are not cloned
+    fn clone(&self) -> Self {
+        let mut dfg = Self {
+            entry: self.entry,
+            attrs: self.attrs.clone(),
+            blocks: self.blocks.clone(),
+            insts: ArenaMap::new(),
+            results: self.results.clone(),
+            values: self.values.clone(),
+            value_lists: self.value_lists.clone(),
+            imports: self.imports.clone(),
+            globals: self.globals.clone(),
+            constants: self.constants.clone(),
+            value_names: self.value_names.clone(),
+        };
+        for _ in self.insts.iter() {
+            dfg.insts.alloc_key();
+        }
+        for (block, data) in self.blocks.iter() {
+            for node in data.insts.iter() {
+                let inst = node.key;
+                let node = node.deep_clone(&mut dfg.value_lists);
+                dfg.insts.append(inst, node);
+                let data = unsafe { UnsafeRef::from_raw(&dfg.insts[inst]) };
+                dfg.blocks[block].append(data);
+            }
+        }
+        dfg
+    }
+}
 impl DataFlowGraph {
     /// Create a new, completely uninitialized DataFlowGraph
     pub fn new_uninit() -> Self {
@@ -42,6 +77,7 @@ impl DataFlowGraph {
             imports: Default::default(),
             globals: PrimaryMap::new(),
             constants: ConstantPool::default(),
+            value_names: Default::default(),
         }
     }
 
@@ -182,6 +218,19 @@ impl DataFlowGraph {
         self.values[v].set_type(ty)
     }
 
+    /// Get the debug name of `v`, if it has one
+    pub fn value_name(&self, v: Value) -> Option<Symbol> {
+        self.value_names.get(&v).copied()
+    }
+
+    /// Give `v` a debug name, unless it already has one.
+    ///
+    /// The first name given to a value is kept, as it is the one closest to where the value is
+    /// defined, e.g. a function parameter which is later assigned to another variable.
+    pub fn set_value_name(&mut self, v: Value, name: Symbol) {
+        self.value_names.entry(v).or_insert(name);
+    }
+
     pub fn get_value(&self, v: Value) -> ValueData {
         self.values[v].clone()
     }
@@ -300,6 +349,33 @@ impl DataFlowGraph {
         id
     }
 
+    /// Unlink `inst` from the block containing it, leaving it detached.
+    ///
+    /// NOTE: It is up to the caller to ensure that the results of `inst` are no longer used.
+    pub fn remove_inst(&mut self, inst: Inst) {
+        let block = self.inst_block(inst).expect("cannot remove a detached instruction");
+        let mut cursor = unsafe { self.blocks[block].insts.cursor_mut_from_ptr(&self.insts[inst]) };
+        cursor.remove();
+    }
+
+    /// Move `inst` from the block containing it to just before `before`, which may be in another
+    /// block.
+    ///
+    /// NOTE: It is up to the caller to ensure that the arguments of `inst` are available at its
+    /// new location, and that its results are not used prior to it.
+    pub fn move_inst_before(&mut self, inst: Inst, before: Inst) {
+        assert_ne!(inst, before, "cannot move an instruction before itself");
+        let block = self.inst_block(inst).expect("cannot move a detached instruction");
+        let dest = self.inst_block(before).expect("cannot move before a detached instruction");
+        let node = unsafe { self.blocks[block].insts.cursor_mut_from_ptr(&self.insts[inst]) }
+            .remove()
+            .unwrap();
+        self.insts[inst].block = dest;
+        let mut cursor =
+            unsafe { self.blocks[dest].insts.cursor_mut_from_ptr(&self.insts[before]) };
+        cursor.insert_before(node);
+    }
+
     /// Create a new instruction which is a clone of `inst`, but detached from any block.
     ///
     /// NOTE: The instruction is in a temporarily invalid state, because if it has arguments,
@@ -713,6 +789,43 @@ impl DataFlowGraph {
             _ => panic!("{} must be a branch instruction", branch_inst),
         }
     }
+
+    /// Removes the argument at `index` from the arguments `branch_inst` passes to `dest`, i.e. the
+    /// argument corresponding to the block parameter of `dest` at `index`.
+    ///
+    /// If `branch_inst` branches to `dest` on more than one edge, it is removed from each of them.
+    /// Panics if `branch_inst` is not a branch instruction with arguments.
+    pub fn remove_branch_destination_argument(
+        &mut self,
+        branch_inst: Inst,
+        dest: Block,
+        index: usize,
+    ) {
+        match self.insts[branch_inst].data.item {
+            Instruction::Br(Br {
+                destination,
+                ref mut args,
+                ..
+            }) => {
+                if destination == dest {
+                    args.remove(index, &mut self.value_lists);
+                }
+            }
+            Instruction::CondBr(CondBr {
+                then_dest: (then_dest, ref mut then_args),
+                else_dest: (else_dest, ref mut else_args),
+                ..
+            }) => {
+                if then_dest == dest {
+                    then_args.remove(index, &mut self.value_lists);
+                }
+                if else_dest == dest {
+                    else_args.remove(index, &mut self.value_lists);
+                }
+            }
+            _ => panic!("{} must be a branch instruction with arguments", branch_inst),
+        }
+    }
 }
 impl Index<Inst> for DataFlowGraph {
     type Output = Instruction;
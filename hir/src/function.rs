@@ -54,6 +54,13 @@ pub enum CallConv {
     ///
     /// In all other respects, this calling convention is the same as `SystemV`
     Kernel,
+    /// A function with this calling convention is invoked in a new memory context, using the
+    /// `call` instruction, e.g. the procedures of an account, which may only be interacted with
+    /// across a context boundary.
+    ///
+    /// Arguments and results are passed on the operand stack, as the caller and callee do not
+    /// share memory. In all other respects, this calling convention is the same as `SystemV`
+    Account,
 }
 impl fmt::Display for CallConv {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -62,6 +69,7 @@ impl fmt::Display for CallConv {
             Self::SystemV => f.write_str("C"),
             Self::Wasm => f.write_str("wasm"),
             Self::Kernel => f.write_str("kernel"),
+            Self::Account => f.write_str("account"),
         }
     }
 }
@@ -362,6 +370,16 @@ pub struct Function {
     pub signature: Signature,
     pub dfg: DataFlowGraph,
 }
+impl Clone for Function {
+    fn clone(&self) -> Self {
+        Self {
+            link: Default::default(),
+            id: self.id,
+            signature: self.signature.clone(),
+            dfg: self.dfg.clone(),
+        }
+    }
+}
 impl Function {
     /// Create a new [Function] with the given name, signature, and source location.
     ///
@@ -127,6 +127,27 @@ impl Default for GlobalVariableTable {
         Self::new(Default::default())
     }
 }
+impl Clone for GlobalVariableTable {
+    fn clone(&self) -> Self {
+        let mut table = Self {
+            layout: Default::default(),
+            names: self.names.clone(),
+            arena: self.arena.clone(),
+            data: self.data.clone(),
+            next_unique_id: self.next_unique_id,
+            conflict_strategy: self.conflict_strategy,
+        };
+        // Removed global variables remain in the arena, so we only link those which are laid out
+        for gv in self.layout.iter() {
+            let unsafe_ref = unsafe {
+                let ptr = table.arena.get_raw(gv.id).unwrap();
+                UnsafeRef::from_raw(ptr.as_ptr())
+            };
+            table.layout.push_back(unsafe_ref);
+        }
+        table
+    }
+}
 impl GlobalVariableTable {
     pub fn new(conflict_strategy: ConflictResolutionStrategy) -> Self {
         Self {
@@ -3,7 +3,7 @@ use std::{
     hash::{Hash, Hasher},
 };
 
-use super::{Felt, FieldElement, StarkField, Type};
+use super::{Felt, FieldElement, StarkField, Type, FIELD_MODULUS};
 
 #[derive(Debug, Copy, Clone)]
 pub enum Immediate {
@@ -155,19 +155,23 @@ impl Immediate {
     }
 
     /// Attempts to convert this value to a field element
+    ///
+    /// Negative integers are encoded as their additive inverse in the field, i.e. `-n` becomes
+    /// `FIELD_MODULUS - n`. Integers whose magnitude is not less than [FIELD_MODULUS] have no
+    /// canonical representation as a field element, and are rejected.
     pub fn as_felt(self) -> Option<Felt> {
         match self {
             Self::I1(b) => Some(Felt::new(b as u64)),
             Self::U8(b) => Some(Felt::new(b as u64)),
-            Self::I8(b) => u64::try_from(b).ok().map(Felt::new),
+            Self::I8(b) => int_to_felt(b as i128),
             Self::U16(b) => Some(Felt::new(b as u64)),
-            Self::I16(b) => u64::try_from(b).ok().map(Felt::new),
+            Self::I16(b) => int_to_felt(b as i128),
             Self::U32(b) => Some(Felt::new(b as u64)),
-            Self::I32(b) => u64::try_from(b).ok().map(Felt::new),
-            Self::U64(b) => Some(Felt::new(b)),
-            Self::I64(b) => u64::try_from(b).ok().map(Felt::new),
+            Self::I32(b) => int_to_felt(b as i128),
+            Self::U64(b) => int_to_felt(b as i128),
+            Self::I64(b) => int_to_felt(b as i128),
             Self::Felt(i) => Some(i),
-            Self::I128(b) => u64::try_from(b).ok().map(Felt::new),
+            Self::I128(b) => int_to_felt(b),
             Self::F64(f) => FloatToInt::<Felt>::to_int(f).ok(),
         }
     }
@@ -646,7 +650,7 @@ impl FloatToInt<Felt> for f64 {
     const ZERO: Felt = Felt::ZERO;
 
     fn upper_bound() -> Self {
-        64.0f64.exp2() - 32.0f64.exp2() + 1.0
+        FIELD_MODULUS as f64
     }
 
     fn lower_bound() -> Self {
@@ -703,3 +707,15 @@ where
         }
     }
 }
+
+/// Encode `value` as a field element, if its magnitude is less than [FIELD_MODULUS]
+fn int_to_felt(value: i128) -> Option<Felt> {
+    let modulus = FIELD_MODULUS as i128;
+    if value >= modulus || value <= -modulus {
+        None
+    } else if value < 0 {
+        Some(Felt::new((modulus + value) as u64))
+    } else {
+        Some(Felt::new(value as u64))
+    }
+}
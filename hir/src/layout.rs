@@ -249,18 +249,14 @@ impl<K: EntityRef, V> Drop for OrderedArenaMap<K, V> {
     }
 }
 impl<K: EntityRef, V: Clone> Clone for OrderedArenaMap<K, V> {
+    /// Clones the linked values in this map, preserving their keys and their order in the list
     fn clone(&self) -> Self {
         let mut cloned = Self::new();
-        for opt in self.map.iter() {
-            match opt {
-                None => {
-                    cloned.map.alloc_key();
-                }
-                Some(nn) => {
-                    let value = unsafe { nn.as_ref() }.value();
-                    cloned.push(value.clone());
-                }
-            }
+        for _ in self.map.iter() {
+            cloned.map.alloc_key();
+        }
+        for node in self.list.iter() {
+            cloned.append(node.key(), node.value().clone());
         }
         cloned
     }
@@ -28,6 +28,12 @@ pub use winter_math::{FieldElement, StarkField};
 /// Represents a field element in Miden
 pub type Felt = winter_math::fields::f64::BaseElement;
 
+/// The modulus of the prime field over which [Felt] is defined, i.e. `2^64 - 2^32 + 1`
+///
+/// Anything which depends on the specific field used by the VM, such as checking that a value is
+/// a canonical field element, should refer to this rather than hardcoding the modulus.
+pub const FIELD_MODULUS: u64 = <Felt as StarkField>::MODULUS;
+
 /// Represents an offset from the base of linear memory in Miden
 pub type Offset = u32;
 
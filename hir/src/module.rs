@@ -65,6 +65,24 @@ pub struct Module {
     /// kernel functions, as they are not available in the root context.
     is_kernel: bool,
 }
+impl Clone for Module {
+    /// Creates a deep copy of this module, which is not attached to any [Program]
+    fn clone(&self) -> Self {
+        let mut module = Self {
+            link: Default::default(),
+            name: self.name,
+            docs: self.docs.clone(),
+            segments: self.segments.clone(),
+            globals: self.globals.clone(),
+            functions: Default::default(),
+            is_kernel: self.is_kernel,
+        };
+        for function in self.functions.iter() {
+            module.functions.push_back(Box::new(function.clone()));
+        }
+        module
+    }
+}
 impl fmt::Display for Module {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.pretty_print(f)
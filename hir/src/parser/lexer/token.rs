@@ -40,6 +40,7 @@ pub enum Token {
     Cc,
     Fast,
     Wasm,
+    Account,
     Sret,
     Sext,
     Zext,
@@ -197,6 +198,7 @@ impl Token {
             "cc" => Self::Cc,
             "fast" => Self::Fast,
             "wasm" => Self::Wasm,
+            "account" => Self::Account,
             "sret" => Self::Sret,
             "zext" => Self::Zext,
             "sext" => Self::Sext,
@@ -400,6 +402,7 @@ impl fmt::Display for Token {
             Self::Cc => write!(f, "cc"),
             Self::Fast => write!(f, "fast"),
             Self::Wasm => write!(f, "wasm"),
+            Self::Account => write!(f, "account"),
             Self::Sret => write!(f, "sret"),
             Self::Zext => write!(f, "zext"),
             Self::Sext => write!(f, "sext"),
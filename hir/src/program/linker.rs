@@ -207,6 +207,19 @@ impl Linker {
         Self::default()
     }
 
+    /// Reserve the memory below `base` in the linked program, by allocating the global variables,
+    /// and the heap, at or above it.
+    ///
+    /// Data segments are referred to by absolute address, so they cannot be moved above `base`.
+    /// Instead, a [LinkerError] is returned if any of the modules being linked declare a data
+    /// segment which overlaps the memory below `base`.
+    ///
+    /// Returns a [LinkerError] if `base` is not word-aligned, i.e. a multiple of 16 bytes.
+    pub fn with_memory_base(&mut self, base: u32) -> Result<(), LinkerError> {
+        self.program.segments.set_base(base)?;
+        Ok(())
+    }
+
     /// Set the entrypoint for the linked program
     ///
     /// Returns a [LinkerError] if a different entrypoint was already declared.
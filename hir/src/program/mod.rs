@@ -125,6 +125,7 @@ impl crate::pass::AnalysisKey for Program {
 pub struct ProgramBuilder<'a> {
     modules: std::collections::BTreeMap<Ident, Box<Module>>,
     entry: Option<FunctionIdent>,
+    memory_base: u32,
     diagnostics: &'a miden_diagnostics::DiagnosticsHandler,
 }
 impl<'a> ProgramBuilder<'a> {
@@ -132,10 +133,20 @@ impl<'a> ProgramBuilder<'a> {
         Self {
             modules: Default::default(),
             entry: None,
+            memory_base: 0,
             diagnostics,
         }
     }
 
+    /// Reserve the memory below `base` in the [Program].
+    ///
+    /// See [Linker::with_memory_base] for details.
+    #[inline]
+    pub fn with_memory_base(mut self, base: u32) -> Self {
+        self.memory_base = base;
+        self
+    }
+
     /// Set the entrypoint for the [Program] being built.
     #[inline]
     pub fn with_entrypoint(mut self, id: FunctionIdent) -> Self {
@@ -186,6 +197,7 @@ impl<'a> ProgramBuilder<'a> {
     /// Link a [Program] from the current [ProgramBuilder] state
     pub fn link(self) -> Result<Box<Program>, LinkerError> {
         let mut linker = Linker::new();
+        linker.with_memory_base(self.memory_base)?;
         let entrypoint = self.entry.or_else(|| self.modules.values().find_map(|m| m.entrypoint()));
         if let Some(entry) = entrypoint {
             linker.with_entrypoint(entry)?;
@@ -59,17 +59,40 @@ pub enum DataSegmentError {
         size: u32,
         actual: u32,
     },
+    /// The current segment overlaps the memory reserved below the memory base. Data segments
+    /// cannot be moved, as the code using them refers to them by absolute address.
+    #[error(
+        "invalid data segment: segment of {size} bytes at {offset:#x} overlaps the memory \
+         reserved below {base:#x}"
+    )]
+    BelowBase {
+        offset: Offset,
+        size: u32,
+        base: Offset,
+    },
+    /// The memory base is not word-aligned, i.e. a multiple of 16 bytes
+    #[error("invalid memory base: {0:#x} is not word-aligned")]
+    UnalignedBase(Offset),
 }
 
 /// Similar to [GlobalVariableTable], this structure is used to track data segments in a module or
 /// program.
+///
+/// Anything laid out after the segments, i.e. global variables and the heap, starts at the base
+/// address of the table at the earliest, which is zero by default. A non-zero base can be used to
+/// reserve the memory below it, e.g. as scratch space for the VM. Data segments are referred to by
+/// absolute address, so they cannot be moved, and must not overlap the reserved memory.
 #[derive(Default)]
 pub struct DataSegmentTable {
     segments: LinkedList<DataSegmentAdapter>,
+    base: Offset,
 }
 impl Clone for DataSegmentTable {
     fn clone(&self) -> Self {
-        let mut table = Self::default();
+        let mut table = Self {
+            base: self.base,
+            ..Self::default()
+        };
         for segment in self.segments.iter() {
             table.segments.push_back(UnsafeRef::from_box(Box::new(segment.clone())));
         }
@@ -82,15 +105,41 @@ impl DataSegmentTable {
         self.segments.is_empty()
     }
 
-    /// Returns the offset in linear memory where the last data segment ends
-    pub fn next_available_offset(&self) -> u32 {
-        if let Some(last_segment) = self.last() {
-            let next_offset = last_segment.offset() + last_segment.size();
-            // Ensure the start of the globals segment is word-aligned
-            next_offset.align_up(32)
-        } else {
-            0
+    /// Returns the address in linear memory below which memory is reserved
+    #[inline]
+    pub fn base(&self) -> Offset {
+        self.base
+    }
+
+    /// Reserve the memory below `base`, so that anything laid out after the data segments starts
+    /// at `base` at the earliest.
+    ///
+    /// Returns `Err` if `base` is not word-aligned, i.e. a multiple of 16 bytes, or if a data
+    /// segment which overlaps the memory below `base` has been declared.
+    pub fn set_base(&mut self, base: Offset) -> Result<(), DataSegmentError> {
+        if base % 16 != 0 {
+            return Err(DataSegmentError::UnalignedBase(base));
+        }
+        if let Some(segment) = self.segments.front().get().filter(|segment| segment.offset < base) {
+            return Err(DataSegmentError::BelowBase {
+                offset: segment.offset,
+                size: segment.size,
+                base,
+            });
         }
+        self.base = base;
+        Ok(())
+    }
+
+    /// Returns the offset in linear memory where the last data segment, or the reserved memory
+    /// below the base of the table, ends, whichever is greater
+    pub fn next_available_offset(&self) -> u32 {
+        let next_offset = match self.last() {
+            Some(last_segment) => self.base.max(last_segment.offset() + last_segment.size()),
+            None => self.base,
+        };
+        // Ensure the start of the globals segment is word-aligned
+        next_offset.align_up(32)
     }
 
     /// Declare a new [DataSegment], with the given offset, size, and data.
@@ -108,8 +157,17 @@ impl DataSegmentTable {
 
     /// Insert a [DataSegment] into this table, while preserving the order of the table.
     ///
-    /// This will fail if the segment is invalid, or overlaps/conflicts with an existing segment.
+    /// This will fail if the segment is invalid, overlaps/conflicts with an existing segment, or
+    /// overlaps the memory reserved below the base of the table.
     pub fn insert(&mut self, segment: Box<DataSegment>) -> Result<(), DataSegmentError> {
+        if segment.offset < self.base {
+            return Err(DataSegmentError::BelowBase {
+                offset: segment.offset,
+                size: segment.size,
+                base: self.base,
+            });
+        }
+
         let mut cursor = self.segments.front_mut();
         let end = segment.offset + segment.size;
         while let Some(current_segment) = cursor.get() {
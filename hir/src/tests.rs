@@ -142,3 +142,43 @@ fn linker_test() {
         .link()
         .expect("failed to link program");
 }
+
+/// Test that cloning a module produces an identical, but independent, copy of it
+#[test]
+fn module_clone_test() {
+    let context = TestContext::default();
+
+    let mut builder = ModuleBuilder::new("test");
+    let id = testing::sum_matrix(&mut builder, &context);
+    builder
+        .declare_global_variable("counter", Type::U32, Linkage::External, None, SourceSpan::UNKNOWN)
+        .expect("unexpected global variable conflict");
+    builder
+        .declare_data_segment(0, 4, vec![1u8, 2, 3, 4], true)
+        .expect("invalid data segment");
+    let module = builder.build();
+
+    let mut cloned = module.clone();
+    assert_eq!(cloned.to_string(), module.to_string());
+
+    // Modifying the clone does not affect the original
+    let mut function = cloned.cursor_mut_at(id.function).remove().unwrap();
+    function.dfg.set_attribute(symbols::Entrypoint, ());
+    assert!(cloned.function(id.function).is_none());
+    assert!(!module.function(id.function).unwrap().dfg.has_attribute(&symbols::Entrypoint));
+}
+
+/// Test that immediates are encoded as field elements relative to [FIELD_MODULUS]
+#[test]
+fn immediate_felt_encoding_test() {
+    // Negative immediates are encoded as their additive inverse in the field
+    assert_eq!(Immediate::I32(-1).as_felt(), Some(Felt::new(FIELD_MODULUS - 1)));
+    assert_eq!(Immediate::I8(i8::MIN).as_felt(), Some(Felt::new(FIELD_MODULUS - 128)));
+    assert_eq!(Immediate::I128(1 - FIELD_MODULUS as i128).as_felt(), Some(Felt::ONE));
+    assert_eq!(Immediate::I32(-1).as_felt().map(|felt| felt + Felt::ONE), Some(Felt::ZERO));
+
+    // Values without a canonical representation are rejected
+    assert_eq!(Immediate::U64(FIELD_MODULUS - 1).as_felt(), Some(Felt::new(FIELD_MODULUS - 1)));
+    assert_eq!(Immediate::U64(FIELD_MODULUS).as_felt(), None);
+    assert_eq!(Immediate::I128(-(FIELD_MODULUS as i128)).as_felt(), None);
+}
@@ -3,8 +3,8 @@ use std::{path::PathBuf, sync::Arc};
 use clap::{Args, ColorChoice};
 use miden_diagnostics::{term::termcolor::ColorChoice as MDColorChoice, Emitter};
 use midenc_session::{
-    InputFile, Options, OutputFile, OutputType, OutputTypeSpec, OutputTypes, ProjectType, Session,
-    TargetEnv, VerbosityFlag, Warnings,
+    InputFile, OptLevel, Options, OutputFile, OutputType, OutputTypeSpec, OutputTypes, ProjectType,
+    Session, TargetEnv, VerbosityFlag, Warnings,
 };
 
 /// Compile a program from WebAssembly or Miden IR, to Miden Assembly.
@@ -110,6 +110,72 @@ pub struct Compiler {
     /// Print the IR after running a specific pass
     #[arg(long, value_name = "PASS", help_heading = "Passes")]
     print_ir_after_pass: Option<String>,
+    /// Remove bounds checks, e.g. when indexing into an array, which are proven to always succeed
+    ///
+    /// A bounds check is redundant if it compares constants, or repeats a check which has
+    /// already succeeded, all other bounds checks are kept
+    #[arg(long, default_value_t = false, help_heading = "Compiler")]
+    remove_redundant_bounds_checks: bool,
+    /// Specify the degree to which the compiled program should be optimized
+    #[arg(
+        long = "opt-level",
+        short = 'O',
+        value_enum,
+        value_name = "LEVEL",
+        next_line_help(true),
+        default_value_t = OptLevel::None,
+        default_missing_value = "balanced",
+        help_heading = "Compiler"
+    )]
+    opt_level: OptLevel,
+    /// Spill values to procedure locals when the operand stack grows deeper than DEPTH elements
+    ///
+    /// This trades some memory accesses for the ability to compile functions with more live
+    /// values than can be reached on the operand stack. DEPTH must be no greater than 16.
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        value_parser = clap::value_parser!(u8).range(1..=16),
+        help_heading = "Compiler"
+    )]
+    spill_threshold: Option<u8>,
+    /// Warn about functions which use more than COUNT procedure locals
+    ///
+    /// Excessive use of locals usually comes from spilling, and can be a sign that the spill
+    /// threshold is too low.
+    #[arg(long, value_name = "COUNT", help_heading = "Compiler")]
+    locals_budget: Option<usize>,
+    /// Annotate the emitted Miden Assembly with the IR block each block of code originated from
+    ///
+    /// This is useful when debugging code generation, as each block of code is preceded by a
+    /// comment such as `# from block2`.
+    #[arg(long, default_value_t = false, help_heading = "Compiler")]
+    annotate_blocks: bool,
+    /// Reject functions whose loops and conditionals are nested more than DEPTH levels deep
+    ///
+    /// Deeply nested control flow produces deeply nested MAST, which can exceed the limits of the
+    /// assembler or the VM, so this catches such functions early.
+    #[arg(long, value_name = "DEPTH", help_heading = "Compiler")]
+    max_nesting_depth: Option<usize>,
+    /// Pass procedure arguments in a fixed region of memory rather than on the operand stack
+    ///
+    /// This makes the calling convention independent of how the caller and callee were compiled,
+    /// at the cost of additional memory accesses on every call.
+    #[arg(long, default_value_t = false, help_heading = "Compiler")]
+    memory_call_args: bool,
+    /// Fail if a dead value is left on the operand stack below the results of a function
+    ///
+    /// Such values are otherwise dropped on return, which masks code generation bugs in which a
+    /// value is not dropped after its last use.
+    #[arg(long, default_value_t = false, help_heading = "Compiler")]
+    strict_returns: bool,
+    /// Trap instead of spinning in loops which can never exit, such as `loop {}` in a panic
+    /// handler
+    ///
+    /// A `no_std` crate's panic handler typically loops forever, which would otherwise consume
+    /// every remaining cycle before the VM gives up, rather than aborting as soon as it panics.
+    #[arg(long, default_value_t = false, help_heading = "Compiler")]
+    trap_infinite_loops: bool,
 }
 impl Compiler {
     /// Use this configuration to obtain a [Session] used for compilation
@@ -141,6 +207,15 @@ impl Compiler {
             .with_output_types(output_types);
         options.print_ir_after_all = self.print_ir_after_all;
         options.print_ir_after_pass = self.print_ir_after_pass;
+        options.remove_redundant_bounds_checks = self.remove_redundant_bounds_checks;
+        options.optimize = self.opt_level;
+        options.spill_threshold = self.spill_threshold.map(usize::from);
+        options.locals_budget = self.locals_budget;
+        options.annotate_blocks = self.annotate_blocks;
+        options.max_nesting_depth = self.max_nesting_depth;
+        options.memory_call_args = self.memory_call_args;
+        options.strict_returns = self.strict_returns;
+        options.trap_infinite_loops = self.trap_infinite_loops;
 
         let output_file = match self.output_file {
             Some(path) => Some(OutputFile::Real(path)),
@@ -44,7 +44,14 @@ impl Stage for ApplyRewritesStage {
         let mut rewrites = RewriteSet::default();
         if registered.is_empty() {
             if session.should_codegen() {
+                rewrites.push(ModuleRewritePassAdapter::new(transforms::TrapInfiniteLoops));
+                rewrites.push(ModuleRewritePassAdapter::new(transforms::ElideBoundsChecks));
+                rewrites.push(ModuleRewritePassAdapter::new(transforms::FoldConstants));
+                rewrites.push(ModuleRewritePassAdapter::new(transforms::FoldConstantOperands));
+                rewrites.push(ModuleRewritePassAdapter::new(transforms::EliminateDeadBlockParams));
+                rewrites.push(ModuleRewritePassAdapter::new(transforms::EliminateDeadCode));
                 rewrites.push(ModuleRewritePassAdapter::new(transforms::SplitCriticalEdges));
+                rewrites.push(ModuleRewritePassAdapter::new(transforms::HoistLoopInvariants));
                 rewrites.push(ModuleRewritePassAdapter::new(transforms::Treeify));
                 rewrites.push(ModuleRewritePassAdapter::new(transforms::InlineBlocks));
             }
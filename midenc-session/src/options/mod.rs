@@ -28,6 +28,34 @@ pub struct Options {
     pub print_ir_after_all: bool,
     /// Print IR to stdout each time the named pass is applied
     pub print_ir_after_pass: Option<String>,
+    /// Remove bounds checks, e.g. those emitted when indexing into an array, which are proven to
+    /// always succeed, i.e. which compare constants, or repeat a check which has already
+    /// succeeded. All other bounds checks are kept.
+    pub remove_redundant_bounds_checks: bool,
+    /// When set, values which would otherwise be kept deeper than this many field elements on
+    /// the operand stack are spilled to procedure locals instead. Must be no greater than 16,
+    /// the maximum depth reachable by the stack manipulation instructions.
+    pub spill_threshold: Option<usize>,
+    /// When set, a warning is emitted for each function which uses more procedure locals than
+    /// this, which typically indicates that values are being spilled excessively.
+    pub locals_budget: Option<usize>,
+    /// Annotate each block of emitted Miden Assembly with a comment naming the block of the
+    /// original IR function it was emitted from, e.g. `# from block2`
+    pub annotate_blocks: bool,
+    /// When set, an error is raised for each function whose control flow, i.e. `if.true`,
+    /// `while.true` and `repeat` blocks, is nested more deeply than this in the emitted code.
+    pub max_nesting_depth: Option<usize>,
+    /// Pass the arguments of all procedure calls in a fixed region of memory, rather than on the
+    /// operand stack, so that procedures compiled separately agree on how arguments are passed.
+    pub memory_call_args: bool,
+    /// Panic if any value other than the results of a function is left on the operand stack when
+    /// it returns, and that value is dead, rather than silently dropping it, as this indicates
+    /// that code generation failed to drop the value after its last use.
+    pub strict_returns: bool,
+    /// Replace loops which can never exit, and which do nothing, with a trap, so that a panic in
+    /// a `no_std` crate whose panic handler is `loop {}` aborts execution rather than spinning
+    /// until the VM runs out of cycles.
+    pub trap_infinite_loops: bool,
 }
 impl Default for Options {
     fn default() -> Self {
@@ -48,6 +76,14 @@ impl Options {
             current_dir,
             print_ir_after_all: false,
             print_ir_after_pass: None,
+            remove_redundant_bounds_checks: false,
+            spill_threshold: None,
+            locals_budget: None,
+            annotate_blocks: false,
+            max_nesting_depth: None,
+            memory_call_args: false,
+            strict_returns: false,
+            trap_infinite_loops: false,
         }
     }
 
@@ -32,6 +32,18 @@ extern "C" {
     #[link_name = "exp"]
     fn extern_exp(a: Felt, b: Felt) -> Felt;
 
+    #[link_name = "shl"]
+    fn extern_shl(a: Felt, b: u32) -> Felt;
+
+    #[link_name = "shr"]
+    fn extern_shr(a: Felt, b: u32) -> Felt;
+
+    #[link_name = "rotl"]
+    fn extern_rotl(a: Felt, b: u32) -> Felt;
+
+    #[link_name = "rotr"]
+    fn extern_rotr(a: Felt, b: u32) -> Felt;
+
     #[link_name = "eq"]
     fn extern_eq(a: Felt, b: Felt) -> i32;
 
@@ -72,11 +84,22 @@ macro_rules! felt {
     }};
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum FeltError {
+    /// The value is not less than the field modulus, [Felt::M]
     InvalidValue,
 }
 
+impl core::fmt::Display for FeltError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidValue => {
+                f.write_str("invalid field element: value must be less than 2^64 - 2^32 + 1")
+            }
+        }
+    }
+}
+
 #[repr(transparent)]
 #[derive(Copy, Clone)]
 pub struct Felt(f64);
@@ -90,15 +113,38 @@ impl Felt {
         unsafe { extern_from_u64_unchecked(value) }
     }
 
+    /// Creates a `Felt` from any u64 value, reducing it modulo the field modulus
+    ///
+    /// Unlike [Felt::from_u64_unchecked], this is safe to use for values which are not canonical,
+    /// i.e. greater than or equal to [Felt::M].
+    #[inline(always)]
+    pub fn reduce_u64(value: u64) -> Self {
+        Self::from_u64_unchecked(reduce_u64(value))
+    }
+
     #[inline(always)]
     pub fn new(value: u64) -> Result<Self, FeltError> {
-        if value > Self::M {
-            Err(FeltError::InvalidValue)
-        } else {
-            Ok(Self::from_u64_unchecked(value))
+        Self::try_from_u64(value)
+    }
+
+    /// Creates a `Felt` from `value`, or returns an error if it is not less than [Felt::M]
+    ///
+    /// Use this to convert untrusted inputs, which may be out of range for a field element.
+    #[inline(always)]
+    pub fn try_from_u64(value: u64) -> Result<Self, FeltError> {
+        match canonical_u64(value) {
+            Ok(value) => Ok(Self::from_u64_unchecked(value)),
+            Err(err) => Err(err),
         }
     }
 
+    /// Creates a `Felt` from `value`, clamping it to the largest field element, i.e.
+    /// `Felt::M - 1`, if it is not less than [Felt::M]
+    #[inline(always)]
+    pub fn from_u64_saturating(value: u64) -> Self {
+        Self::from_u64_unchecked(saturate_u64(value))
+    }
+
     #[inline(always)]
     pub fn as_u64(self) -> u64 {
         unsafe { extern_as_u64(self) }
@@ -129,6 +175,62 @@ impl Felt {
     pub fn exp(self, other: Felt) -> Felt {
         unsafe { extern_exp(self, other) }
     }
+
+    /// Returns (a << b) mod 2^32, where a is interpreted as a 32-bit integer
+    ///
+    /// The bits shifted out of the 32-bit range are discarded, i.e. this is not the same as
+    /// multiplying by 2^b in the field.
+    /// Fails if a >= 2^32 or b > 31
+    #[inline(always)]
+    pub fn shl(self, b: u32) -> Felt {
+        unsafe { extern_shl(self, b) }
+    }
+
+    /// Returns a >> b, where a is interpreted as a 32-bit integer
+    ///
+    /// This is integer division by 2^b, rounding down, not division in the field.
+    /// Fails if a >= 2^32 or b > 31
+    #[inline(always)]
+    pub fn shr(self, b: u32) -> Felt {
+        unsafe { extern_shr(self, b) }
+    }
+
+    /// Rotates the bits of a left by b bits, where a is interpreted as a 32-bit integer
+    ///
+    /// Fails if a >= 2^32 or b > 31
+    #[inline(always)]
+    pub fn rotl(self, b: u32) -> Felt {
+        unsafe { extern_rotl(self, b) }
+    }
+
+    /// Rotates the bits of a right by b bits, where a is interpreted as a 32-bit integer
+    ///
+    /// Fails if a >= 2^32 or b > 31
+    #[inline(always)]
+    pub fn rotr(self, b: u32) -> Felt {
+        unsafe { extern_rotr(self, b) }
+    }
+
+    /// Returns r such that r^2 = x, or `None` if x is not a square
+    ///
+    /// Either r or -r may be returned, as both are square roots of x.
+    #[inline(always)]
+    pub fn sqrt(self) -> Option<Felt> {
+        sqrt(self)
+    }
+
+    /// Returns true if x is a square, i.e. if it has a square root
+    #[inline(always)]
+    pub fn is_square(self) -> bool {
+        is_square(self)
+    }
+}
+
+impl FieldOps for Felt {
+    #[inline(always)]
+    fn from_canonical_u64(value: u64) -> Self {
+        Self::from_u64_unchecked(value)
+    }
 }
 
 impl From<Felt> for u64 {
@@ -137,6 +239,15 @@ impl From<Felt> for u64 {
     }
 }
 
+impl TryFrom<u64> for Felt {
+    type Error = FeltError;
+
+    #[inline(always)]
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        Self::try_from_u64(value)
+    }
+}
+
 impl From<u32> for Felt {
     fn from(value: u32) -> Self {
         Self::from_u64_unchecked(value as u64)
@@ -256,6 +367,113 @@ impl Ord for Felt {
     }
 }
 
+/// Returns the canonical representative of `value` modulo [Felt::M]
+///
+/// Since `u64::MAX < 2 * Felt::M`, a single subtraction is enough to reduce any u64 value.
+#[inline(always)]
+const fn reduce_u64(value: u64) -> u64 {
+    if value >= Felt::M {
+        value - Felt::M
+    } else {
+        value
+    }
+}
+
+/// Returns `value` if it is a canonical field element, i.e. less than [Felt::M]
+#[inline(always)]
+const fn canonical_u64(value: u64) -> Result<u64, FeltError> {
+    if value >= Felt::M {
+        Err(FeltError::InvalidValue)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Returns `value`, clamped to the largest canonical field element, i.e. `Felt::M - 1`
+#[inline(always)]
+const fn saturate_u64(value: u64) -> u64 {
+    if value >= Felt::M {
+        Felt::M - 1
+    } else {
+        value
+    }
+}
+
+/// The field operations needed to compute square roots, see [sqrt]
+trait FieldOps: Copy + PartialEq + Mul<Output = Self> {
+    /// Creates an element from a value which is known to be less than [Felt::M]
+    fn from_canonical_u64(value: u64) -> Self;
+
+    /// Returns x^exponent, computed by square-and-multiply
+    fn pow(self, mut exponent: u64) -> Self {
+        let mut base = self;
+        let mut result = Self::from_canonical_u64(1);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    /// Returns x^(2^n), computed by squaring n times
+    fn square_n(self, n: u32) -> Self {
+        let mut result = self;
+        for _ in 0..n {
+            result = result * result;
+        }
+        result
+    }
+}
+
+/// The largest n such that 2^n divides [Felt::M] - 1
+const TWO_ADICITY: u32 = 32;
+
+/// The odd factor of [Felt::M] - 1, i.e. `(Felt::M - 1) / 2^TWO_ADICITY`
+const TRACE: u64 = (Felt::M - 1) >> TWO_ADICITY;
+
+/// A primitive 2^[TWO_ADICITY]-th root of unity, i.e. `7^TRACE`, where 7 is not a square
+const TWO_ADIC_ROOT_OF_UNITY: u64 = 1753635133440165772;
+
+/// Returns true if `x` is zero, or a quadratic residue, according to Euler's criterion
+fn is_square<F: FieldOps>(x: F) -> bool {
+    let one = F::from_canonical_u64(1);
+    let zero = F::from_canonical_u64(0);
+    x == zero || x.pow((Felt::M - 1) / 2) == one
+}
+
+/// Computes a square root of `x` using the Tonelli-Shanks algorithm, or returns `None` if `x` is
+/// not a square
+fn sqrt<F: FieldOps>(x: F) -> Option<F> {
+    let one = F::from_canonical_u64(1);
+    if x == F::from_canonical_u64(0) {
+        return Some(x);
+    }
+    if !is_square(x) {
+        return None;
+    }
+
+    let mut m = TWO_ADICITY;
+    let mut c = F::from_canonical_u64(TWO_ADIC_ROOT_OF_UNITY);
+    let mut t = x.pow(TRACE);
+    let mut r = x.pow((TRACE + 1) / 2);
+    while t != one {
+        // Find the least i such that t^(2^i) = 1, which is less than m, as t is a square
+        let mut i = 1;
+        while t.square_n(i) != one {
+            i += 1;
+        }
+        let b = c.square_n(m - i - 1);
+        m = i;
+        c = b * b;
+        t = t * c;
+        r = r * b;
+    }
+    Some(r)
+}
+
 /// If `a` == 1, removes it from the stack.  Fails if `a` != 1
 #[inline(always)]
 pub fn assert(a: Felt) {
@@ -288,4 +506,88 @@ mod tests {
     fn felt_macro_smoke_test() {
         let _ = felt!(1);
     }
+
+    #[test]
+    fn reduce_u64_canonical_values() {
+        assert_eq!(reduce_u64(0), 0);
+        assert_eq!(reduce_u64(42), 42);
+        assert_eq!(reduce_u64(Felt::M - 1), Felt::M - 1);
+    }
+
+    #[test]
+    fn reduce_u64_above_modulus() {
+        assert_eq!(reduce_u64(Felt::M), 0);
+        assert_eq!(reduce_u64(Felt::M + 1), 1);
+        assert_eq!(reduce_u64(u64::MAX), u64::MAX - Felt::M);
+        assert_eq!(reduce_u64(u64::MAX), 0xfffffffe);
+    }
+
+    #[test]
+    fn canonical_u64_boundaries() {
+        assert_eq!(canonical_u64(0), Ok(0));
+        assert_eq!(canonical_u64(Felt::M - 1), Ok(Felt::M - 1));
+        assert_eq!(canonical_u64(Felt::M), Err(FeltError::InvalidValue));
+        assert_eq!(canonical_u64(Felt::M + 1), Err(FeltError::InvalidValue));
+        assert_eq!(canonical_u64(u64::MAX), Err(FeltError::InvalidValue));
+    }
+
+    #[test]
+    fn saturate_u64_boundaries() {
+        assert_eq!(saturate_u64(0), 0);
+        assert_eq!(saturate_u64(Felt::M - 1), Felt::M - 1);
+        assert_eq!(saturate_u64(Felt::M), Felt::M - 1);
+        assert_eq!(saturate_u64(Felt::M + 1), Felt::M - 1);
+        assert_eq!(saturate_u64(u64::MAX), Felt::M - 1);
+    }
+
+    /// An element of the same field as [Felt], implemented natively, as the intrinsics backing
+    /// [Felt] are only available when compiled for Miden
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Fp(u64);
+
+    impl Mul for Fp {
+        type Output = Self;
+
+        fn mul(self, other: Self) -> Self {
+            Self(((self.0 as u128 * other.0 as u128) % Felt::M as u128) as u64)
+        }
+    }
+
+    impl FieldOps for Fp {
+        fn from_canonical_u64(value: u64) -> Self {
+            Self(value)
+        }
+    }
+
+    #[test]
+    fn two_adic_root_of_unity() {
+        let root = Fp(TWO_ADIC_ROOT_OF_UNITY);
+        assert_eq!(root.square_n(TWO_ADICITY), Fp(1));
+        assert_eq!(root.square_n(TWO_ADICITY - 1), Fp(Felt::M - 1));
+    }
+
+    #[test]
+    fn sqrt_of_squares() {
+        for r in [0, 1, 2, 3, 7, 0xdeadbeef, 1 << 40, Felt::M - 1, Felt::M - 12345] {
+            let x = Fp(r) * Fp(r);
+            assert!(is_square(x));
+            assert_eq!(
+                sqrt(x).map(|root| root * root),
+                Some(x),
+                "square root of {x:?} is incorrect"
+            );
+        }
+        assert_eq!(sqrt(Fp(0)), Some(Fp(0)));
+        assert_eq!(sqrt(Fp(4)).map(|r| r * r), Some(Fp(4)));
+    }
+
+    #[test]
+    fn sqrt_of_non_squares() {
+        // 7 is the multiplicative generator of the field, so none of its odd powers are squares,
+        // and neither is -7, as -1 is a square
+        for x in [Fp(7), Fp(7).pow(3), Fp(7).pow(12345), Fp(Felt::M - 1) * Fp(7)] {
+            assert!(!is_square(x));
+            assert_eq!(sqrt(x), None);
+        }
+    }
 }
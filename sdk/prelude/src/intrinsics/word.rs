@@ -1,3 +1,38 @@
 use crate::Felt;
 
 pub type Word = [Felt; 4];
+
+#[link(wasm_import_module = "miden:prelude/intrinsics_word")]
+extern "C" {
+    #[link_name = "assert_eqw"]
+    fn extern_assert_eqw(
+        a0: Felt,
+        a1: Felt,
+        a2: Felt,
+        a3: Felt,
+        b0: Felt,
+        b1: Felt,
+        b2: Felt,
+        b3: Felt,
+    );
+
+    #[link_name = "assert_word_zero"]
+    fn extern_assert_word_zero(a0: Felt, a1: Felt, a2: Felt, a3: Felt);
+}
+
+/// If `a` == `b`, removes them from the stack.  Fails if any element of `a` != the corresponding
+/// element of `b`
+#[inline(always)]
+pub fn assert_eqw(a: Word, b: Word) {
+    unsafe {
+        extern_assert_eqw(a[0], a[1], a[2], a[3], b[0], b[1], b[2], b[3]);
+    }
+}
+
+/// If all elements of `a` are 0, removes it from the stack.  Fails if any element of `a` != 0
+#[inline(always)]
+pub fn assert_word_zero(a: Word) {
+    unsafe {
+        extern_assert_word_zero(a[0], a[1], a[2], a[3]);
+    }
+}
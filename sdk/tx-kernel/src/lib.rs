@@ -7,6 +7,7 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 extern crate alloc;
 
 use alloc::vec::Vec;
+use core::ops::{Index, IndexMut};
 
 use miden_prelude::{Felt, Word};
 
@@ -32,15 +33,43 @@ extern "C" {
 extern "C" {
     #[link_name = "get_inputs<0x0000000000000000000000000000000000000000000000000000000000000000>"]
     fn extern_note_get_inputs(ptr: *mut Felt) -> usize;
+    #[link_name = "get_sender<0x0000000000000000000000000000000000000000000000000000000000000000>"]
+    fn extern_note_get_sender() -> AccountId;
 }
 
+/// Returns the id of the account whose code is currently executing.
+///
+/// When called from a note script, this is the account consuming the note, not the account
+/// which created it (see [get_sender]).
 #[inline(always)]
 pub fn get_id() -> AccountId {
     unsafe { extern_account_get_id() }
 }
 
+/// Returns the id of the account whose code is currently executing.
+///
+/// This is an alias for [get_id], which reads better in note scripts, where several account ids
+/// are in play.
+#[inline(always)]
+pub fn executing_account_id() -> AccountId {
+    get_id()
+}
+
+/// Returns the id of the account which created the currently executing note.
+///
+/// The account a note is intended for is not tracked by the transaction kernel. Notes which
+/// target a specific account typically carry its id in their inputs (see [get_inputs]).
+#[inline(always)]
+pub fn get_sender() -> AccountId {
+    unsafe { extern_note_get_sender() }
+}
+
 const MAX_INPUTS: usize = 256;
 
+/// Returns the inputs of the currently executing note.
+///
+/// The number of inputs is known up front, i.e. iterating over the inputs yields an
+/// [ExactSizeIterator]. To read individual inputs, see [get_input].
 #[inline(always)]
 pub fn get_inputs() -> Vec<Felt> {
     // The MASM for this function is here:
@@ -63,12 +92,63 @@ pub fn get_inputs() -> Vec<Felt> {
     }
 }
 
+/// Returns the number of inputs of the currently executing note
+#[inline(always)]
+pub fn num_inputs() -> usize {
+    with_inputs(|ptr| unsafe { extern_note_get_inputs(ptr) }, <[Felt]>::len)
+}
+
+/// Returns the input at `index` of the currently executing note, or `None` if the note has no
+/// more than `index` inputs
+#[inline(always)]
+pub fn get_input(index: usize) -> Option<Felt> {
+    with_inputs(|ptr| unsafe { extern_note_get_inputs(ptr) }, |inputs| input_at(inputs, index))
+}
+
+/// Calls `f` with the note inputs written by `get_inputs` to a buffer on the stack, so that
+/// inspecting them does not allocate, unlike [get_inputs].
+///
+/// `get_inputs` is generic rather than a function pointer, so that the call to the kernel is
+/// direct, and not an indirect call through a table.
+#[inline(always)]
+fn with_inputs<G, R>(get_inputs: G, f: impl FnOnce(&[Felt]) -> R) -> R
+where
+    G: FnOnce(*mut Felt) -> usize,
+{
+    unsafe {
+        let mut inputs = ::core::mem::MaybeUninit::<[Felt; MAX_INPUTS]>::uninit();
+        let ptr = inputs.as_mut_ptr() as *mut Felt;
+        let num_inputs = get_inputs(ptr);
+        f(::core::slice::from_raw_parts(ptr, num_inputs))
+    }
+}
+
+#[inline(always)]
+fn input_at(inputs: &[Felt], index: usize) -> Option<Felt> {
+    inputs.get(index).copied()
+}
+
+/// An asset, as represented by the transaction kernel, i.e. a single word.
+///
+/// The layout of the word depends on the kind of asset:
+///
+/// * A fungible asset is laid out as `[amount, 0, 0, faucet_id]`
+/// * A non-fungible asset is laid out as `[hash0, faucet_id, hash2, hash3]`, i.e. the hash of the
+/// asset data, with its second element replaced by the id of the faucet which issued it
+///
+/// The accessors below assume the layout of a fungible asset, the individual elements of any asset
+/// can be accessed by indexing it.
 #[repr(transparent)]
 pub struct CoreAsset {
     inner: Word,
 }
 
 impl CoreAsset {
+    /// The index of the amount of a fungible asset
+    const AMOUNT_INDEX: usize = 0;
+    /// The index of the id of the faucet which issued a fungible asset
+    const FAUCET_ID_INDEX: usize = 3;
+
     pub fn new(word: Word) -> Self {
         CoreAsset { inner: word }
     }
@@ -76,6 +156,34 @@ impl CoreAsset {
     pub fn as_word(&self) -> Word {
         self.inner
     }
+
+    /// Returns the id of the faucet which issued this asset, if it is a fungible asset
+    #[inline(always)]
+    pub fn faucet_id(&self) -> Felt {
+        self.inner[Self::FAUCET_ID_INDEX]
+    }
+
+    /// Returns the amount of this asset, if it is a fungible asset
+    #[inline(always)]
+    pub fn amount(&self) -> Felt {
+        self.inner[Self::AMOUNT_INDEX]
+    }
+}
+
+impl Index<usize> for CoreAsset {
+    type Output = Felt;
+
+    #[inline(always)]
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.inner[index]
+    }
+}
+
+impl IndexMut<usize> for CoreAsset {
+    #[inline(always)]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.inner[index]
+    }
 }
 
 pub fn add_assets(asset: CoreAsset) -> CoreAsset {
@@ -91,3 +199,76 @@ pub fn add_assets(asset: CoreAsset) -> CoreAsset {
         ret_area.assume_init()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The intrinsics used to construct a [Felt] are only available when compiled for Miden, so
+    /// construct felts from their underlying representation instead
+    fn felt(value: u64) -> Felt {
+        unsafe { core::mem::transmute::<f64, Felt>(value as f64) }
+    }
+
+    fn word(values: [u64; 4]) -> Word {
+        values.map(felt)
+    }
+
+    #[test]
+    fn fungible_asset_components() {
+        let asset = CoreAsset::new(word([100, 0, 0, 42]));
+        assert_eq!(u64::from(asset.amount()), 100);
+        assert_eq!(u64::from(asset.faucet_id()), 42);
+    }
+
+    #[test]
+    fn index_asset_elements() {
+        let mut asset = CoreAsset::new(word([1, 2, 3, 4]));
+        assert_eq!([0, 1, 2, 3].map(|i| u64::from(asset[i])), [1, 2, 3, 4]);
+
+        asset[1] = felt(5);
+        assert_eq!(u64::from(asset[1]), 5);
+        assert_eq!(asset.as_word().map(u64::from), [1, 5, 3, 4]);
+    }
+
+    /// Checks that iterating over the inputs returned by `get_inputs` yields an
+    /// [ExactSizeIterator], without calling it, as the kernel is only available on Miden
+    fn assert_exact_size_inputs<F, I>(_get_inputs: F)
+    where
+        F: FnOnce() -> I,
+        I: IntoIterator<Item = Felt>,
+        I::IntoIter: ExactSizeIterator,
+    {
+    }
+
+    #[test]
+    fn read_fixed_number_of_inputs() {
+        assert_exact_size_inputs(get_inputs);
+
+        let inputs = [7, 11, 13].map(felt);
+        let sum = (0..2)
+            .map(|i| input_at(&inputs, i).map(u64::from).expect("missing input"))
+            .sum::<u64>();
+        assert_eq!(sum, 18);
+        assert_eq!(input_at(&inputs, 2).map(u64::from), Some(13));
+        assert!(input_at(&inputs, 3).is_none());
+        assert!(input_at(&[], 0).is_none());
+    }
+
+    fn get_three_inputs(ptr: *mut Felt) -> usize {
+        for (i, value) in [7, 11, 13].into_iter().enumerate() {
+            unsafe { ptr.add(i).write(felt(value)) };
+        }
+        3
+    }
+
+    #[test]
+    fn count_inputs_in_place() {
+        assert_eq!(with_inputs(get_three_inputs, <[Felt]>::len), 3);
+        assert_eq!(
+            with_inputs(get_three_inputs, |inputs| input_at(inputs, 1).map(u64::from)),
+            Some(11)
+        );
+        assert!(with_inputs(get_three_inputs, |inputs| input_at(inputs, 3)).is_none());
+    }
+}
@@ -511,6 +511,33 @@ impl CompilerTest {
         }
         self.ir_masm.clone().unwrap()
     }
+
+    /// Compile the function `name` to MASM in isolation, along with only the functions it
+    /// transitively calls, and return the compiled function.
+    ///
+    /// NOTE: This consumes the compiled IR, compiling the Wasm first if necessary
+    pub fn masm_function(&mut self, name: &str) -> miden_codegen_masm::Function {
+        self.hir();
+        let mut modules: Vec<Box<miden_hir::Module>> = match self.hir.take().unwrap() {
+            HirArtifact::Program(mut hir_program) => {
+                hir_program.modules_mut().take().into_iter().collect()
+            }
+            HirArtifact::Component(mut hir_component) => {
+                hir_component.modules_mut().drain(..).map(|(_, module)| module).collect()
+            }
+            HirArtifact::Module(hir_module) => vec![hir_module],
+        };
+        let name = Ident::with_empty_span(Symbol::intern(name));
+        let index = modules
+            .iter()
+            .position(|module| module.contains(name))
+            .unwrap_or_else(|| panic!("no function named '{name}' in IR"));
+        let module = modules.swap_remove(index);
+        let mut compiler = MasmCompiler::new(&self.session);
+        compiler
+            .compile_function(module, name)
+            .expect("Failed to compile function to MASM")
+    }
 }
 
 fn report_cargo_error(child: std::process::Child) {
@@ -3,3 +3,51 @@ mod components;
 mod instructions;
 mod rust_sdk;
 mod wit_sdk;
+
+use miden_core::Felt;
+use proptest::{prelude::*, test_runner::TestRunner};
+
+use crate::{execute_emulator, execute_vm, CompilerTest};
+
+#[test]
+fn br_table() {
+    let main_fn = r#"(a: u32, b: u32) -> u32 {
+        match a {
+            0 => b.wrapping_add(7),
+            1 => b.wrapping_mul(3),
+            2 => b ^ 0xff,
+            3 => b.wrapping_sub(5),
+            4 => b >> 2,
+            _ => b,
+        }
+    }"#;
+    let mut test = CompilerTest::rust_fn_body(main_fn);
+    let wat = crate::compiler_test::wasm_to_wat(&test.wasm_bytes);
+    assert!(
+        wat.contains("br_table"),
+        "expected the match to be compiled to a br_table:\n{wat}"
+    );
+    let ir_masm = test.ir_masm_program();
+    let vm_program = test.vm_masm_program();
+
+    // Run the Rust and compiled MASM code against a bunch of random inputs and compare the results
+    TestRunner::default()
+        .run(&(0u32..8, any::<u32>()), move |(a, b)| {
+            let rust_out = match a {
+                0 => b.wrapping_add(7),
+                1 => b.wrapping_mul(3),
+                2 => b ^ 0xff,
+                3 => b.wrapping_sub(5),
+                4 => b >> 2,
+                _ => b,
+            };
+            let args = [Felt::from(a), Felt::from(b)];
+            let vm_out: u32 = execute_vm(&vm_program, &args).first().unwrap().clone().into();
+            prop_assert_eq!(rust_out, vm_out);
+            let emul_out: u32 =
+                execute_emulator(ir_masm.clone(), &args).first().unwrap().clone().into();
+            prop_assert_eq!(rust_out, emul_out);
+            Ok(())
+        })
+        .unwrap();
+}
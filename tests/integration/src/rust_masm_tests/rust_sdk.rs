@@ -1,6 +1,8 @@
 use expect_test::expect_file;
+use miden_core::Felt;
+use proptest::{prelude::*, test_runner::TestRunner};
 
-use crate::CompilerTest;
+use crate::{execute_emulator, execute_vm, CompilerTest};
 
 #[test]
 fn account() {
@@ -13,3 +15,109 @@ fn account() {
         "../../expected/rust_sdk_account_test/{artifact_name}.hir"
     )]);
 }
+
+#[test]
+fn account_get_wallet_magic_number() {
+    use miden_codegen_masm::Op;
+
+    let mut test = CompilerTest::rust_source_cargo_lib("rust-sdk/account-test");
+    let function = test.masm_function("get_wallet_magic_number");
+    assert_eq!(function.name.function.as_str(), "get_wallet_magic_number");
+
+    // There is no control flow in this function, so everything is in the entry block
+    assert_eq!(function.body.blocks.len(), 1);
+    let ops = function.body.block(function.body.id()).ops.as_slice();
+
+    // The account id is fetched from the kernel, and the range check of the felt conversion of
+    // the magic number is the only other procedure invoked
+    let mut callees = ops
+        .iter()
+        .filter_map(|op| match op {
+            Op::Exec(callee) => Some(callee.function.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    callees.sort();
+    assert_eq!(callees, ["checked_lte", "get_id"]);
+
+    // The result is the sum of the magic number and the account id
+    assert_eq!(ops.last(), Some(&Op::Add));
+}
+
+#[test]
+fn felt_bitwise() {
+    let mut test = CompilerTest::rust_source_cargo(
+        "rust-sdk/felt-bitwise-test",
+        "miden_sdk_felt_bitwise_test",
+        "entrypoint",
+    );
+    let ir_masm = test.ir_masm_program();
+    let vm_program = test.vm_masm_program();
+
+    // Run the SDK functions compiled to MASM against a bunch of random inputs, and compare the
+    // results with the equivalent operations on u32
+    TestRunner::default()
+        .run(&(0u32..5, any::<u32>(), 0u32..32), move |(op, a, b)| {
+            let rust_out = match op {
+                0 => a << b,
+                1 => a >> b,
+                2 => a.rotate_left(b),
+                3 => a.rotate_right(b),
+                _ => a,
+            };
+            let args = [Felt::from(op), Felt::from(a), Felt::from(b)];
+            let vm_out: u32 = execute_vm(&vm_program, &args).first().unwrap().clone().into();
+            prop_assert_eq!(rust_out, vm_out);
+            let emul_out: u32 =
+                execute_emulator(ir_masm.clone(), &args).first().unwrap().clone().into();
+            prop_assert_eq!(rust_out, emul_out);
+            Ok(())
+        })
+        .unwrap();
+
+    // Shifting by 4 is the same as multiplying by 16
+    let args = [Felt::from(0u32), Felt::from(1u32), Felt::from(4u32)];
+    let emul_out: u32 = execute_emulator(ir_masm.clone(), &args).first().unwrap().clone().into();
+    assert_eq!(emul_out, 16);
+}
+
+#[test]
+fn word_assertions() {
+    let mut test = CompilerTest::rust_source_cargo(
+        "rust-sdk/word-assert-test",
+        "miden_sdk_word_assert_test",
+        "entrypoint",
+    );
+    let ir_masm = test.ir_masm_program();
+    let vm_program = test.vm_masm_program();
+
+    // A pair of equal words passes both assertions
+    let args = [Felt::from(5u32), Felt::from(5u32)];
+    let vm_out: u32 = execute_vm(&vm_program, &args).first().unwrap().clone().into();
+    assert_eq!(vm_out, 5);
+    let emul_out: u32 = execute_emulator(ir_masm, &args).first().unwrap().clone().into();
+    assert_eq!(emul_out, 5);
+
+    // A mismatched pair of words fails the equality assertion
+    let args = [Felt::from(5u32), Felt::from(6u32)];
+    let result = miden_processor::execute(
+        &vm_program,
+        miden_core::StackInputs::new(args.to_vec()),
+        miden_processor::DefaultHost::default(),
+        miden_processor::ExecutionOptions::default(),
+    );
+    assert!(result.is_err(), "expected the assertion on a mismatched pair of words to fail");
+}
+
+#[test]
+#[should_panic(expected = "equality assertion failed")]
+fn word_assertions_mismatch_emulator() {
+    let mut test = CompilerTest::rust_source_cargo(
+        "rust-sdk/word-assert-test",
+        "miden_sdk_word_assert_test",
+        "entrypoint",
+    );
+    let ir_masm = test.ir_masm_program();
+
+    execute_emulator(ir_masm, &[Felt::from(5u32), Felt::from(6u32)]);
+}
@@ -0,0 +1,19 @@
+;; RUN: bin/midenc compile --stdout --emit=masm %s | filecheck %s
+(module
+    (func $main (result i32)
+        i32.const 2
+        if (result i32)
+            i32.const 3
+        else
+            i32.const 5
+        end
+    )
+)
+
+;; CHECK-LABEL: export.main
+;; CHECK: if.true
+;; CHECK-NEXT: push.3
+;; CHECK-NEXT: else
+;; CHECK-NEXT: push.5
+;; CHECK-NEXT: end
+;; CHECK-NEXT: end
@@ -0,0 +1,34 @@
+;; RUN: bin/midenc compile --stdout --emit=masm %s | filecheck %s
+(module
+    ;; Sum the decreasing numbers from 2 to 0, i.e. 2 + 1 + 0, then exit the loop
+    (func $main (result i32) (local i32 i32)
+        i32.const 2
+        local.set 0
+        loop
+            local.get 0
+            local.get 1
+            i32.add
+            local.set 1
+            local.get 0
+            i32.const 1
+            i32.sub
+            local.tee 0
+            br_if 0
+        end
+        local.get 1
+    )
+)
+
+;; The loop is entered unconditionally, and dispatches on the condition computed by the loop
+;; header in each iteration, either continuing the loop, or exiting it
+;;
+;; CHECK-LABEL: export.main
+;; CHECK: push.1
+;; CHECK-NEXT: while.true
+;; CHECK-NEXT: if.true
+;; CHECK: push.1
+;; CHECK-NEXT: else
+;; CHECK: push.0
+;; CHECK-NEXT: end
+;; CHECK-NEXT: end
+;; CHECK-NEXT: end
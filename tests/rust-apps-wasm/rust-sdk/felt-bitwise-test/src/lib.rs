@@ -0,0 +1,21 @@
+#![no_std]
+
+#[panic_handler]
+fn my_panic(_info: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+use miden_sdk::*;
+
+/// Applies the bitwise operation selected by `op` to `a` and `b`
+#[no_mangle]
+pub fn entrypoint(op: u32, a: Felt, b: u32) -> Felt {
+    match op {
+        0 => a.shl(b),
+        1 => a.shr(b),
+        2 => a.rotl(b),
+        3 => a.rotr(b),
+        // Rotating in both directions by the same amount must give back `a`
+        _ => a.rotl(b).rotr(b),
+    }
+}
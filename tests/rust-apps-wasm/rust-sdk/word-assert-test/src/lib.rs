@@ -0,0 +1,19 @@
+#![no_std]
+
+#[panic_handler]
+fn my_panic(_info: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+use miden_sdk::*;
+
+/// Asserts that two words, which differ at most in their first element, `a` and `b`, are equal,
+/// and that the word holding `a - b` is zero
+#[no_mangle]
+pub fn entrypoint(a: Felt, b: Felt) -> Felt {
+    let rest = [Felt::from(1u32), Felt::from(2u32), Felt::from(3u32)];
+    assert_eqw([a, rest[0], rest[1], rest[2]], [b, rest[0], rest[1], rest[2]]);
+    let zero = Felt::from(0u32);
+    assert_word_zero([a - b, zero, zero, zero]);
+    a
+}